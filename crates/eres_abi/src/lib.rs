@@ -4,6 +4,9 @@ use wasmtime::{Val, ValType};
 
 pub use eres_abi_macros::EresAbi;
 
+// Lets the derive's `::eres_abi::...` paths resolve inside this crate's own tests.
+extern crate self as eres_abi;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum NamedTypeKind {
     Struct,
@@ -40,6 +43,10 @@ pub enum AbiType {
     List(Box<AbiType>),
     Tuple(Vec<AbiType>),
     Named(NamedTypeRef),
+    /// The language's prelude `Option<T>`.
+    Option(Box<AbiType>),
+    /// The language's prelude `Result<T, E>`.
+    Result(Box<AbiType>, Box<AbiType>),
     Unit,
 }
 
@@ -129,6 +136,14 @@ pub enum RuntimeValue {
         variant: String,
         payload: EnumPayload,
     },
+    /// A string or aggregate nested inside a heap value, stored in a slot of its own.
+    ///
+    /// Only appears inside [`RuntimeHeap`] slots; [`RuntimeHeap::resolve`] replaces it by
+    /// the value it points to. `ty` is the type of that value.
+    Handle {
+        handle: RuntimeValueHandle,
+        ty: AbiType,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -138,6 +153,125 @@ pub enum EnumPayload {
     Struct(Vec<(String, RuntimeValue)>),
 }
 
+impl RuntimeValue {
+    /// Returns the ABI type that describes this value.
+    pub fn abi_type(&self) -> AbiType {
+        match self {
+            RuntimeValue::Int(_) => AbiType::Int,
+            RuntimeValue::Float(_) => AbiType::Float,
+            RuntimeValue::Bool(_) => AbiType::Bool,
+            RuntimeValue::String(_) => AbiType::String,
+            RuntimeValue::List { element_type, .. } => AbiType::List(Box::new(element_type.clone())),
+            RuntimeValue::Tuple { element_types, .. } => AbiType::Tuple(element_types.clone()),
+            RuntimeValue::Struct { named, .. } | RuntimeValue::Enum { named, .. } => {
+                AbiType::Named(named.clone())
+            }
+            RuntimeValue::Handle { ty, .. } => ty.clone(),
+        }
+    }
+
+    /// Positional access into aggregate values (struct fields, tuple/list elements, enum payloads).
+    pub fn element(&self, index: usize) -> Result<&RuntimeValue, String> {
+        let found = match self {
+            RuntimeValue::Struct { fields, .. } => fields.get(index).map(|(_, value)| value),
+            RuntimeValue::Tuple { elements, .. } | RuntimeValue::List { elements, .. } => {
+                elements.get(index)
+            }
+            RuntimeValue::Enum { payload, .. } => match payload {
+                EnumPayload::Unit => None,
+                EnumPayload::Tuple(inner) => (index == 0).then_some(inner.as_ref()),
+                EnumPayload::Struct(fields) => fields.get(index).map(|(_, value)| value),
+            },
+            other => return Err(format!("expected aggregate runtime value, found {:?}", other)),
+        };
+        found.ok_or_else(|| self.out_of_bounds(index))
+    }
+
+    /// Number of positional elements (fields, list/tuple elements, payload values).
+    pub fn element_count(&self) -> Result<usize, String> {
+        match self {
+            RuntimeValue::Struct { fields, .. } => Ok(fields.len()),
+            RuntimeValue::Tuple { elements, .. } | RuntimeValue::List { elements, .. } => {
+                Ok(elements.len())
+            }
+            RuntimeValue::Enum { payload, .. } => Ok(match payload {
+                EnumPayload::Unit => 0,
+                EnumPayload::Tuple(_) => 1,
+                EnumPayload::Struct(fields) => fields.len(),
+            }),
+            other => Err(format!("expected aggregate runtime value, found {:?}", other)),
+        }
+    }
+
+    /// Removes and returns the last element of a list.
+    pub fn pop_element(&mut self) -> Result<RuntimeValue, String> {
+        match self {
+            RuntimeValue::List { elements, .. } => elements
+                .pop()
+                .ok_or_else(|| "pop from empty list".to_string()),
+            other => Err(format!("expected List runtime value, found {:?}", other)),
+        }
+    }
+
+    fn out_of_bounds(&self, index: usize) -> String {
+        format!(
+            "index {} out of bounds for {} of length {}",
+            index,
+            format_abi_type(&self.abi_type()),
+            self.element_count().unwrap_or(0)
+        )
+    }
+
+    /// Mutable positional access, see [`RuntimeValue::element`].
+    pub fn element_mut(&mut self, index: usize) -> Result<&mut RuntimeValue, String> {
+        let out_of_bounds = self.out_of_bounds(index);
+        let found = match self {
+            RuntimeValue::Struct { fields, .. } => fields.get_mut(index).map(|(_, value)| value),
+            RuntimeValue::Tuple { elements, .. } | RuntimeValue::List { elements, .. } => {
+                elements.get_mut(index)
+            }
+            RuntimeValue::Enum { payload, .. } => match payload {
+                EnumPayload::Unit => None,
+                EnumPayload::Tuple(inner) => (index == 0).then_some(inner.as_mut()),
+                EnumPayload::Struct(fields) => fields.get_mut(index).map(|(_, value)| value),
+            },
+            other => return Err(format!("expected aggregate runtime value, found {:?}", other)),
+        };
+        found.ok_or(out_of_bounds)
+    }
+
+    /// Appends one element to an aggregate under construction.
+    ///
+    /// `key` names the field for structs and struct-like enum payloads; positional
+    /// aggregates (tuples, lists, tuple payloads) ignore it.
+    pub fn push_element(&mut self, key: Option<String>, value: RuntimeValue) -> Result<(), String> {
+        match self {
+            RuntimeValue::Struct { fields, .. } => {
+                let key = key.ok_or_else(|| "struct field push requires a field name".to_string())?;
+                fields.push((key, value));
+            }
+            RuntimeValue::Tuple {
+                element_types,
+                elements,
+            } => {
+                element_types.push(value.abi_type());
+                elements.push(value);
+            }
+            RuntimeValue::List { elements, .. } => elements.push(value),
+            RuntimeValue::Enum { payload, .. } => match (key, &mut *payload) {
+                (Some(key), EnumPayload::Struct(fields)) => fields.push((key, value)),
+                (Some(key), EnumPayload::Unit) => *payload = EnumPayload::Struct(vec![(key, value)]),
+                (None, EnumPayload::Unit) => *payload = EnumPayload::Tuple(Box::new(value)),
+                (_, other) => {
+                    return Err(format!("cannot extend enum payload {:?}", other));
+                }
+            },
+            other => return Err(format!("expected aggregate runtime value, found {:?}", other)),
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct RuntimeValueHandle(pub u32);
 
@@ -147,17 +281,183 @@ pub struct RuntimeHeap {
 }
 
 impl RuntimeHeap {
+    /// Stores `value` in a new slot and returns its handle.
+    ///
+    /// Strings and aggregates nested in `value` get slots of their own, so reading them
+    /// back shares the slot instead of copying it. A [`RuntimeValue::Handle`] is not
+    /// stored again; its handle is returned as is.
     pub fn allocate(&mut self, value: RuntimeValue) -> RuntimeValueHandle {
+        if let RuntimeValue::Handle { handle, .. } = value {
+            return handle;
+        }
+        let value = match value {
+            RuntimeValue::List {
+                element_type,
+                elements,
+            } => RuntimeValue::List {
+                element_type,
+                elements: self.nest_all(elements),
+            },
+            RuntimeValue::Tuple {
+                element_types,
+                elements,
+            } => RuntimeValue::Tuple {
+                element_types,
+                elements: self.nest_all(elements),
+            },
+            RuntimeValue::Struct { named, fields } => RuntimeValue::Struct {
+                named,
+                fields: self.nest_fields(fields),
+            },
+            RuntimeValue::Enum {
+                named,
+                variant,
+                payload,
+            } => RuntimeValue::Enum {
+                named,
+                variant,
+                payload: match payload {
+                    EnumPayload::Unit => EnumPayload::Unit,
+                    EnumPayload::Tuple(inner) => EnumPayload::Tuple(Box::new(self.nest(*inner))),
+                    EnumPayload::Struct(fields) => EnumPayload::Struct(self.nest_fields(fields)),
+                },
+            },
+            scalar => scalar,
+        };
         let index = self.values.len() as u32;
         self.values.push(value);
         RuntimeValueHandle(index)
     }
 
+    /// Moves a nested string or aggregate into its own slot; scalars stay inline.
+    fn nest(&mut self, value: RuntimeValue) -> RuntimeValue {
+        match value {
+            RuntimeValue::Int(_)
+            | RuntimeValue::Float(_)
+            | RuntimeValue::Bool(_)
+            | RuntimeValue::Handle { .. } => value,
+            value => {
+                let ty = value.abi_type();
+                RuntimeValue::Handle {
+                    handle: self.allocate(value),
+                    ty,
+                }
+            }
+        }
+    }
+
+    fn nest_all(&mut self, values: Vec<RuntimeValue>) -> Vec<RuntimeValue> {
+        values.into_iter().map(|value| self.nest(value)).collect()
+    }
+
+    fn nest_fields(&mut self, fields: Vec<(String, RuntimeValue)>) -> Vec<(String, RuntimeValue)> {
+        fields
+            .into_iter()
+            .map(|(name, value)| (name, self.nest(value)))
+            .collect()
+    }
+
+    /// A [`RuntimeValue::Handle`] to an existing slot, for storing it inside another value.
+    pub fn share(&self, handle: RuntimeValueHandle) -> Result<RuntimeValue, String> {
+        Ok(RuntimeValue::Handle {
+            handle,
+            ty: self.get(handle)?.abi_type(),
+        })
+    }
+
+    /// Number of slots allocated so far; slots are never freed.
+    pub fn allocated(&self) -> usize {
+        self.values.len()
+    }
+
     pub fn get(&self, handle: RuntimeValueHandle) -> Result<&RuntimeValue, String> {
         self.values
             .get(handle.0 as usize)
             .ok_or_else(|| format!("invalid runtime handle {}", handle.0))
     }
+
+    pub fn get_mut(&mut self, handle: RuntimeValueHandle) -> Result<&mut RuntimeValue, String> {
+        self.values
+            .get_mut(handle.0 as usize)
+            .ok_or_else(|| format!("invalid runtime handle {}", handle.0))
+    }
+
+    /// Reads the value behind `handle` with every nested handle replaced by its value, the
+    /// way hosts and the CLI see it. Fails for a value that contains itself.
+    pub fn resolve(&self, handle: RuntimeValueHandle) -> Result<RuntimeValue, String> {
+        self.resolve_slot(handle, &mut Vec::new())
+    }
+
+    /// `open` holds the slots currently being resolved, to detect cycles.
+    fn resolve_slot(
+        &self,
+        handle: RuntimeValueHandle,
+        open: &mut Vec<RuntimeValueHandle>,
+    ) -> Result<RuntimeValue, String> {
+        if open.contains(&handle) {
+            return Err(format!("runtime value {} contains itself", handle.0));
+        }
+        open.push(handle);
+        let value = self.resolve_value(self.get(handle)?, open)?;
+        open.pop();
+        Ok(value)
+    }
+
+    fn resolve_value(
+        &self,
+        value: &RuntimeValue,
+        open: &mut Vec<RuntimeValueHandle>,
+    ) -> Result<RuntimeValue, String> {
+        let all = |values: &[RuntimeValue], open: &mut Vec<RuntimeValueHandle>| {
+            values
+                .iter()
+                .map(|value| self.resolve_value(value, open))
+                .collect::<Result<Vec<_>, String>>()
+        };
+        let fields = |fields: &[(String, RuntimeValue)], open: &mut Vec<RuntimeValueHandle>| {
+            fields
+                .iter()
+                .map(|(name, value)| Ok((name.clone(), self.resolve_value(value, open)?)))
+                .collect::<Result<Vec<_>, String>>()
+        };
+        Ok(match value {
+            RuntimeValue::Handle { handle, .. } => return self.resolve_slot(*handle, open),
+            RuntimeValue::List {
+                element_type,
+                elements,
+            } => RuntimeValue::List {
+                element_type: element_type.clone(),
+                elements: all(elements, open)?,
+            },
+            RuntimeValue::Tuple {
+                element_types,
+                elements,
+            } => RuntimeValue::Tuple {
+                element_types: element_types.clone(),
+                elements: all(elements, open)?,
+            },
+            RuntimeValue::Struct { named, fields: values } => RuntimeValue::Struct {
+                named: named.clone(),
+                fields: fields(values, open)?,
+            },
+            RuntimeValue::Enum {
+                named,
+                variant,
+                payload,
+            } => RuntimeValue::Enum {
+                named: named.clone(),
+                variant: variant.clone(),
+                payload: match payload {
+                    EnumPayload::Unit => EnumPayload::Unit,
+                    EnumPayload::Tuple(inner) => {
+                        EnumPayload::Tuple(Box::new(self.resolve_value(inner, open)?))
+                    }
+                    EnumPayload::Struct(values) => EnumPayload::Struct(fields(values, open)?),
+                },
+            },
+            scalar => scalar.clone(),
+        })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -213,7 +513,7 @@ where
 {
     fn from_eres_value(value: &HostValue, heap: &RuntimeHeap) -> Result<Self, String> {
         match value {
-            HostValue::Handle(handle) => T::from_runtime_value(heap.get(*handle)?),
+            HostValue::Handle(handle) => T::from_runtime_value(&heap.resolve(*handle)?),
             other => Err(format!(
                 "expected handle for {}, found {:?}",
                 format_abi_type(&T::abi_type()),
@@ -232,16 +532,39 @@ where
     }
 }
 
-impl<T> IntoEresReturn for Result<T, String>
-where
-    T: IntoEresValue,
-{
-    fn into_eres_return(self, heap: &mut RuntimeHeap) -> Result<Option<HostValue>, String> {
-        self.and_then(|value| value.into_eres_return(heap))
+impl EresAbi for () {
+    fn abi_type() -> AbiType {
+        AbiType::Unit
+    }
+}
+
+impl IntoEresReturn for () {
+    fn into_eres_return(self, _heap: &mut RuntimeHeap) -> Result<Option<HostValue>, String> {
+        Ok(None)
+    }
+}
+
+/// Error of a host function that aborts the program, returned as `Result<T, HostTrap>`.
+///
+/// The function's Eres result type is just `T`; an `Err` stops execution with the message
+/// as runtime error. A plain `Result<T, E>` instead reaches the program as a prelude
+/// `Result` value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostTrap(pub String);
+
+impl From<String> for HostTrap {
+    fn from(message: String) -> Self {
+        HostTrap(message)
     }
 }
 
-impl<T> EresAbi for Result<T, String>
+impl From<&str> for HostTrap {
+    fn from(message: &str) -> Self {
+        HostTrap(message.to_string())
+    }
+}
+
+impl<T> EresAbi for Result<T, HostTrap>
 where
     T: EresAbi,
 {
@@ -254,21 +577,13 @@ where
     }
 }
 
-impl EresAbi for () {
-    fn abi_type() -> AbiType {
-        AbiType::Unit
-    }
-}
-
-impl IntoEresReturn for () {
-    fn into_eres_return(self, _heap: &mut RuntimeHeap) -> Result<Option<HostValue>, String> {
-        Ok(None)
-    }
-}
-
-impl IntoEresReturn for Result<(), String> {
-    fn into_eres_return(self, _heap: &mut RuntimeHeap) -> Result<Option<HostValue>, String> {
-        self.map(|_| None)
+impl<T> IntoEresReturn for Result<T, HostTrap>
+where
+    T: IntoEresReturn,
+{
+    fn into_eres_return(self, heap: &mut RuntimeHeap) -> Result<Option<HostValue>, String> {
+        self.map_err(|HostTrap(message)| message)
+            .and_then(|value| value.into_eres_return(heap))
     }
 }
 
@@ -594,6 +909,134 @@ where
 {
 }
 
+/// Named type of prelude enum values; wasm-built values resolve to the same id.
+fn prelude_named(name: &str) -> NamedTypeRef {
+    NamedTypeRef::new(name, name, NamedTypeKind::Enum)
+}
+
+/// Checks that `value` is a prelude enum called `name` and returns its variant and payload.
+fn prelude_variant<'a>(
+    value: &'a RuntimeValue,
+    name: &str,
+) -> Result<(&'a str, &'a EnumPayload), String> {
+    match value {
+        RuntimeValue::Enum {
+            named,
+            variant,
+            payload,
+        } if named.type_id == name => Ok((variant.as_str(), payload)),
+        other => Err(format!("expected {} runtime value, found {:?}", name, other)),
+    }
+}
+
+fn tuple_payload<T: FromRuntimeValue>(variant: &str, payload: &EnumPayload) -> Result<T, String> {
+    match payload {
+        EnumPayload::Tuple(inner) => T::from_runtime_value(inner),
+        other => Err(format!("expected tuple payload for {}, found {:?}", variant, other)),
+    }
+}
+
+impl<T> EresAbi for Option<T>
+where
+    T: EresAbi,
+{
+    fn abi_type() -> AbiType {
+        AbiType::Option(Box::new(T::abi_type()))
+    }
+
+    fn descriptors() -> Vec<TypeDescriptor> {
+        T::descriptors()
+    }
+}
+
+impl<T> IntoRuntimeValue for Option<T>
+where
+    T: EresAbi + IntoRuntimeValue,
+{
+    fn into_runtime_value(self) -> Result<RuntimeValue, String> {
+        let (variant, payload) = match self {
+            Some(value) => ("Some", EnumPayload::Tuple(Box::new(value.into_runtime_value()?))),
+            None => ("None", EnumPayload::Unit),
+        };
+        Ok(RuntimeValue::Enum {
+            named: prelude_named("Option"),
+            variant: variant.to_string(),
+            payload,
+        })
+    }
+}
+
+impl<T> FromRuntimeValue for Option<T>
+where
+    T: EresAbi + FromRuntimeValue,
+{
+    fn from_runtime_value(value: &RuntimeValue) -> Result<Self, String> {
+        match prelude_variant(value, "Option")? {
+            ("Some", payload) => tuple_payload("Some", payload).map(Some),
+            ("None", _) => Ok(None),
+            (other, _) => Err(format!("unknown enum variant `{}`", other)),
+        }
+    }
+}
+
+impl<T> ComplexEresAbi for Option<T> where T: EresAbi + IntoRuntimeValue + FromRuntimeValue {}
+
+impl<T, E> EresAbi for Result<T, E>
+where
+    T: EresAbi,
+    E: EresAbi,
+{
+    fn abi_type() -> AbiType {
+        AbiType::Result(Box::new(T::abi_type()), Box::new(E::abi_type()))
+    }
+
+    fn descriptors() -> Vec<TypeDescriptor> {
+        let mut registry = DescriptorRegistry::default();
+        registry.extend(T::descriptors());
+        registry.extend(E::descriptors());
+        registry.finish()
+    }
+}
+
+impl<T, E> IntoRuntimeValue for Result<T, E>
+where
+    T: EresAbi + IntoRuntimeValue,
+    E: EresAbi + IntoRuntimeValue,
+{
+    fn into_runtime_value(self) -> Result<RuntimeValue, String> {
+        let (variant, payload) = match self {
+            Ok(value) => ("Ok", value.into_runtime_value()?),
+            Err(error) => ("Err", error.into_runtime_value()?),
+        };
+        Ok(RuntimeValue::Enum {
+            named: prelude_named("Result"),
+            variant: variant.to_string(),
+            payload: EnumPayload::Tuple(Box::new(payload)),
+        })
+    }
+}
+
+impl<T, E> FromRuntimeValue for Result<T, E>
+where
+    T: EresAbi + FromRuntimeValue,
+    E: EresAbi + FromRuntimeValue,
+{
+    fn from_runtime_value(value: &RuntimeValue) -> Result<Self, String> {
+        match prelude_variant(value, "Result")? {
+            ("Ok", payload) => tuple_payload("Ok", payload).map(Ok),
+            ("Err", payload) => tuple_payload("Err", payload).map(Err),
+            (other, _) => Err(format!("unknown enum variant `{}`", other)),
+        }
+    }
+}
+
+impl<T, E> ComplexEresAbi for Result<T, E>
+where
+    T: EresAbi + IntoRuntimeValue + FromRuntimeValue,
+    E: EresAbi + IntoRuntimeValue + FromRuntimeValue,
+{
+}
+
 #[derive(Clone)]
 pub struct HostFunction {
     pub name: &'static str,
@@ -619,6 +1062,10 @@ pub fn format_abi_type(ty: &AbiType) -> String {
                 .join(", ")
         ),
         AbiType::Named(named) => named.name.clone(),
+        AbiType::Option(inner) => format!("Option<{}>", format_abi_type(inner)),
+        AbiType::Result(ok, err) => {
+            format!("Result<{}, {}>", format_abi_type(ok), format_abi_type(err))
+        }
         AbiType::Unit => "Unit".to_string(),
     }
 }
@@ -628,9 +1075,12 @@ pub fn abi_type_to_val_type(ty: &AbiType) -> Option<ValType> {
         AbiType::Int => Some(ValType::I64),
         AbiType::Float => Some(ValType::F64),
         AbiType::Bool => Some(ValType::I32),
-        AbiType::String | AbiType::List(_) | AbiType::Tuple(_) | AbiType::Named(_) => {
-            Some(ValType::I32)
-        }
+        AbiType::String
+        | AbiType::List(_)
+        | AbiType::Tuple(_)
+        | AbiType::Named(_)
+        | AbiType::Option(_)
+        | AbiType::Result(_, _) => Some(ValType::I32),
         AbiType::Unit => None,
     }
 }
@@ -640,9 +1090,15 @@ pub fn val_to_host_value(val: &Val, ty: &AbiType) -> Result<HostValue, String> {
         (Val::I64(value), AbiType::Int) => Ok(HostValue::Int(*value)),
         (Val::F64(value), AbiType::Float) => Ok(HostValue::Float(f64::from_bits(*value))),
         (Val::I32(value), AbiType::Bool) => Ok(HostValue::Bool(*value != 0)),
-        (Val::I32(value), AbiType::String | AbiType::List(_) | AbiType::Tuple(_) | AbiType::Named(_)) => {
-            Ok(HostValue::Handle(RuntimeValueHandle(*value as u32)))
-        }
+        (
+            Val::I32(value),
+            AbiType::String
+            | AbiType::List(_)
+            | AbiType::Tuple(_)
+            | AbiType::Named(_)
+            | AbiType::Option(_)
+            | AbiType::Result(_, _),
+        ) => Ok(HostValue::Handle(RuntimeValueHandle(*value as u32))),
         other => Err(format!("unsupported wasm value for host bridge: {:?}", other)),
     }
 }
@@ -654,7 +1110,12 @@ pub fn host_value_to_val(value: HostValue, ty: &AbiType) -> Result<Val, String>
         (HostValue::Bool(value), AbiType::Bool) => Ok(Val::I32(if value { 1 } else { 0 })),
         (
             HostValue::Handle(handle),
-            AbiType::String | AbiType::List(_) | AbiType::Tuple(_) | AbiType::Named(_),
+            AbiType::String
+            | AbiType::List(_)
+            | AbiType::Tuple(_)
+            | AbiType::Named(_)
+            | AbiType::Option(_)
+            | AbiType::Result(_, _),
         ) => Ok(Val::I32(handle.0 as i32)),
         (value, ty) => Err(format!(
             "host bridge type mismatch for {}: {:?}",
@@ -760,6 +1221,79 @@ mod tests {
         assert_eq!(decoded, tuple);
     }
 
+    #[test]
+    fn option_and_result_roundtrip_as_prelude_enums() {
+        let mut heap = RuntimeHeap::default();
+
+        let value: Option<Vec<i64>> = Some(vec![1, 2]);
+        let encoded = value.clone().into_eres_value(&mut heap).expect("encode option");
+        let decoded = Option::<Vec<i64>>::from_eres_value(&encoded, &heap).expect("decode");
+        assert_eq!(decoded, value);
+
+        let value: Result<i64, String> = Err("bad digit".to_string());
+        let encoded = value.clone().into_eres_value(&mut heap).expect("encode result");
+        let decoded = Result::<i64, String>::from_eres_value(&encoded, &heap).expect("decode");
+        assert_eq!(decoded, value);
+        assert_eq!(
+            format_abi_type(&<Result<Option<f64>, String>>::abi_type()),
+            "Result<Option<Float>, String>"
+        );
+
+        let err = Option::<i64>::from_eres_value(&encoded, &heap).expect_err("not an Option");
+        assert!(err.contains("expected Option runtime value"));
+    }
+
+    #[test]
+    fn nested_values_live_in_their_own_slots() {
+        let mut heap = RuntimeHeap::default();
+        let rows = vec![vec![1_i64, 2], vec![3]];
+        let encoded = rows.clone().into_eres_value(&mut heap).expect("encode rows");
+        let HostValue::Handle(outer) = encoded else {
+            panic!("expected a handle");
+        };
+        assert_eq!(heap.allocated(), 3);
+        let Ok(RuntimeValue::Handle { handle, ty }) = heap.get(outer).and_then(|v| v.element(1))
+        else {
+            panic!("expected a nested handle");
+        };
+        assert_eq!(ty, &AbiType::List(Box::new(AbiType::Int)));
+        let inner = *handle;
+        assert_eq!(heap.get(inner).and_then(|v| v.element_count()), Ok(1));
+        let decoded = Vec::<Vec<i64>>::from_eres_value(&encoded, &heap).expect("decode rows");
+        assert_eq!(decoded, rows);
+
+        let shared = heap.share(outer).expect("share outer");
+        heap.get_mut(inner)
+            .and_then(|row| row.push_element(None, shared))
+            .expect("push");
+        let err = heap.resolve(outer).expect_err("cyclic value");
+        assert!(err.contains("contains itself"));
+    }
+
+    #[test]
+    fn host_trap_results_abort_instead_of_returning_a_value() {
+        fn parse(text: String) -> Result<i64, HostTrap> {
+            text.parse().map_err(|_| HostTrap::from(format!("not a number: {}", text)))
+        }
+        fn check(ok: bool) -> Result<(), HostTrap> {
+            if ok { Ok(()) } else { Err("check failed".into()) }
+        }
+
+        let host = eres_host_function!(parse, name = "parse", params = [String], result = Result<i64, HostTrap>);
+        assert_eq!(host.result, AbiType::Int);
+        let mut heap = RuntimeHeap::default();
+        let arg = "x1".to_string().into_eres_value(&mut heap).expect("encode");
+        let err = (host.call)(&mut heap, &[arg]).expect_err("expected a trap");
+        assert_eq!(err, "not a number: x1");
+        let arg = "12".to_string().into_eres_value(&mut heap).expect("encode");
+        assert_eq!((host.call)(&mut heap, &[arg]), Ok(Some(HostValue::Int(12))));
+
+        let host = eres_host_function!(check, name = "check", params = [bool], result = Result<(), HostTrap>);
+        assert_eq!(host.result, AbiType::Unit);
+        let err = (host.call)(&mut heap, &[HostValue::Bool(false)]).expect_err("expected a trap");
+        assert_eq!(err, "check failed");
+    }
+
     #[test]
     fn nominal_struct_types_do_not_cross_decode() {
         let mut heap = RuntimeHeap::default();
@@ -32,6 +32,18 @@ fn len(value: String) -> i64 {
     value.chars().count() as i64
 }
 
+fn parse_int(value: String) -> Option<i64> {
+    value.trim().parse().ok()
+}
+
+fn parse_float(value: String) -> Option<f64> {
+    value.trim().parse().ok()
+}
+
+fn read_file(path: String) -> Result<String, String> {
+    std::fs::read_to_string(&path).map_err(|err| format!("{}: {}", path, err))
+}
+
 pub fn standard_library() -> Vec<HostFunction> {
     vec![
         eres_host_function!(print_string, name = "print", params = [String], result = ()),
@@ -39,6 +51,9 @@ pub fn standard_library() -> Vec<HostFunction> {
         eres_host_function!(print_f64, name = "print_float", params = [f64], result = ()),
         eres_host_function!(print_bool, name = "print_bool", params = [bool], result = ()),
         eres_host_function!(len, name = "len", params = [String], result = i64),
+        eres_host_function!(parse_int, name = "parse_int", params = [String], result = Option<i64>),
+        eres_host_function!(parse_float, name = "parse_float", params = [String], result = Option<f64>),
+        eres_host_function!(read_file, name = "read_file", params = [String], result = Result<String, String>),
         eres_host_function!(add_one, name = "add_one", params = [i64], result = i64),
         eres_host_function!(is_positive, name = "is_positive", params = [i64], result = bool),
         eres_host_function!(half, name = "half", params = [f64], result = f64),
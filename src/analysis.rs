@@ -2,10 +2,12 @@ use std::collections::HashMap;
 
 use crate::ast::{EnumDecl, EnumVariant, Param, Program, StructDecl, TopLevelDecl, Type};
 use crate::compiler::CompileError;
+use crate::diagnostics::Severity;
 use crate::lexer::{LexError, lex_file};
+use crate::lint::lint_program;
 use crate::parser::{ParseError, Parser};
 use crate::token::{Span, Token, TokenKind};
-use crate::typing::{TypedProgram, check_program};
+use crate::typing::{TypedProgram, check_program_all_errors};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SymbolKind {
@@ -56,6 +58,9 @@ pub struct CheckedSource {
 
 #[derive(Debug)]
 pub struct AnalysisDiagnostic {
+    pub severity: Severity,
+    /// Stable diagnostic code, e.g. `E-TC05`.
+    pub code: &'static str,
     pub message: String,
     pub span: Option<Span>,
 }
@@ -67,10 +72,54 @@ pub struct FunctionSymbol {
     pub full_span: Span,
     pub body_span: Span,
     pub params: HashMap<String, Span>,
-    pub locals: HashMap<String, Span>,
+    /// `let`/`for` bindings in source order (matches `TypedFunction::locals`).
+    pub locals: Vec<LocalBinding>,
     pub signature: String,
 }
 
+/// One local binding and the source range in which uses of its name refer to it.
+#[derive(Debug, Clone)]
+pub struct LocalBinding {
+    pub name: String,
+    /// Span of the bound identifier.
+    pub span: Span,
+    /// Uses from this offset on see the binding (after the `let` statement, or the loop body).
+    pub visible_from: usize,
+    /// End of the enclosing block.
+    pub visible_until: usize,
+}
+
+impl FunctionSymbol {
+    /// Finds the binding that `name` at `offset` refers to, with its position in `locals`.
+    fn binding_at(&self, name: &str, offset: usize) -> Option<(usize, &LocalBinding)> {
+        let mut candidates = self
+            .locals
+            .iter()
+            .enumerate()
+            .filter(|(_, binding)| binding.name == name);
+        if let Some(found) = candidates
+            .clone()
+            .find(|(_, binding)| span_contains(&binding.span, offset))
+        {
+            return Some(found);
+        }
+        candidates
+            .rfind(|(_, binding)| binding.visible_from <= offset && offset < binding.visible_until)
+    }
+
+    /// Bindings visible at `offset`, innermost (latest) binding per name.
+    fn bindings_in_scope(&self, offset: usize) -> Vec<(usize, &LocalBinding)> {
+        let mut visible: Vec<(usize, &LocalBinding)> = Vec::new();
+        for (slot, binding) in self.locals.iter().enumerate() {
+            if binding.visible_from <= offset && offset < binding.visible_until {
+                visible.retain(|(_, earlier)| earlier.name != binding.name);
+                visible.push((slot, binding));
+            }
+        }
+        visible
+    }
+}
+
 #[derive(Debug)]
 pub struct SymbolIndex {
     functions: HashMap<String, FunctionSymbol>,
@@ -81,14 +130,14 @@ pub struct SymbolIndex {
 pub fn parse(source: &str) -> Result<Program, CompileError> {
     let tokens = lex_file(source).map_err(CompileError::Lex)?;
     let mut parser = Parser::new(&tokens);
-    parser.parse_program().map_err(CompileError::Parse)
+    parser.parse_program_all_errors().map_err(CompileError::Parse)
 }
 
 pub fn check(source: &str) -> Result<CheckedSource, CompileError> {
     let tokens = lex_file(source).map_err(CompileError::Lex)?;
     let mut parser = Parser::new(&tokens);
-    let program = parser.parse_program().map_err(CompileError::Parse)?;
-    let typed = check_program(&program).map_err(CompileError::TypeCheck)?;
+    let program = parser.parse_program_all_errors().map_err(CompileError::Parse)?;
+    let typed = check_program_all_errors(&program, &[]).map_err(CompileError::TypeCheck)?;
     Ok(CheckedSource {
         tokens,
         program,
@@ -96,26 +145,64 @@ pub fn check(source: &str) -> Result<CheckedSource, CompileError> {
     })
 }
 
+/// First diagnostic of [`analyze_diagnostics`].
 pub fn analyze_diagnostic(source: &str) -> Option<AnalysisDiagnostic> {
+    analyze_diagnostics(source).into_iter().next()
+}
+
+/// Every lex, parse or type error of `source`, in the order the compiler reports them, or the
+/// lint warnings if it checks.
+pub fn analyze_diagnostics(source: &str) -> Vec<AnalysisDiagnostic> {
     match check(source) {
-        Ok(_) => None,
-        Err(CompileError::Lex(err)) => Some(AnalysisDiagnostic {
+        Ok(checked) => lint_program(&checked.program, &checked.typed)
+            .into_iter()
+            .map(|warning| AnalysisDiagnostic {
+                severity: warning.severity,
+                code: warning.code,
+                message: warning.message,
+                span: warning.span,
+            })
+            .collect(),
+        Err(CompileError::Lex(err)) => vec![AnalysisDiagnostic {
+            severity: Severity::Error,
+            code: err.code(),
             message: err.to_string(),
             span: Some(lex_error_span(&err)),
-        }),
-        Err(CompileError::Parse(err)) => Some(AnalysisDiagnostic {
-            message: err.to_string(),
-            span: Some(parse_error_span(&err)),
-        }),
-        Err(CompileError::TypeCheck(err)) => Some(AnalysisDiagnostic {
-            message: err.to_string(),
-            span: None,
-        }),
-        Err(CompileError::Load(err)) => Some(AnalysisDiagnostic {
+        }],
+        Err(CompileError::Parse(errors)) => errors
+            .iter()
+            .map(|err| AnalysisDiagnostic {
+                severity: Severity::Error,
+                code: err.code(),
+                message: err.to_string(),
+                span: Some(parse_error_span(err)),
+            })
+            .collect(),
+        Err(CompileError::TypeCheck(errors)) => errors
+            .iter()
+            .map(|err| AnalysisDiagnostic {
+                severity: Severity::Error,
+                code: err.kind.code(),
+                message: err.to_string(),
+                span: err.span.clone(),
+            })
+            .collect(),
+        Err(CompileError::FileTypeCheck(errors)) => errors
+            .iter()
+            .map(|err| AnalysisDiagnostic {
+                severity: Severity::Error,
+                code: err.error.kind.code(),
+                message: err.error.to_string(),
+                span: None,
+            })
+            .collect(),
+        Err(CompileError::Load(err)) => vec![AnalysisDiagnostic {
+            severity: Severity::Error,
+            code: err.to_diagnostics().first().map_or("E-LD01", |d| d.code),
             message: err.to_string(),
             span: None,
-        }),
-        Err(CompileError::Codegen(_)) => None,
+        }],
+        Err(CompileError::Codegen(_)) => Vec::new(),
     }
 }
 
@@ -132,22 +219,13 @@ pub fn symbol_at(source: &str, offset: usize) -> Result<Option<SymbolInfo>, Comp
     };
 
     let index = SymbolIndex::from_tokens(&checked.tokens, &checked.program);
-    if let Some(function_name) = index.function_name_for_offset(offset) {
-        if let Some(function) = checked.typed.function_infos.get(function_name) {
-            if let Some((slot, ty)) = function.local_map.get(name) {
-                let kind = if (*slot as usize) < function.params.len() {
-                    SymbolKind::Parameter
-                } else {
-                    SymbolKind::Local
-                };
-                return Ok(Some(SymbolInfo {
-                    name: name.clone(),
-                    kind,
-                    detail: format!("{}: {}", name, ty),
-                    span: token.span.clone(),
-                }));
-            }
-        }
+    if let Some((kind, _, ty)) = resolve_variable(&index, &checked.typed, name, offset) {
+        return Ok(Some(SymbolInfo {
+            name: name.clone(),
+            kind,
+            detail: format!("{}: {}", name, ty),
+            span: token.span.clone(),
+        }));
     }
 
     if let Some(function) = index.functions.get(name) {
@@ -203,23 +281,12 @@ pub fn definition_at(source: &str, offset: usize) -> Result<Option<DefinitionInf
 
     let index = SymbolIndex::from_tokens(&checked.tokens, &checked.program);
 
-    if let Some(function_name) = index.function_name_for_offset(offset)
-        && let Some(function) = index.functions.get(function_name)
-    {
-        if let Some(span) = function.params.get(name) {
-            return Ok(Some(DefinitionInfo {
-                name: name.clone(),
-                kind: SymbolKind::Parameter,
-                target_span: span.clone(),
-            }));
-        }
-        if let Some(span) = function.locals.get(name) {
-            return Ok(Some(DefinitionInfo {
-                name: name.clone(),
-                kind: SymbolKind::Local,
-                target_span: span.clone(),
-            }));
-        }
+    if let Some((kind, target_span, _)) = resolve_variable(&index, &checked.typed, name, offset) {
+        return Ok(Some(DefinitionInfo {
+            name: name.clone(),
+            kind,
+            target_span,
+        }));
     }
 
     if let Some(function) = index.functions.get(name) {
@@ -263,20 +330,33 @@ pub fn completions_at(source: &str, offset: usize) -> Result<Vec<CompletionItemI
 
     if let Some(function_name) = index.function_name_for_offset(offset)
         && let Some(function) = checked.typed.function_infos.get(function_name)
+        && let Some(symbol) = index.functions.get(function_name)
     {
+        let visible = symbol.bindings_in_scope(offset);
         for param in &function.params {
+            if visible.iter().any(|(_, binding)| binding.name == param.name) {
+                continue;
+            }
             items.push(CompletionItemInfo {
                 label: param.name.clone(),
                 kind: SymbolKind::Parameter,
                 detail: format!("{}: {}", param.name, param.ty),
             });
         }
-        for (name, ty) in &function.locals {
-            items.push(CompletionItemInfo {
-                label: name.clone(),
-                kind: SymbolKind::Local,
-                detail: format!("{}: {}", name, ty),
-            });
+        for (slot, binding) in visible {
+            if let Some((name, ty)) = function.locals.get(slot) {
+                items.push(CompletionItemInfo {
+                    label: name.clone(),
+                    kind: SymbolKind::Local,
+                    detail: format!("{}: {}", name, ty),
+                });
+            } else {
+                items.push(CompletionItemInfo {
+                    label: binding.name.clone(),
+                    kind: SymbolKind::Local,
+                    detail: binding.name.clone(),
+                });
+            }
         }
     }
 
@@ -316,7 +396,11 @@ pub fn document_symbols(source: &str) -> Result<Vec<DocumentSymbolInfo>, Compile
 
     for item in &checked.program.items {
         match item {
-            TopLevelDecl::Use(_) => {}
+            TopLevelDecl::Use(..)
+            | TopLevelDecl::Impl(_)
+            | TopLevelDecl::Trait(_)
+            | TopLevelDecl::Const(_)
+            | TopLevelDecl::Static(_) => {}
             TopLevelDecl::Function(function) => {
                 if let Some(symbol) = index.functions.get(&function.name) {
                     let mut children = Vec::new();
@@ -331,12 +415,12 @@ pub fn document_symbols(source: &str) -> Result<Vec<DocumentSymbolInfo>, Compile
                             });
                         }
                     }
-                    for (name, span) in &symbol.locals {
+                    for binding in &symbol.locals {
                         children.push(DocumentSymbolInfo {
-                            name: name.clone(),
+                            name: binding.name.clone(),
                             kind: SymbolKind::Local,
-                            span: span.clone(),
-                            selection_span: span.clone(),
+                            span: binding.span.clone(),
+                            selection_span: binding.span.clone(),
                             children: Vec::new(),
                         });
                     }
@@ -386,15 +470,21 @@ impl SymbolIndex {
 
         for item in &program.items {
             match item {
-                TopLevelDecl::Use(_) => {}
+                TopLevelDecl::Use(..) => {}
                 TopLevelDecl::Function(function) => {
                     function_signatures.insert(function.name.clone(), format_function_signature(
                         &function.name,
+                        &function.type_params,
                         &function.params,
                         &function.return_type,
                     ));
                 }
-                TopLevelDecl::Struct(_) | TopLevelDecl::Enum(_) => {}
+                TopLevelDecl::Struct(_)
+                | TopLevelDecl::Enum(_)
+                | TopLevelDecl::Impl(_)
+                | TopLevelDecl::Trait(_)
+                | TopLevelDecl::Const(_)
+                | TopLevelDecl::Static(_) => {}
             }
         }
 
@@ -526,9 +616,9 @@ fn repair_source_for_completion(
             repaired.insert_str(offset, "len()");
             Ok(repaired)
         }
-        _ => Err(CompileError::Parse(ParseError::ExpectedExpression {
+        _ => Err(CompileError::Parse(vec![ParseError::ExpectedExpression {
             span: Span { start: offset, end: offset },
-        })),
+        }])),
     }
 }
 
@@ -597,16 +687,8 @@ fn complete_methods(source: &str, offset: usize) -> Result<Option<Vec<Completion
         Some(TokenKind::Ident(name)) => name,
         _ => return Ok(None),
     };
-    let function_name = match index.function_name_for_offset(offset) {
-        Some(name) => name,
-        None => return Ok(None),
-    };
-    let function = match checked.typed.function_infos.get(function_name) {
-        Some(function) => function,
-        None => return Ok(None),
-    };
-    let (_, receiver_ty) = match function.local_map.get(&receiver_name) {
-        Some(info) => info,
+    let receiver_ty = match resolve_variable(&index, &checked.typed, &receiver_name, offset) {
+        Some((_, _, ty)) => ty,
         None => return Ok(None),
     };
 
@@ -660,28 +742,116 @@ fn scan_params(tokens: &[Token], fn_idx: usize, body_start_idx: usize) -> HashMa
     params
 }
 
-fn scan_locals(tokens: &[Token], body_start_idx: usize, body_end_idx: usize) -> HashMap<String, Span> {
-    let mut locals = HashMap::new();
+/// Resolves a variable use to `(kind, definition span, type)`: locals shadow parameters.
+fn resolve_variable(
+    index: &SymbolIndex,
+    typed: &TypedProgram,
+    name: &str,
+    offset: usize,
+) -> Option<(SymbolKind, Span, Type)> {
+    let function_name = index.function_name_for_offset(offset)?;
+    let symbol = index.functions.get(function_name)?;
+    let function = typed.function_infos.get(function_name)?;
+
+    if let Some((slot, binding)) = symbol.binding_at(name, offset) {
+        let (_, ty) = function.locals.get(slot)?;
+        return Some((SymbolKind::Local, binding.span.clone(), ty.clone()));
+    }
+
+    let param = function.params.iter().find(|param| param.name == name)?;
+    let span = symbol.params.get(name)?.clone();
+    Some((SymbolKind::Parameter, span, param.ty.clone()))
+}
+
+fn scan_locals(tokens: &[Token], body_start_idx: usize, body_end_idx: usize) -> Vec<LocalBinding> {
+    let mut locals = Vec::new();
+    let mut open_blocks = Vec::new();
 
     for token_idx in body_start_idx..body_end_idx {
-        if tokens[token_idx].kind == TokenKind::Let
-            && let Some(token) = tokens.get(token_idx + 1)
-            && let TokenKind::Ident(name) = &token.kind
-        {
-            locals.insert(name.clone(), token.span.clone());
+        match tokens[token_idx].kind {
+            TokenKind::LBrace => open_blocks.push(token_idx),
+            TokenKind::RBrace => {
+                open_blocks.pop();
+            }
+            TokenKind::Let | TokenKind::For => {
+                let Some(token) = tokens.get(token_idx + 1) else {
+                    continue;
+                };
+                let TokenKind::Ident(name) = &token.kind else {
+                    continue;
+                };
+                let scope = if tokens[token_idx].kind == TokenKind::Let {
+                    // Visible after the statement's `;`, until the enclosing block closes.
+                    let block_end = open_blocks
+                        .last()
+                        .and_then(|open| find_matching_brace(tokens, *open));
+                    find_statement_end(tokens, token_idx, body_end_idx).zip(block_end)
+                } else {
+                    // The loop variable is visible inside the loop body only.
+                    tokens[token_idx..body_end_idx]
+                        .iter()
+                        .position(|token| token.kind == TokenKind::LBrace)
+                        .map(|rel| token_idx + rel)
+                        .and_then(|open| Some((open, find_matching_brace(tokens, open)?)))
+                };
+                let Some((from_idx, until_idx)) = scope else {
+                    continue;
+                };
+                locals.push(LocalBinding {
+                    name: name.clone(),
+                    span: token.span.clone(),
+                    visible_from: tokens[from_idx].span.end,
+                    visible_until: tokens[until_idx].span.start,
+                });
+            }
+            _ => {}
         }
     }
 
     locals
 }
 
-fn format_function_signature(name: &str, params: &[Param], return_type: &Type) -> String {
+/// Index of the `;` that ends the statement starting at `start_idx` (skipping nested braces).
+fn find_statement_end(tokens: &[Token], start_idx: usize, end_idx: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (idx, token) in tokens.iter().enumerate().take(end_idx).skip(start_idx) {
+        match token.kind {
+            TokenKind::LBrace => depth += 1,
+            TokenKind::RBrace => depth = depth.saturating_sub(1),
+            TokenKind::Semicolon if depth == 0 => return Some(idx),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn format_function_signature(
+    name: &str,
+    type_params: &[String],
+    params: &[Param],
+    return_type: &Type,
+) -> String {
     let params = params
         .iter()
         .map(|param| format!("{}: {}", param.name, param.ty))
         .collect::<Vec<_>>()
         .join(", ");
-    format!("fn {}({}) -> {}", name, params, return_type)
+    format!(
+        "fn {}{}({}) -> {}",
+        name,
+        format_type_params(type_params),
+        params,
+        return_type
+    )
+}
+
+/// `<A, B>` for generic items, empty otherwise.
+fn format_type_params(type_params: &[String]) -> String {
+    if type_params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", type_params.join(", "))
+    }
 }
 
 fn format_struct_decl(def: &StructDecl) -> String {
@@ -691,7 +861,12 @@ fn format_struct_decl(def: &StructDecl) -> String {
         .map(|(name, ty)| format!("{}: {}", name, ty))
         .collect::<Vec<_>>()
         .join(", ");
-    format!("struct {} {{ {} }}", def.name, fields)
+    format!(
+        "struct {}{} {{ {} }}",
+        def.name,
+        format_type_params(&def.type_params),
+        fields
+    )
 }
 
 fn format_enum_decl(def: &EnumDecl) -> String {
@@ -701,7 +876,12 @@ fn format_enum_decl(def: &EnumDecl) -> String {
         .map(format_enum_variant)
         .collect::<Vec<_>>()
         .join(", ");
-    format!("enum {} {{ {} }}", def.name, variants)
+    format!(
+        "enum {}{} {{ {} }}",
+        def.name,
+        format_type_params(&def.type_params),
+        variants
+    )
 }
 
 fn format_enum_variant(variant: &EnumVariant) -> String {
@@ -735,7 +915,8 @@ fn lex_error_span(error: &LexError) -> Span {
     match error {
         LexError::UnexpectedChar { span, .. }
         | LexError::InvalidNumber { span }
-        | LexError::UnterminatedString { span } => span.clone(),
+        | LexError::UnterminatedString { span }
+        | LexError::InvalidInterpolation { span } => span.clone(),
     }
 }
 
@@ -749,8 +930,8 @@ fn parse_error_span(error: &ParseError) -> Span {
 #[cfg(test)]
 mod tests {
     use super::{
-        SymbolKind, analyze_diagnostic, check, completions_at, definition_at, document_symbols,
-        symbol_at,
+        Severity, SymbolKind, analyze_diagnostic, analyze_diagnostics, check, completions_at,
+        definition_at, document_symbols, symbol_at,
     };
 
     #[test]
@@ -762,6 +943,45 @@ mod tests {
         assert_eq!(symbol.detail, "y: Int");
     }
 
+    #[test]
+    fn hover_and_definition_follow_shadowing_and_block_scopes() {
+        let src = "fn main(x: Int) -> Int { let x = 1.5; if true { let x = true; let a = x; } for x in 0..3 { let b = x; } let c = x; return 0; }";
+
+        let in_if = src.find("a = x").expect("missing a = x") + 4;
+        let symbol = symbol_at(src, in_if).expect("analysis failed").expect("missing symbol");
+        assert_eq!(symbol.detail, "x: Bool");
+        let definition = definition_at(src, in_if)
+            .expect("analysis failed")
+            .expect("missing definition");
+        assert_eq!(definition.target_span.start, src.find("let x = true").expect("missing let") + 4);
+
+        let in_loop = src.find("b = x").expect("missing b = x") + 4;
+        let symbol = symbol_at(src, in_loop).expect("analysis failed").expect("missing symbol");
+        assert_eq!(symbol.detail, "x: Int");
+        assert_eq!(symbol.kind, SymbolKind::Local);
+
+        let after = src.find("c = x").expect("missing c = x") + 4;
+        let symbol = symbol_at(src, after).expect("analysis failed").expect("missing symbol");
+        assert_eq!(symbol.detail, "x: Float");
+
+        let initializer = src.find("1.5").expect("missing initializer");
+        let completions = completions_at(src, initializer).expect("analysis failed");
+        assert!(completions.iter().any(|item| item.label == "x" && item.kind == SymbolKind::Parameter));
+        assert!(!completions.iter().any(|item| item.label == "a"));
+    }
+
+    #[test]
+    fn hover_shows_type_parameters() {
+        let src = "enum Maybe<T> { Just(T), Nothing } fn first<T>(xs: List<T>) -> Maybe<T> { return Maybe::Just(xs[0]); } fn main() -> Int { let m = first([1]); return 0; }";
+        let offset = src.find("first([").expect("missing call");
+        let symbol = symbol_at(src, offset).expect("analysis failed").expect("missing symbol");
+        assert_eq!(symbol.detail, "fn first<T>(xs: List<T>) -> Maybe<T>");
+
+        let offset = src.find("m = ").expect("missing local");
+        let symbol = symbol_at(src, offset).expect("analysis failed").expect("missing symbol");
+        assert_eq!(symbol.detail, "m: Maybe<Int>");
+    }
+
     #[test]
     fn hover_marks_parameters_separately() {
         let src = "fn main(x: Int) -> Int { return x; }";
@@ -787,6 +1007,30 @@ mod tests {
         assert!(diagnostic.span.is_some());
     }
 
+    #[test]
+    fn diagnostics_report_every_parse_error() {
+        let diagnostics = analyze_diagnostics("fn a() -> Int { let x = ; return 1 +; }");
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.span.is_some()));
+    }
+
+    #[test]
+    fn diagnostic_reports_type_error_span() {
+        let src = "fn main() -> Int { return missing; }";
+        let diagnostic = analyze_diagnostic(src).expect("expected diagnostic");
+        assert_eq!(diagnostic.code, "E-TC05");
+        let span = diagnostic.span.expect("type error without span");
+        assert_eq!(&src[span.start..span.end], "missing");
+    }
+
+    #[test]
+    fn diagnostics_report_lint_warnings_of_valid_programs() {
+        let diagnostics = analyze_diagnostics("fn main() -> Int { let x = 1; return 0; }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].code, "W-LN01");
+    }
+
     #[test]
     fn hover_formats_structs() {
         let src = "struct Point { x: Int, y: Int } fn main() -> Int { return 0; }";
@@ -798,11 +1042,11 @@ mod tests {
 
     #[test]
     fn hover_formats_enums() {
-        let src = "enum Result { Ok, Err(Int) } fn main() -> Int { return 0; }";
-        let offset = src.find("Result").expect("missing Result");
+        let src = "enum Outcome { Ok, Err(Int) } fn main() -> Int { return 0; }";
+        let offset = src.find("Outcome").expect("missing Outcome");
         let symbol = symbol_at(src, offset).expect("analysis failed").expect("missing symbol");
         assert_eq!(symbol.kind, SymbolKind::Enum);
-        assert_eq!(symbol.detail, "enum Result { Ok, Err(Int) }");
+        assert_eq!(symbol.detail, "enum Outcome { Ok, Err(Int) }");
     }
 
     #[test]
@@ -841,7 +1085,7 @@ mod tests {
 
     #[test]
     fn completions_include_enum_variants_after_double_colon() {
-        let src = "enum Result { Ok, Err(Int) } fn main() -> Int { let v = Result::; return 0; }";
+        let src = "enum Outcome { Ok, Err(Int) } fn main() -> Int { let v = Outcome::; return 0; }";
         let offset = src.find("::").expect("missing ::") + 2;
         let completions = completions_at(src, offset).expect("analysis failed");
         assert!(completions.iter().any(|item| item.label == "Ok"));
@@ -1,16 +1,26 @@
+use std::collections::HashMap;
+
+use crate::token::Span;
+
 /// Type nodes in the language AST.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     /// Signed 64-bit integer.
     Int,
+    /// Signed 32-bit integer (wasm `i32`, wrapping arithmetic).
+    Int32,
+    /// Unsigned 8-bit integer, also spelled `Byte` (wasm `i32` kept in `0..=255`).
+    UInt8,
     /// 64-bit floating-point number.
     Float,
     /// Boolean value.
     Bool,
     /// UTF-8 string value.
     String,
-    /// Named type (struct/enum/reference aliases).
+    /// Named type (struct/enum/reference aliases, type parameters inside generic items).
     Named(String),
+    /// Generic struct/enum applied to type arguments, e.g. `Pair<Int, Bool>`.
+    Generic(String, Vec<Type>),
     /// Reference type, explicit via `&T`.
     Ref(Box<Type>),
     /// Homogeneous list type, implemented via `List<T>`.
@@ -21,6 +31,8 @@ pub enum Type {
     Function(Vec<Type>, Box<Type>),
     /// Empty tuple-like return type.
     Unit,
+    /// Inference variable; only exists while the type checker works on a function body.
+    Var(u32),
 }
 
 /// Function parameters.
@@ -28,11 +40,20 @@ pub enum Type {
 pub struct Param {
     pub name: String,
     pub ty: Type,
+    /// Span of the parameter name.
+    pub span: Span,
+}
+
+/// Expression with the source span it was parsed from.
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
 }
 
 /// Expression AST nodes.
 #[derive(Debug, Clone)]
-pub enum Expr {
+pub enum ExprKind {
     /// Integer literal.
     Int(i64),
     /// Floating-point literal.
@@ -47,13 +68,19 @@ pub enum Expr {
     Ref(Box<Expr>),
     /// Unary dereference operator (`*expr`).
     Deref(Box<Expr>),
+    /// Logical negation (`!expr`).
+    Not(Box<Expr>),
+    /// Arithmetic negation (`-expr`).
+    Neg(Box<Expr>),
+    /// Error propagation (`expr?`) on `Option`/`Result` values.
+    Try(Box<Expr>),
     /// Binary operation expression.
     Binary {
         op: BinOp,
         left: Box<Expr>,
         right: Box<Expr>,
     },
-    /// Function call expression.
+    /// Call of a function by name, or of a local holding a function value.
     Call {
         name: String,
         args: Vec<Expr>,
@@ -78,6 +105,16 @@ pub enum Expr {
     ListLiteral(Vec<Expr>),
     /// Tuple construction (`(1, true, 3)`).
     TupleLiteral(Vec<Expr>),
+    /// Struct field access (`expr.field`).
+    Field {
+        base: Box<Expr>,
+        name: String,
+    },
+    /// Half-open integer range (`start..end`), used as a `for` loop iterable.
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+    },
     /// Indexed access (`expr[index]`).
     Index {
         base: Box<Expr>,
@@ -89,6 +126,34 @@ pub enum Expr {
         name: String,
         args: Vec<Expr>,
     },
+    /// Numeric conversion (`x as Float`).
+    Cast {
+        expr: Box<Expr>,
+        ty: Type,
+    },
+    /// Anonymous function (`|x, y: Int| x + y`) capturing the locals it uses by value.
+    Closure {
+        params: Vec<ClosureParam>,
+        body: Box<Expr>,
+    },
+    /// Interpolated string literal (`"x = {x}"`), concatenated from its parts at runtime.
+    Interpolation(Vec<InterpolationPart>),
+}
+
+/// Piece of an interpolated string; expressions of any non-String type are rendered.
+#[derive(Debug, Clone)]
+pub enum InterpolationPart {
+    Text(String),
+    Expr(Expr),
+}
+
+/// Closure parameter; without annotation its type is inferred from the uses.
+#[derive(Debug, Clone)]
+pub struct ClosureParam {
+    pub name: String,
+    pub ty: Option<Type>,
+    /// Span of the parameter name.
+    pub span: Span,
 }
 
 /// Match arm.
@@ -96,6 +161,8 @@ pub enum Expr {
 pub struct MatchArm {
     pub pattern: Pattern,
     pub body: Expr,
+    /// Span of the pattern.
+    pub span: Span,
 }
 
 /// Pattern used in match arms.
@@ -104,9 +171,17 @@ pub enum Pattern {
     /// `A`
     UnitVariant(String),
     /// `A(x)`
-    TupleVariant(String, Vec<String>),
+    TupleVariant(String, Vec<PatternBinding>),
     /// `A { x, y }`
-    StructVariant(String, Vec<String>),
+    StructVariant(String, Vec<PatternBinding>),
+}
+
+/// Name bound by a pattern.
+#[derive(Debug, Clone)]
+pub struct PatternBinding {
+    pub name: String,
+    /// Span of the name.
+    pub span: Span,
 }
 
 /// Binary operators.
@@ -116,20 +191,35 @@ pub enum BinOp {
     Sub,
     Mul,
     Div,
+    /// Remainder (`%`), truncating like Rust.
+    Rem,
     Eq,
     NotEq,
     Lt,
     Le,
     Gt,
     Ge,
+    /// Short-circuiting `&&`.
+    And,
+    /// Short-circuiting `||`.
+    Or,
+}
+
+/// Statement with the source span it was parsed from.
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
 }
 
 /// Statements in function bodies.
 #[derive(Debug, Clone)]
-pub enum Stmt {
+pub enum StmtKind {
     /// Local declaration with optional annotation and initializer.
     Let {
         name: String,
+        /// Span of the bound name.
+        name_span: Span,
         ty: Option<Type>,
         value: Expr,
     },
@@ -137,6 +227,14 @@ pub enum Stmt {
     Return(Option<Expr>),
     /// Standalone expression statement.
     Expr(Expr),
+    /// Assignment to a local, struct field or list/tuple element (`x = e;`, `p.x += e;`).
+    ///
+    /// `op` is set for compound assignments and holds the arithmetic operator.
+    Assign {
+        target: Expr,
+        op: Option<BinOp>,
+        value: Expr,
+    },
     /// Conditional branch.
     If {
         cond: Expr,
@@ -148,20 +246,38 @@ pub enum Stmt {
         cond: Expr,
         body: Vec<Stmt>,
     },
+    /// `for var in iter { ... }` over a range or a list.
+    For {
+        var: String,
+        /// Span of the loop variable.
+        var_span: Span,
+        iter: Expr,
+        body: Vec<Stmt>,
+    },
+    /// Leaves the innermost loop.
+    Break,
+    /// Jumps to the next iteration of the innermost loop.
+    Continue,
 }
 
 /// Struct declaration.
 #[derive(Debug, Clone)]
 pub struct StructDecl {
     pub name: String,
+    /// Type parameter names (`struct Pair<A, B>`), empty for plain structs.
+    pub type_params: Vec<String>,
     pub fields: Vec<(String, Type)>,
+    pub span: Span,
 }
 
 /// Enum declaration.
 #[derive(Debug, Clone)]
 pub struct EnumDecl {
     pub name: String,
+    /// Type parameter names (`enum Option<T>`), empty for plain enums.
+    pub type_params: Vec<String>,
     pub variants: Vec<EnumVariant>,
+    pub span: Span,
 }
 
 /// Enum variants.
@@ -180,21 +296,81 @@ pub enum EnumVariant {
 pub struct FunctionDecl {
     /// Function name.
     pub name: String,
+    /// Type parameter names (`fn first<T>`), empty for plain functions.
+    pub type_params: Vec<String>,
     /// Function parameter names and types.
     pub params: Vec<Param>,
     /// Function body statements.
     pub body: Vec<Stmt>,
     /// Function return type annotation.
     pub return_type: Type,
+    /// Trait bounds on type parameters as `(parameter, trait)` pairs (`fn show<T: Show>`).
+    pub bounds: Vec<(String, String)>,
+    /// Span of the function name.
+    pub span: Span,
+}
+
+/// `impl` block, optionally implementing a trait (`impl Show for Point`).
+#[derive(Debug, Clone)]
+pub struct ImplDecl {
+    /// Implemented trait, `None` for inherent impls.
+    pub trait_name: Option<String>,
+    /// Type parameter names of the impl (`impl<T> Pair<T>`).
+    pub type_params: Vec<String>,
+    /// Type the methods belong to.
+    pub target: Type,
+    /// Methods, already named `Type::method` and with `self` as an ordinary first parameter.
+    pub methods: Vec<FunctionDecl>,
+    pub span: Span,
+}
+
+/// Trait declaration listing method signatures (`trait Show { fn show(&self) -> String; }`).
+#[derive(Debug, Clone)]
+pub struct TraitDecl {
+    pub name: String,
+    pub methods: Vec<TraitMethod>,
+    pub span: Span,
+}
+
+/// Method signature inside a trait; `Self` stays `Type::Named("Self")`.
+#[derive(Debug, Clone)]
+pub struct TraitMethod {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub return_type: Type,
+    pub span: Span,
+}
+
+/// `const NAME: Type = value;`, evaluated at compile time and inlined at every use.
+#[derive(Debug, Clone)]
+pub struct ConstDecl {
+    pub name: String,
+    pub ty: Type,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// `static mut NAME: Type = value;`, a global variable with a constant initial value.
+#[derive(Debug, Clone)]
+pub struct StaticDecl {
+    pub name: String,
+    pub ty: Type,
+    pub value: Expr,
+    pub span: Span,
 }
 
 /// Top-level AST items.
 #[derive(Debug, Clone)]
 pub enum TopLevelDecl {
-    Use(String),
+    /// `use "path";` with the span of the whole declaration.
+    Use(String, Span),
     Struct(StructDecl),
     Enum(EnumDecl),
     Function(FunctionDecl),
+    Impl(ImplDecl),
+    Trait(TraitDecl),
+    Const(ConstDecl),
+    Static(StaticDecl),
 }
 
 /// Root AST node for a whole source file.
@@ -203,15 +379,252 @@ pub struct Program {
     pub items: Vec<TopLevelDecl>,
 }
 
+impl Program {
+    /// Free functions and impl methods in item order.
+    pub fn functions(&self) -> impl Iterator<Item = &FunctionDecl> {
+        self.items.iter().flat_map(|item| match item {
+            TopLevelDecl::Function(func) => std::slice::from_ref(func),
+            TopLevelDecl::Impl(imp) => imp.methods.as_slice(),
+            _ => &[],
+        })
+    }
+}
+
+/// Function name a method is registered under, e.g. `Point::norm`.
+pub fn method_function_name(type_name: &str, method: &str) -> String {
+    format!("{}::{}", type_name, method)
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+
+    /// Value of an integer literal, including a negated one (`-5`).
+    pub fn int_literal(&self) -> Option<i64> {
+        match &self.kind {
+            ExprKind::Int(value) => Some(*value),
+            ExprKind::Neg(inner) => match &inner.kind {
+                ExprKind::Int(value) => value.checked_neg(),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether this is integer arithmetic on literals only (`1`, `2 * 3`, `-(4 - 1)`): like a
+    /// single literal, it takes its integer type from the context.
+    pub fn is_untyped_int(&self) -> bool {
+        match &self.kind {
+            ExprKind::Int(_) => true,
+            ExprKind::Neg(inner) => inner.is_untyped_int(),
+            ExprKind::Binary {
+                op: BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem,
+                left,
+                right,
+            } => left.is_untyped_int() && right.is_untyped_int(),
+            _ => false,
+        }
+    }
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Stmt { kind, span }
+    }
+}
+
+impl TopLevelDecl {
+    /// Span of the item's name (or the whole `use` declaration).
+    pub fn span(&self) -> &Span {
+        match self {
+            TopLevelDecl::Use(_, span) => span,
+            TopLevelDecl::Struct(decl) => &decl.span,
+            TopLevelDecl::Enum(decl) => &decl.span,
+            TopLevelDecl::Function(decl) => &decl.span,
+            TopLevelDecl::Impl(decl) => &decl.span,
+            TopLevelDecl::Trait(decl) => &decl.span,
+            TopLevelDecl::Const(decl) => &decl.span,
+            TopLevelDecl::Static(decl) => &decl.span,
+        }
+    }
+}
+
+impl Pattern {
+    /// Name of the enum variant this pattern matches.
+    pub fn variant_name(&self) -> String {
+        match self {
+            Pattern::UnitVariant(v) => v.clone(),
+            Pattern::TupleVariant(v, _) => v.clone(),
+            Pattern::StructVariant(v, _) => v.clone(),
+        }
+    }
+}
+
+impl EnumVariant {
+    /// Variant name without payload.
+    pub fn name(&self) -> String {
+        match self {
+            EnumVariant::Unit(name) => name.clone(),
+            EnumVariant::Tuple(name, _) => name.clone(),
+            EnumVariant::Struct(name, _) => name.clone(),
+        }
+    }
+}
+
+impl Type {
+    /// Replaces type parameters by the types bound to them in `bindings`.
+    pub fn substitute(&self, bindings: &HashMap<String, Type>) -> Type {
+        match self {
+            Type::Named(name) => bindings.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Type::Generic(name, args) => Type::Generic(
+                name.clone(),
+                args.iter().map(|arg| arg.substitute(bindings)).collect(),
+            ),
+            Type::Ref(inner) => Type::Ref(Box::new(inner.substitute(bindings))),
+            Type::List(inner) => Type::List(Box::new(inner.substitute(bindings))),
+            Type::Tuple(elements) => {
+                Type::Tuple(elements.iter().map(|elem| elem.substitute(bindings)).collect())
+            }
+            Type::Function(params, ret) => Type::Function(
+                params.iter().map(|param| param.substitute(bindings)).collect(),
+                Box::new(ret.substitute(bindings)),
+            ),
+            Type::Int
+            | Type::Int32
+            | Type::UInt8
+            | Type::Float
+            | Type::Bool
+            | Type::String
+            | Type::Unit
+            | Type::Var(_) => {
+                self.clone()
+            }
+        }
+    }
+
+    /// Name the methods of this type are registered under (`Int`, `Point`, `Pair` for
+    /// `Pair<Int>`); `None` for types that cannot have impls.
+    pub fn impl_name(&self) -> Option<&str> {
+        match self {
+            Type::Int => Some("Int"),
+            Type::Int32 => Some("Int32"),
+            Type::UInt8 => Some("UInt8"),
+            Type::Float => Some("Float"),
+            Type::Bool => Some("Bool"),
+            Type::String => Some("String"),
+            Type::Named(name) | Type::Generic(name, _) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Whether this is one of the integer types `Int`, `Int32` and `UInt8`.
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Type::Int | Type::Int32 | Type::UInt8)
+    }
+
+    /// Whether this is an integer type or `Float`.
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || *self == Type::Float
+    }
+
+    /// Whether `name` occurs anywhere in this type.
+    pub fn mentions(&self, name: &str) -> bool {
+        match self {
+            Type::Named(found) => found == name,
+            Type::Generic(_, args) | Type::Tuple(args) => args.iter().any(|arg| arg.mentions(name)),
+            Type::Ref(inner) | Type::List(inner) => inner.mentions(name),
+            Type::Function(params, ret) => {
+                params.iter().any(|param| param.mentions(name)) || ret.mentions(name)
+            }
+            Type::Int
+            | Type::Int32
+            | Type::UInt8
+            | Type::Float
+            | Type::Bool
+            | Type::String
+            | Type::Unit
+            | Type::Var(_) => {
+                false
+            }
+        }
+    }
+}
+
+/// Binds each type parameter to the argument at the same position.
+pub fn type_bindings(params: &[String], args: &[Type]) -> HashMap<String, Type> {
+    params.iter().cloned().zip(args.iter().cloned()).collect()
+}
+
+impl StructDecl {
+    /// Field list with the type parameters replaced by `args`.
+    pub fn instantiate(&self, args: &[Type]) -> StructDecl {
+        let bindings = type_bindings(&self.type_params, args);
+        StructDecl {
+            name: self.name.clone(),
+            span: self.span.clone(),
+            type_params: Vec::new(),
+            fields: self
+                .fields
+                .iter()
+                .map(|(name, ty)| (name.clone(), ty.substitute(&bindings)))
+                .collect(),
+        }
+    }
+}
+
+impl EnumDecl {
+    /// Variant list with the type parameters replaced by `args`.
+    pub fn instantiate(&self, args: &[Type]) -> EnumDecl {
+        let bindings = type_bindings(&self.type_params, args);
+        EnumDecl {
+            name: self.name.clone(),
+            span: self.span.clone(),
+            type_params: Vec::new(),
+            variants: self
+                .variants
+                .iter()
+                .map(|variant| match variant {
+                    EnumVariant::Unit(name) => EnumVariant::Unit(name.clone()),
+                    EnumVariant::Tuple(name, ty) => {
+                        EnumVariant::Tuple(name.clone(), ty.substitute(&bindings))
+                    }
+                    EnumVariant::Struct(name, fields) => EnumVariant::Struct(
+                        name.clone(),
+                        fields
+                            .iter()
+                            .map(|(field, ty)| (field.clone(), ty.substitute(&bindings)))
+                            .collect(),
+                    ),
+                })
+                .collect(),
+        }
+    }
+}
+
 impl std::fmt::Display for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Type::Int => write!(f, "Int"),
+            Type::Int32 => write!(f, "Int32"),
+            Type::UInt8 => write!(f, "UInt8"),
             Type::Float => write!(f, "Float"),
             Type::Bool => write!(f, "Bool"),
             Type::String => write!(f, "String"),
             Type::Named(name) => write!(f, "{}", name),
+            Type::Generic(name, args) => {
+                write!(f, "{}<", name)?;
+                for (idx, arg) in args.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ">")
+            }
             Type::Ref(inner) => write!(f, "&{}", inner),
+            // A part the type checker has not inferred yet, e.g. the element of `[]`.
+            Type::Var(_) => write!(f, "{{unknown}}"),
             Type::List(inner) => write!(f, "List<{}>", inner),
             Type::Tuple(elements) => {
                 write!(f, "(")?;
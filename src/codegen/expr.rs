@@ -1,9 +1,23 @@
 use std::collections::HashMap;
 
-use crate::ast::{BinOp, Expr, Type};
+use crate::ast::{
+    BinOp, ClosureParam, EnumDecl, EnumVariant, Expr, ExprKind, InterpolationPart, MatchArm, Pattern, StructDecl, Type,
+    method_function_name, type_bindings,
+};
 use crate::codegen::ir::IrInstruction;
-use crate::codegen::module::{CodegenError, FuncGen, FunctionSig};
-use crate::runtime::{string_eq_import_name, string_literal_import_name};
+use crate::codegen::module::{
+    ClosureDef, CodegenError, FuncGen, FunctionSig, wasm_signature, wasm_val_type_for,
+};
+use crate::prelude::{OPTION, option_of, try_payload_type};
+use crate::typing::{applied_type, generic_self_type, method_call, unify};
+use crate::runtime::{
+    AGGREGATE_LEN_IMPORT, ENUM_NEW_IMPORT, ENUM_TAG_IMPORT, LIST_NEW_IMPORT, OVERFLOW_IMPORT,
+    RuntimeLane, STRUCT_NEW_IMPORT, TUPLE_NEW_IMPORT, aggregate_get_import_name, aggregate_pop_import_name,
+    aggregate_push_import_name, box_import_name, string_cmp_import_name,
+    string_concat_import_name, string_eq_import_name, string_literal_import_name,
+    to_string_import_name, unbox_import_name,
+};
+use wasm_encoder::{BlockType, ValType};
 
 /// Emits instructions for an expression and returns whether it leaves a stack value.
 pub fn emit_expr(
@@ -11,165 +25,1555 @@ pub fn emit_expr(
     cg: &mut FuncGen,
     funcs: &HashMap<String, FunctionSig>,
 ) -> Result<bool, CodegenError> {
-    match expr {
-        Expr::Int(v) => {
+    if let Some(call) = lower_method_syntax(expr, cg, funcs)? {
+        return emit_expr(&call, cg, funcs);
+    }
+    match &expr.kind {
+        ExprKind::Int(v) => {
             cg.instructions.push(IrInstruction::I64Const(*v));
             Ok(true)
         }
-        Expr::Float(v) => {
+        ExprKind::Float(v) => {
             cg.instructions.push(IrInstruction::F64Const(*v));
             Ok(true)
         }
-        Expr::Bool(v) => {
+        ExprKind::Bool(v) => {
             let bit = if *v { 1 } else { 0 };
             cg.instructions.push(IrInstruction::I32Const(bit));
             Ok(true)
         }
-        Expr::String(value) => {
+        ExprKind::String(value) => {
             let name = string_literal_import_name(value);
-            let (idx, _, _) = funcs
-                .get(&name)
-                .cloned()
-                .ok_or_else(|| CodegenError::UnknownFunction { name })?;
-            cg.instructions.push(IrInstruction::Call(idx));
+            call_runtime(cg, funcs, &name)?;
             Ok(true)
         }
-        Expr::ListLiteral(_) => {
-            Err(CodegenError::UnsupportedType("list literal lowering".to_string()))
+        ExprKind::Interpolation(parts) => emit_interpolation(parts, cg, funcs),
+        ExprKind::ListLiteral(elements) => {
+            let element_ty = list_literal_element_type(elements, cg, funcs)?;
+            emit_list_literal(elements, &element_ty, cg, funcs)
         }
-        Expr::TupleLiteral(_) => {
-            Err(CodegenError::UnsupportedType("tuple literal lowering".to_string()))
+        ExprKind::TupleLiteral(elements) => {
+            let element_tys = elements
+                .iter()
+                .map(|element| infer_expr_type(element, cg, funcs))
+                .collect::<Result<Vec<_>, _>>()?;
+            emit_tuple_literal(elements, &element_tys, cg, funcs)
         }
-        Expr::Index { .. } => {
-            Err(CodegenError::UnsupportedType("index expression lowering".to_string()))
+        ExprKind::Range { .. } => Err(CodegenError::UnsupportedType(
+            "range outside of a `for` loop".to_string(),
+        )),
+        ExprKind::Index { base, index } => {
+            let element_ty = index_element_type(base, index, cg, funcs)?;
+            let lane = lane_for(&element_ty)?;
+            let _ = emit_expr(base, cg, funcs)?;
+            let _ = emit_expr(index, cg, funcs)?;
+            call_runtime(cg, funcs, &aggregate_get_import_name(lane))?;
+            Ok(true)
         }
-        Expr::MethodCall { .. } => {
-            Err(CodegenError::UnsupportedType(
-                "method call lowering".to_string(),
-            ))
+        ExprKind::MethodCall {
+            receiver,
+            name,
+            args,
+        } => emit_list_method(receiver, name, args, cg, funcs),
+        ExprKind::Local(name) => {
+            if let Some((idx, _)) = cg.local_map.get(name) {
+                cg.instructions.push(IrInstruction::LocalGet(*idx));
+                return Ok(true);
+            }
+            if let Some(item) = cg.consts.get(name).cloned() {
+                let literal = item.value.to_expr(expr.span.clone());
+                return emit_expr_expected(&literal, &item.ty, cg, funcs);
+            }
+            if let Some((idx, _)) = cg.globals.get(name) {
+                cg.instructions.push(IrInstruction::GlobalGet(*idx));
+                return Ok(true);
+            }
+            if !funcs.contains_key(name) {
+                return Err(CodegenError::UnknownLocal { name: name.clone() });
+            }
+            // A named function used as a value: a closure without captures around its adapter.
+            let slot = cg.instances.request_function_ref(name);
+            emit_function_value(slot, &[], cg, funcs)
         }
-        Expr::Local(name) => {
-            let idx = cg
-                .local_map
-                .get(name)
-                .ok_or_else(|| CodegenError::UnknownLocal { name: name.clone() })?;
-            cg.instructions.push(IrInstruction::LocalGet(idx.0));
+        ExprKind::Ref(inner) => {
+            // Heap values already are handles; scalars get boxed into the runtime heap.
+            let inner_ty = infer_expr_type(inner, cg, funcs)?;
+            let _ = emit_expr(inner, cg, funcs)?;
+            if let Some(lane) = RuntimeLane::for_type(&inner_ty).filter(|lane| lane.is_scalar()) {
+                call_runtime(cg, funcs, &box_import_name(lane))?;
+            }
             Ok(true)
         }
-        Expr::Ref(_inner) => {
-            Err(CodegenError::UnsupportedType("&expr lowering".to_string()))
-        }
-        Expr::Deref(_inner) => {
-            Err(CodegenError::UnsupportedType("*expr lowering".to_string()))
-        }
-            Expr::Binary { op, left, right } => {
-            let left_ty = infer_expr_type(left, cg, funcs)?;
-            let right_ty = infer_expr_type(right, cg, funcs)?;
-            if left_ty != right_ty {
-                return Err(CodegenError::UnsupportedType(format!(
-                    "binary type mismatch: expected {}, found {}",
-                    left_ty, right_ty
-                )));
-            }
-
-            let _ = emit_expr(left, cg, funcs)?;
-            let _ = emit_expr(right, cg, funcs)?;
-
-            match (op, &left_ty) {
-                (BinOp::Add, Type::Int) => cg.instructions.push(IrInstruction::I64Add),
-                (BinOp::Sub, Type::Int) => cg.instructions.push(IrInstruction::I64Sub),
-                (BinOp::Mul, Type::Int) => cg.instructions.push(IrInstruction::I64Mul),
-                (BinOp::Div, Type::Int) => cg.instructions.push(IrInstruction::I64DivS),
-                (BinOp::Add, Type::Float) => cg.instructions.push(IrInstruction::F64Add),
-                (BinOp::Sub, Type::Float) => cg.instructions.push(IrInstruction::F64Sub),
-                (BinOp::Mul, Type::Float) => cg.instructions.push(IrInstruction::F64Mul),
-                (BinOp::Div, Type::Float) => cg.instructions.push(IrInstruction::F64Div),
-                (BinOp::Eq, Type::Int) => cg.instructions.push(IrInstruction::I64Eq),
-                (BinOp::Eq, Type::Float) => cg.instructions.push(IrInstruction::F64Eq),
-                (BinOp::Eq, Type::Bool) => cg.instructions.push(IrInstruction::I32Eq),
-                (BinOp::Eq, Type::String) => {
-                    let (idx, _, _) = funcs
-                        .get(string_eq_import_name())
-                        .cloned()
-                        .ok_or_else(|| CodegenError::UnknownFunction {
-                            name: string_eq_import_name().to_string(),
-                        })?;
-                    cg.instructions.push(IrInstruction::Call(idx));
-                }
-                (BinOp::NotEq, Type::Int) => {
-                    cg.instructions.push(IrInstruction::I64Eq);
-                    cg.instructions.push(IrInstruction::I32Eqz);
-                }
-                (BinOp::NotEq, Type::Float) => {
-                    cg.instructions.push(IrInstruction::F64Eq);
-                    cg.instructions.push(IrInstruction::I32Eqz);
+        ExprKind::Deref(inner) => {
+            let ref_ty = infer_expr_type(inner, cg, funcs)?;
+            let _ = emit_expr(inner, cg, funcs)?;
+            emit_deref_step(&ref_ty, cg, funcs)?;
+            Ok(true)
+        }
+        ExprKind::Not(inner) => {
+            let _ = emit_expr_expected(inner, &Type::Bool, cg, funcs)?;
+            cg.instructions.push(IrInstruction::I32Eqz);
+            Ok(true)
+        }
+        ExprKind::Neg(inner) => {
+            let ty = infer_expr_type(expr, cg, funcs)?;
+            match ty {
+                Type::Int => {
+                    cg.instructions.push(IrInstruction::I64Const(0));
+                    let _ = emit_expr_expected(inner, &ty, cg, funcs)?;
+                    emit_binary_op(&BinOp::Sub, &ty, cg, funcs)?;
                 }
-                (BinOp::NotEq, Type::Bool) => {
-                    cg.instructions.push(IrInstruction::I32Eq);
-                    cg.instructions.push(IrInstruction::I32Eqz);
+                Type::Int32 => {
+                    cg.instructions.push(IrInstruction::I32Const(0));
+                    let _ = emit_expr_expected(inner, &ty, cg, funcs)?;
+                    emit_binary_op(&BinOp::Sub, &ty, cg, funcs)?;
                 }
-                (BinOp::NotEq, Type::String) => {
-                    let (idx, _, _) = funcs
-                        .get(string_eq_import_name())
-                        .cloned()
-                        .ok_or_else(|| CodegenError::UnknownFunction {
-                            name: string_eq_import_name().to_string(),
-                        })?;
-                    cg.instructions.push(IrInstruction::Call(idx));
-                    cg.instructions.push(IrInstruction::I32Eqz);
+                Type::Float => {
+                    let _ = emit_expr_expected(inner, &ty, cg, funcs)?;
+                    cg.instructions.push(IrInstruction::F64Neg);
                 }
-                (BinOp::Lt, Type::Int) => cg.instructions.push(IrInstruction::I64LtS),
-                (BinOp::Lt, Type::Float) => cg.instructions.push(IrInstruction::F64Lt),
-                (BinOp::Gt, Type::Int) => cg.instructions.push(IrInstruction::I64GtS),
-                (BinOp::Gt, Type::Float) => cg.instructions.push(IrInstruction::F64Gt),
-                (BinOp::Le, Type::Int) => {
-                    cg.instructions.push(IrInstruction::I64GtS);
-                    cg.instructions.push(IrInstruction::I32Eqz);
+                other => {
+                    return Err(CodegenError::UnsupportedType(format!("negating {}", other)));
                 }
-                (BinOp::Le, Type::Float) => {
-                    cg.instructions.push(IrInstruction::F64Gt);
-                    cg.instructions.push(IrInstruction::I32Eqz);
+            }
+            Ok(true)
+        }
+        ExprKind::Try(inner) => emit_try(inner, cg, funcs),
+        ExprKind::Binary { op, left, right } if matches!(op, BinOp::And | BinOp::Or) => {
+            emit_logical(op, left, right, cg, funcs)
+        }
+        ExprKind::Binary { op, left, right } => {
+            let operand_ty = binary_operand_type(left, right, cg, funcs)?;
+            let _ = emit_expr_expected(left, &operand_ty, cg, funcs)?;
+            let _ = emit_expr_expected(right, &operand_ty, cg, funcs)?;
+            emit_binary_op(op, &operand_ty, cg, funcs)?;
+            Ok(true)
+        }
+        ExprKind::Cast { expr: inner, ty } => {
+            let mut from = infer_expr_type(inner, cg, funcs)?;
+            while let Type::Ref(referent) = from {
+                from = *referent;
+            }
+            let _ = emit_expr_expected(inner, &from, cg, funcs)?;
+            emit_cast(&from, ty, cg)?;
+            Ok(true)
+        }
+        ExprKind::Call { name, args } => emit_call(name, args, None, cg, funcs),
+        ExprKind::StructInit { name, fields } => emit_struct_init(name, fields, None, cg, funcs),
+        ExprKind::Field { base, name } => {
+            let (index, field_ty) = resolve_field(cg, funcs, base, name)?;
+            let lane = lane_for(&field_ty)?;
+            let _ = emit_expr(base, cg, funcs)?;
+            cg.instructions.push(IrInstruction::I64Const(index as i64));
+            call_runtime(cg, funcs, &aggregate_get_import_name(lane))?;
+            Ok(true)
+        }
+        ExprKind::EnumInit {
+            enum_name,
+            variant,
+            payload,
+        } => emit_enum_init(enum_name, variant, payload, None, cg, funcs),
+        ExprKind::Match { subject, arms } => emit_match(subject, arms, cg, funcs),
+        ExprKind::Closure { params, body } => emit_closure(params, body, None, cg, funcs),
+    }
+}
+
+/// Shared operand type of a binary expression; literal-only integer arithmetic on one side
+/// takes the integer type of the other (`b + 1` on a `UInt8`).
+fn binary_operand_type(
+    left: &Expr,
+    right: &Expr,
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<Type, CodegenError> {
+    let left_ty = infer_expr_type(left, cg, funcs)?;
+    let right_ty = infer_expr_type(right, cg, funcs)?;
+    match (left.is_untyped_int(), right.is_untyped_int()) {
+        (true, false) if right_ty.is_integer() => Ok(right_ty),
+        (false, true) if left_ty.is_integer() => Ok(left_ty),
+        _ if left_ty == right_ty => Ok(left_ty),
+        _ => Err(CodegenError::UnsupportedType(format!(
+            "binary type mismatch: expected {}, found {}",
+            left_ty, right_ty
+        ))),
+    }
+}
+
+/// Converts the value on the stack for `as`: integer casts wrap (or zero/sign-extend),
+/// `Float` to integer saturates at the target's bounds and maps NaN to 0.
+fn emit_cast(from: &Type, to: &Type, cg: &mut FuncGen) -> Result<(), CodegenError> {
+    let steps = match (from, to) {
+        _ if from == to => vec![],
+        (Type::Int, Type::Float) => vec![IrInstruction::F64ConvertI64S],
+        (Type::Int32, Type::Float) => vec![IrInstruction::F64ConvertI32S],
+        (Type::UInt8, Type::Float) => vec![IrInstruction::F64ConvertI32U],
+        (Type::Float, Type::Int) => vec![IrInstruction::I64TruncSatF64S],
+        (Type::Float, Type::Int32) => vec![IrInstruction::I32TruncSatF64S],
+        (Type::Float, Type::UInt8) => vec![
+            IrInstruction::F64Const(u8::MAX as f64),
+            IrInstruction::F64Min,
+            IrInstruction::I32TruncSatF64U,
+        ],
+        (Type::Int, Type::Int32) => vec![IrInstruction::I32WrapI64],
+        (Type::Int, Type::UInt8) => vec![
+            IrInstruction::I32WrapI64,
+            IrInstruction::I32Const(u8::MAX as i32),
+            IrInstruction::I32And,
+        ],
+        (Type::Int32, Type::Int) => vec![IrInstruction::I64ExtendI32S],
+        (Type::UInt8 | Type::Bool, Type::Int) => vec![IrInstruction::I64ExtendI32U],
+        (Type::Int32, Type::UInt8) => {
+            vec![IrInstruction::I32Const(u8::MAX as i32), IrInstruction::I32And]
+        }
+        (Type::UInt8 | Type::Bool, Type::Int32 | Type::UInt8) => vec![],
+        _ => {
+            return Err(CodegenError::UnsupportedType(format!(
+                "cast from {} to {}",
+                from, to
+            )));
+        }
+    };
+    cg.instructions.extend(steps);
+    Ok(())
+}
+
+/// Emits a call, instantiating a generic callee for the argument types at hand.
+fn emit_call(
+    name: &str,
+    args: &[Expr],
+    hint: Option<&Type>,
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    let (callee, params, ret) = resolve_call(name, args, hint, cg, funcs)?;
+    if let Callee::Value(local) = callee {
+        // The function value is also the environment argument of the lifted function.
+        cg.instructions.push(IrInstruction::LocalGet(local));
+    }
+    for (pos, arg) in args.iter().enumerate() {
+        match params.get(pos) {
+            Some(param_ty) => emit_expr_expected(arg, param_ty, cg, funcs)?,
+            None => emit_expr(arg, cg, funcs)?,
+        };
+    }
+    let idx = match callee {
+        Callee::Function(idx) => idx,
+        Callee::Instance(type_args) => cg.instances.request(name, &type_args)?,
+        Callee::Value(local) => {
+            let env = Type::Function(params.clone(), Box::new(ret.clone()));
+            let wasm_params: Vec<Type> = std::iter::once(env).chain(params).collect();
+            let (param_tys, results) = wasm_signature(name, &wasm_params, &ret)?;
+            let type_index = cg.types.intern(param_tys, results);
+            cg.instructions.push(IrInstruction::LocalGet(local));
+            cg.instructions.push(IrInstruction::I64Const(0));
+            call_runtime(cg, funcs, &aggregate_get_import_name(RuntimeLane::Int))?;
+            cg.instructions.push(IrInstruction::I32WrapI64);
+            cg.instructions.push(IrInstruction::CallIndirect(type_index));
+            cg.instances.calls_values = true;
+            return Ok(ret != Type::Unit);
+        }
+    };
+    cg.instructions.push(IrInstruction::Call(idx));
+    Ok(ret != Type::Unit)
+}
+
+/// Target of a call: a declared function, a generic one with inferred type arguments
+/// or the function value in a local.
+enum Callee {
+    Function(u32),
+    Instance(Vec<Type>),
+    Value(u32),
+}
+
+/// Resolves a call to its callee, parameter types and return type.
+fn resolve_call(
+    name: &str,
+    args: &[Expr],
+    hint: Option<&Type>,
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(Callee, Vec<Type>, Type), CodegenError> {
+    // Locals shadow functions of the same name.
+    if let Some((local, ty)) = cg.local_map.get(name) {
+        return match ty {
+            Type::Function(params, ret) => {
+                Ok((Callee::Value(*local), params.clone(), (**ret).clone()))
+            }
+            other => Err(CodegenError::UnsupportedType(format!(
+                "calling a value of type {}",
+                other
+            ))),
+        };
+    }
+    if let Some((idx, params, ret)) = funcs.get(name).cloned() {
+        return Ok((Callee::Function(idx), params, ret));
+    }
+    let template = cg
+        .generics
+        .get(name)
+        .cloned()
+        .ok_or_else(|| CodegenError::UnknownFunction {
+            name: name.to_string(),
+        })?;
+    let typed = &template.typed;
+    let slots: Vec<_> = args
+        .iter()
+        .zip(&typed.params)
+        .map(|(arg, param)| (arg, &param.ty))
+        .collect();
+    let type_args = infer_type_args(name, &typed.type_params, &slots, &typed.return_type, hint, cg, funcs)?;
+    let bindings = type_bindings(&typed.type_params, &type_args);
+    let params = typed
+        .params
+        .iter()
+        .map(|param| param.ty.substitute(&bindings))
+        .collect();
+    let ret = typed.return_type.substitute(&bindings);
+    Ok((Callee::Instance(type_args), params, ret))
+}
+
+/// Builds a struct; fields are pushed in declaration order so positional reads match host structs.
+fn emit_struct_init(
+    name: &str,
+    fields: &[(String, Expr)],
+    hint: Option<&Type>,
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    let (def, _) = struct_init_type(name, fields, hint, cg, funcs)?;
+    push_static_str(cg, name);
+    call_runtime(cg, funcs, STRUCT_NEW_IMPORT)?;
+    for (field_name, field_ty) in &def.fields {
+        let value = fields
+            .iter()
+            .find(|(candidate, _)| candidate == field_name)
+            .map(|(_, value)| value)
+            .ok_or_else(|| {
+                CodegenError::UnsupportedType(format!(
+                    "missing field `{}` in `{}` initializer",
+                    field_name, name
+                ))
+            })?;
+        let lane = lane_for(field_ty)?;
+        push_static_str(cg, field_name);
+        let _ = emit_expr_expected(value, field_ty, cg, funcs)?;
+        call_runtime(cg, funcs, &aggregate_push_import_name(lane))?;
+    }
+    Ok(true)
+}
+
+/// The struct declaration (type arguments substituted) and type of a struct literal.
+fn struct_init_type(
+    name: &str,
+    fields: &[(String, Expr)],
+    hint: Option<&Type>,
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(StructDecl, Type), CodegenError> {
+    let def = lookup_struct(cg, name)?;
+    let slots = fields
+        .iter()
+        .map(|(field_name, value)| {
+            def.fields
+                .iter()
+                .find(|(candidate, _)| candidate == field_name)
+                .map(|(_, ty)| (value, ty))
+                .ok_or_else(|| {
+                    CodegenError::UnsupportedType(format!(
+                        "struct `{}` has no field `{}`",
+                        name, field_name
+                    ))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let self_ty = generic_self_type(name, &def.type_params);
+    let type_args = infer_type_args(name, &def.type_params, &slots, &self_ty, hint, cg, funcs)?;
+    Ok((def.instantiate(&type_args), applied_type(name, type_args)))
+}
+
+/// Builds an enum value: `__eres_enum_new` followed by one push per payload slot.
+fn emit_enum_init(
+    enum_name: &str,
+    variant: &str,
+    payload: &[Expr],
+    hint: Option<&Type>,
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    let (def, _) = enum_init_type(enum_name, variant, payload, hint, cg, funcs)?;
+    let variant_decl = find_variant(&def, variant)?;
+    push_static_str(cg, enum_name);
+    push_static_str(cg, variant);
+    call_runtime(cg, funcs, ENUM_NEW_IMPORT)?;
+    for ((key, ty), value) in payload_slots(variant_decl).into_iter().zip(payload) {
+        let lane = lane_for(&ty)?;
+        match key {
+            Some(key) => push_static_str(cg, &key),
+            None => {
+                cg.instructions.push(IrInstruction::I32Const(0));
+                cg.instructions.push(IrInstruction::I32Const(0));
+            }
+        }
+        let _ = emit_expr_expected(value, &ty, cg, funcs)?;
+        call_runtime(cg, funcs, &aggregate_push_import_name(lane))?;
+    }
+    Ok(true)
+}
+
+/// The enum declaration (type arguments substituted) and type of a variant constructor.
+fn enum_init_type(
+    enum_name: &str,
+    variant: &str,
+    payload: &[Expr],
+    hint: Option<&Type>,
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(EnumDecl, Type), CodegenError> {
+    let def = lookup_enum(cg, enum_name)?;
+    let slot_tys = payload_slots(find_variant(&def, variant)?);
+    let slots: Vec<_> = payload.iter().zip(slot_tys.iter().map(|(_, ty)| ty)).collect();
+    let self_ty = generic_self_type(enum_name, &def.type_params);
+    let type_args = infer_type_args(enum_name, &def.type_params, &slots, &self_ty, hint, cg, funcs)?;
+    Ok((def.instantiate(&type_args), applied_type(enum_name, type_args)))
+}
+
+/// Repeats the checker's type argument inference (see `typing::infer_type_args`) on concrete types.
+fn infer_type_args(
+    item: &str,
+    type_params: &[String],
+    slots: &[(&Expr, &Type)],
+    result: &Type,
+    hint: Option<&Type>,
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<Vec<Type>, CodegenError> {
+    let mut bindings = HashMap::new();
+    if let Some(hint) = hint {
+        unify(result, hint, type_params, &mut bindings);
+    }
+    let unbound = |ty: &Type, bindings: &HashMap<String, Type>| {
+        type_params
+            .iter()
+            .any(|param| ty.mentions(param) && !bindings.contains_key(param))
+    };
+    // Closures need their parameter types from other slots, so they go last.
+    let (closures, values): (Vec<_>, Vec<_>) = slots
+        .iter()
+        .partition(|(value, _)| matches!(value.kind, ExprKind::Closure { .. }));
+    let mut first_err = None;
+    for (value, slot_ty) in values.into_iter().chain(closures) {
+        if !unbound(slot_ty, &bindings) {
+            continue;
+        }
+        let found = match (&value.kind, slot_ty) {
+            (ExprKind::Closure { params, body }, Type::Function(slot_params, slot_ret)) => {
+                if slot_params.iter().any(|param| unbound(param, &bindings)) {
+                    continue;
                 }
-                (BinOp::Ge, Type::Int) => {
-                    cg.instructions.push(IrInstruction::I64LtS);
-                    cg.instructions.push(IrInstruction::I32Eqz);
+                let param_tys: Vec<Type> =
+                    slot_params.iter().map(|param| param.substitute(&bindings)).collect();
+                let ret_hint =
+                    (!unbound(slot_ret, &bindings)).then(|| slot_ret.substitute(&bindings));
+                closure_type(params, body, Some(&param_tys), ret_hint.as_ref(), cg, funcs)
+                    .map(|(param_tys, ret)| Type::Function(param_tys, Box::new(ret)))
+            }
+            _ => infer_expr_type(value, cg, funcs),
+        };
+        match found {
+            Ok(found) => unify(slot_ty, &found, type_params, &mut bindings),
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    type_params
+        .iter()
+        .map(|param| {
+            bindings.get(param).cloned().ok_or_else(|| {
+                first_err.take().unwrap_or_else(|| {
+                    CodegenError::UnsupportedType(format!(
+                        "uninferred type parameter `{}` of `{}`",
+                        param, item
+                    ))
+                })
+            })
+        })
+        .collect()
+}
+
+/// Lowers a closure to a function value: the body is lifted into its own function and the
+/// captured locals are copied into the value next to its table slot.
+fn emit_closure(
+    params: &[ClosureParam],
+    body: &Expr,
+    hint: Option<&Type>,
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    let (param_tys, return_type) = match hint {
+        Some(Type::Function(param_tys, ret)) if param_tys.len() == params.len() => {
+            (param_tys.clone(), (**ret).clone())
+        }
+        _ => closure_type(params, body, None, None, cg, funcs)?,
+    };
+    let mut names = Vec::new();
+    free_locals(body, &mut params.iter().map(|param| param.name.clone()).collect(), &mut names);
+    let captures: Vec<(String, Type)> = names
+        .into_iter()
+        .filter_map(|name| {
+            let (_, ty) = cg.local_map.get(&name)?;
+            Some((name.clone(), ty.clone()))
+        })
+        .collect();
+    let slot = cg.instances.request_closure(ClosureDef {
+        params: params
+            .iter()
+            .map(|param| param.name.clone())
+            .zip(param_tys)
+            .collect(),
+        return_type,
+        captures: captures.clone(),
+        body: body.clone(),
+        type_bindings: cg.type_bindings.clone(),
+        owner: cg.function_name.clone(),
+    });
+    emit_function_value(slot, &captures, cg, funcs)
+}
+
+/// Builds a function value: a tuple of the table slot and the captured locals.
+///
+/// Scalars are captured by value; aggregates are pushed as their handle, so the closure
+/// shares them with the enclosing function like `let q = p;` does.
+fn emit_function_value(
+    slot: u32,
+    captures: &[(String, Type)],
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    call_runtime(cg, funcs, TUPLE_NEW_IMPORT)?;
+    cg.instructions.push(IrInstruction::I32Const(0));
+    cg.instructions.push(IrInstruction::I32Const(0));
+    cg.instructions.push(IrInstruction::I64Const(slot as i64));
+    call_runtime(cg, funcs, &aggregate_push_import_name(RuntimeLane::Int))?;
+    for (name, ty) in captures {
+        let (local, _) = cg
+            .local_map
+            .get(name)
+            .cloned()
+            .ok_or_else(|| CodegenError::UnknownLocal { name: name.clone() })?;
+        cg.instructions.push(IrInstruction::I32Const(0));
+        cg.instructions.push(IrInstruction::I32Const(0));
+        cg.instructions.push(IrInstruction::LocalGet(local));
+        call_runtime(cg, funcs, &aggregate_push_import_name(lane_for(ty)?))?;
+    }
+    Ok(true)
+}
+
+/// Parameter and return types of a closure; unannotated parameters take `param_hint`.
+fn closure_type(
+    params: &[ClosureParam],
+    body: &Expr,
+    param_hint: Option<&[Type]>,
+    ret_hint: Option<&Type>,
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(Vec<Type>, Type), CodegenError> {
+    let param_tys = params
+        .iter()
+        .enumerate()
+        .map(|(pos, param)| match (&param.ty, param_hint) {
+            (Some(ty), _) => Ok(ty.substitute(&cg.type_bindings)),
+            (None, Some(hint)) => Ok(hint[pos].clone()),
+            (None, None) => Err(CodegenError::UnsupportedType(format!(
+                "closure parameter `{}` without a type",
+                param.name
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let scope = cg.type_scope(
+        params
+            .iter()
+            .map(|param| param.name.clone())
+            .zip(param_tys.iter().cloned()),
+    );
+    let ret = match (infer_expr_type_hinted(body, ret_hint, &scope, funcs), ret_hint) {
+        (Ok(ty), _) => ty,
+        (Err(_), Some(hint)) => hint.clone(),
+        (Err(err), None) => return Err(err),
+    };
+    Ok((param_tys, ret))
+}
+
+/// Collects the names `expr` reads that are not bound inside it, in first-use order.
+fn free_locals(expr: &Expr, bound: &mut Vec<String>, found: &mut Vec<String>) {
+    match &expr.kind {
+        ExprKind::Local(name) => {
+            if !bound.contains(name) && !found.contains(name) {
+                found.push(name.clone());
+            }
+        }
+        ExprKind::Call { name, args } => {
+            if !bound.contains(name) && !found.contains(name) {
+                found.push(name.clone());
+            }
+            for arg in args {
+                free_locals(arg, bound, found);
+            }
+        }
+        ExprKind::Closure { params, body } => {
+            let outer = bound.len();
+            bound.extend(params.iter().map(|param| param.name.clone()));
+            free_locals(body, bound, found);
+            bound.truncate(outer);
+        }
+        ExprKind::Match { subject, arms } => {
+            free_locals(subject, bound, found);
+            for arm in arms {
+                let outer = bound.len();
+                if let Pattern::TupleVariant(_, names) | Pattern::StructVariant(_, names) =
+                    &arm.pattern
+                {
+                    bound.extend(names.iter().map(|binding| binding.name.clone()));
                 }
-                (BinOp::Ge, Type::Float) => {
-                    cg.instructions.push(IrInstruction::F64Lt);
-                    cg.instructions.push(IrInstruction::I32Eqz);
+                free_locals(&arm.body, bound, found);
+                bound.truncate(outer);
+            }
+        }
+        ExprKind::Ref(inner)
+        | ExprKind::Deref(inner)
+        | ExprKind::Not(inner)
+        | ExprKind::Neg(inner)
+        | ExprKind::Try(inner)
+        | ExprKind::Cast { expr: inner, .. } => free_locals(inner, bound, found),
+        ExprKind::Binary { left, right, .. }
+        | ExprKind::Range {
+            start: left,
+            end: right,
+        }
+        | ExprKind::Index {
+            base: left,
+            index: right,
+        } => {
+            free_locals(left, bound, found);
+            free_locals(right, bound, found);
+        }
+        ExprKind::StructInit { fields, .. } => {
+            for (_, value) in fields {
+                free_locals(value, bound, found);
+            }
+        }
+        ExprKind::EnumInit { payload: values, .. }
+        | ExprKind::ListLiteral(values)
+        | ExprKind::TupleLiteral(values) => {
+            for value in values {
+                free_locals(value, bound, found);
+            }
+        }
+        ExprKind::Field { base, .. } => free_locals(base, bound, found),
+        ExprKind::MethodCall { receiver, args, .. } => {
+            free_locals(receiver, bound, found);
+            for arg in args {
+                free_locals(arg, bound, found);
+            }
+        }
+        ExprKind::Interpolation(parts) => {
+            for part in parts {
+                if let InterpolationPart::Expr(expr) = part {
+                    free_locals(expr, bound, found);
                 }
-                _ => {
-                    return Err(CodegenError::UnsupportedType(format!(
-                        "unsupported operator {:?} for type {}",
-                        op, left_ty
-                    )));
+            }
+        }
+        ExprKind::Int(_) | ExprKind::Float(_) | ExprKind::Bool(_) | ExprKind::String(_) => {}
+    }
+}
+
+/// Lowers `"a{x}b"` to a chain of concatenations; non-String parts are rendered first.
+fn emit_interpolation(
+    parts: &[InterpolationPart],
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    for (index, part) in parts.iter().enumerate() {
+        match part {
+            InterpolationPart::Text(text) => {
+                call_runtime(cg, funcs, &string_literal_import_name(text))?;
+            }
+            InterpolationPart::Expr(expr) => {
+                let ty = infer_expr_type(expr, cg, funcs)?;
+                emit_expr(expr, cg, funcs)?;
+                if ty != Type::String {
+                    call_runtime(cg, funcs, &to_string_import_name(lane_for(&ty)?))?;
                 }
             }
+        }
+        if index > 0 {
+            call_runtime(cg, funcs, string_concat_import_name())?;
+        }
+    }
+    Ok(true)
+}
 
-            Ok(true)
+/// Lowers `expr?`: a `None`/`Err` operand is returned as is, otherwise its payload is read.
+///
+/// Runtime enum values carry no type arguments, so the operand handle is also a valid
+/// `Option<U>`/`Result<U, E>` for the enclosing function's return type.
+fn emit_try(
+    inner: &Expr,
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    let operand_ty = infer_expr_type(inner, cg, funcs)?;
+    let (Type::Generic(enum_name, _), Some(payload_ty)) =
+        (&operand_ty, try_payload_type(&operand_ty))
+    else {
+        return Err(CodegenError::UnsupportedType(format!("`?` on {}", operand_ty)));
+    };
+    let variant_list = lookup_enum(cg, enum_name)?
+        .variants
+        .iter()
+        .map(EnumVariant::name)
+        .collect::<Vec<_>>()
+        .join(",");
+    let lane = lane_for(payload_ty)?;
+
+    let operand = cg.alloc_local(&operand_ty)?;
+    let _ = emit_expr(inner, cg, funcs)?;
+    cg.instructions.push(IrInstruction::LocalSet(operand));
+    cg.instructions.push(IrInstruction::LocalGet(operand));
+    push_static_str(cg, &variant_list);
+    call_runtime(cg, funcs, ENUM_TAG_IMPORT)?;
+    cg.instructions.push(IrInstruction::If(BlockType::Empty));
+    cg.instructions.push(IrInstruction::LocalGet(operand));
+    cg.instructions.push(IrInstruction::Return);
+    cg.instructions.push(IrInstruction::End);
+    cg.instructions.push(IrInstruction::LocalGet(operand));
+    cg.instructions.push(IrInstruction::I64Const(0));
+    call_runtime(cg, funcs, &aggregate_get_import_name(lane))?;
+    Ok(true)
+}
+
+/// Lowers `&&`/`||` to a wasm `if` so the right operand only runs when it decides the result.
+fn emit_logical(
+    op: &BinOp,
+    left: &Expr,
+    right: &Expr,
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    let _ = emit_expr_expected(left, &Type::Bool, cg, funcs)?;
+    cg.instructions
+        .push(IrInstruction::If(BlockType::Result(ValType::I32)));
+    if matches!(op, BinOp::And) {
+        let _ = emit_expr_expected(right, &Type::Bool, cg, funcs)?;
+        cg.instructions.push(IrInstruction::Else);
+        cg.instructions.push(IrInstruction::I32Const(0));
+    } else {
+        cg.instructions.push(IrInstruction::I32Const(1));
+        cg.instructions.push(IrInstruction::Else);
+        let _ = emit_expr_expected(right, &Type::Bool, cg, funcs)?;
+    }
+    cg.instructions.push(IrInstruction::End);
+    Ok(true)
+}
+
+/// Emits the opcode(s) for `op` applied to two operands of type `ty` already on the stack.
+pub(crate) fn emit_binary_op(
+    op: &BinOp,
+    ty: &Type,
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(), CodegenError> {
+    if cg.overflow_checks
+        && matches!(op, BinOp::Add | BinOp::Sub | BinOp::Mul)
+        && ty.is_integer()
+    {
+        return emit_checked_arith(op, ty, cg, funcs);
+    }
+    match (op, ty) {
+        (BinOp::Add, Type::Int) => cg.instructions.push(IrInstruction::I64Add),
+        (BinOp::Sub, Type::Int) => cg.instructions.push(IrInstruction::I64Sub),
+        (BinOp::Mul, Type::Int) => cg.instructions.push(IrInstruction::I64Mul),
+        (BinOp::Div, Type::Int) => cg.instructions.push(IrInstruction::I64DivS),
+        (BinOp::Rem, Type::Int) => cg.instructions.push(IrInstruction::I64RemS),
+        (BinOp::Add, Type::Float) => cg.instructions.push(IrInstruction::F64Add),
+        (BinOp::Sub, Type::Float) => cg.instructions.push(IrInstruction::F64Sub),
+        (BinOp::Mul, Type::Float) => cg.instructions.push(IrInstruction::F64Mul),
+        (BinOp::Div, Type::Float) => cg.instructions.push(IrInstruction::F64Div),
+        (BinOp::Rem, Type::Float) => {
+            // wasm has no `f64.rem`: compute `a - trunc(a / b) * b` like Rust's `%`.
+            let rhs = cg.alloc_local(&Type::Float)?;
+            let lhs = cg.alloc_local(&Type::Float)?;
+            cg.instructions.push(IrInstruction::LocalSet(rhs));
+            cg.instructions.push(IrInstruction::LocalSet(lhs));
+            cg.instructions.push(IrInstruction::LocalGet(lhs));
+            cg.instructions.push(IrInstruction::LocalGet(lhs));
+            cg.instructions.push(IrInstruction::LocalGet(rhs));
+            cg.instructions.push(IrInstruction::F64Div);
+            cg.instructions.push(IrInstruction::F64Trunc);
+            cg.instructions.push(IrInstruction::LocalGet(rhs));
+            cg.instructions.push(IrInstruction::F64Mul);
+            cg.instructions.push(IrInstruction::F64Sub);
         }
-        Expr::Call { name, args } => {
-            for arg in args {
-                let _ = emit_expr(arg, cg, funcs)?;
+        (BinOp::Add, Type::Int32) => cg.instructions.push(IrInstruction::I32Add),
+        (BinOp::Sub, Type::Int32) => cg.instructions.push(IrInstruction::I32Sub),
+        (BinOp::Mul, Type::Int32) => cg.instructions.push(IrInstruction::I32Mul),
+        (BinOp::Div, Type::Int32) => cg.instructions.push(IrInstruction::I32DivS),
+        (BinOp::Rem, Type::Int32) => cg.instructions.push(IrInstruction::I32RemS),
+        (BinOp::Add | BinOp::Sub | BinOp::Mul, Type::UInt8) => {
+            // Computed in 32 bits, then wrapped back into `0..=255`.
+            cg.instructions.push(match op {
+                BinOp::Add => IrInstruction::I32Add,
+                BinOp::Sub => IrInstruction::I32Sub,
+                _ => IrInstruction::I32Mul,
+            });
+            cg.instructions.push(IrInstruction::I32Const(u8::MAX as i32));
+            cg.instructions.push(IrInstruction::I32And);
+        }
+        (BinOp::Div, Type::UInt8) => cg.instructions.push(IrInstruction::I32DivU),
+        (BinOp::Rem, Type::UInt8) => cg.instructions.push(IrInstruction::I32RemU),
+        (BinOp::Eq, Type::Int32 | Type::UInt8) => cg.instructions.push(IrInstruction::I32Eq),
+        (BinOp::NotEq, Type::Int32 | Type::UInt8) => {
+            cg.instructions.push(IrInstruction::I32Eq);
+            cg.instructions.push(IrInstruction::I32Eqz);
+        }
+        (BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge, Type::Int32 | Type::UInt8) => {
+            let signed = *ty == Type::Int32;
+            // `a <= b` is `!(a > b)` and `a >= b` is `!(a < b)`.
+            let less = matches!(op, BinOp::Lt | BinOp::Ge);
+            cg.instructions.push(match (less, signed) {
+                (true, true) => IrInstruction::I32LtS,
+                (true, false) => IrInstruction::I32LtU,
+                (false, true) => IrInstruction::I32GtS,
+                (false, false) => IrInstruction::I32GtU,
+            });
+            if matches!(op, BinOp::Le | BinOp::Ge) {
+                cg.instructions.push(IrInstruction::I32Eqz);
             }
-            let (idx, _params, ret) = funcs
-                .get(name)
+        }
+        (BinOp::Eq, Type::Int) => cg.instructions.push(IrInstruction::I64Eq),
+        (BinOp::Eq, Type::Float) => cg.instructions.push(IrInstruction::F64Eq),
+        (BinOp::Eq, Type::Bool) => cg.instructions.push(IrInstruction::I32Eq),
+        (BinOp::Eq, Type::String) => {
+            let (idx, _, _) = funcs
+                .get(string_eq_import_name())
                 .cloned()
-                .ok_or_else(|| CodegenError::UnknownFunction { name: name.clone() })?;
+                .ok_or_else(|| CodegenError::UnknownFunction {
+                    name: string_eq_import_name().to_string(),
+                })?;
             cg.instructions.push(IrInstruction::Call(idx));
-            Ok(ret != Type::Unit)
         }
-        Expr::StructInit { name: _, fields: _ } => Err(CodegenError::UnsupportedType(
-            "struct initialization lowering".to_string(),
-        )),
-        Expr::EnumInit {
-            enum_name: _,
-            variant: _,
-            payload: _,
-        } => Err(CodegenError::UnsupportedType(
-            "enum initialization lowering".to_string(),
-        )),
-        Expr::Match { .. } => Err(CodegenError::UnsupportedType("match lowering".to_string())),
+        (BinOp::Add, Type::String) => call_runtime(cg, funcs, string_concat_import_name())?,
+        (BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge, Type::String) => {
+            // The import yields the ordering as `-1/0/1`, which is then compared against 0.
+            call_runtime(cg, funcs, string_cmp_import_name())?;
+            cg.instructions.push(IrInstruction::I32Const(0));
+            cg.instructions.push(match op {
+                BinOp::Lt => IrInstruction::I32LtS,
+                BinOp::Gt => IrInstruction::I32GtS,
+                BinOp::Le => IrInstruction::I32GtS,
+                _ => IrInstruction::I32LtS,
+            });
+            if matches!(op, BinOp::Le | BinOp::Ge) {
+                cg.instructions.push(IrInstruction::I32Eqz);
+            }
+        }
+        (BinOp::NotEq, Type::Int) => {
+            cg.instructions.push(IrInstruction::I64Eq);
+            cg.instructions.push(IrInstruction::I32Eqz);
+        }
+        (BinOp::NotEq, Type::Float) => {
+            cg.instructions.push(IrInstruction::F64Eq);
+            cg.instructions.push(IrInstruction::I32Eqz);
+        }
+        (BinOp::NotEq, Type::Bool) => {
+            cg.instructions.push(IrInstruction::I32Eq);
+            cg.instructions.push(IrInstruction::I32Eqz);
+        }
+        (BinOp::NotEq, Type::String) => {
+            let (idx, _, _) = funcs
+                .get(string_eq_import_name())
+                .cloned()
+                .ok_or_else(|| CodegenError::UnknownFunction {
+                    name: string_eq_import_name().to_string(),
+                })?;
+            cg.instructions.push(IrInstruction::Call(idx));
+            cg.instructions.push(IrInstruction::I32Eqz);
+        }
+        (BinOp::Lt, Type::Int) => cg.instructions.push(IrInstruction::I64LtS),
+        (BinOp::Lt, Type::Float) => cg.instructions.push(IrInstruction::F64Lt),
+        (BinOp::Gt, Type::Int) => cg.instructions.push(IrInstruction::I64GtS),
+        (BinOp::Gt, Type::Float) => cg.instructions.push(IrInstruction::F64Gt),
+        (BinOp::Le, Type::Int) => {
+            cg.instructions.push(IrInstruction::I64GtS);
+            cg.instructions.push(IrInstruction::I32Eqz);
+        }
+        (BinOp::Le, Type::Float) => {
+            cg.instructions.push(IrInstruction::F64Gt);
+            cg.instructions.push(IrInstruction::I32Eqz);
+        }
+        (BinOp::Ge, Type::Int) => {
+            cg.instructions.push(IrInstruction::I64LtS);
+            cg.instructions.push(IrInstruction::I32Eqz);
+        }
+        (BinOp::Ge, Type::Float) => {
+            cg.instructions.push(IrInstruction::F64Lt);
+            cg.instructions.push(IrInstruction::I32Eqz);
+        }
+        _ => {
+            return Err(CodegenError::UnsupportedType(format!(
+                "unsupported operator {:?} for type {}",
+                op, ty
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checked `+`, `-` and `*`: computes the result, leaves it on the stack and traps through
+/// the overflow import if it does not fit into `ty`.
+fn emit_checked_arith(
+    op: &BinOp,
+    ty: &Type,
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(), CodegenError> {
+    match ty {
+        Type::Int => {
+            let rhs = cg.alloc_local(&Type::Int)?;
+            let lhs = cg.alloc_local(&Type::Int)?;
+            let result = cg.alloc_local(&Type::Int)?;
+            cg.instructions.push(IrInstruction::LocalSet(rhs));
+            cg.instructions.push(IrInstruction::LocalSet(lhs));
+            cg.instructions.push(IrInstruction::LocalGet(lhs));
+            cg.instructions.push(IrInstruction::LocalGet(rhs));
+            cg.instructions.push(match op {
+                BinOp::Add => IrInstruction::I64Add,
+                BinOp::Sub => IrInstruction::I64Sub,
+                _ => IrInstruction::I64Mul,
+            });
+            cg.instructions.push(IrInstruction::LocalSet(result));
+            if matches!(op, BinOp::Mul) {
+                // Overflowed iff `r / a != b`; `a == 0` never overflows and `a == -1` only
+                // for `b == MIN` (where the division itself would trap).
+                cg.instructions.push(IrInstruction::LocalGet(lhs));
+                cg.instructions.push(IrInstruction::I64Eqz);
+                cg.instructions.push(IrInstruction::If(BlockType::Result(ValType::I32)));
+                cg.instructions.push(IrInstruction::I32Const(0));
+                cg.instructions.push(IrInstruction::Else);
+                cg.instructions.push(IrInstruction::LocalGet(lhs));
+                cg.instructions.push(IrInstruction::I64Const(-1));
+                cg.instructions.push(IrInstruction::I64Eq);
+                cg.instructions.push(IrInstruction::If(BlockType::Result(ValType::I32)));
+                cg.instructions.push(IrInstruction::LocalGet(rhs));
+                cg.instructions.push(IrInstruction::I64Const(i64::MIN));
+                cg.instructions.push(IrInstruction::I64Eq);
+                cg.instructions.push(IrInstruction::Else);
+                cg.instructions.push(IrInstruction::LocalGet(result));
+                cg.instructions.push(IrInstruction::LocalGet(lhs));
+                cg.instructions.push(IrInstruction::I64DivS);
+                cg.instructions.push(IrInstruction::LocalGet(rhs));
+                cg.instructions.push(IrInstruction::I64Eq);
+                cg.instructions.push(IrInstruction::I32Eqz);
+                cg.instructions.push(IrInstruction::End);
+                cg.instructions.push(IrInstruction::End);
+            } else {
+                // Sign bit trick: `a + b` overflowed iff both operands differ in sign from
+                // the result, `a - b` iff the operands differ in sign and `a` from the result.
+                let (first, second) = if matches!(op, BinOp::Add) {
+                    ((lhs, result), (rhs, result))
+                } else {
+                    ((lhs, rhs), (lhs, result))
+                };
+                for (x, y) in [first, second] {
+                    cg.instructions.push(IrInstruction::LocalGet(x));
+                    cg.instructions.push(IrInstruction::LocalGet(y));
+                    cg.instructions.push(IrInstruction::I64Xor);
+                }
+                cg.instructions.push(IrInstruction::I64And);
+                cg.instructions.push(IrInstruction::I64Const(0));
+                cg.instructions.push(IrInstruction::I64LtS);
+            }
+            emit_overflow_trap(cg, funcs)?;
+            cg.instructions.push(IrInstruction::LocalGet(result));
+        }
+        Type::Int32 => {
+            // Exact in 64 bits; overflowed iff sign-extending the low half changes the value.
+            let rhs = cg.alloc_local(&Type::Int32)?;
+            let result = cg.alloc_local(&Type::Int)?;
+            cg.instructions.push(IrInstruction::LocalSet(rhs));
+            cg.instructions.push(IrInstruction::I64ExtendI32S);
+            cg.instructions.push(IrInstruction::LocalGet(rhs));
+            cg.instructions.push(IrInstruction::I64ExtendI32S);
+            cg.instructions.push(match op {
+                BinOp::Add => IrInstruction::I64Add,
+                BinOp::Sub => IrInstruction::I64Sub,
+                _ => IrInstruction::I64Mul,
+            });
+            cg.instructions.push(IrInstruction::LocalSet(result));
+            cg.instructions.push(IrInstruction::LocalGet(result));
+            cg.instructions.push(IrInstruction::LocalGet(result));
+            cg.instructions.push(IrInstruction::I32WrapI64);
+            cg.instructions.push(IrInstruction::I64ExtendI32S);
+            cg.instructions.push(IrInstruction::I64Eq);
+            cg.instructions.push(IrInstruction::I32Eqz);
+            emit_overflow_trap(cg, funcs)?;
+            cg.instructions.push(IrInstruction::LocalGet(result));
+            cg.instructions.push(IrInstruction::I32WrapI64);
+        }
+        Type::UInt8 => {
+            // Exact in 32 bits; a negative difference shows up as a huge unsigned value.
+            let result = cg.alloc_local(&Type::UInt8)?;
+            cg.instructions.push(match op {
+                BinOp::Add => IrInstruction::I32Add,
+                BinOp::Sub => IrInstruction::I32Sub,
+                _ => IrInstruction::I32Mul,
+            });
+            cg.instructions.push(IrInstruction::LocalSet(result));
+            cg.instructions.push(IrInstruction::LocalGet(result));
+            cg.instructions.push(IrInstruction::I32Const(u8::MAX as i32));
+            cg.instructions.push(IrInstruction::I32GtU);
+            emit_overflow_trap(cg, funcs)?;
+            cg.instructions.push(IrInstruction::LocalGet(result));
+        }
+        other => {
+            return Err(CodegenError::UnsupportedType(format!(
+                "checked arithmetic on {}",
+                other
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Consumes an `i32` condition and, if it is set, aborts with the current function's name.
+fn emit_overflow_trap(
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(), CodegenError> {
+    cg.instructions.push(IrInstruction::If(BlockType::Empty));
+    let name = cg.function_name.clone();
+    push_static_str(cg, &name);
+    call_runtime(cg, funcs, OVERFLOW_IMPORT)?;
+    cg.instructions.push(IrInstruction::Unreachable);
+    cg.instructions.push(IrInstruction::End);
+    Ok(())
+}
+
+/// Emits `expr` in a position whose type is fixed by context (`let` annotations,
+/// parameters, fields, returns), which is what gives `[]` its element type.
+pub fn emit_expr_expected(
+    expr: &Expr,
+    expected: &Type,
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    if let Some(call) = lower_method_syntax(expr, cg, funcs)? {
+        return emit_expr_expected(&call, expected, cg, funcs);
+    }
+    if let (Some(value), Type::Int32 | Type::UInt8) = (expr.int_literal(), expected) {
+        // The type checker made sure the literal fits.
+        cg.instructions.push(IrInstruction::I32Const(value as i32));
+        return Ok(true);
+    }
+    if matches!(expected, Type::Int32 | Type::UInt8) && expr.is_untyped_int() {
+        // Literal-only arithmetic (`2 * 3`) is computed in the expected integer type.
+        match &expr.kind {
+            ExprKind::Binary { op, left, right } => {
+                emit_expr_expected(left, expected, cg, funcs)?;
+                emit_expr_expected(right, expected, cg, funcs)?;
+                emit_binary_op(op, expected, cg, funcs)?;
+                return Ok(true);
+            }
+            ExprKind::Neg(inner) => {
+                cg.instructions.push(IrInstruction::I32Const(0));
+                emit_expr_expected(inner, expected, cg, funcs)?;
+                emit_binary_op(&BinOp::Sub, expected, cg, funcs)?;
+                return Ok(true);
+            }
+            _ => {}
+        }
+    }
+    match (&expr.kind, expected) {
+        (ExprKind::ListLiteral(elements), Type::List(element_ty)) => {
+            emit_list_literal(elements, element_ty, cg, funcs)
+        }
+        (ExprKind::TupleLiteral(elements), Type::Tuple(element_tys))
+            if elements.len() == element_tys.len() =>
+        {
+            emit_tuple_literal(elements, element_tys, cg, funcs)
+        }
+        _ => {
+            // Generic calls and constructors may need the expected type to pick their type arguments.
+            let hint = Some(expected);
+            let produced = match &expr.kind {
+                ExprKind::Call { name, args } => emit_call(name, args, hint, cg, funcs)?,
+                ExprKind::StructInit { name, fields } => emit_struct_init(name, fields, hint, cg, funcs)?,
+                ExprKind::EnumInit {
+                    enum_name,
+                    variant,
+                    payload,
+                } => emit_enum_init(enum_name, variant, payload, hint, cg, funcs)?,
+                ExprKind::Closure { params, body } => emit_closure(params, body, hint, cg, funcs)?,
+                _ => emit_expr(expr, cg, funcs)?,
+            };
+            // Auto-deref: a `&T` flowing into a `T` position is unwrapped level by level.
+            let mut actual = infer_expr_type_hinted(expr, hint, cg, funcs)?;
+            while &actual != expected
+                && let Type::Ref(inner) = &actual
+            {
+                emit_deref_step(&actual, cg, funcs)?;
+                actual = (**inner).clone();
+            }
+            Ok(produced)
+        }
+    }
+}
+
+/// Turns a `&T` on the stack into `T`: unboxes scalars, handles stay as they are.
+fn emit_deref_step(
+    ref_ty: &Type,
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(), CodegenError> {
+    let Type::Ref(inner) = ref_ty else {
+        return Err(CodegenError::UnsupportedType(format!("dereferencing {}", ref_ty)));
+    };
+    if let Some(lane) = RuntimeLane::for_type(inner).filter(|lane| lane.is_scalar()) {
+        call_runtime(cg, funcs, &unbox_import_name(lane))?;
+    }
+    Ok(())
+}
+
+/// Builds a heap tuple: `__eres_tuple_new` followed by one positional push per element.
+fn emit_tuple_literal(
+    elements: &[Expr],
+    element_tys: &[Type],
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    call_runtime(cg, funcs, TUPLE_NEW_IMPORT)?;
+    for (element, element_ty) in elements.iter().zip(element_tys) {
+        let lane = lane_for(element_ty)?;
+        cg.instructions.push(IrInstruction::I32Const(0));
+        cg.instructions.push(IrInstruction::I32Const(0));
+        let _ = emit_expr_expected(element, element_ty, cg, funcs)?;
+        call_runtime(cg, funcs, &aggregate_push_import_name(lane))?;
+    }
+    Ok(true)
+}
+
+/// Builds a heap list: `__eres_list_new` followed by one push per element.
+fn emit_list_literal(
+    elements: &[Expr],
+    element_ty: &Type,
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    let lane = lane_for(element_ty)?;
+    push_static_str(cg, &element_ty.to_string());
+    call_runtime(cg, funcs, LIST_NEW_IMPORT)?;
+    for element in elements {
+        cg.instructions.push(IrInstruction::I32Const(0));
+        cg.instructions.push(IrInstruction::I32Const(0));
+        let _ = emit_expr_expected(element, element_ty, cg, funcs)?;
+        call_runtime(cg, funcs, &aggregate_push_import_name(lane))?;
+    }
+    Ok(true)
+}
+
+fn list_literal_element_type(
+    elements: &[Expr],
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<Type, CodegenError> {
+    let first = elements.first().ok_or_else(|| {
+        CodegenError::UnsupportedType("empty list literal without a type context".to_string())
+    })?;
+    infer_expr_type(first, cg, funcs)
+}
+
+pub(crate) fn index_element_type(
+    base: &Expr,
+    index: &Expr,
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<Type, CodegenError> {
+    match infer_expr_type(base, cg, funcs)? {
+        Type::List(element_ty) => Ok(*element_ty),
+        Type::Tuple(elements) => match &index.kind {
+            ExprKind::Int(pos) => usize::try_from(*pos)
+                .ok()
+                .and_then(|pos| elements.get(pos).cloned())
+                .ok_or_else(|| {
+                    CodegenError::UnsupportedType(format!("tuple index {} out of range", pos))
+                }),
+            _ => Err(CodegenError::UnsupportedType(
+                "non-constant tuple index".to_string(),
+            )),
+        },
+        other => Err(CodegenError::UnsupportedType(format!("indexing into {}", other))),
+    }
+}
+
+/// Rewrites user method calls (`p.norm()`) and associated function calls (`Point::new(..)`)
+/// to plain calls of the lowered `Type::name` functions; `None` for any other expression.
+fn lower_method_syntax(
+    expr: &Expr,
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<Option<Expr>, CodegenError> {
+    let first_param = |function: &str| {
+        funcs
+            .get(function)
+            .and_then(|(_, params, _)| params.first().cloned())
+            .or_else(|| {
+                let template = cg.generics.get(function)?;
+                template.typed.params.first().map(|param| param.ty.clone())
+            })
+    };
+    match &expr.kind {
+        ExprKind::MethodCall {
+            receiver,
+            name,
+            args,
+        } => {
+            let receiver_ty = infer_expr_type(receiver, cg, funcs)?;
+            let mut base_ty = &receiver_ty;
+            while let Type::Ref(inner) = base_ty {
+                base_ty = inner;
+            }
+            let Some(type_name) = base_ty.impl_name() else {
+                return Ok(None);
+            };
+            let function = method_function_name(type_name, name);
+            Ok(first_param(&function)
+                .map(|self_ty| method_call(receiver, &receiver_ty, function, &self_ty, args)))
+        }
+        ExprKind::EnumInit {
+            enum_name,
+            variant,
+            payload,
+        } => {
+            let is_variant = cg
+                .type_decls
+                .enums
+                .get(enum_name)
+                .is_some_and(|def| def.variants.iter().any(|candidate| candidate.name() == *variant));
+            let function = method_function_name(enum_name, variant);
+            let is_function = funcs.contains_key(&function) || cg.generics.contains_key(&function);
+            let call = ExprKind::Call {
+                name: function,
+                args: payload.clone(),
+            };
+            Ok((!is_variant && is_function).then(|| Expr::new(call, expr.span.clone())))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Lowers the built-in list methods `len`, `get`, `push` and `pop`.
+fn emit_list_method(
+    receiver: &Expr,
+    name: &str,
+    args: &[Expr],
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    let element_ty = match infer_expr_type(receiver, cg, funcs)? {
+        Type::List(element_ty) => *element_ty,
+        other => {
+            return Err(CodegenError::UnsupportedType(format!(
+                "method `{}` on {}",
+                name, other
+            )));
+        }
+    };
+    let lane = lane_for(&element_ty)?;
+    let _ = emit_expr(receiver, cg, funcs)?;
+    match (name, args) {
+        ("len", []) => {
+            call_runtime(cg, funcs, AGGREGATE_LEN_IMPORT)?;
+            Ok(true)
+        }
+        ("get", [index]) => {
+            // `Some(xs[i])` when `0 <= i < len` (one unsigned compare), `None` otherwise.
+            let list_ty = Type::List(Box::new(element_ty.clone()));
+            let list = cg.alloc_local(&list_ty)?;
+            let position = cg.alloc_local(&Type::Int)?;
+            cg.instructions.push(IrInstruction::LocalSet(list));
+            let _ = emit_expr(index, cg, funcs)?;
+            cg.instructions.push(IrInstruction::LocalSet(position));
+            cg.instructions.push(IrInstruction::LocalGet(position));
+            cg.instructions.push(IrInstruction::LocalGet(list));
+            call_runtime(cg, funcs, AGGREGATE_LEN_IMPORT)?;
+            cg.instructions.push(IrInstruction::I64LtU);
+            cg.instructions.push(IrInstruction::If(BlockType::Result(ValType::I32)));
+            push_static_str(cg, OPTION);
+            push_static_str(cg, "Some");
+            call_runtime(cg, funcs, ENUM_NEW_IMPORT)?;
+            cg.instructions.push(IrInstruction::I32Const(0));
+            cg.instructions.push(IrInstruction::I32Const(0));
+            cg.instructions.push(IrInstruction::LocalGet(list));
+            cg.instructions.push(IrInstruction::LocalGet(position));
+            call_runtime(cg, funcs, &aggregate_get_import_name(lane))?;
+            call_runtime(cg, funcs, &aggregate_push_import_name(lane))?;
+            cg.instructions.push(IrInstruction::Else);
+            push_static_str(cg, OPTION);
+            push_static_str(cg, "None");
+            call_runtime(cg, funcs, ENUM_NEW_IMPORT)?;
+            cg.instructions.push(IrInstruction::End);
+            Ok(true)
+        }
+        ("push", [value]) => {
+            cg.instructions.push(IrInstruction::I32Const(0));
+            cg.instructions.push(IrInstruction::I32Const(0));
+            let _ = emit_expr_expected(value, &element_ty, cg, funcs)?;
+            call_runtime(cg, funcs, &aggregate_push_import_name(lane))?;
+            cg.instructions.push(IrInstruction::Drop);
+            Ok(false)
+        }
+        ("pop", []) => {
+            call_runtime(cg, funcs, &aggregate_pop_import_name(lane))?;
+            Ok(true)
+        }
+        _ => Err(CodegenError::UnsupportedType(format!(
+            "list method `{}` with {} arguments",
+            name,
+            args.len()
+        ))),
+    }
+}
+
+fn list_method_type(
+    receiver: &Expr,
+    name: &str,
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<Type, CodegenError> {
+    match (name, infer_expr_type(receiver, cg, funcs)?) {
+        ("len", Type::List(_)) => Ok(Type::Int),
+        ("get", Type::List(element_ty)) => Ok(option_of(*element_ty)),
+        ("pop", Type::List(element_ty)) => Ok(*element_ty),
+        ("push", Type::List(_)) => Ok(Type::Unit),
+        (_, other) => Err(CodegenError::UnsupportedType(format!(
+            "method `{}` on {}",
+            name, other
+        ))),
+    }
+}
+
+/// Lowers `match` to a `br_table` over the runtime variant tag.
+///
+/// Layout: one nested `block` per arm, the innermost block holds the dispatch.
+/// Branching to depth `i` leaves the blocks of arms `0..=i` and runs arm `i`,
+/// which then branches out of the remaining arm blocks to the result block.
+fn emit_match(
+    subject: &Expr,
+    arms: &[MatchArm],
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<bool, CodegenError> {
+    let (def, result_ty) = match_types(subject, arms, cg, funcs)?;
+    let result_block = if result_ty == Type::Unit {
+        BlockType::Empty
+    } else {
+        BlockType::Result(wasm_val_type_for(&result_ty).ok_or_else(|| {
+            CodegenError::UnsupportedType(format!("{} match results", result_ty))
+        })?)
+    };
+
+    let subject_ty = infer_expr_type(subject, cg, funcs)?;
+    let subject_local = cg.alloc_local(&subject_ty)?;
+    let _ = emit_expr(subject, cg, funcs)?;
+    cg.instructions.push(IrInstruction::LocalSet(subject_local));
+
+    let mut targets = Vec::with_capacity(def.variants.len());
+    for variant in &def.variants {
+        let arm_pos = arms
+            .iter()
+            .position(|arm| arm.pattern.variant_name() == variant.name())
+            .ok_or_else(|| {
+                CodegenError::UnsupportedType(format!(
+                    "non-exhaustive match on `{}`",
+                    def.name
+                ))
+            })?;
+        targets.push(arm_pos as u32);
+    }
+    let variant_list = def
+        .variants
+        .iter()
+        .map(EnumVariant::name)
+        .collect::<Vec<_>>()
+        .join(",");
+
+    cg.instructions.push(IrInstruction::Block(result_block));
+    for _ in arms {
+        cg.instructions.push(IrInstruction::Block(BlockType::Empty));
+    }
+    cg.instructions.push(IrInstruction::LocalGet(subject_local));
+    push_static_str(cg, &variant_list);
+    call_runtime(cg, funcs, ENUM_TAG_IMPORT)?;
+    let default = targets.last().copied().unwrap_or(0);
+    cg.instructions.push(IrInstruction::BrTable(targets, default));
+
+    for (pos, arm) in arms.iter().enumerate() {
+        cg.instructions.push(IrInstruction::End);
+        let variant = find_variant(&def, &arm.pattern.variant_name())?.clone();
+        let bindings = pattern_bindings(&arm.pattern, &variant)?;
+
+        let mut shadowed = Vec::new();
+        for (name, index, ty) in bindings {
+            let lane = lane_for(&ty)?;
+            let local = cg.alloc_local(&ty)?;
+            cg.instructions.push(IrInstruction::LocalGet(subject_local));
+            cg.instructions.push(IrInstruction::I64Const(index as i64));
+            call_runtime(cg, funcs, &aggregate_get_import_name(lane))?;
+            cg.instructions.push(IrInstruction::LocalSet(local));
+            shadowed.push((name.clone(), cg.local_map.insert(name, (local, ty))));
+        }
+
+        let body = if result_ty == Type::Unit {
+            emit_expr(&arm.body, cg, funcs)
+        } else {
+            emit_expr_expected(&arm.body, &result_ty, cg, funcs)
+        };
+        for (name, previous) in shadowed.into_iter().rev() {
+            match previous {
+                Some(previous) => cg.local_map.insert(name, previous),
+                None => cg.local_map.remove(&name),
+            };
+        }
+        let produced = body?;
+        if produced && result_ty == Type::Unit {
+            cg.instructions.push(IrInstruction::Drop);
+        }
+        cg.instructions
+            .push(IrInstruction::Br((arms.len() - 1 - pos) as u32));
+    }
+    cg.instructions.push(IrInstruction::End);
+    Ok(result_ty != Type::Unit)
+}
+
+/// Resolves the matched enum and the common arm result type.
+fn match_types(
+    subject: &Expr,
+    arms: &[MatchArm],
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(EnumDecl, Type), CodegenError> {
+    let def = match infer_expr_type(subject, cg, funcs)? {
+        Type::Named(name) => lookup_enum(cg, &name)?,
+        Type::Generic(name, args) => lookup_enum(cg, &name)?.instantiate(&args),
+        other => {
+            return Err(CodegenError::UnsupportedType(format!(
+                "match on {}",
+                other
+            )));
+        }
+    };
+    // The first arm whose body has a type on its own decides; `None => []` does not.
+    let mut first_err = None;
+    for arm in arms {
+        let variant = find_variant(&def, &arm.pattern.variant_name())?;
+        let scope = cg.type_scope(
+            pattern_bindings(&arm.pattern, variant)?
+                .into_iter()
+                .map(|(name, _, ty)| (name, ty)),
+        );
+        match infer_expr_type(&arm.body, &scope, funcs) {
+            Ok(result_ty) => return Ok((def, result_ty)),
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    match first_err {
+        Some(err) => Err(err),
+        None => Ok((def, Type::Unit)),
+    }
+}
+
+/// Payload slots of a variant in push order: `(field key, type)`.
+fn payload_slots(variant: &EnumVariant) -> Vec<(Option<String>, Type)> {
+    match variant {
+        EnumVariant::Unit(_) => Vec::new(),
+        EnumVariant::Tuple(_, ty) => vec![(None, ty.clone())],
+        EnumVariant::Struct(_, fields) => fields
+            .iter()
+            .map(|(name, ty)| (Some(name.clone()), ty.clone()))
+            .collect(),
+    }
+}
+
+/// Pattern bindings as `(name, payload position, type)`.
+fn pattern_bindings(
+    pattern: &Pattern,
+    variant: &EnumVariant,
+) -> Result<Vec<(String, usize, Type)>, CodegenError> {
+    match (pattern, variant) {
+        (Pattern::UnitVariant(_), _) => Ok(Vec::new()),
+        (Pattern::TupleVariant(_, names), EnumVariant::Tuple(_, ty)) => Ok(names
+            .iter()
+            .map(|binding| (binding.name.clone(), 0, ty.clone()))
+            .collect()),
+        (Pattern::StructVariant(_, names), EnumVariant::Struct(_, fields)) => names
+            .iter()
+            .map(|binding| &binding.name)
+            .map(|name| {
+                fields
+                    .iter()
+                    .position(|(field, _)| field == name)
+                    .map(|index| (name.clone(), index, fields[index].1.clone()))
+                    .ok_or_else(|| {
+                        CodegenError::UnsupportedType(format!(
+                            "unknown payload field `{}` in pattern",
+                            name
+                        ))
+                    })
+            })
+            .collect(),
+        _ => Err(CodegenError::UnsupportedType(format!(
+            "pattern shape does not match variant `{}`",
+            variant.name()
+        ))),
+    }
+}
+
+/// Like `infer_expr_type`, but lets generic calls and constructors use the expected type.
+fn infer_expr_type_hinted(
+    expr: &Expr,
+    hint: Option<&Type>,
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<Type, CodegenError> {
+    if let Some(call) = lower_method_syntax(expr, cg, funcs)? {
+        return infer_expr_type_hinted(&call, hint, cg, funcs);
+    }
+    if let (true, Some(ty @ (Type::Int32 | Type::UInt8))) = (expr.is_untyped_int(), hint) {
+        return Ok(ty.clone());
+    }
+    match &expr.kind {
+        ExprKind::Call { name, args } => resolve_call(name, args, hint, cg, funcs).map(|(_, _, ret)| ret),
+        ExprKind::StructInit { name, fields } => {
+            struct_init_type(name, fields, hint, cg, funcs).map(|(_, ty)| ty)
+        }
+        ExprKind::EnumInit {
+            enum_name,
+            variant,
+            payload,
+        } => enum_init_type(enum_name, variant, payload, hint, cg, funcs).map(|(_, ty)| ty),
+        ExprKind::Closure { params, body } => {
+            let (param_hint, ret_hint) = match hint {
+                Some(Type::Function(param_tys, ret)) if param_tys.len() == params.len() => {
+                    (Some(param_tys.as_slice()), Some(&**ret))
+                }
+                _ => (None, None),
+            };
+            closure_type(params, body, param_hint, ret_hint, cg, funcs)
+                .map(|(param_tys, ret)| Type::Function(param_tys, Box::new(ret)))
+        }
+        _ => infer_expr_type(expr, cg, funcs),
     }
 }
 
@@ -178,51 +1582,73 @@ pub(crate) fn infer_expr_type(
     cg: &FuncGen,
     funcs: &HashMap<String, FunctionSig>,
 ) -> Result<Type, CodegenError> {
-    match expr {
-        Expr::Int(_) => Ok(Type::Int),
-        Expr::Float(_) => Ok(Type::Float),
-        Expr::Bool(_) => Ok(Type::Bool),
-        Expr::String(_) => Ok(Type::String),
-        Expr::ListLiteral(_) => Err(CodegenError::UnsupportedType(
-            "list literal lowering".to_string(),
-        )),
-        Expr::TupleLiteral(_) => Err(CodegenError::UnsupportedType(
-            "tuple literal lowering".to_string(),
-        )),
-        Expr::Index { .. } => Err(CodegenError::UnsupportedType(
-            "index expression lowering".to_string(),
-        )),
-        Expr::MethodCall { .. } => Err(CodegenError::UnsupportedType(
-            "method call lowering".to_string(),
+    if let Some(call) = lower_method_syntax(expr, cg, funcs)? {
+        return infer_expr_type(&call, cg, funcs);
+    }
+    match &expr.kind {
+        ExprKind::Int(_) => Ok(Type::Int),
+        ExprKind::Float(_) => Ok(Type::Float),
+        ExprKind::Bool(_) => Ok(Type::Bool),
+        ExprKind::String(_) | ExprKind::Interpolation(_) => Ok(Type::String),
+        ExprKind::ListLiteral(elements) => {
+            list_literal_element_type(elements, cg, funcs).map(|ty| Type::List(Box::new(ty)))
+        }
+        ExprKind::TupleLiteral(elements) => elements
+            .iter()
+            .map(|element| infer_expr_type(element, cg, funcs))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Type::Tuple),
+        ExprKind::Index { base, index } => index_element_type(base, index, cg, funcs),
+        ExprKind::Range { .. } => Err(CodegenError::UnsupportedType(
+            "range outside of a `for` loop".to_string(),
         )),
-        Expr::Local(name) => cg
-            .local_map
-            .get(name)
-            .map(|(_, ty)| ty.clone())
-            .ok_or_else(|| CodegenError::UnknownLocal { name: name.clone() }),
-        Expr::Ref(_) => Err(CodegenError::UnsupportedType("&expr lowering".to_string())),
-        Expr::Deref(inner) => match infer_expr_type(inner, cg, funcs)? {
+        ExprKind::MethodCall { receiver, name, .. } => list_method_type(receiver, name, cg, funcs),
+        ExprKind::Local(name) => match (cg.local_map.get(name), funcs.get(name)) {
+            (Some((_, ty)), _) => Ok(ty.clone()),
+            (None, _) if cg.consts.contains_key(name) => Ok(cg.consts[name].ty.clone()),
+            (None, _) if cg.globals.contains_key(name) => Ok(cg.globals[name].1.clone()),
+            (None, Some((_, params, ret))) => {
+                Ok(Type::Function(params.clone(), Box::new(ret.clone())))
+            }
+            (None, None) => Err(CodegenError::UnknownLocal { name: name.clone() }),
+        },
+        ExprKind::Ref(inner) => infer_expr_type(inner, cg, funcs).map(|ty| Type::Ref(Box::new(ty))),
+        ExprKind::Deref(inner) => match infer_expr_type(inner, cg, funcs)? {
             Type::Ref(inner) => Ok(*inner),
-            _ => Err(CodegenError::UnsupportedType("*expr lowering".to_string())),
+            other => Err(CodegenError::UnsupportedType(format!("dereferencing {}", other))),
         },
-            Expr::Binary { op, left, right } => {
-                let left_ty = infer_expr_type(left, cg, funcs)?;
-                let right_ty = infer_expr_type(right, cg, funcs)?;
-                if left_ty != right_ty {
-                    return Err(CodegenError::UnsupportedType(format!(
-                        "binary type mismatch: {} vs {}",
-                    left_ty, right_ty
-                )));
+        ExprKind::Not(_) => Ok(Type::Bool),
+        ExprKind::Cast { ty, .. } => Ok(ty.clone()),
+        ExprKind::Try(inner) => {
+            let operand_ty = infer_expr_type(inner, cg, funcs)?;
+            try_payload_type(&operand_ty)
+                .cloned()
+                .ok_or_else(|| CodegenError::UnsupportedType(format!("`?` on {}", operand_ty)))
+        }
+        ExprKind::Neg(inner) => {
+            let mut ty = infer_expr_type(inner, cg, funcs)?;
+            while let Type::Ref(inner) = ty {
+                ty = *inner;
             }
+            Ok(ty)
+        }
+        ExprKind::Binary { op: BinOp::And | BinOp::Or, .. } => Ok(Type::Bool),
+            ExprKind::Binary { op, left, right } => {
+                let left_ty = binary_operand_type(left, right, cg, funcs)?;
                 match (op, left_ty.clone()) {
-                    (
-                        BinOp::Eq | BinOp::NotEq,
-                        Type::Int | Type::Float | Type::Bool | Type::String,
-                    ) => Ok(Type::Bool),
-                    (BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge, Type::Int | Type::Float) => {
+                    (BinOp::Eq | BinOp::NotEq, Type::Bool | Type::String) => Ok(Type::Bool),
+                    (BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge, Type::String) => {
                         Ok(Type::Bool)
                     }
-                    (BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div, Type::Int | Type::Float) => {
+                    (BinOp::Add, Type::String) => Ok(Type::String),
+                    (BinOp::Eq | BinOp::NotEq | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge, ty)
+                        if ty.is_numeric() =>
+                    {
+                        Ok(Type::Bool)
+                    }
+                    (BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem, ty)
+                        if ty.is_numeric() =>
+                    {
                         Ok(left_ty)
                     }
                     _ => Err(CodegenError::UnsupportedType(format!(
@@ -231,19 +1657,104 @@ pub(crate) fn infer_expr_type(
                     ))),
                 }
             }
-        Expr::Call { name, .. } => {
-            let (_idx, _params, ret) = funcs
-                .get(name)
-                .cloned()
-                .ok_or_else(|| CodegenError::UnknownFunction { name: name.clone() })?;
-            Ok(ret)
+        ExprKind::Call { .. }
+        | ExprKind::StructInit { .. }
+        | ExprKind::EnumInit { .. }
+        | ExprKind::Closure { .. } => {
+            infer_expr_type_hinted(expr, None, cg, funcs)
         }
-        Expr::StructInit { .. } => Err(CodegenError::UnsupportedType(
-            "struct initialization lowering".to_string(),
-        )),
-        Expr::EnumInit { .. } => Err(CodegenError::UnsupportedType(
-            "enum initialization lowering".to_string(),
-        )),
-        Expr::Match { .. } => Err(CodegenError::UnsupportedType("match lowering".to_string())),
+        ExprKind::Field { base, name } => resolve_field(cg, funcs, base, name).map(|(_, ty)| ty),
+        ExprKind::Match { subject, arms } => match_types(subject, arms, cg, funcs).map(|(_, ty)| ty),
     }
 }
+
+/// Emits a call to a runtime or host import by name.
+pub(crate) fn call_runtime(
+    cg: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+    name: &str,
+) -> Result<(), CodegenError> {
+    let (idx, _, _) = funcs
+        .get(name)
+        .cloned()
+        .ok_or_else(|| CodegenError::UnknownFunction {
+            name: name.to_string(),
+        })?;
+    cg.instructions.push(IrInstruction::Call(idx));
+    Ok(())
+}
+
+/// Pushes the `(ptr, len)` pair of a static string from the data segment.
+fn push_static_str(cg: &mut FuncGen, value: &str) {
+    let (ptr, len) = cg.static_data.intern(value);
+    cg.instructions.push(IrInstruction::I32Const(ptr));
+    cg.instructions.push(IrInstruction::I32Const(len));
+}
+
+pub(crate) fn lane_for(ty: &Type) -> Result<RuntimeLane, CodegenError> {
+    RuntimeLane::for_type(ty).ok_or_else(|| {
+        CodegenError::UnsupportedType(format!("{} values inside heap aggregates", ty))
+    })
+}
+
+fn lookup_struct(cg: &FuncGen, name: &str) -> Result<StructDecl, CodegenError> {
+    cg.type_decls
+        .structs
+        .get(name)
+        .cloned()
+        .ok_or_else(|| CodegenError::UnsupportedType(format!("unknown struct `{}`", name)))
+}
+
+fn lookup_enum(cg: &FuncGen, name: &str) -> Result<EnumDecl, CodegenError> {
+    cg.type_decls
+        .enums
+        .get(name)
+        .cloned()
+        .ok_or_else(|| CodegenError::UnsupportedType(format!("unknown enum `{}`", name)))
+}
+
+fn find_variant<'a>(def: &'a EnumDecl, variant: &str) -> Result<&'a EnumVariant, CodegenError> {
+    def.variants
+        .iter()
+        .find(|candidate| candidate.name() == variant)
+        .ok_or_else(|| {
+            CodegenError::UnsupportedType(format!(
+                "enum `{}` has no variant `{}`",
+                def.name, variant
+            ))
+        })
+}
+
+/// Resolves `base.name` to the field position and type.
+pub(crate) fn resolve_field(
+    cg: &FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+    base: &Expr,
+    name: &str,
+) -> Result<(usize, Type), CodegenError> {
+    let mut base_ty = infer_expr_type(base, cg, funcs)?;
+    while let Type::Ref(inner) = base_ty {
+        base_ty = *inner;
+    }
+    let def = match base_ty {
+        Type::Named(struct_name) => lookup_struct(cg, &struct_name)?,
+        Type::Generic(struct_name, args) => lookup_struct(cg, &struct_name)?.instantiate(&args),
+        other => {
+            return Err(CodegenError::UnsupportedType(format!(
+                "field access `.{}` on {}",
+                name, other
+            )));
+        }
+    };
+    let struct_name = def.name.clone();
+    def.fields
+        .iter()
+        .position(|(field, _)| field == name)
+        .map(|index| (index, def.fields[index].1.clone()))
+        .ok_or_else(|| {
+            CodegenError::UnsupportedType(format!(
+                "struct `{}` has no field `{}`",
+                struct_name, name
+            ))
+        })
+}
@@ -8,15 +8,22 @@ pub enum IrInstruction {
     I64Eqz,
     BrIf(u32),
     Br(u32),
+    /// `br_table` with per-index targets and a default target.
+    BrTable(Vec<u32>, u32),
     LocalSet(u32),
     LocalGet(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
     Call(u32),
+    /// `call_indirect` through table 0 with the given type index.
+    CallIndirect(u32),
     If(BlockType),
     Else,
     Block(BlockType),
     Loop(BlockType),
     Drop,
     Return,
+    Unreachable,
     End,
 
     // Arithmetic
@@ -24,20 +31,51 @@ pub enum IrInstruction {
     I64Sub,
     I64Mul,
     I64DivS,
+    I64RemS,
+    I64And,
+    I64Xor,
     F64Add,
     F64Sub,
     F64Mul,
     F64Div,
+    F64Neg,
+    F64Trunc,
 
     I64Eq,
     I64LtS,
+    I64LtU,
     I64GtS,
     I32Eq,
     F64Eq,
     F64Lt,
     F64Gt,
 
+    I32Add,
+    I32Sub,
+    I32Mul,
+    I32DivS,
+    I32DivU,
+    I32RemS,
+    I32RemU,
+    I32And,
+    I32LtS,
+    I32LtU,
+    I32GtS,
+    I32GtU,
+    F64Min,
+
     I32Eqz,
+    I32WrapI64,
+
+    // Conversions
+    I64ExtendI32S,
+    I64ExtendI32U,
+    F64ConvertI64S,
+    F64ConvertI32S,
+    F64ConvertI32U,
+    I64TruncSatF64S,
+    I32TruncSatF64S,
+    I32TruncSatF64U,
 }
 
 impl IrInstruction {
@@ -50,32 +88,70 @@ impl IrInstruction {
             IrInstruction::I64Eqz => Instruction::I64Eqz,
             IrInstruction::BrIf(idx) => Instruction::BrIf(*idx),
             IrInstruction::Br(idx) => Instruction::Br(*idx),
+            IrInstruction::BrTable(targets, default) => {
+                Instruction::BrTable(targets.as_slice().into(), *default)
+            }
             IrInstruction::LocalSet(idx) => Instruction::LocalSet(*idx),
             IrInstruction::LocalGet(idx) => Instruction::LocalGet(*idx),
+            IrInstruction::GlobalGet(idx) => Instruction::GlobalGet(*idx),
+            IrInstruction::GlobalSet(idx) => Instruction::GlobalSet(*idx),
             IrInstruction::Call(idx) => Instruction::Call(*idx),
+            IrInstruction::CallIndirect(type_index) => Instruction::CallIndirect {
+                type_index: *type_index,
+                table_index: 0,
+            },
             IrInstruction::If(block_type) => Instruction::If(*block_type),
             IrInstruction::Else => Instruction::Else,
             IrInstruction::Block(block_type) => Instruction::Block(*block_type),
             IrInstruction::Loop(block_type) => Instruction::Loop(*block_type),
             IrInstruction::Drop => Instruction::Drop,
             IrInstruction::Return => Instruction::Return,
+            IrInstruction::Unreachable => Instruction::Unreachable,
             IrInstruction::End => Instruction::End,
             IrInstruction::I64Add => Instruction::I64Add,
             IrInstruction::I64Sub => Instruction::I64Sub,
             IrInstruction::I64Mul => Instruction::I64Mul,
             IrInstruction::I64DivS => Instruction::I64DivS,
+            IrInstruction::I64RemS => Instruction::I64RemS,
+            IrInstruction::I64And => Instruction::I64And,
+            IrInstruction::I64Xor => Instruction::I64Xor,
             IrInstruction::F64Add => Instruction::F64Add,
             IrInstruction::F64Sub => Instruction::F64Sub,
             IrInstruction::F64Mul => Instruction::F64Mul,
             IrInstruction::F64Div => Instruction::F64Div,
+            IrInstruction::F64Neg => Instruction::F64Neg,
+            IrInstruction::F64Trunc => Instruction::F64Trunc,
             IrInstruction::I64Eq => Instruction::I64Eq,
             IrInstruction::I64LtS => Instruction::I64LtS,
+            IrInstruction::I64LtU => Instruction::I64LtU,
             IrInstruction::I64GtS => Instruction::I64GtS,
             IrInstruction::I32Eq => Instruction::I32Eq,
             IrInstruction::F64Eq => Instruction::F64Eq,
             IrInstruction::F64Lt => Instruction::F64Lt,
             IrInstruction::F64Gt => Instruction::F64Gt,
             IrInstruction::I32Eqz => Instruction::I32Eqz,
+            IrInstruction::I32WrapI64 => Instruction::I32WrapI64,
+            IrInstruction::I32Add => Instruction::I32Add,
+            IrInstruction::I32Sub => Instruction::I32Sub,
+            IrInstruction::I32Mul => Instruction::I32Mul,
+            IrInstruction::I32DivS => Instruction::I32DivS,
+            IrInstruction::I32DivU => Instruction::I32DivU,
+            IrInstruction::I32RemS => Instruction::I32RemS,
+            IrInstruction::I32RemU => Instruction::I32RemU,
+            IrInstruction::I32And => Instruction::I32And,
+            IrInstruction::I32LtS => Instruction::I32LtS,
+            IrInstruction::I32LtU => Instruction::I32LtU,
+            IrInstruction::I32GtS => Instruction::I32GtS,
+            IrInstruction::I32GtU => Instruction::I32GtU,
+            IrInstruction::F64Min => Instruction::F64Min,
+            IrInstruction::I64ExtendI32S => Instruction::I64ExtendI32S,
+            IrInstruction::I64ExtendI32U => Instruction::I64ExtendI32U,
+            IrInstruction::F64ConvertI64S => Instruction::F64ConvertI64S,
+            IrInstruction::F64ConvertI32S => Instruction::F64ConvertI32S,
+            IrInstruction::F64ConvertI32U => Instruction::F64ConvertI32U,
+            IrInstruction::I64TruncSatF64S => Instruction::I64TruncSatF64S,
+            IrInstruction::I32TruncSatF64S => Instruction::I32TruncSatF64S,
+            IrInstruction::I32TruncSatF64U => Instruction::I32TruncSatF64U,
         }
     }
 }
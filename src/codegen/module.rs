@@ -1,11 +1,22 @@
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::rc::Rc;
 
-use crate::ast::{FunctionDecl, Type};
+use crate::ast::{EnumDecl, Expr, FunctionDecl, Stmt, StmtKind, StructDecl, Type, type_bindings};
+use crate::codegen::expr::{call_runtime, lane_for};
 use crate::codegen::ir::IrInstruction;
-use crate::runtime::{RuntimeImports, string_eq_import_name, string_literal_import_name};
-use crate::typing::TypedFunction;
+use crate::consteval::ConstValue;
+use crate::diagnostics::Diagnostic;
+use crate::host::abi_type_to_ast_type;
+use crate::runtime::{
+    AGGREGATE_LEN_IMPORT, ENUM_NEW_IMPORT, ENUM_TAG_IMPORT, LIST_NEW_IMPORT, MEMORY_EXPORT,
+    OVERFLOW_IMPORT, RuntimeImports, RuntimeLane, STRUCT_NEW_IMPORT, TUPLE_NEW_IMPORT, aggregate_get_import_name,
+    aggregate_pop_import_name, aggregate_push_import_name, aggregate_set_import_name,
+    box_import_name, string_cmp_import_name, string_concat_import_name, string_eq_import_name,
+    string_literal_import_name, to_string_import_name, unbox_import_name,
+};
+use crate::typing::{TypedConst, TypedFunction, TypedProgram, TypedStatic};
 use eres_abi::{AbiType, HostFunction};
 use wasm_encoder::*;
 
@@ -41,20 +52,231 @@ impl Display for CodegenError {
 
 impl Error for CodegenError {}
 
+impl CodegenError {
+    /// Stable diagnostic code, one per variant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CodegenError::DuplicateFunction { .. } => "E-CG01",
+            CodegenError::UnknownLocal { .. } => "E-CG02",
+            CodegenError::UnknownFunction { .. } => "E-CG03",
+            CodegenError::UnsupportedType(_) => "E-CG04",
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.code(), self.to_string())
+    }
+}
+
 /// Stateful WebAssembly module builder for language functions.
 pub type FunctionSig = (u32, Vec<Type>, Type);
 
 pub struct ModuleGen {
     module: Module,
-    types: TypeSection,
+    types: FuncTypes,
     imports: ImportSection,
     functions: FunctionSection,
     codes: CodeSection,
     exports: ExportSection,
 
     func_indices: HashMap<String, FunctionSig>,
-    next_type_index: u32,
     next_func_index: u32,
+
+    type_decls: Rc<TypeDecls>,
+    consts: Rc<HashMap<String, TypedConst>>,
+    globals: Rc<HashMap<String, (u32, Type)>>,
+    global_section: GlobalSection,
+    static_data: StaticData,
+    uses_memory: bool,
+
+    generics: Rc<HashMap<String, GenericFunction>>,
+    instances: Instances,
+    overflow_checks: bool,
+}
+
+/// A generic function kept as a template; each type-argument list becomes its own wasm function.
+#[derive(Debug, Clone)]
+pub struct GenericFunction {
+    pub decl: FunctionDecl,
+    pub typed: TypedFunction,
+}
+
+/// Upper bounds on generic instantiations and the size of their type arguments,
+/// which stop polymorphic recursion.
+const MAX_INSTANCES: usize = 1024;
+const MAX_TYPE_SIZE: usize = 128;
+
+/// Functions created while emitting others, numbered after all plain functions:
+/// generic instances, lifted closures and adapters for named functions used as values.
+#[derive(Debug, Default)]
+pub struct Instances {
+    /// Function index of the first instance (one past the last plain function).
+    base: u32,
+    /// Queued functions in function index order.
+    order: Vec<Instance>,
+    /// Function index of every generic instance by name.
+    indices: HashMap<String, u32>,
+    /// Table slot of the adapter of every named function used as a value.
+    refs: HashMap<String, u32>,
+    /// Function index of every table slot; function values hold a slot.
+    table: Vec<u32>,
+    /// Whether a function value is called anywhere, which needs the table even when empty.
+    pub calls_values: bool,
+    /// Number of entries of `order` that have been emitted.
+    emitted: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Instance {
+    /// A generic function and its type arguments.
+    Generic(String, Vec<Type>),
+    /// Boxed, since closure bodies carry whole expressions.
+    Closure(Box<ClosureDef>),
+    /// `fn(env, args...) { name(args...) }` for a named function used as a value.
+    FunctionRef(String),
+}
+
+/// A closure lifted into its own function `fn(env, params...) -> return_type`.
+///
+/// The environment is the function value itself: a tuple of the table slot
+/// followed by the captured values.
+#[derive(Debug, Clone)]
+pub struct ClosureDef {
+    pub params: Vec<(String, Type)>,
+    pub return_type: Type,
+    /// Captured locals in environment order.
+    pub captures: Vec<(String, Type)>,
+    pub body: Expr,
+    /// Type arguments of the enclosing generic instance, for annotations in `body`.
+    pub type_bindings: HashMap<String, Type>,
+    /// Source function the closure is written in, named by overflow traps.
+    pub owner: String,
+}
+
+impl Instances {
+    /// Function index of `generic` instantiated with `type_args`, queueing it on first use.
+    pub fn request(&mut self, generic: &str, type_args: &[Type]) -> Result<u32, CodegenError> {
+        if self.order.len() >= MAX_INSTANCES
+            || type_args.iter().map(type_size).sum::<usize>() > MAX_TYPE_SIZE
+        {
+            return Err(CodegenError::UnsupportedType(format!(
+                "instantiation limit reached for generic function `{}` (polymorphic recursion?)",
+                generic
+            )));
+        }
+        let name = instance_name(generic, type_args);
+        if let Some(idx) = self.indices.get(&name) {
+            return Ok(*idx);
+        }
+        let idx = self.base + self.order.len() as u32;
+        self.order.push(Instance::Generic(generic.to_string(), type_args.to_vec()));
+        self.indices.insert(name, idx);
+        Ok(idx)
+    }
+
+    /// Queues a lifted closure and returns its table slot.
+    pub fn request_closure(&mut self, closure: ClosureDef) -> u32 {
+        self.push_table_entry(Instance::Closure(Box::new(closure)))
+    }
+
+    /// Table slot of the adapter that turns the named function `name` into a value.
+    pub fn request_function_ref(&mut self, name: &str) -> u32 {
+        if let Some(slot) = self.refs.get(name) {
+            return *slot;
+        }
+        let slot = self.push_table_entry(Instance::FunctionRef(name.to_string()));
+        self.refs.insert(name.to_string(), slot);
+        slot
+    }
+
+    fn push_table_entry(&mut self, instance: Instance) -> u32 {
+        let idx = self.base + self.order.len() as u32;
+        self.order.push(instance);
+        self.table.push(idx);
+        self.table.len() as u32 - 1
+    }
+}
+
+/// The module's function types, shared by declarations, imports and `call_indirect`.
+#[derive(Debug, Default)]
+pub struct FuncTypes {
+    section: TypeSection,
+    indices: HashMap<(Vec<ValType>, Vec<ValType>), u32>,
+}
+
+impl FuncTypes {
+    /// Index of the type `params -> results`, adding it on first use.
+    pub fn intern(&mut self, params: Vec<ValType>, results: Vec<ValType>) -> u32 {
+        let next = self.indices.len() as u32;
+        *self
+            .indices
+            .entry((params, results))
+            .or_insert_with_key(|(params, results)| {
+                self.section.ty().function(params.clone(), results.clone());
+                next
+            })
+    }
+}
+
+/// Number of nodes in a type.
+fn type_size(ty: &Type) -> usize {
+    match ty {
+        Type::Ref(inner) | Type::List(inner) => 1 + type_size(inner),
+        Type::Tuple(args) | Type::Generic(_, args) => 1 + args.iter().map(type_size).sum::<usize>(),
+        Type::Function(params, ret) => {
+            1 + params.iter().map(type_size).sum::<usize>() + type_size(ret)
+        }
+        Type::Int
+        | Type::Int32
+        | Type::UInt8
+        | Type::Float
+        | Type::Bool
+        | Type::String
+        | Type::Named(_)
+        | Type::Unit
+        | Type::Var(_) => 1,
+    }
+}
+
+/// Name of one instantiation, e.g. `first<Int>`.
+fn instance_name(generic: &str, type_args: &[Type]) -> String {
+    Type::Generic(generic.to_string(), type_args.to_vec()).to_string()
+}
+
+/// Struct/enum declarations visible to the backend (user code + host descriptors).
+#[derive(Debug, Default)]
+pub struct TypeDecls {
+    pub structs: HashMap<String, StructDecl>,
+    pub enums: HashMap<String, EnumDecl>,
+}
+
+/// Interned static strings placed into the module's data segment.
+///
+/// Runtime imports receive `(ptr, len)` pairs into this segment to learn
+/// type and field names without allocating heap strings.
+#[derive(Debug, Default)]
+pub struct StaticData {
+    bytes: Vec<u8>,
+    offsets: HashMap<String, (i32, i32)>,
+}
+
+impl StaticData {
+    /// Returns the `(ptr, len)` of `value`, appending it on first use.
+    pub fn intern(&mut self, value: &str) -> (i32, i32) {
+        if let Some(found) = self.offsets.get(value) {
+            return *found;
+        }
+        let entry = (self.bytes.len() as i32, value.len() as i32);
+        self.bytes.extend_from_slice(value.as_bytes());
+        self.offsets.insert(value.to_string(), entry);
+        entry
+    }
+}
+
+impl Default for ModuleGen {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ModuleGen {
@@ -62,17 +284,71 @@ impl ModuleGen {
     pub fn new() -> Self {
         Self {
             module: Module::new(),
-            types: TypeSection::new(),
+            types: FuncTypes::default(),
             imports: ImportSection::new(),
             functions: FunctionSection::new(),
             codes: CodeSection::new(),
             exports: ExportSection::new(),
             func_indices: HashMap::new(),
-            next_type_index: 0,
             next_func_index: 0,
+            type_decls: Rc::new(TypeDecls::default()),
+            consts: Rc::new(HashMap::new()),
+            globals: Rc::new(HashMap::new()),
+            global_section: GlobalSection::new(),
+            static_data: StaticData::default(),
+            uses_memory: false,
+            generics: Rc::new(HashMap::new()),
+            instances: Instances::default(),
+            overflow_checks: false,
         }
     }
 
+    /// Makes the checked struct/enum declarations and const values available to expression
+    /// lowering.
+    pub fn init_with_type_decls(mut self, typed: &TypedProgram) -> Self {
+        self.type_decls = Rc::new(TypeDecls {
+            structs: typed.structs.clone(),
+            enums: typed.enums.clone(),
+        });
+        self.consts = Rc::new(typed.consts.clone());
+        self
+    }
+
+    /// Declares one mutable wasm global per `static mut`, initialized with its constant value.
+    pub fn init_with_statics(mut self, statics: &[TypedStatic]) -> Result<Self, CodegenError> {
+        let mut globals = HashMap::new();
+        for (index, item) in statics.iter().enumerate() {
+            let val_type = wasm_val_type_for(&item.ty).ok_or_else(|| {
+                CodegenError::UnsupportedType(format!("static `{}` of type {}", item.name, item.ty))
+            })?;
+            let init = match &item.init {
+                ConstValue::Int(value) if val_type == ValType::I32 => {
+                    ConstExpr::i32_const(*value as i32)
+                }
+                ConstValue::Int(value) => ConstExpr::i64_const(*value),
+                ConstValue::Float(value) => ConstExpr::f64_const((*value).into()),
+                ConstValue::Bool(value) => ConstExpr::i32_const(*value as i32),
+                ConstValue::String(_) => {
+                    return Err(CodegenError::UnsupportedType(format!(
+                        "static `{}` of type String",
+                        item.name
+                    )));
+                }
+            };
+            self.global_section.global(
+                GlobalType {
+                    val_type,
+                    mutable: true,
+                    shared: false,
+                },
+                &init,
+            );
+            globals.insert(item.name.clone(), (index as u32, item.ty.clone()));
+        }
+        self.globals = Rc::new(globals);
+        Ok(self)
+    }
+
     pub fn init_with_runtime_imports(
         mut self,
         imports: &RuntimeImports,
@@ -85,13 +361,135 @@ impl ModuleGen {
             )?;
         }
 
+        if imports.needs_string_concat {
+            self.add_internal_import(
+                string_concat_import_name(),
+                &[Type::String, Type::String],
+                &Type::String,
+            )?;
+        }
+
+        if imports.needs_string_cmp {
+            self.add_internal_import(
+                string_cmp_import_name(),
+                &[Type::String, Type::String],
+                &Type::Int32,
+            )?;
+        }
+
+        if imports.needs_to_string {
+            for lane in RuntimeLane::ALL {
+                self.add_raw_import(
+                    &to_string_import_name(lane),
+                    vec![lane_val_type(lane)],
+                    Some((ValType::I32, Type::String)),
+                );
+            }
+        }
+
         for literal in &imports.string_literals {
             self.add_internal_import(&string_literal_import_name(literal), &[], &Type::String)?;
         }
 
+        if imports.needs_aggregates {
+            self.uses_memory = true;
+            self.add_raw_import(
+                STRUCT_NEW_IMPORT,
+                vec![ValType::I32, ValType::I32],
+                Some((ValType::I32, Type::String)),
+            );
+            self.add_raw_import(
+                ENUM_NEW_IMPORT,
+                vec![ValType::I32, ValType::I32, ValType::I32, ValType::I32],
+                Some((ValType::I32, Type::String)),
+            );
+            self.add_raw_import(
+                ENUM_TAG_IMPORT,
+                vec![ValType::I32, ValType::I32, ValType::I32],
+                Some((ValType::I32, Type::Int)),
+            );
+            self.add_raw_import(
+                LIST_NEW_IMPORT,
+                vec![ValType::I32, ValType::I32],
+                Some((ValType::I32, Type::String)),
+            );
+            self.add_raw_import(TUPLE_NEW_IMPORT, Vec::new(), Some((ValType::I32, Type::String)));
+            self.add_raw_import(
+                AGGREGATE_LEN_IMPORT,
+                vec![ValType::I32],
+                Some((ValType::I64, Type::Int)),
+            );
+            for lane in RuntimeLane::ALL.into_iter().filter(|lane| lane.is_scalar()) {
+                let value = lane_val_type(lane);
+                self.add_raw_import(
+                    &box_import_name(lane),
+                    vec![value],
+                    Some((ValType::I32, Type::Ref(Box::new(lane.value_type())))),
+                );
+                self.add_raw_import(
+                    &unbox_import_name(lane),
+                    vec![ValType::I32],
+                    Some((value, lane.value_type())),
+                );
+            }
+            for lane in RuntimeLane::ALL {
+                let value = lane_val_type(lane);
+                self.add_raw_import(
+                    &aggregate_pop_import_name(lane),
+                    vec![ValType::I32],
+                    Some((value, lane.value_type())),
+                );
+                self.add_raw_import(
+                    &aggregate_push_import_name(lane),
+                    vec![ValType::I32, ValType::I32, ValType::I32, value],
+                    Some((ValType::I32, Type::String)),
+                );
+                self.add_raw_import(
+                    &aggregate_get_import_name(lane),
+                    vec![ValType::I32, ValType::I64],
+                    Some((value, lane.value_type())),
+                );
+                self.add_raw_import(
+                    &aggregate_set_import_name(lane),
+                    vec![ValType::I32, ValType::I64, value],
+                    None,
+                );
+            }
+        }
+
         Ok(self)
     }
 
+    /// Lowers `Int`, `Int32` and `UInt8` arithmetic to overflow-checked sequences that call
+    /// the overflow import (with the function name) instead of wrapping.
+    pub fn init_with_overflow_checks(mut self, enabled: bool) -> Self {
+        if enabled {
+            self.overflow_checks = true;
+            self.uses_memory = true;
+            self.add_raw_import(OVERFLOW_IMPORT, vec![ValType::I32, ValType::I32], None);
+        }
+        self
+    }
+
+    /// Declares a runtime import by its raw wasm signature.
+    fn add_raw_import(&mut self, name: &str, params: Vec<ValType>, result: Option<(ValType, Type)>) {
+        if self.func_indices.contains_key(name) {
+            return;
+        }
+
+        let (results, result_ty) = match result {
+            Some((val_ty, ty)) => (vec![val_ty], ty),
+            None => (Vec::new(), Type::Unit),
+        };
+        let type_index = self.types.intern(params, results);
+        self.imports.import("env", name, EntityType::Function(type_index));
+
+        let idx = self.next_func_index;
+        self.next_func_index += 1;
+        self.func_indices
+            .insert(name.to_string(), (idx, Vec::new(), result_ty));
+    }
+
     /// Registers host functions that are available globally to eres code.
     pub fn init_with_host_functions(mut self, hosts: &[HostFunction]) -> Result<Self, CodegenError> {
         for host in hosts {
@@ -110,8 +508,6 @@ impl ModuleGen {
             return Ok(());
         }
 
-        let type_index = self.next_type_index;
-        self.next_type_index += 1;
         let wasm_params = params
             .iter()
             .map(wasm_val_type_for)
@@ -126,7 +522,7 @@ impl ModuleGen {
                 CodegenError::UnsupportedType(format!("unsupported runtime import return in `{}`", name))
             })?]
         };
-        self.types.ty().function(wasm_params, wasm_results);
+        let type_index = self.types.intern(wasm_params, wasm_results);
         self.imports.import("env", name, EntityType::Function(type_index));
 
         let idx = self.next_func_index;
@@ -145,8 +541,6 @@ impl ModuleGen {
             });
         }
 
-        let type_index = self.next_type_index;
-        self.next_type_index += 1;
         let params = host
             .params
             .iter()
@@ -157,11 +551,11 @@ impl ModuleGen {
             })?;
         let results = match &host.result {
             AbiType::Unit => Vec::new(),
-            other => vec![abi_to_wasm_encoder_val_type(&other).ok_or_else(|| {
+            other => vec![abi_to_wasm_encoder_val_type(other).ok_or_else(|| {
                 CodegenError::UnsupportedType(format!("unsupported host return type in `{}`", host.name))
             })?],
         };
-        self.types.ty().function(params, results);
+        let type_index = self.types.intern(params, results);
 
         self.imports
             .import("env", host.name, EntityType::Function(type_index));
@@ -174,9 +568,9 @@ impl ModuleGen {
                 idx,
                 host.params
                     .iter()
-                    .map(abi_to_ast_type)
+                    .map(abi_type_to_ast_type)
                     .collect(),
-                abi_to_ast_type(&host.result),
+                abi_type_to_ast_type(&host.result),
             ),
         );
         Ok(())
@@ -184,11 +578,60 @@ impl ModuleGen {
 
     /// Finalizes sections and returns the encoded wasm module bytes.
     pub fn finish(mut self) -> Vec<u8> {
-        self.module.section(&self.types);
+        let mut memories = MemorySection::new();
+        let mut data = DataSection::new();
+        if self.uses_memory {
+            let pages = (self.static_data.bytes.len() as u64).div_ceil(65536).max(1);
+            memories.memory(MemoryType {
+                minimum: pages,
+                maximum: None,
+                memory64: false,
+                shared: false,
+                page_size_log2: None,
+            });
+            self.exports.export(MEMORY_EXPORT, ExportKind::Memory, 0);
+            data.active(0, &ConstExpr::i32_const(0), self.static_data.bytes.iter().copied());
+        }
+
+        // Function values are slots in table 0, filled by one active element segment.
+        let uses_table = !self.instances.table.is_empty() || self.instances.calls_values;
+        let mut tables = TableSection::new();
+        let mut elements = ElementSection::new();
+        if uses_table {
+            tables.table(TableType {
+                element_type: RefType::FUNCREF,
+                table64: false,
+                minimum: self.instances.table.len() as u64,
+                maximum: None,
+                shared: false,
+            });
+            elements.active(
+                None,
+                &ConstExpr::i32_const(0),
+                Elements::Functions(self.instances.table.as_slice().into()),
+            );
+        }
+
+        self.module.section(&self.types.section);
         self.module.section(&self.imports);
         self.module.section(&self.functions);
+        if uses_table {
+            self.module.section(&tables);
+        }
+        if self.uses_memory {
+            self.module.section(&memories);
+        }
+        if !self.globals.is_empty() {
+            self.module.section(&self.global_section);
+        }
         self.module.section(&self.exports);
+        if uses_table {
+            self.module.section(&elements);
+        }
         self.module.section(&self.codes);
+        if self.uses_memory {
+            self.module.section(&data);
+        }
         self.module.finish()
     }
 
@@ -204,46 +647,146 @@ impl ModuleGen {
             });
         }
 
-        let param_tys: Vec<ValType> = typed
-            .params
-            .iter()
-            .map(|p| wasm_val_type_for(&p.ty))
-            .collect::<Option<Vec<_>>>()
-            .ok_or_else(|| CodegenError::UnsupportedType(format!(
-                "unsupported parameter in function `{}`",
-                func.name
-            )))?;
+        let idx = self.declare_signature(typed)?;
+        self.exports.export(&func.name, ExportKind::Func, idx);
+        self.instances.base = self.next_func_index;
+        Ok(())
+    }
 
-        let results = if func.return_type == Type::Unit {
-            Vec::new()
+    /// Keeps a generic function as a template for the instances its callers need.
+    pub fn declare_generic_function(&mut self, func: &FunctionDecl, typed: &TypedFunction) {
+        Rc::make_mut(&mut self.generics).insert(
+            func.name.clone(),
+            GenericFunction {
+                decl: func.clone(),
+                typed: typed.clone(),
+            },
+        );
+    }
+
+    /// Emits every queued generic instance, closure and function adapter,
+    /// including the ones their bodies request.
+    pub fn emit_instances(&mut self) -> Result<(), CodegenError> {
+        while let Some(instance) = self.instances.order.get(self.instances.emitted).cloned() {
+            self.instances.emitted += 1;
+            match instance {
+                Instance::Generic(generic, type_args) => {
+                    let template = self.generics.get(&generic).cloned().ok_or_else(|| {
+                        CodegenError::UnknownFunction {
+                            name: generic.clone(),
+                        }
+                    })?;
+                    let typed = template
+                        .typed
+                        .instantiate(&instance_name(&generic, &type_args), &type_args);
+                    self.declare_signature(&typed)?;
+                    let bindings = type_bindings(&template.typed.type_params, &type_args);
+                    self.emit_body(&template.decl, &typed, bindings)?;
+                }
+                Instance::Closure(closure) => self.emit_closure(*closure)?,
+                Instance::FunctionRef(name) => self.emit_function_ref(&name)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits a lifted closure: captures are copied out of the environment into locals first.
+    fn emit_closure(&mut self, closure: ClosureDef) -> Result<(), CodegenError> {
+        let param_tys: Vec<Type> = closure.params.iter().map(|(_, ty)| ty.clone()).collect();
+        self.declare_value_function("closure", &param_tys, &closure.return_type)?;
+
+        let param_count = 1 + param_tys.len() as u32;
+        let mut cg = self.func_gen(
+            &closure.owner,
+            param_count,
+            &closure.return_type,
+            closure.type_bindings,
+        );
+        for (pos, (name, ty)) in closure.params.iter().enumerate() {
+            cg.local_map.insert(name.clone(), (pos as u32 + 1, ty.clone()));
+        }
+        let body = self.emit_closure_body(&closure.captures, &closure.body, &mut cg);
+        self.finish_function(cg, body)
+    }
+
+    fn emit_closure_body(
+        &self,
+        captures: &[(String, Type)],
+        body: &Expr,
+        cg: &mut FuncGen,
+    ) -> Result<(), CodegenError> {
+        for (pos, (name, ty)) in captures.iter().enumerate() {
+            let local = cg.alloc_local(ty)?;
+            cg.instructions.push(IrInstruction::LocalGet(0));
+            cg.instructions.push(IrInstruction::I64Const(pos as i64 + 1));
+            call_runtime(cg, &self.func_indices, &aggregate_get_import_name(lane_for(ty)?))?;
+            cg.instructions.push(IrInstruction::LocalSet(local));
+            cg.local_map.insert(name.clone(), (local, ty.clone()));
+        }
+        let kind = if cg.return_type == Type::Unit {
+            StmtKind::Expr(body.clone())
         } else {
-            vec![wasm_val_type_for(&func.return_type).ok_or_else(|| {
-                CodegenError::UnsupportedType(format!(
-                    "unsupported return type for function `{}`",
-                    func.name
-                ))
-            })?]
+            StmtKind::Return(Some(body.clone()))
         };
+        let stmt = Stmt::new(kind, body.span.clone());
+        emit_stmt(&stmt, cg, &self.func_indices)
+    }
+
+    /// Emits the adapter that lets a named function be called like a closure.
+    fn emit_function_ref(&mut self, name: &str) -> Result<(), CodegenError> {
+        let (idx, params, ret) = self.func_indices.get(name).cloned().ok_or_else(|| {
+            CodegenError::UnknownFunction {
+                name: name.to_string(),
+            }
+        })?;
+        self.declare_value_function(name, &params, &ret)?;
+
+        let mut cg = self.func_gen(name, 1 + params.len() as u32, &ret, HashMap::new());
+        for pos in 1..=params.len() as u32 {
+            cg.instructions.push(IrInstruction::LocalGet(pos));
+        }
+        cg.instructions.push(IrInstruction::Call(idx));
+        cg.instructions.push(IrInstruction::Return);
+        self.finish_function(cg, Ok(()))
+    }
+
+    /// Declares a function taking the function value itself before `params`.
+    fn declare_value_function(
+        &mut self,
+        what: &str,
+        params: &[Type],
+        ret: &Type,
+    ) -> Result<u32, CodegenError> {
+        let env = Type::Function(params.to_vec(), Box::new(ret.clone()));
+        let params: Vec<Type> = std::iter::once(env).chain(params.iter().cloned()).collect();
+        let (param_tys, results) = wasm_signature(what, &params, ret)?;
+        let type_index = self.types.intern(param_tys, results);
+        let idx = self.next_func_index;
+        self.next_func_index += 1;
+        self.functions.function(type_index);
+        Ok(idx)
+    }
 
-        let type_index = self.next_type_index;
-        self.next_type_index += 1;
-        self.types.ty().function(param_tys, results);
+    /// Adds the wasm type and function entries for `typed` and returns its function index.
+    fn declare_signature(&mut self, typed: &TypedFunction) -> Result<u32, CodegenError> {
+        let params: Vec<Type> = typed.params.iter().map(|p| p.ty.clone()).collect();
+        let (param_tys, results) = wasm_signature(&typed.name, &params, &typed.return_type)?;
+        let type_index = self.types.intern(param_tys, results);
 
         let idx = self.next_func_index;
         self.next_func_index += 1;
         self.func_indices
             .insert(
-                func.name.clone(),
+                typed.name.clone(),
                 (
                     idx,
                     typed.params.iter().map(|p| p.ty.clone()).collect(),
-                    func.return_type.clone(),
+                    typed.return_type.clone(),
                 ),
             );
 
         self.functions.function(type_index);
-        self.exports.export(&func.name, ExportKind::Func, idx);
-        Ok(())
+        Ok(idx)
     }
 
     /// Emits one function body into the code section.
@@ -252,48 +795,93 @@ impl ModuleGen {
         func: &FunctionDecl,
         typed: &TypedFunction,
     ) -> Result<(), CodegenError> {
-        let mut cg = FuncGen {
-            locals: Vec::new(),
-            local_map: HashMap::new(),
-            instructions: Vec::new(),
-            has_return: func.return_type != Type::Unit,
-            return_type: func.return_type.clone(),
-        };
+        self.emit_body(func, typed, HashMap::new())
+    }
+
+    /// Emits a function body; `type_bindings` are the type arguments of a generic instance.
+    fn emit_body(
+        &mut self,
+        func: &FunctionDecl,
+        typed: &TypedFunction,
+        type_bindings: HashMap<String, Type>,
+    ) -> Result<(), CodegenError> {
+        let mut cg = self.func_gen(
+            &func.name,
+            typed.params.len() as u32,
+            &typed.return_type,
+            type_bindings,
+        );
 
         for (i, param) in typed.params.iter().enumerate() {
             cg.local_map
                 .insert(param.name.clone(), (i as u32, param.ty.clone()));
         }
 
+        // One wasm local per binding; `let`/`for` claim them in source order (see `bind_next`).
         for (name, ty) in &typed.locals {
-            let wasm_ty = wasm_val_type_for(ty).ok_or_else(|| {
+            let idx = cg.alloc_local(ty).map_err(|_| {
                 CodegenError::UnsupportedType(format!("unsupported local `{}`", name))
             })?;
-            let idx = cg.local_map.len() as u32;
-            cg.local_map
-                .insert(name.clone(), (idx, ty.clone()));
-            cg.locals.push(wasm_ty);
+            cg.bindings.push((idx, ty.clone()));
         }
 
-        for stmt in &func.body {
-            emit_stmt(stmt, &mut cg, &self.func_indices)?;
+        let body = func
+            .body
+            .iter()
+            .try_for_each(|stmt| emit_stmt(stmt, &mut cg, &self.func_indices));
+        self.finish_function(cg, body)
+    }
+
+    /// Starts a function body, lending it the module-wide tables.
+    fn func_gen(
+        &mut self,
+        function_name: &str,
+        param_count: u32,
+        return_type: &Type,
+        type_bindings: HashMap<String, Type>,
+    ) -> FuncGen {
+        FuncGen {
+            param_count,
+            locals: Vec::new(),
+            local_map: HashMap::new(),
+            instructions: Vec::new(),
+            has_return: return_type != &Type::Unit,
+            return_type: return_type.clone(),
+            type_decls: Rc::clone(&self.type_decls),
+            consts: Rc::clone(&self.consts),
+            globals: Rc::clone(&self.globals),
+            static_data: std::mem::take(&mut self.static_data),
+            loops: Vec::new(),
+            bindings: Vec::new(),
+            next_binding: 0,
+            generics: Rc::clone(&self.generics),
+            instances: std::mem::take(&mut self.instances),
+            types: std::mem::take(&mut self.types),
+            type_bindings,
+            function_name: function_name.to_string(),
+            overflow_checks: self.overflow_checks,
         }
+    }
 
-        if func.return_type != Type::Unit {
-            match &func.return_type {
-                Type::Int => cg.instructions.push(IrInstruction::I64Const(0)),
-                Type::Float => cg.instructions.push(IrInstruction::F64Const(0.0)),
-                Type::Bool
-                | Type::String
-                | Type::Ref(_)
-                | Type::List(_)
-                | Type::Tuple(_)
-                | Type::Named(_) => {
-                    cg.instructions.push(IrInstruction::I32Const(0))
-                }
-                Type::Unit | Type::Function(_, _) => {}
-            }
-            cg.instructions.push(IrInstruction::Return);
+    /// Takes the lent tables back and, if `body` succeeded, adds the function to the code section.
+    fn finish_function(
+        &mut self,
+        mut cg: FuncGen,
+        body: Result<(), CodegenError>,
+    ) -> Result<(), CodegenError> {
+        self.static_data = std::mem::take(&mut cg.static_data);
+        self.instances = std::mem::take(&mut cg.instances);
+        self.types = std::mem::take(&mut cg.types);
+        body?;
+
+        // The type checker only accepts non-`Unit` bodies that return on every path
+        // (`typing::always_returns`), so falling off the end cannot happen.
+        let ends_body = matches!(
+            cg.instructions.last(),
+            Some(IrInstruction::Return | IrInstruction::Unreachable)
+        );
+        if cg.return_type != Type::Unit && !ends_body {
+            cg.instructions.push(IrInstruction::Unreachable);
         }
 
         let mut local_groups = Vec::new();
@@ -314,17 +902,32 @@ impl ModuleGen {
     }
 }
 
-fn abi_to_ast_type(ty: &AbiType) -> Type {
-    match ty {
-        AbiType::Int => Type::Int,
-        AbiType::Float => Type::Float,
-        AbiType::Bool => Type::Bool,
-        AbiType::String => Type::String,
-        AbiType::List(inner) => Type::List(Box::new(abi_to_ast_type(inner))),
-        AbiType::Tuple(elements) => Type::Tuple(elements.iter().map(abi_to_ast_type).collect()),
-        AbiType::Named(named) => Type::Named(named.name.clone()),
-        AbiType::Unit => Type::Unit,
-    }
+/// Wasm parameter and result types of a function, `what` names it in errors.
+pub(crate) fn wasm_signature(
+    what: &str,
+    params: &[Type],
+    ret: &Type,
+) -> Result<(Vec<ValType>, Vec<ValType>), CodegenError> {
+    let param_tys = params
+        .iter()
+        .map(wasm_val_type_for)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| CodegenError::UnsupportedType(format!(
+            "unsupported parameter in function `{}`",
+            what
+        )))?;
+
+    let results = if ret == &Type::Unit {
+        Vec::new()
+    } else {
+        vec![wasm_val_type_for(ret).ok_or_else(|| {
+            CodegenError::UnsupportedType(format!(
+                "unsupported return type for function `{}`",
+                what
+            ))
+        })?]
+    };
+    Ok((param_tys, results))
 }
 
 fn abi_to_wasm_encoder_val_type(ty: &AbiType) -> Option<ValType> {
@@ -332,28 +935,44 @@ fn abi_to_wasm_encoder_val_type(ty: &AbiType) -> Option<ValType> {
         AbiType::Int => Some(ValType::I64),
         AbiType::Float => Some(ValType::F64),
         AbiType::Bool => Some(ValType::I32),
-        AbiType::String | AbiType::List(_) | AbiType::Tuple(_) | AbiType::Named(_) => {
-            Some(ValType::I32)
-        }
+        AbiType::String
+        | AbiType::List(_)
+        | AbiType::Tuple(_)
+        | AbiType::Named(_)
+        | AbiType::Option(_)
+        | AbiType::Result(_, _) => Some(ValType::I32),
         AbiType::Unit => None,
     }
 }
 
-fn wasm_val_type_for(ty: &Type) -> Option<ValType> {
+fn lane_val_type(lane: RuntimeLane) -> ValType {
+    match lane {
+        RuntimeLane::Int => ValType::I64,
+        RuntimeLane::Float => ValType::F64,
+        RuntimeLane::Bool | RuntimeLane::Int32 | RuntimeLane::Handle => ValType::I32,
+    }
+}
+
+pub(crate) fn wasm_val_type_for(ty: &Type) -> Option<ValType> {
     match ty {
         Type::Int => Some(ValType::I64),
         Type::Float => Some(ValType::F64),
-        Type::Bool => Some(ValType::I32),
-        Type::String | Type::Ref(_) | Type::List(_) | Type::Tuple(_) | Type::Named(_) => {
-            Some(ValType::I32)
-        }
-        Type::Function(_, _) => None,
-        Type::Unit => None,
+        Type::Bool | Type::Int32 | Type::UInt8 => Some(ValType::I32),
+        Type::String
+        | Type::Ref(_)
+        | Type::List(_)
+        | Type::Tuple(_)
+        | Type::Named(_)
+        | Type::Generic(_, _)
+        | Type::Function(_, _) => Some(ValType::I32),
+        Type::Unit | Type::Var(_) => None,
     }
 }
 
 /// Per-function code generation state.
 pub struct FuncGen {
+    /// Number of wasm parameters (locals `0..param_count`).
+    pub param_count: u32,
     /// Additional local declarations (params are implicit locals 0..n).
     pub locals: Vec<ValType>,
     /// Symbol table for locals and parameters -> (index, type).
@@ -364,4 +983,118 @@ pub struct FuncGen {
     pub has_return: bool,
     /// Current function return type.
     pub return_type: Type,
+    /// Struct/enum declarations for aggregate lowering.
+    pub type_decls: Rc<TypeDecls>,
+    /// Values of `const` items, emitted as literals where they are read.
+    pub consts: Rc<HashMap<String, TypedConst>>,
+    /// `static mut` items as `(global index, type)`.
+    pub globals: Rc<HashMap<String, (u32, Type)>>,
+    /// Static strings shared with the module data segment.
+    pub static_data: StaticData,
+    /// Branch targets of the enclosing loops, innermost last.
+    pub loops: Vec<LoopTargets>,
+    /// Pre-allocated `(local index, type)` for every `let`/`for` binding in source order.
+    pub bindings: Vec<(u32, Type)>,
+    /// Next entry of `bindings` to hand out.
+    pub next_binding: usize,
+    /// Generic function templates, instantiated on demand by calls.
+    pub generics: Rc<HashMap<String, GenericFunction>>,
+    /// Generic instances, closures and function adapters requested so far (shared with the module).
+    pub instances: Instances,
+    /// Function types of the module, needed for `call_indirect`.
+    pub types: FuncTypes,
+    /// Type arguments of the generic instance being emitted.
+    pub type_bindings: HashMap<String, Type>,
+    /// Source name of the function being emitted, reported by overflow traps.
+    pub function_name: String,
+    /// Whether integer arithmetic traps on overflow instead of wrapping.
+    pub overflow_checks: bool,
+}
+
+/// Absolute block depths that `break` and `continue` branch to.
+#[derive(Debug, Clone, Copy)]
+pub struct LoopTargets {
+    pub break_depth: u32,
+    pub continue_depth: u32,
+}
+
+impl FuncGen {
+    /// Number of currently open `block`/`loop`/`if` constructs.
+    pub fn block_depth(&self) -> u32 {
+        self.instructions
+            .iter()
+            .fold(0, |depth, instruction| match instruction {
+                IrInstruction::Block(_) | IrInstruction::Loop(_) | IrInstruction::If(_) => {
+                    depth + 1
+                }
+                IrInstruction::End => depth - 1,
+                _ => depth,
+            })
+    }
+
+    /// Type of the binding the next `let`/`for` will introduce.
+    pub fn peek_binding(&self) -> Result<Type, CodegenError> {
+        self.bindings
+            .get(self.next_binding)
+            .map(|(_, ty)| ty.clone())
+            .ok_or_else(|| CodegenError::UnsupportedType("untyped local binding".to_string()))
+    }
+
+    /// Brings `name` into scope on its own wasm local, shadowing earlier bindings.
+    pub fn bind_next(&mut self, name: &str) -> Result<(u32, Type), CodegenError> {
+        let binding = self
+            .bindings
+            .get(self.next_binding)
+            .cloned()
+            .ok_or_else(|| CodegenError::UnknownLocal {
+                name: name.to_string(),
+            })?;
+        self.next_binding += 1;
+        self.local_map.insert(name.to_string(), binding.clone());
+        Ok(binding)
+    }
+
+    /// Emits a `br` to the construct opened at absolute depth `target`.
+    pub fn branch_to(&mut self, target: u32) {
+        let relative = self.block_depth() - target;
+        self.instructions.push(IrInstruction::Br(relative));
+    }
+
+    /// Declares a fresh wasm local for compiler-introduced values (match subjects, pattern bindings).
+    pub fn alloc_local(&mut self, ty: &Type) -> Result<u32, CodegenError> {
+        let wasm_ty = wasm_val_type_for(ty)
+            .ok_or_else(|| CodegenError::UnsupportedType(format!("{} locals", ty)))?;
+        let idx = self.param_count + self.locals.len() as u32;
+        self.locals.push(wasm_ty);
+        Ok(idx)
+    }
+
+    /// Type-only view with extra bindings in scope, used to infer match arm bodies.
+    pub fn type_scope(&self, bindings: impl IntoIterator<Item = (String, Type)>) -> FuncGen {
+        let mut local_map = self.local_map.clone();
+        for (name, ty) in bindings {
+            local_map.insert(name, (u32::MAX, ty));
+        }
+        FuncGen {
+            param_count: self.param_count,
+            locals: Vec::new(),
+            local_map,
+            instructions: Vec::new(),
+            has_return: self.has_return,
+            return_type: self.return_type.clone(),
+            type_decls: Rc::clone(&self.type_decls),
+            consts: Rc::clone(&self.consts),
+            globals: Rc::clone(&self.globals),
+            static_data: StaticData::default(),
+            loops: Vec::new(),
+            bindings: Vec::new(),
+            next_binding: 0,
+            generics: Rc::clone(&self.generics),
+            instances: Instances::default(),
+            types: FuncTypes::default(),
+            type_bindings: self.type_bindings.clone(),
+            function_name: self.function_name.clone(),
+            overflow_checks: self.overflow_checks,
+        }
+    }
 }
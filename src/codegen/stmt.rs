@@ -1,10 +1,15 @@
 use crate::codegen::ir::IrInstruction;
 use crate::{
-    ast::Stmt,
+    ast::{BinOp, Expr, ExprKind, Stmt, StmtKind, Type},
     codegen::{
-        expr::{emit_expr, infer_expr_type},
-        module::{CodegenError, FuncGen, FunctionSig},
+        expr::{
+            call_runtime, emit_binary_op, emit_expr, emit_expr_expected, index_element_type,
+            infer_expr_type, lane_for, resolve_field,
+        },
+        module::{CodegenError, FuncGen, FunctionSig, LoopTargets},
     },
+    runtime::{AGGREGATE_LEN_IMPORT, aggregate_get_import_name, aggregate_set_import_name},
+    typing::always_returns,
 };
 use std::collections::HashMap;
 use wasm_encoder::*;
@@ -15,31 +20,15 @@ pub fn emit_stmt(
     r#gen: &mut FuncGen,
     funcs: &HashMap<String, FunctionSig>,
 ) -> Result<(), CodegenError> {
-    match stmt {
-        Stmt::Let { name, value, .. } => {
-            let ty = r#gen
-                .local_map
-                .get(name)
-                .ok_or_else(|| CodegenError::UnknownLocal {
-                    name: name.clone(),
-                })?
-                .1
-                .clone();
-            let produced = match ty {
-                crate::ast::Type::Unit => false,
-                _ => true,
-            };
+    match &stmt.kind {
+        StmtKind::Let { name, value, .. } => {
+            let ty = r#gen.peek_binding()?;
+            let produced = !matches!(ty, crate::ast::Type::Unit);
 
-            emit_expr(value, r#gen, funcs)?;
+            // The initializer still sees a shadowed binding of the same name.
+            emit_expr_expected(value, &ty, r#gen, funcs)?;
 
-            let idx = match r#gen.local_map.get(name) {
-                Some((idx, _)) => *idx,
-                None => {
-                    return Err(CodegenError::UnknownLocal {
-                        name: name.clone(),
-                    })
-                }
-            };
+            let (idx, _) = r#gen.bind_next(name)?;
             if produced {
                 r#gen.instructions.push(IrInstruction::LocalSet(idx));
             } else {
@@ -49,17 +38,59 @@ pub fn emit_stmt(
             }
         }
 
-        Stmt::Expr(expr) => {
+        StmtKind::Expr(expr) => {
             let produced = emit_expr(expr, r#gen, funcs)?;
             if produced {
                 r#gen.instructions.push(IrInstruction::Drop);
             }
         }
 
-        Stmt::Return(expr_opt) => {
+        StmtKind::Assign { target, op, value } => match &target.kind {
+            ExprKind::Local(name) => {
+                // Locals shadow `static mut` globals of the same name.
+                let (get, set, ty) = match r#gen.local_map.get(name).cloned() {
+                    Some((idx, ty)) => {
+                        (IrInstruction::LocalGet(idx), IrInstruction::LocalSet(idx), ty)
+                    }
+                    None => {
+                        let (idx, ty) = r#gen
+                            .globals
+                            .get(name)
+                            .cloned()
+                            .ok_or_else(|| CodegenError::UnknownLocal { name: name.clone() })?;
+                        (IrInstruction::GlobalGet(idx), IrInstruction::GlobalSet(idx), ty)
+                    }
+                };
+                if let Some(op) = op {
+                    r#gen.instructions.push(get);
+                    emit_expr_expected(value, &ty, r#gen, funcs)?;
+                    emit_binary_op(op, &ty, r#gen, funcs)?;
+                } else {
+                    emit_expr_expected(value, &ty, r#gen, funcs)?;
+                }
+                r#gen.instructions.push(set);
+            }
+            ExprKind::Field { base, name } => {
+                let (index, field_ty) = resolve_field(r#gen, funcs, base, name)?;
+                let index = Expr::new(ExprKind::Int(index as i64), target.span.clone());
+                emit_element_assign(base, &index, &field_ty, op.as_ref(), value, r#gen, funcs)?;
+            }
+            ExprKind::Index { base, index } => {
+                let element_ty = index_element_type(base, index, r#gen, funcs)?;
+                emit_element_assign(base, index, &element_ty, op.as_ref(), value, r#gen, funcs)?;
+            }
+            _ => {
+                return Err(CodegenError::UnsupportedType(
+                    "assignment target must be a local, field or indexed element".to_string(),
+                ));
+            }
+        },
+
+        StmtKind::Return(expr_opt) => {
             match expr_opt {
                 Some(expr) => {
-                    let _ = emit_expr(expr, r#gen, funcs)?;
+                    let return_type = r#gen.return_type.clone();
+                    let _ = emit_expr_expected(expr, &return_type, r#gen, funcs)?;
                 }
                 None => {
                     // If a return type exists, return a default value.
@@ -81,7 +112,7 @@ pub fn emit_stmt(
             r#gen.instructions.push(IrInstruction::Return);
         }
 
-        Stmt::If {
+        StmtKind::If {
             cond,
             then_block,
             else_block,
@@ -97,21 +128,21 @@ pub fn emit_stmt(
             // cond is Bool with expected type i32 in this backend shape.
             r#gen.instructions.push(IrInstruction::If(BlockType::Empty));
 
-            for s in then_block {
-                emit_stmt(s, r#gen, funcs)?;
-            }
+            emit_block(then_block, r#gen, funcs)?;
 
             if !else_block.is_empty() {
                 r#gen.instructions.push(IrInstruction::Else);
-                for s in else_block {
-                    emit_stmt(s, r#gen, funcs)?;
-                }
+                emit_block(else_block, r#gen, funcs)?;
             }
 
             r#gen.instructions.push(IrInstruction::End);
+            // Both branches returned; tells wasm validation nothing follows the `if`.
+            if always_returns(std::slice::from_ref(stmt)) {
+                r#gen.instructions.push(IrInstruction::Unreachable);
+            }
         }
 
-        Stmt::While { cond, body } => {
+        StmtKind::While { cond, body } => {
             let cond_ty = infer_expr_type(cond, r#gen, funcs)?;
             if cond_ty != crate::ast::Type::Bool {
                 return Err(CodegenError::UnsupportedType(
@@ -122,23 +153,206 @@ pub fn emit_stmt(
             r#gen
                 .instructions
                 .push(IrInstruction::Block(BlockType::Empty));
+            let break_depth = r#gen.block_depth();
             r#gen
                 .instructions
                 .push(IrInstruction::Loop(BlockType::Empty));
+            let continue_depth = r#gen.block_depth();
 
             let _ = emit_expr(cond, r#gen, funcs)?;
             // cond is Bool encoded as i32. Exit loop on cond == 0.
             r#gen.instructions.push(IrInstruction::I32Eqz);
             r#gen.instructions.push(IrInstruction::BrIf(1));
 
-            for s in body {
-                emit_stmt(s, r#gen, funcs)?;
-            }
+            r#gen.loops.push(LoopTargets {
+                break_depth,
+                continue_depth,
+            });
+            emit_block(body, r#gen, funcs)?;
+            r#gen.loops.pop();
 
             r#gen.instructions.push(IrInstruction::Br(0));
             r#gen.instructions.push(IrInstruction::End);
             r#gen.instructions.push(IrInstruction::End);
         }
+
+        StmtKind::For { var, iter, body, .. } => emit_for(var, iter, body, r#gen, funcs)?,
+
+        StmtKind::Break | StmtKind::Continue => {
+            let targets = *r#gen.loops.last().ok_or_else(|| {
+                CodegenError::UnsupportedType("`break`/`continue` outside of a loop".to_string())
+            })?;
+            let target = if matches!(stmt.kind, StmtKind::Break) {
+                targets.break_depth
+            } else {
+                targets.continue_depth
+            };
+            r#gen.branch_to(target);
+        }
     }
     Ok(())
 }
+
+/// Overwrites one heap aggregate element via `__eres_set_<lane>(target, index, value)`.
+///
+/// Compound assignments evaluate `base` and `index` once into temporaries, then read
+/// the old element, apply the operator and write the result back. Nested places
+/// (`s.a.x = v`) need no write-back: reading `s.a` yields the handle stored in `s`.
+fn emit_element_assign(
+    base: &Expr,
+    index: &Expr,
+    element_ty: &Type,
+    op: Option<&BinOp>,
+    value: &Expr,
+    r#gen: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(), CodegenError> {
+    let lane = lane_for(element_ty)?;
+    match op {
+        None => {
+            emit_expr(base, r#gen, funcs)?;
+            emit_expr(index, r#gen, funcs)?;
+            emit_expr_expected(value, element_ty, r#gen, funcs)?;
+        }
+        Some(op) => {
+            let base_ty = infer_expr_type(base, r#gen, funcs)?;
+            let base_local = r#gen.alloc_local(&base_ty)?;
+            let index_local = r#gen.alloc_local(&Type::Int)?;
+            emit_expr(base, r#gen, funcs)?;
+            r#gen.instructions.push(IrInstruction::LocalSet(base_local));
+            emit_expr(index, r#gen, funcs)?;
+            r#gen.instructions.push(IrInstruction::LocalSet(index_local));
+
+            for _ in 0..2 {
+                r#gen.instructions.push(IrInstruction::LocalGet(base_local));
+                r#gen.instructions.push(IrInstruction::LocalGet(index_local));
+            }
+            call_runtime(r#gen, funcs, &aggregate_get_import_name(lane))?;
+            emit_expr_expected(value, element_ty, r#gen, funcs)?;
+            emit_binary_op(op, element_ty, r#gen, funcs)?;
+        }
+    }
+    call_runtime(r#gen, funcs, &aggregate_set_import_name(lane))
+}
+
+/// Emits a nested statement block; bindings made inside go out of scope at its end.
+fn emit_block(
+    body: &[Stmt],
+    r#gen: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(), CodegenError> {
+    let outer_scope = r#gen.local_map.clone();
+    let emitted = body.iter().try_for_each(|s| emit_stmt(s, r#gen, funcs));
+    r#gen.local_map = outer_scope;
+    emitted
+}
+
+/// Lowers `for var in start..end` and `for var in list` to a counting `block`/`loop`.
+///
+/// A hidden counter drives the iteration; `var` gets its own binding local, assigned
+/// at the top of every iteration. The body sits in its own block so `continue`
+/// still reaches the increment.
+fn emit_for(
+    var: &str,
+    iter: &Expr,
+    body: &[Stmt],
+    r#gen: &mut FuncGen,
+    funcs: &HashMap<String, FunctionSig>,
+) -> Result<(), CodegenError> {
+    let counter = r#gen.alloc_local(&Type::Int)?;
+    let (item_ty, source) = match &iter.kind {
+        ExprKind::Range { start, end } => {
+            let end_local = r#gen.alloc_local(&Type::Int)?;
+            emit_expr_expected(start, &Type::Int, r#gen, funcs)?;
+            r#gen.instructions.push(IrInstruction::LocalSet(counter));
+            emit_expr_expected(end, &Type::Int, r#gen, funcs)?;
+            r#gen.instructions.push(IrInstruction::LocalSet(end_local));
+            (Type::Int, LoopSource::Range { end_local })
+        }
+        _ => {
+            let mut list_ty = infer_expr_type(iter, r#gen, funcs)?;
+            while let Type::Ref(inner) = list_ty {
+                list_ty = *inner;
+            }
+            let Type::List(item_ty) = list_ty else {
+                return Err(CodegenError::UnsupportedType(format!(
+                    "iterating over {}",
+                    list_ty
+                )));
+            };
+            let list_local = r#gen.alloc_local(&Type::List(item_ty.clone()))?;
+            emit_expr(iter, r#gen, funcs)?;
+            r#gen.instructions.push(IrInstruction::LocalSet(list_local));
+            r#gen.instructions.push(IrInstruction::I64Const(0));
+            r#gen.instructions.push(IrInstruction::LocalSet(counter));
+            (*item_ty, LoopSource::List { list_local })
+        }
+    };
+
+    r#gen
+        .instructions
+        .push(IrInstruction::Block(BlockType::Empty));
+    let break_depth = r#gen.block_depth();
+    r#gen
+        .instructions
+        .push(IrInstruction::Loop(BlockType::Empty));
+
+    // Exit once `counter >= end` (ranges) or `counter >= len(list)` (lists).
+    r#gen.instructions.push(IrInstruction::LocalGet(counter));
+    match source {
+        LoopSource::Range { end_local } => {
+            r#gen.instructions.push(IrInstruction::LocalGet(end_local));
+        }
+        LoopSource::List { list_local } => {
+            r#gen.instructions.push(IrInstruction::LocalGet(list_local));
+            call_runtime(r#gen, funcs, AGGREGATE_LEN_IMPORT)?;
+        }
+    }
+    r#gen.instructions.push(IrInstruction::I64LtS);
+    r#gen.instructions.push(IrInstruction::I32Eqz);
+    r#gen.instructions.push(IrInstruction::BrIf(1));
+
+    match source {
+        LoopSource::Range { .. } => {
+            r#gen.instructions.push(IrInstruction::LocalGet(counter));
+        }
+        LoopSource::List { list_local } => {
+            r#gen.instructions.push(IrInstruction::LocalGet(list_local));
+            r#gen.instructions.push(IrInstruction::LocalGet(counter));
+            call_runtime(r#gen, funcs, &aggregate_get_import_name(lane_for(&item_ty)?))?;
+        }
+    }
+    let outer_scope = r#gen.local_map.clone();
+    let (var_local, _) = r#gen.bind_next(var)?;
+    r#gen.instructions.push(IrInstruction::LocalSet(var_local));
+
+    r#gen
+        .instructions
+        .push(IrInstruction::Block(BlockType::Empty));
+    let continue_depth = r#gen.block_depth();
+    r#gen.loops.push(LoopTargets {
+        break_depth,
+        continue_depth,
+    });
+    let emitted = emit_block(body, r#gen, funcs);
+    r#gen.loops.pop();
+    r#gen.local_map = outer_scope;
+    emitted?;
+    r#gen.instructions.push(IrInstruction::End);
+
+    r#gen.instructions.push(IrInstruction::LocalGet(counter));
+    r#gen.instructions.push(IrInstruction::I64Const(1));
+    r#gen.instructions.push(IrInstruction::I64Add);
+    r#gen.instructions.push(IrInstruction::LocalSet(counter));
+    r#gen.instructions.push(IrInstruction::Br(0));
+    r#gen.instructions.push(IrInstruction::End);
+    r#gen.instructions.push(IrInstruction::End);
+    Ok(())
+}
+
+/// Where the values of a `for` loop come from.
+#[derive(Clone, Copy)]
+enum LoopSource {
+    Range { end_local: u32 },
+    List { list_local: u32 },
+}
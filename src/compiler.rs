@@ -2,15 +2,17 @@ use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 
-use crate::ast::{Program, TopLevelDecl};
+use crate::ast::{Program, TopLevelDecl, Type};
 use crate::codegen::module::{CodegenError, ModuleGen};
+use crate::diagnostics::{Diagnostic, Label};
 use crate::host::default_host_functions;
 use crate::lexer::{LexError, lex_file};
-use crate::loader::{LoadError, load_program_from_entry};
+use crate::lint::{lint_imports, lint_program};
+use crate::loader::{LoadError, LoadedProgram, SourceFile, load_program_from_entry};
 use crate::parser::{ParseError, Parser};
 use crate::token::Token;
-use crate::typing::{TypedProgram, check_program_with_hosts};
-use crate::runtime::collect_runtime_imports;
+use crate::typing::{TypeError, TypedProgram, check_program_all_errors};
+use crate::runtime::collect_runtime_imports_with_hosts;
 
 /// Compiled artifacts produced by the compiler frontend + backend pipeline.
 #[derive(Debug)]
@@ -25,8 +27,33 @@ pub struct CompileArtifacts {
     pub bytes: Vec<u8>,
     /// Number of parameters expected by `main`.
     pub main_param_count: usize,
+    /// Declared return type of `main` (`Unit` if there is no `main`).
+    pub main_return_type: Type,
     /// Files that were loaded to produce this program.
     pub loaded_files: Vec<PathBuf>,
+    /// Lint warnings in source order; for loaded programs each carries its file's path and a
+    /// span relative to that file.
+    pub warnings: Vec<Diagnostic>,
+    /// Source text of every loaded file; empty for single-source compiles.
+    pub sources: Vec<SourceFile>,
+}
+
+impl CompileArtifacts {
+    /// Source text a warning's spans point into, for loaded programs.
+    pub fn source_of(&self, diagnostic: &Diagnostic) -> Option<&str> {
+        let path = diagnostic.path.as_ref()?;
+        self.sources
+            .iter()
+            .find(|file| &file.path == path)
+            .map(|file| file.src.as_str())
+    }
+}
+
+/// Code generation switches chosen by the caller (CLI flags, tests).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompileOptions {
+    /// Trap with `integer overflow in function ...` instead of wrapping integer `+`, `-`, `*`.
+    pub overflow_checks: bool,
 }
 
 /// Structured compile errors to keep frontend failures distinct.
@@ -34,23 +61,42 @@ pub struct CompileArtifacts {
 pub enum CompileError {
     /// Lexing failed.
     Lex(LexError),
-    /// Parsing failed.
-    Parse(ParseError),
+    /// Parsing failed; every error found with recovery, in source order.
+    Parse(Vec<ParseError>),
     /// File loading failed.
     Load(LoadError),
-    /// Type checking failed.
-    TypeCheck(crate::typing::TypeError),
+    /// Type checking failed; one error per failing function, in declaration order.
+    TypeCheck(Vec<TypeError>),
+    /// Type checking of a program loaded from files failed.
+    FileTypeCheck(Vec<FileTypeError>),
     /// Code generation failed.
     Codegen(CodegenError),
 }
 
+/// A type error in a loaded file, with its span relative to that file's `src`.
+#[derive(Debug)]
+pub struct FileTypeError {
+    pub path: PathBuf,
+    pub src: String,
+    pub error: TypeError,
+}
+
 impl Display for CompileError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             CompileError::Lex(e) => write!(f, "lex error: {}", e),
-            CompileError::Parse(e) => write!(f, "parse error: {}", e),
+            CompileError::Parse(errors) => write!(f, "parse error: {}", join_errors(errors)),
             CompileError::Load(e) => write!(f, "load error: {}", e),
-            CompileError::TypeCheck(e) => write!(f, "type check error: {}", e),
+            CompileError::TypeCheck(errors) => {
+                write!(f, "type check error: {}", join_errors(errors))
+            }
+            CompileError::FileTypeCheck(errors) => {
+                let messages: Vec<_> = errors
+                    .iter()
+                    .map(|err| format!("{}: {}", err.path.display(), err.error))
+                    .collect();
+                write!(f, "type check error in {}", messages.join("; "))
+            }
             CompileError::Codegen(e) => write!(f, "codegen error: {}", e),
         }
     }
@@ -58,33 +104,71 @@ impl Display for CompileError {
 
 impl Error for CompileError {}
 
+impl CompileError {
+    /// Every error as a structured diagnostic, in the order they were found. Errors from
+    /// loaded files carry the file's path.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            CompileError::Lex(e) => vec![e.to_diagnostic()],
+            CompileError::Parse(errors) => errors.iter().map(ParseError::to_diagnostic).collect(),
+            CompileError::Load(e) => e.to_diagnostics(),
+            CompileError::TypeCheck(errors) => errors.iter().map(TypeError::to_diagnostic).collect(),
+            CompileError::FileTypeCheck(errors) => errors
+                .iter()
+                .map(|err| err.error.to_diagnostic().with_path(err.path.clone()))
+                .collect(),
+            CompileError::Codegen(e) => vec![e.to_diagnostic()],
+        }
+    }
+
+    /// Source text a diagnostic's spans point into, if this error carries it.
+    ///
+    /// Single-source compiles don't embed their input; callers fall back to it.
+    pub fn source_of(&self, diagnostic: &Diagnostic) -> Option<&str> {
+        let path = diagnostic.path.as_ref()?;
+        match self {
+            CompileError::Load(e) => e.source_text(),
+            CompileError::FileTypeCheck(errors) => errors
+                .iter()
+                .find(|err| &err.path == path)
+                .map(|err| err.src.as_str()),
+            _ => None,
+        }
+    }
+}
+
+fn join_errors(errors: &[impl Display]) -> String {
+    errors.iter().map(|err| err.to_string()).collect::<Vec<_>>().join("; ")
+}
+
 /// Compile source code to WebAssembly bytes and keep intermediate artifacts.
 pub fn compile_source(src: &str) -> Result<CompileArtifacts, CompileError> {
     let hosts = default_host_functions();
-    compile_source_with_hosts(src, &hosts)
+    compile_source_with_hosts(src, &hosts, CompileOptions::default())
 }
 
 pub fn compile_source_with_hosts(
     src: &str,
     hosts: &[eres_abi::HostFunction],
+    options: CompileOptions,
 ) -> Result<CompileArtifacts, CompileError> {
     let (tokens, program, typed) = parse_and_check(src, hosts)?;
-    build_artifacts(tokens, program, typed, Vec::new(), false, hosts)
+    let warnings = lint_program(&program, &typed);
+    let out = build_artifacts(tokens, program, typed, Vec::new(), false, hosts, options)?;
+    Ok(CompileArtifacts { warnings, ..out })
 }
 
 /// Compile a file entrypoint and recursively load `use "..."` modules.
 pub fn compile_entry_file(path: impl AsRef<Path>) -> Result<CompileArtifacts, CompileError> {
-    let hosts = default_host_functions();
-    let loaded = load_program_from_entry(path.as_ref()).map_err(CompileError::Load)?;
-    let typed = check_program_with_hosts(&loaded.program, &hosts).map_err(CompileError::TypeCheck)?;
-    build_artifacts(
-        Vec::new(),
-        loaded.program,
-        typed,
-        loaded.loaded_files,
-        false,
-        &hosts,
-    )
+    compile_entry_file_with_options(path, CompileOptions::default())
+}
+
+/// Like [`compile_entry_file`], with explicit code generation options.
+pub fn compile_entry_file_with_options(
+    path: impl AsRef<Path>,
+    options: CompileOptions,
+) -> Result<CompileArtifacts, CompileError> {
+    compile_loaded(path.as_ref(), false, options)
 }
 
 /// Compile only through parse+typecheck. Useful for `--check` and frontend validation.
@@ -98,22 +182,104 @@ pub fn compile_source_check_with_hosts(
     hosts: &[eres_abi::HostFunction],
 ) -> Result<CompileArtifacts, CompileError> {
     let (tokens, program, typed) = parse_and_check(src, hosts)?;
-    build_artifacts(tokens, program, typed, Vec::new(), true, hosts)
+    let warnings = lint_program(&program, &typed);
+    let out = build_artifacts(
+        tokens,
+        program,
+        typed,
+        Vec::new(),
+        true,
+        hosts,
+        CompileOptions::default(),
+    )?;
+    Ok(CompileArtifacts { warnings, ..out })
 }
 
 /// Compile only through parse+typecheck for a file entrypoint.
 pub fn compile_entry_file_check(path: impl AsRef<Path>) -> Result<CompileArtifacts, CompileError> {
+    compile_loaded(path.as_ref(), true, CompileOptions::default())
+}
+
+fn compile_loaded(
+    path: &Path,
+    check_only: bool,
+    options: CompileOptions,
+) -> Result<CompileArtifacts, CompileError> {
     let hosts = default_host_functions();
-    let loaded = load_program_from_entry(path.as_ref()).map_err(CompileError::Load)?;
-    let typed = check_program_with_hosts(&loaded.program, &hosts).map_err(CompileError::TypeCheck)?;
-    build_artifacts(
+    let loaded = load_program_from_entry(path).map_err(CompileError::Load)?;
+    let typed = check_program_all_errors(&loaded.program, &hosts)
+        .map_err(|errors| file_type_errors(&loaded, errors))?;
+    let warnings = file_warnings(&loaded, &typed);
+    let out = build_artifacts(
         Vec::new(),
         loaded.program,
         typed,
         loaded.loaded_files,
-        true,
+        check_only,
         &hosts,
-    )
+        options,
+    )?;
+    Ok(CompileArtifacts {
+        warnings,
+        sources: loaded.sources,
+        ..out
+    })
+}
+
+/// Lint warnings of a loaded program, attributed to their files like type errors.
+///
+/// Functions of imported modules are their interface, so only the entry file's unused
+/// functions are reported.
+fn file_warnings(loaded: &LoadedProgram, typed: &TypedProgram) -> Vec<Diagnostic> {
+    let mut warnings = lint_program(&loaded.program, typed);
+    warnings.extend(lint_imports(loaded));
+    // Files get increasing offsets in load order, starting with the entry file.
+    warnings.sort_by_key(|warning| warning.span.as_ref().map(|span| span.start));
+    warnings
+        .into_iter()
+        .filter_map(|mut warning| {
+            let (file, local) = loaded.locate(warning.span.as_ref()?)?;
+            if warning.code == "W-LN03" && file.path != loaded.sources[0].path {
+                return None;
+            }
+            warning.span = Some(local);
+            warning.labels = localize_labels(loaded, warning.labels, &file.path);
+            Some(warning.with_path(file.path.clone()))
+        })
+        .collect()
+}
+
+/// Attributes each type error of the merged program to the file its span points into;
+/// errors without a span are reported against the entry file.
+fn file_type_errors(loaded: &LoadedProgram, errors: Vec<TypeError>) -> CompileError {
+    let errors = errors
+        .into_iter()
+        .map(|mut error| {
+            let located = error.span.as_ref().and_then(|span| loaded.locate(span));
+            let (file, local) = match located {
+                Some((file, local)) => (file, Some(local)),
+                None => (&loaded.sources[0], None),
+            };
+            let (path, src) = (file.path.clone(), file.src.clone());
+            error.span = local;
+            error.labels = localize_labels(loaded, std::mem::take(&mut error.labels), &path);
+            FileTypeError { path, src, error }
+        })
+        .collect();
+    CompileError::FileTypeCheck(errors)
+}
+
+/// Makes label spans relative to `path`; labels in other files can't be shown next to this
+/// file's snippet and are dropped.
+fn localize_labels(loaded: &LoadedProgram, labels: Vec<Label>, path: &Path) -> Vec<Label> {
+    labels
+        .into_iter()
+        .filter_map(|mut label| {
+            let (file, local) = loaded.locate(&label.span)?;
+            label.span = local;
+            (file.path == path).then_some(label)
+        })
+        .collect()
 }
 
 fn build_artifacts(
@@ -123,46 +289,55 @@ fn build_artifacts(
     loaded_files: Vec<PathBuf>,
     check_only: bool,
     hosts: &[eres_abi::HostFunction],
+    options: CompileOptions,
 ) -> Result<CompileArtifacts, CompileError> {
-    let main_param_count = program
+    let (main_param_count, main_return_type) = program
         .items
         .iter()
         .find_map(|item| match item {
-            TopLevelDecl::Function(func) if func.name == "main" => Some(func.params.len()),
+            TopLevelDecl::Function(func) if func.name == "main" => {
+                Some((func.params.len(), func.return_type.clone()))
+            }
             _ => None,
         })
-        .unwrap_or(0);
+        .unwrap_or((0, Type::Unit));
 
     let bytes = if check_only {
         Vec::new()
     } else {
-        let runtime_imports = collect_runtime_imports(&program);
+        let runtime_imports = collect_runtime_imports_with_hosts(&program, hosts);
         let mut module_gen = ModuleGen::new()
+            .init_with_type_decls(&typed)
+            .init_with_statics(&typed.statics)
+            .map_err(CompileError::Codegen)?
             .init_with_runtime_imports(&runtime_imports)
             .map_err(CompileError::Codegen)?
             .init_with_host_functions(hosts)
-            .map_err(CompileError::Codegen)?;
+            .map_err(CompileError::Codegen)?
+            .init_with_overflow_checks(options.overflow_checks);
 
-        for item in &program.items {
-            if let TopLevelDecl::Function(func) = item {
+        for func in program.functions() {
+            let func_info = &typed.function_infos[&func.name];
+            if func.type_params.is_empty() {
                 module_gen
-                    .declare_function(func, &typed.function_infos[&func.name])
+                    .declare_function(func, func_info)
                     .map_err(CompileError::Codegen)?;
+            } else {
+                module_gen.declare_generic_function(func, func_info);
             }
         }
 
-        for item in &program.items {
-            if let TopLevelDecl::Function(func) = item {
-                let func_info = typed
-                    .function_infos
-                    .get(&func.name)
-                    .expect("type info must exist after checking");
-                module_gen
-                    .emit_function(func, func_info)
-                    .map_err(CompileError::Codegen)?;
-            }
+        for func in program.functions().filter(|func| func.type_params.is_empty()) {
+            let func_info = typed
+                .function_infos
+                .get(&func.name)
+                .expect("type info must exist after checking");
+            module_gen
+                .emit_function(func, func_info)
+                .map_err(CompileError::Codegen)?;
         }
 
+        module_gen.emit_instances().map_err(CompileError::Codegen)?;
         module_gen.finish()
     };
 
@@ -172,7 +347,10 @@ fn build_artifacts(
         typed,
         bytes,
         main_param_count,
+        main_return_type,
         loaded_files,
+        warnings: Vec::new(),
+        sources: Vec::new(),
     })
 }
 
@@ -182,8 +360,8 @@ fn parse_and_check(
 ) -> Result<(Vec<Token>, Program, TypedProgram), CompileError> {
     let tokens = lex_file(src).map_err(CompileError::Lex)?;
     let mut parser = Parser::new(&tokens);
-    let program = parser.parse_program().map_err(CompileError::Parse)?;
-    let typed = check_program_with_hosts(&program, hosts).map_err(CompileError::TypeCheck)?;
+    let program = parser.parse_program_all_errors().map_err(CompileError::Parse)?;
+    let typed = check_program_all_errors(&program, hosts).map_err(CompileError::TypeCheck)?;
     Ok((tokens, program, typed))
 }
 
@@ -207,9 +385,10 @@ mod tests {
         let src = "fn main() -> Int { return 1; } fn main() -> Int { return 2; }";
         let err = compile_source(src).expect_err("expected duplicate-function error");
         match err {
-            CompileError::TypeCheck(crate::typing::TypeError::DuplicateFunction { name }) => {
-                assert_eq!(name, "main")
-            }
+            CompileError::TypeCheck(errors) => match &*errors[0].kind {
+                crate::typing::TypeErrorKind::DuplicateFunction { name } => assert_eq!(name, "main"),
+                _ => panic!("unexpected type error"),
+            },
             _ => panic!("unexpected error variant"),
         }
     }
@@ -223,6 +402,44 @@ mod tests {
         assert!(wat.contains("(func (;"));
     }
 
+    #[test]
+    fn match_lowers_to_br_table() {
+        let src = "enum E { A, B(Int) } fn main() -> Int { return match E::B(2) { A => 0, B(v) => v }; }";
+        let out = compile_source(src).expect("compile failed");
+        let wat = wasmprinter::print_bytes(&out.bytes).expect("wat conversion failed");
+        assert!(wat.contains("br_table"));
+    }
+
+    #[test]
+    fn references_auto_deref_into_value_parameters() {
+        let src = "fn inc(x: Int) -> Int { return x + 1; } fn main() -> Int { let x: Int = 4; let r = &x; return inc(r) + *r; }";
+        let out = compile_source(src).expect("compile failed");
+        let result = crate::runner::run_wasm_bytes(&out.bytes, vec![]).expect("run failed");
+        assert_eq!(result, Some(9));
+    }
+
+    #[test]
+    fn generic_instances_become_separate_wasm_functions() {
+        let src = "fn id<T>(x: T) -> T { return x; } fn main() -> Int { let f = id(1.5); let b = id(true); if b { return id(2); } return 0; }";
+        let out = compile_source(src).expect("compile failed");
+        let wat = wasmprinter::print_bytes(&out.bytes).expect("wat conversion failed");
+        assert!(wat.contains("(param i64) (result i64)"));
+        assert!(wat.contains("(param f64) (result f64)"));
+        assert!(wat.contains("(param i32) (result i32)"));
+        assert!(!wat.contains("(export \"id\""));
+    }
+
+    #[test]
+    fn polymorphic_recursion_hits_the_instantiation_limit() {
+        let src = "fn grow<T>(x: T, n: Int) -> Int { if n == 0 { return 0; } return grow((x, x), n - 1); } fn main() -> Int { return grow(1, 3); }";
+        match compile_source(src) {
+            Err(CompileError::Codegen(err)) => {
+                assert!(err.to_string().contains("instantiation limit"))
+            }
+            other => panic!("expected codegen error, got {:?}", other.map(|_| ())),
+        }
+    }
+
     #[test]
     fn compile_rejects_untyped_function_parameters() {
         let src = "fn main(x, y) -> Int { return x; }";
@@ -254,22 +471,15 @@ mod tests {
     }
 
     #[test]
-    fn compile_examples_expect_frontend_codegen_boundary() {
-        let checks = [
-            "examples/check_refs_enums.eres",
+    fn compile_examples_with_aggregate_backend() {
+        for path in [
             "examples/check_aggregates.eres",
             "examples/check_match.eres",
             "examples/check_strings.eres",
-        ];
-
-        for path in checks {
+            "examples/check_refs_enums.eres",
+        ] {
             let src = read_example(path);
-            let err = compile_source(&src)
-                .expect_err(&format!("expected codegen boundary for {}", path));
-            match err {
-                CompileError::Codegen(_) => {}
-                _ => panic!("expected Codegen error for {}", path),
-            }
+            compile_source(&src).unwrap_or_else(|e| panic!("expected {} to compile: {}", path, e));
         }
     }
 
@@ -296,6 +506,92 @@ mod tests {
         assert_eq!(result, Some(2));
     }
 
+    #[test]
+    fn compile_entry_file_reports_type_errors_in_the_imported_file() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time went backwards")
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("facharbeit_type_span_{}", nanos));
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let helper = dir.join("helper.eres");
+        let main = dir.join("main.eres");
+        fs::write(&helper, "fn helper() -> Int {\n    return missing;\n}\n").expect("write helper");
+        fs::write(&main, "use \"./helper.eres\";\nfn main() -> Int { return helper(); }")
+            .expect("write main");
+
+        match compile_entry_file(&main) {
+            Err(CompileError::FileTypeCheck(errors)) => {
+                let FileTypeError { path, src, error } = &errors[0];
+                assert!(path.ends_with("helper.eres"));
+                let span = error.span.clone().expect("type error without span");
+                assert_eq!(&src[span.start..span.end], "missing");
+            }
+            other => panic!("expected file type error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn compile_entry_file_reports_warnings_per_file() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time went backwards")
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("facharbeit_warnings_{}", nanos));
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let used = "fn one() -> Int { return 1; } fn two() -> Int { return 2; }";
+        fs::write(dir.join("used.eres"), used).expect("write used");
+        let unused = "fn three() -> Int { return 3; }";
+        fs::write(dir.join("unused.eres"), unused).expect("write unused");
+        let main = dir.join("main.eres");
+        fs::write(
+            &main,
+            "use \"./used.eres\";\nuse \"./unused.eres\";\n\
+             fn dead() -> Int { return 0; }\nfn main() -> Int { return one(); }",
+        )
+        .expect("write main");
+
+        let out = compile_entry_file_check(&main).expect("check failed");
+        let warnings: Vec<_> = out
+            .warnings
+            .iter()
+            .map(|warning| {
+                let src = out.source_of(warning).expect("warning without source");
+                let span = warning.span.clone().expect("warning without span");
+                (warning.code, &src[span.start..span.end])
+            })
+            .collect();
+        assert_eq!(warnings, [("W-LN04", "use \"./unused.eres\";"), ("W-LN03", "dead")]);
+        let in_main = |warning: &Diagnostic| {
+            warning.path.as_ref().is_some_and(|path| path.ends_with("main.eres"))
+        };
+        assert!(out.warnings.iter().all(in_main));
+    }
+
+    #[test]
+    fn compile_error_diagnostics_carry_file_and_source() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time went backwards")
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("facharbeit_diagnostics_{}", nanos));
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let main = dir.join("main.eres");
+        fs::write(&main, "fn main() -> Int { let x = ; return y }").expect("write main");
+
+        let err = compile_entry_file(&main).expect_err("expected parse errors");
+        let diagnostics = err.diagnostics();
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, ["E-PS02", "E-PS01"]);
+        assert!(diagnostics[0].path.as_ref().expect("missing path").ends_with("main.eres"));
+        assert_eq!(err.source_of(&diagnostics[0]), Some("fn main() -> Int { let x = ; return y }"));
+
+        let err = compile_source("fn main() -> Int { return true; }").expect_err("type error");
+        let diagnostics = err.diagnostics();
+        assert_eq!(diagnostics[0].code, "E-TC07");
+        assert_eq!(err.source_of(&diagnostics[0]), None);
+    }
+
     #[test]
     fn compile_real_module_string_example() {
         let out = compile_entry_file("examples/module_strings_main.eres")
@@ -0,0 +1,265 @@
+//! Compile-time evaluation of `const` initializers.
+//!
+//! Only a small expression subset is constant: literals, other consts, unary and binary
+//! operators (including string `+`), interpolated strings and `as` casts between `Int`,
+//! `Float` and `Bool`. Integer arithmetic is checked, so an overflowing initializer is
+//! reported instead of silently wrapping.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+use crate::ast::{BinOp, ConstDecl, Expr, ExprKind, InterpolationPart, Program, TopLevelDecl, Type};
+use crate::token::Span;
+
+/// Value of an evaluated const.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl ConstValue {
+    pub fn ty(&self) -> Type {
+        match self {
+            ConstValue::Int(_) => Type::Int,
+            ConstValue::Float(_) => Type::Float,
+            ConstValue::Bool(_) => Type::Bool,
+            ConstValue::String(_) => Type::String,
+        }
+    }
+
+    /// Literal expression with this value, which codegen emits at every use site (`span`).
+    pub fn to_expr(&self, span: Span) -> Expr {
+        let kind = match self {
+            ConstValue::Int(value) => ExprKind::Int(*value),
+            ConstValue::Float(value) => ExprKind::Float(*value),
+            ConstValue::Bool(value) => ExprKind::Bool(*value),
+            ConstValue::String(value) => ExprKind::String(value.clone()),
+        };
+        Expr::new(kind, span)
+    }
+}
+
+impl Display for ConstValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstValue::Int(value) => write!(f, "{}", value),
+            ConstValue::Float(value) => write!(f, "{:?}", value),
+            ConstValue::Bool(value) => write!(f, "{}", value),
+            ConstValue::String(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+/// Why a const initializer could not be evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstError {
+    /// The initializer uses something that only exists at runtime (calls, locals, lists, ...).
+    NotConstant(String),
+    /// The initializer is constant but has no valid value (overflow, division by zero, types).
+    Invalid(String),
+}
+
+impl Display for ConstError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstError::NotConstant(what) | ConstError::Invalid(what) => write!(f, "{}", what),
+        }
+    }
+}
+
+/// Evaluates every const of `program` in declaration order; a const may only use earlier ones.
+///
+/// The error names the const whose initializer failed.
+pub fn eval_program_consts(
+    program: &Program,
+) -> Result<HashMap<String, ConstValue>, (String, ConstError)> {
+    let mut consts = HashMap::new();
+    for item in &program.items {
+        if let TopLevelDecl::Const(ConstDecl { name, value, .. }) = item {
+            let evaluated = eval_const(value, &consts).map_err(|err| (name.clone(), err))?;
+            consts.insert(name.clone(), evaluated);
+        }
+    }
+    Ok(consts)
+}
+
+/// Whether `expr` reads one of `names`, looking at the expressions a const may contain.
+pub fn reads_any(expr: &Expr, names: &HashSet<String>) -> bool {
+    match &expr.kind {
+        ExprKind::Local(name) => names.contains(name),
+        ExprKind::Neg(inner) | ExprKind::Not(inner) | ExprKind::Cast { expr: inner, .. } => {
+            reads_any(inner, names)
+        }
+        ExprKind::Binary { left, right, .. } => reads_any(left, names) || reads_any(right, names),
+        ExprKind::Interpolation(parts) => parts.iter().any(|part| match part {
+            InterpolationPart::Text(_) => false,
+            InterpolationPart::Expr(expr) => reads_any(expr, names),
+        }),
+        _ => false,
+    }
+}
+
+/// Evaluates `expr` with the already known `consts` in scope.
+pub fn eval_const(
+    expr: &Expr,
+    consts: &HashMap<String, ConstValue>,
+) -> Result<ConstValue, ConstError> {
+    match &expr.kind {
+        ExprKind::Int(value) => Ok(ConstValue::Int(*value)),
+        ExprKind::Float(value) => Ok(ConstValue::Float(*value)),
+        ExprKind::Bool(value) => Ok(ConstValue::Bool(*value)),
+        ExprKind::String(value) => Ok(ConstValue::String(value.clone())),
+        ExprKind::Local(name) => consts.get(name).cloned().ok_or_else(|| {
+            ConstError::NotConstant(format!("`{}` is not a const declared before this one", name))
+        }),
+        ExprKind::Neg(inner) => match eval_const(inner, consts)? {
+            ConstValue::Int(value) => value.checked_neg().map(ConstValue::Int).ok_or_else(overflow),
+            ConstValue::Float(value) => Ok(ConstValue::Float(-value)),
+            other => Err(ConstError::Invalid(format!("cannot negate {}", other.ty()))),
+        },
+        ExprKind::Not(inner) => match eval_const(inner, consts)? {
+            ConstValue::Bool(value) => Ok(ConstValue::Bool(!value)),
+            other => Err(ConstError::Invalid(format!("`!` expects Bool, found {}", other.ty()))),
+        },
+        ExprKind::Binary { op, left, right } => {
+            let left = eval_const(left, consts)?;
+            let right = eval_const(right, consts)?;
+            eval_binary(op, left, right)
+        }
+        ExprKind::Cast { expr: inner, ty } => {
+            let value = eval_const(inner, consts)?;
+            match (value, ty) {
+                (ConstValue::Int(value), Type::Int) => Ok(ConstValue::Int(value)),
+                (ConstValue::Int(value), Type::Float) => Ok(ConstValue::Float(value as f64)),
+                // Saturates and maps NaN to 0, like the runtime `as`.
+                (ConstValue::Float(value), Type::Int) => Ok(ConstValue::Int(value as i64)),
+                (ConstValue::Float(value), Type::Float) => Ok(ConstValue::Float(value)),
+                (ConstValue::Bool(value), Type::Int) => Ok(ConstValue::Int(value as i64)),
+                (value, ty) => Err(ConstError::Invalid(format!(
+                    "cannot cast {} to {} in a const",
+                    value.ty(),
+                    ty
+                ))),
+            }
+        }
+        ExprKind::Interpolation(parts) => {
+            let mut rendered = String::new();
+            for part in parts {
+                match part {
+                    InterpolationPart::Text(text) => rendered.push_str(text),
+                    InterpolationPart::Expr(expr) => match eval_const(expr, consts)? {
+                        ConstValue::String(value) => rendered.push_str(&value),
+                        value => rendered.push_str(&value.to_string()),
+                    },
+                }
+            }
+            Ok(ConstValue::String(rendered))
+        }
+        ExprKind::Call { name, .. } => Err(ConstError::NotConstant(format!(
+            "call to `{}` cannot be evaluated at compile time",
+            name
+        ))),
+        _ => Err(ConstError::NotConstant(
+            "only literals, consts, operators and `as` are allowed".to_string(),
+        )),
+    }
+}
+
+fn eval_binary(op: &BinOp, left: ConstValue, right: ConstValue) -> Result<ConstValue, ConstError> {
+    use ConstValue::{Bool, Float, Int, String};
+    let value = match (op, &left, &right) {
+        (BinOp::And, Bool(a), Bool(b)) => Bool(*a && *b),
+        (BinOp::Or, Bool(a), Bool(b)) => Bool(*a || *b),
+        (BinOp::Add, Int(a), Int(b)) => Int(a.checked_add(*b).ok_or_else(overflow)?),
+        (BinOp::Sub, Int(a), Int(b)) => Int(a.checked_sub(*b).ok_or_else(overflow)?),
+        (BinOp::Mul, Int(a), Int(b)) => Int(a.checked_mul(*b).ok_or_else(overflow)?),
+        (BinOp::Div | BinOp::Rem, Int(_), Int(0)) => {
+            return Err(ConstError::Invalid("division or remainder by zero".to_string()));
+        }
+        (BinOp::Div, Int(a), Int(b)) => Int(a.checked_div(*b).ok_or_else(overflow)?),
+        (BinOp::Rem, Int(a), Int(b)) => Int(a.checked_rem(*b).ok_or_else(overflow)?),
+        (BinOp::Add, Float(a), Float(b)) => Float(a + b),
+        (BinOp::Sub, Float(a), Float(b)) => Float(a - b),
+        (BinOp::Mul, Float(a), Float(b)) => Float(a * b),
+        (BinOp::Div, Float(a), Float(b)) => Float(a / b),
+        (BinOp::Rem, Float(a), Float(b)) => Float(a % b),
+        (BinOp::Add, String(a), String(b)) => String(format!("{}{}", a, b)),
+        (BinOp::Eq, _, _) if left.ty() == right.ty() => Bool(left == right),
+        (BinOp::NotEq, _, _) if left.ty() == right.ty() => Bool(left != right),
+        (BinOp::Lt, Int(a), Int(b)) => Bool(a < b),
+        (BinOp::Le, Int(a), Int(b)) => Bool(a <= b),
+        (BinOp::Gt, Int(a), Int(b)) => Bool(a > b),
+        (BinOp::Ge, Int(a), Int(b)) => Bool(a >= b),
+        (BinOp::Lt, Float(a), Float(b)) => Bool(a < b),
+        (BinOp::Le, Float(a), Float(b)) => Bool(a <= b),
+        (BinOp::Gt, Float(a), Float(b)) => Bool(a > b),
+        (BinOp::Ge, Float(a), Float(b)) => Bool(a >= b),
+        (BinOp::Lt, String(a), String(b)) => Bool(a < b),
+        (BinOp::Le, String(a), String(b)) => Bool(a <= b),
+        (BinOp::Gt, String(a), String(b)) => Bool(a > b),
+        (BinOp::Ge, String(a), String(b)) => Bool(a >= b),
+        _ => {
+            return Err(ConstError::Invalid(format!(
+                "binary operator {:?} is not defined for {} and {}",
+                op,
+                left.ty(),
+                right.ty()
+            )));
+        }
+    };
+    Ok(value)
+}
+
+fn overflow() -> ConstError {
+    ConstError::Invalid("integer overflow".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_file;
+    use crate::parser::Parser;
+
+    fn consts(src: &str) -> Result<HashMap<String, ConstValue>, (String, ConstError)> {
+        let tokens = lex_file(src).expect("lex failed");
+        let program = Parser::new(&tokens).parse_program().expect("parse failed");
+        eval_program_consts(&program)
+    }
+
+    #[test]
+    fn consts_fold_operators_and_earlier_consts() {
+        let values = consts(
+            "const MAX: Int = 100; const HALF: Int = MAX / 2 - -1; const BIG: Bool = HALF >= 51 && !false; const R: Float = MAX as Float * 0.5; const NAME: String = \"eres\";",
+        )
+        .expect("eval failed");
+        assert_eq!(values["HALF"], ConstValue::Int(51));
+        assert_eq!(values["BIG"], ConstValue::Bool(true));
+        assert_eq!(values["R"], ConstValue::Float(50.0));
+        assert_eq!(values["NAME"], ConstValue::String("eres".to_string()));
+
+        let values = consts(
+            "const A: String = \"er\" + \"es\"; const F: String = \"f\"; const B: String = \"{A} {1 + 1} {0.5} {A < F}\";",
+        )
+        .expect("eval failed");
+        assert_eq!(values["B"], ConstValue::String("eres 2 0.5 true".to_string()));
+    }
+
+    #[test]
+    fn non_constant_and_invalid_initializers_are_rejected() {
+        let cases = [
+            ("fn f() -> Int { return 1; } const A: Int = f();", "A", "call to `f`"),
+            ("const A: Int = B; const B: Int = 1;", "A", "`B` is not a const"),
+            ("const A: Int = 9223372036854775807 + 1;", "A", "integer overflow"),
+            ("const A: Int = 1 / 0;", "A", "division or remainder by zero"),
+            ("const A: Int = 1 + 2.0;", "A", "not defined for Int and Float"),
+        ];
+        for (src, name, message) in cases {
+            let (failed, err) = consts(src).expect_err("expected const error");
+            assert_eq!(failed, name);
+            assert!(err.to_string().contains(message), "unexpected error: {}", err);
+        }
+    }
+}
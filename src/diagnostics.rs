@@ -1,5 +1,181 @@
+use std::path::PathBuf;
+
+use serde_json::{Value, json};
+
 use crate::token::Span;
 
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A secondary source location with an explanation, e.g. an earlier declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A compiler message in a form that can be printed for humans or emitted as JSON.
+///
+/// Codes are stable per error variant: `E-LX` lexer, `E-PS` parser, `E-LD` loader,
+/// `E-TC` type checker, `E-CG` code generation, `E-RT` runtime, `E-CLI` command line.
+/// Warnings of the lint pass use `W-LN`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    /// Primary location, a byte span into the source of `path`.
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    /// Extra explanations, printed as `help:` lines.
+    pub notes: Vec<String>,
+    /// File the spans point into; `None` for single-source compiles and the REPL.
+    pub path: Option<PathBuf>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            span: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            path: None,
+        }
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(code, message)
+        }
+    }
+
+    pub fn with_span(mut self, span: Option<Span>) -> Diagnostic {
+        self.span = span;
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Diagnostic {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_path(mut self, path: PathBuf) -> Diagnostic {
+        self.path = Some(path);
+        self
+    }
+
+    /// Name printed in front of the code, e.g. `TypeCheckError`.
+    fn category(&self) -> &'static str {
+        const CATEGORIES: [(&str, &str); 7] = [
+            ("E-LX", "LexError"),
+            ("E-PS", "ParseError"),
+            ("E-LD", "LoadError"),
+            ("E-TC", "TypeCheckError"),
+            ("E-CG", "CodegenError"),
+            ("E-RT", "Execution error"),
+            ("E-CLI", "Argument error"),
+        ];
+        let fallback = match self.severity {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        };
+        CATEGORIES
+            .iter()
+            .find(|(prefix, _)| self.code.starts_with(prefix))
+            .map_or(fallback, |(_, name)| name)
+    }
+
+    /// Human-readable text: header, `help:` notes and a snippet for every span `src` covers.
+    pub fn render(&self, src: Option<&str>) -> String {
+        let located = |span: &Span| {
+            src.filter(|src| span.start <= src.len())
+                .map(|src| render_snippet(src, span))
+        };
+        let mut out = match self.span.as_ref().and_then(located) {
+            Some(snippet) => format!(
+                "{} [{}] at line {}, column {}: {}",
+                self.category(),
+                self.code,
+                snippet.line,
+                snippet.column,
+                self.message
+            ),
+            None => format!("{} [{}]: {}", self.category(), self.code, self.message),
+        };
+        for note in &self.notes {
+            out.push_str(&format!("\nhelp: {}", note));
+        }
+        if let Some(snippet) = self.span.as_ref().and_then(located) {
+            out.push_str(&format!("\n{}\n{}", snippet.source_line, snippet.marker_line));
+        }
+        for label in &self.labels {
+            match located(&label.span) {
+                Some(snippet) => out.push_str(&format!(
+                    "\nnote at line {}, column {}: {}\n{}\n{}",
+                    snippet.line,
+                    snippet.column,
+                    label.message,
+                    snippet.source_line,
+                    snippet.marker_line
+                )),
+                None => out.push_str(&format!("\nnote: {}", label.message)),
+            }
+        }
+        out
+    }
+
+    /// JSON object for `--error-format=json`; spans get line and column when `src` is known.
+    pub fn to_json(&self, src: Option<&str>) -> Value {
+        let span_json = |span: &Span| {
+            let mut value = json!({ "start": span.start, "end": span.end });
+            if let Some(src) = src.filter(|src| span.start <= src.len()) {
+                let snippet = render_snippet(src, span);
+                value["line"] = json!(snippet.line);
+                value["column"] = json!(snippet.column);
+            }
+            value
+        };
+        json!({
+            "severity": self.severity.as_str(),
+            "code": self.code,
+            "message": self.message,
+            "file": self.path.as_ref().map(|path| path.display().to_string()),
+            "span": self.span.as_ref().map(span_json),
+            "labels": self
+                .labels
+                .iter()
+                .map(|label| json!({ "message": label.message, "span": span_json(&label.span) }))
+                .collect::<Vec<_>>(),
+            "notes": self.notes,
+        })
+    }
+}
+
 /// A rendered source snippet around a byte span.
 #[derive(Debug)]
 pub struct Snippet {
@@ -66,4 +242,39 @@ mod tests {
         assert_eq!(snippet.source_line, "  return 42;");
         assert_eq!(snippet.marker_line, "         ^^");
     }
+
+    #[test]
+    fn diagnostic_renders_notes_and_labels() {
+        let src = "fn f() {}\nfn f() {}";
+        let diagnostic = Diagnostic::error("E-TC03", "duplicate function declaration `f`")
+            .with_span(Some(Span { start: 13, end: 14 }))
+            .with_label(Span { start: 3, end: 4 }, "first declared here")
+            .with_note("rename one of them");
+        assert_eq!(
+            diagnostic.render(Some(src)),
+            "TypeCheckError [E-TC03] at line 2, column 4: duplicate function declaration `f`\n\
+             help: rename one of them\n\
+             fn f() {}\n   ^\n\
+             note at line 1, column 4: first declared here\n\
+             fn f() {}\n   ^"
+        );
+        assert_eq!(
+            diagnostic.render(None).lines().next(),
+            Some("TypeCheckError [E-TC03]: duplicate function declaration `f`")
+        );
+    }
+
+    #[test]
+    fn diagnostic_json_has_code_file_and_positions() {
+        let diagnostic = Diagnostic::error("E-PS02", "expected expression")
+            .with_span(Some(Span { start: 8, end: 9 }))
+            .with_path(PathBuf::from("main.eres"));
+        let value = diagnostic.to_json(Some("let x = ;"));
+        assert_eq!(value["severity"], "error");
+        assert_eq!(value["code"], "E-PS02");
+        assert_eq!(value["file"], "main.eres");
+        assert_eq!(value["span"], json!({ "start": 8, "end": 9, "line": 1, "column": 9 }));
+        assert_eq!(value["labels"], json!([]));
+        assert_eq!(Diagnostic::error("E-RT01", "trap").to_json(None)["span"], Value::Null);
+    }
 }
@@ -1,6 +1,9 @@
 use crate::ast::{EnumDecl, EnumVariant, StructDecl, TopLevelDecl, Type};
+use crate::prelude;
+use crate::token::Span;
 use eres_abi::{
-    AbiType, EnumVariantDescriptor, HostFunction, NamedTypeKind, TypeDescriptor, TypeDescriptorKind,
+    AbiType, EnumVariantDescriptor, HostFunction, NamedTypeKind, NamedTypeRef, TypeDescriptor,
+    TypeDescriptorKind,
 };
 
 pub fn default_host_functions() -> Vec<HostFunction> {
@@ -16,24 +19,68 @@ pub fn abi_type_to_ast_type(ty: &AbiType) -> Type {
         AbiType::List(inner) => Type::List(Box::new(abi_type_to_ast_type(inner))),
         AbiType::Tuple(elements) => Type::Tuple(elements.iter().map(abi_type_to_ast_type).collect()),
         AbiType::Named(named) => Type::Named(named.name.clone()),
+        AbiType::Option(inner) => prelude::option_of(abi_type_to_ast_type(inner)),
+        AbiType::Result(ok, err) => Type::Generic(
+            prelude::RESULT.to_string(),
+            vec![abi_type_to_ast_type(ok), abi_type_to_ast_type(err)],
+        ),
         AbiType::Unit => Type::Unit,
     }
 }
 
+/// Converts a language type into its ABI form; `None` for references.
+///
+/// Function values are tuples of a table slot and the captured values at runtime,
+/// so they show up as empty tuple types. `Int32` and `UInt8` live in the heap as `Int`.
+pub fn ast_type_to_abi_type(ty: &Type, resolve_named: &dyn Fn(&str) -> NamedTypeRef) -> Option<AbiType> {
+    match ty {
+        Type::Int | Type::Int32 | Type::UInt8 => Some(AbiType::Int),
+        Type::Float => Some(AbiType::Float),
+        Type::Bool => Some(AbiType::Bool),
+        Type::String => Some(AbiType::String),
+        Type::List(inner) => Some(AbiType::List(Box::new(ast_type_to_abi_type(inner, resolve_named)?))),
+        Type::Tuple(elements) => elements
+            .iter()
+            .map(|elem| ast_type_to_abi_type(elem, resolve_named))
+            .collect::<Option<Vec<_>>>()
+            .map(AbiType::Tuple),
+        Type::Named(name) => Some(AbiType::Named(resolve_named(name))),
+        Type::Generic(name, args) => match (name.as_str(), args.as_slice()) {
+            (prelude::OPTION, [inner]) => Some(AbiType::Option(Box::new(ast_type_to_abi_type(
+                inner,
+                resolve_named,
+            )?))),
+            (prelude::RESULT, [ok, err]) => Some(AbiType::Result(
+                Box::new(ast_type_to_abi_type(ok, resolve_named)?),
+                Box::new(ast_type_to_abi_type(err, resolve_named)?),
+            )),
+            // Other generic values keep only their base name at runtime.
+            _ => Some(AbiType::Named(resolve_named(name))),
+        },
+        Type::Unit => Some(AbiType::Unit),
+        Type::Function(_, _) => Some(AbiType::Tuple(Vec::new())),
+        Type::Ref(_) | Type::Var(_) => None,
+    }
+}
+
 pub fn host_descriptor_to_ast(descriptor: &TypeDescriptor) -> Option<TopLevelDecl> {
     match (&descriptor.named.kind, &descriptor.kind) {
         (NamedTypeKind::Struct, TypeDescriptorKind::Struct { fields }) => {
             Some(TopLevelDecl::Struct(StructDecl {
                 name: descriptor.named.name.clone(),
+                type_params: Vec::new(),
                 fields: fields
                     .iter()
                     .map(|field| (field.name.clone(), abi_type_to_ast_type(&field.ty)))
                     .collect(),
+                span: Span::default(),
             }))
         }
         (NamedTypeKind::Enum, TypeDescriptorKind::Enum { variants }) => {
             Some(TopLevelDecl::Enum(EnumDecl {
                 name: descriptor.named.name.clone(),
+                span: Span::default(),
+                type_params: Vec::new(),
                 variants: variants
                     .iter()
                     .map(|variant| match variant {
@@ -1,8 +1,8 @@
 use std::str::Chars;
 use std::fmt::{Display, Formatter};
 
-use crate::diagnostics::render_snippet;
-use crate::token::{Span, Token, TokenKind};
+use crate::diagnostics::Diagnostic;
+use crate::token::{Span, StringPart, Token, TokenKind};
 
 /// Stateful lexer for one source string.
 pub struct Lexer<'a> {
@@ -52,7 +52,14 @@ impl<'a> Lexer<'a> {
             '{' => TokenKind::LBrace,
             '}' => TokenKind::RBrace,
             ';' => TokenKind::Semicolon,
-            '+' => TokenKind::Plus,
+            '+' => {
+                if let Some('=') = self.peek() {
+                    self.bump();
+                    TokenKind::PlusEqual
+                } else {
+                    TokenKind::Plus
+                }
+            }
             ':' => {
                 if let Some(':') = self.peek() {
                     self.bump();
@@ -81,6 +88,9 @@ impl<'a> Lexer<'a> {
                 if let Some('>') = self.peek() {
                     self.bump();
                     TokenKind::Arrow
+                } else if let Some('=') = self.peek() {
+                    self.bump();
+                    TokenKind::MinusEqual
                 } else {
                     TokenKind::Minus
                 }
@@ -101,14 +111,25 @@ impl<'a> Lexer<'a> {
                     self.bump();
                     TokenKind::NotEqual
                 } else {
-                    return Err(LexError::UnexpectedChar {
-                        ch: '!',
-                        span: self.span_from(start),
-                    });
+                    TokenKind::Bang
+                }
+            }
+            '.' => {
+                if let Some('.') = self.peek() {
+                    self.bump();
+                    TokenKind::DotDot
+                } else {
+                    TokenKind::Dot
+                }
+            }
+            '*' => {
+                if let Some('=') = self.peek() {
+                    self.bump();
+                    TokenKind::StarEqual
+                } else {
+                    TokenKind::Star
                 }
             }
-            '.' => TokenKind::Dot,
-            '*' => TokenKind::Star,
             '/' => {
                 if let Some('/') = self.peek() {
                     // Single-line comment
@@ -119,12 +140,31 @@ impl<'a> Lexer<'a> {
                         self.bump();
                     }
                     return self.next_token();
+                } else if let Some('=') = self.peek() {
+                    self.bump();
+                    TokenKind::SlashEqual
                 } else {
                     TokenKind::Slash
                 }
             }
             '%' => TokenKind::Percentage,
-            '&' => TokenKind::Ampersand,
+            '?' => TokenKind::Question,
+            '&' => {
+                if let Some('&') = self.peek() {
+                    self.bump();
+                    TokenKind::AndAnd
+                } else {
+                    TokenKind::Ampersand
+                }
+            }
+            '|' => {
+                if let Some('|') = self.peek() {
+                    self.bump();
+                    TokenKind::OrOr
+                } else {
+                    TokenKind::Pipe
+                }
+            }
             ',' => TokenKind::Comma,
             '"' => self.lex_string(start)?,
             '\'' => {
@@ -163,7 +203,11 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        let is_float = if let Some('.') = self.peek() {
+        // `0..n` is an integer followed by a range operator, not the float `0.`.
+        let starts_range = self.chars.clone().nth(1) == Some('.');
+        let is_float = if let Some('.') = self.peek()
+            && !starts_range
+        {
             self.bump();
             number_str.push('.');
             while let Some(c) = self.peek() {
@@ -221,10 +265,20 @@ impl<'a> Lexer<'a> {
             "if" => TokenKind::If,
             "else" => TokenKind::Else,
             "while" => TokenKind::While,
+            "for" => TokenKind::For,
+            "in" => TokenKind::In,
+            "break" => TokenKind::Break,
+            "continue" => TokenKind::Continue,
             "return" => TokenKind::Return,
             "struct" => TokenKind::Struct,
             "enum" => TokenKind::Enum,
             "match" => TokenKind::Match,
+            "impl" => TokenKind::Impl,
+            "trait" => TokenKind::Trait,
+            "as" => TokenKind::As,
+            "const" => TokenKind::Const,
+            "static" => TokenKind::Static,
+            "mut" => TokenKind::Mut,
             "Int" => TokenKind::IntType,
             "Float" => TokenKind::FloatType,
             "Bool" => TokenKind::BoolType,
@@ -237,9 +291,22 @@ impl<'a> Lexer<'a> {
 
     fn lex_string(&mut self, start: usize) -> Result<TokenKind, LexError> {
         let mut value = String::new();
+        let mut parts = Vec::new();
         while let Some(ch) = self.bump() {
             match ch {
-                '"' => return Ok(TokenKind::StringLit(value)),
+                '"' if parts.is_empty() => return Ok(TokenKind::StringLit(value)),
+                '"' => {
+                    if !value.is_empty() {
+                        parts.push(StringPart::Text(value));
+                    }
+                    return Ok(TokenKind::InterpolatedString(parts));
+                }
+                '{' => {
+                    if !value.is_empty() {
+                        parts.push(StringPart::Text(std::mem::take(&mut value)));
+                    }
+                    parts.push(StringPart::Expr(self.lex_interpolation()?));
+                }
                 '\\' => {
                     let escaped = self.bump().ok_or_else(|| LexError::UnterminatedString {
                         span: self.span_from(start),
@@ -261,6 +328,40 @@ impl<'a> Lexer<'a> {
         })
     }
 
+    /// Lexes the expression of a `{...}` hole up to the matching `}`; `\{` in the
+    /// surrounding string stays a literal brace.
+    fn lex_interpolation(&mut self) -> Result<Vec<Token>, LexError> {
+        let start = self.pos;
+        let rest = self.chars.as_str();
+        let mut depth = 0;
+        loop {
+            match self.bump() {
+                Some('}') if depth == 0 => break,
+                Some('{') => depth += 1,
+                Some('}') => depth -= 1,
+                Some('"') | None => {
+                    return Err(LexError::InvalidInterpolation {
+                        span: self.span_from(start - 1),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        let inner = &rest[..self.pos - 1 - start];
+        let shift = |span: Span| Span {
+            start: span.start + start,
+            end: span.end + start,
+        };
+        let tokens = lex_file(inner).map_err(|err| err.shifted(start))?;
+        Ok(tokens
+            .into_iter()
+            .map(|token| Token {
+                kind: token.kind,
+                span: shift(token.span),
+            })
+            .collect())
+    }
+
     fn skip_whitespace(&mut self) {
         while let Some(c) = self.peek() {
             if c.is_whitespace() {
@@ -305,6 +406,31 @@ pub enum LexError {
     InvalidNumber { span: Span },
     /// Double-quoted string literal was not terminated.
     UnterminatedString { span: Span },
+    /// A `{` in a string literal has no matching `}` or contains a nested string literal.
+    InvalidInterpolation { span: Span },
+}
+
+impl LexError {
+    /// Same error with its span moved by `offset` bytes (for nested lexers).
+    fn shifted(self, offset: usize) -> LexError {
+        let shift = |span: Span| Span {
+            start: span.start + offset,
+            end: span.end + offset,
+        };
+        match self {
+            LexError::UnexpectedChar { ch, span } => LexError::UnexpectedChar {
+                ch,
+                span: shift(span),
+            },
+            LexError::InvalidNumber { span } => LexError::InvalidNumber { span: shift(span) },
+            LexError::UnterminatedString { span } => {
+                LexError::UnterminatedString { span: shift(span) }
+            }
+            LexError::InvalidInterpolation { span } => {
+                LexError::InvalidInterpolation { span: shift(span) }
+            }
+        }
+    }
 }
 
 impl Display for LexError {
@@ -321,35 +447,58 @@ impl Display for LexError {
             LexError::UnterminatedString { span } => {
                 write!(f, "unterminated string literal at byte range {}..{}", span.start, span.end)
             }
+            LexError::InvalidInterpolation { span } => write!(
+                f,
+                "unterminated `{{` interpolation at byte range {}..{}",
+                span.start, span.end
+            ),
         }
     }
 }
 
-/// Prints a human-readable lexer error.
-pub fn report_lex_error(src: &str, error: LexError) {
-    match error {
-        LexError::UnexpectedChar { ch, span } => {
-            let snippet = render_snippet(src, &span);
-            eprintln!(
-                "LexError [E-LX01] at line {}, column {}: unexpected character '{}'\nhelp: remove or replace the unsupported character\n{}\n{}",
-                snippet.line, snippet.column, ch, snippet.source_line, snippet.marker_line
-            );
-        }
-        LexError::InvalidNumber { span } => {
-            let snippet = render_snippet(src, &span);
-            eprintln!(
-                "LexError [E-LX02] at line {}, column {}: invalid number literal\nhelp: use a valid numeric literal\n{}\n{}",
-                snippet.line, snippet.column, snippet.source_line, snippet.marker_line
-            );
-        }
-        LexError::UnterminatedString { span } => {
-            let snippet = render_snippet(src, &span);
-            eprintln!(
-                "LexError [E-LX03] at line {}, column {}: unterminated string literal\nhelp: close the string with a double quote\n{}\n{}",
-                snippet.line, snippet.column, snippet.source_line, snippet.marker_line
-            );
+impl LexError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::UnexpectedChar { .. } => "E-LX01",
+            LexError::InvalidNumber { .. } => "E-LX02",
+            LexError::UnterminatedString { .. } => "E-LX03",
+            LexError::InvalidInterpolation { .. } => "E-LX04",
         }
     }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let (message, help, span) = match self {
+            LexError::UnexpectedChar { ch, span } => (
+                format!("unexpected character '{}'", ch),
+                "remove or replace the unsupported character",
+                span,
+            ),
+            LexError::InvalidNumber { span } => (
+                "invalid number literal".to_string(),
+                "use a valid numeric literal",
+                span,
+            ),
+            LexError::UnterminatedString { span } => (
+                "unterminated string literal".to_string(),
+                "close the string with a double quote",
+                span,
+            ),
+            LexError::InvalidInterpolation { span } => (
+                "unterminated `{` interpolation".to_string(),
+                "close the expression with `}`, write `\\{` for a literal brace; string literals \
+                 cannot be nested inside `{}`",
+                span,
+            ),
+        };
+        Diagnostic::error(self.code(), message)
+            .with_span(Some(span.clone()))
+            .with_note(help)
+    }
+}
+
+/// Prints a human-readable lexer error.
+pub fn report_lex_error(src: &str, error: LexError) {
+    eprintln!("{}", error.to_diagnostic().render(Some(src)));
 }
 
 #[cfg(test)]
@@ -358,7 +507,7 @@ mod tests {
 
     #[test]
     fn lex_simple_tokens() {
-        let src = "( ) [ ] { } ; + - * / % , : -> <= < >= > == != -> & => :: .";
+        let src = "( ) [ ] { } ; + - * / % , : -> <= < >= > == != -> & => :: . ?";
         let tokens = lex_file(src).expect("lexing failed");
         assert_eq!(tokens.last().unwrap().kind, TokenKind::Eof);
         let kinds: Vec<_> = tokens.iter().map(|t| &t.kind).collect();
@@ -372,6 +521,50 @@ mod tests {
         assert!(kinds.contains(&&TokenKind::Ampersand));
         assert!(kinds.contains(&&TokenKind::DoubleColon));
         assert!(kinds.contains(&&TokenKind::FatArrow));
+        assert!(kinds.contains(&&TokenKind::Question));
+    }
+
+    #[test]
+    fn lex_compound_assignment_operators() {
+        let tokens = lex_file("x += 1; x -= 2; x *= 3; x /= 4; x = -5;").expect("lexing failed");
+        let kinds: Vec<_> = tokens.iter().map(|t| &t.kind).collect();
+        assert!(kinds.contains(&&TokenKind::PlusEqual));
+        assert!(kinds.contains(&&TokenKind::MinusEqual));
+        assert!(kinds.contains(&&TokenKind::StarEqual));
+        assert!(kinds.contains(&&TokenKind::SlashEqual));
+        assert!(kinds.contains(&&TokenKind::Minus));
+    }
+
+    #[test]
+    fn lex_logical_operators() {
+        let tokens = lex_file("!a && b || c != d").expect("lexing failed");
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(kinds[0], TokenKind::Bang);
+        assert_eq!(kinds[2], TokenKind::AndAnd);
+        assert_eq!(kinds[4], TokenKind::OrOr);
+        assert_eq!(kinds[6], TokenKind::NotEqual);
+        let closure = lex_file("|x| x").expect("lexing failed");
+        assert_eq!(closure[0].kind, TokenKind::Pipe);
+        assert_eq!(closure[2].kind, TokenKind::Pipe);
+    }
+
+    #[test]
+    fn lex_integer_range() {
+        let tokens = lex_file("for i in 0..10 { break; continue; }").expect("lexing failed");
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(
+            kinds[..6],
+            [
+                TokenKind::For,
+                TokenKind::Ident("i".to_string()),
+                TokenKind::In,
+                TokenKind::Int(0),
+                TokenKind::DotDot,
+                TokenKind::Int(10),
+            ]
+        );
+        assert!(kinds.contains(&TokenKind::Break));
+        assert!(kinds.contains(&TokenKind::Continue));
     }
 
     #[test]
@@ -398,6 +591,26 @@ mod tests {
         assert_eq!(tokens[1].kind, TokenKind::StringLit("./foo.eres".to_string()));
     }
 
+    #[test]
+    fn lex_interpolated_string_parts() {
+        let tokens = lex_file("\"x = {x + 1}\\{\"").expect("lexing failed");
+        let TokenKind::InterpolatedString(parts) = &tokens[0].kind else {
+            panic!("expected an interpolated string, got {:?}", tokens[0].kind);
+        };
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], StringPart::Text("x = ".to_string()));
+        let StringPart::Expr(inner) = &parts[1] else {
+            panic!("expected an expression part");
+        };
+        assert_eq!(inner[0].kind, TokenKind::Ident("x".to_string()));
+        // Spans point into the surrounding source, not into the hole.
+        assert_eq!(inner[0].span.start, 6);
+        assert_eq!(parts[2], StringPart::Text("{".to_string()));
+
+        let err = lex_file("\"{x\"").expect_err("expected an error");
+        assert!(matches!(err, LexError::InvalidInterpolation { .. }));
+    }
+
     #[test]
     fn lex_string_literal_with_escapes() {
         let tokens = lex_file("let s = \"line\\n\\t\\\"quoted\\\"\";").expect("lexing failed");
@@ -2,13 +2,17 @@ pub mod analysis;
 pub mod ast;
 pub mod codegen;
 pub mod compiler;
+pub mod consteval;
 pub mod diagnostics;
 pub mod host;
 pub mod lexer;
+pub mod lint;
 pub mod lsp;
 pub mod loader;
 pub mod parser;
+pub mod prelude;
 pub mod runtime;
 pub mod runner;
 pub mod token;
 pub mod typing;
+pub mod visit;
@@ -0,0 +1,542 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::ast::*;
+use crate::diagnostics::Diagnostic;
+use crate::loader::LoadedProgram;
+use crate::token::Span;
+use crate::typing::{TypedProgram, diverges};
+use crate::visit::{Visitor, walk_block, walk_expr, walk_stmt};
+
+/// Warnings of a type-checked program: unused locals, parameters and functions, unreachable
+/// statements and constant `if` conditions, in source order.
+///
+/// Names starting with `_` are never reported as unused. Unused functions are only reported
+/// for programs with a `main`; everything reachable from `main` or from an impl method counts
+/// as used. `typed` is the checker's result for `program`.
+pub fn lint_program(program: &Program, typed: &TypedProgram) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    let mut calls = HashMap::new();
+    for func in program.functions() {
+        let mut linter = FunctionLinter::new(&mut warnings);
+        linter.visit_function(func);
+        calls.insert(func.name.as_str(), linter.references);
+    }
+    warn_unused_functions(program, typed, &calls, &mut warnings);
+    warnings.sort_by_key(|warning| warning.span.as_ref().map(|span| span.start));
+    warnings
+}
+
+/// Warns about `use` declarations whose module (or a module it imports) provides no item the
+/// importing file refers to.
+pub fn lint_imports(loaded: &LoadedProgram) -> Vec<Diagnostic> {
+    let mut declared: HashMap<&Path, HashSet<String>> = HashMap::new();
+    let mut referenced: HashMap<&Path, HashSet<String>> = HashMap::new();
+    for item in &loaded.program.items {
+        let Some((file, _)) = loaded.locate(item.span()) else {
+            continue;
+        };
+        declared.entry(&file.path).or_default().extend(declared_names(item));
+        referenced.entry(&file.path).or_default().extend(referenced_names(item));
+    }
+
+    let empty = HashSet::new();
+    loaded
+        .imports
+        .iter()
+        .filter(|import| {
+            let used = referenced.get(import.importer.as_path()).unwrap_or(&empty);
+            !reachable_files(loaded, &import.target)
+                .iter()
+                .filter_map(|path| declared.get(path.as_path()))
+                .any(|names| !names.is_disjoint(used))
+        })
+        .map(|import| {
+            Diagnostic::warning("W-LN04", format!("unused import `{}`", import.path))
+                .with_span(Some(import.span.clone()))
+                .with_note("no item of this module is used in this file")
+        })
+        .collect()
+}
+
+/// `target` plus every file it imports, directly or indirectly.
+fn reachable_files(loaded: &LoadedProgram, target: &Path) -> Vec<PathBuf> {
+    let mut files = vec![target.to_path_buf()];
+    let mut next = 0;
+    while let Some(file) = files.get(next).cloned() {
+        for import in loaded.imports.iter().filter(|import| import.importer == file) {
+            if !files.contains(&import.target) {
+                files.push(import.target.clone());
+            }
+        }
+        next += 1;
+    }
+    files
+}
+
+/// Names an item makes available to importing files; methods also by their short name.
+fn declared_names(item: &TopLevelDecl) -> Vec<String> {
+    match item {
+        TopLevelDecl::Use(..) => Vec::new(),
+        TopLevelDecl::Struct(decl) => vec![decl.name.clone()],
+        TopLevelDecl::Enum(decl) => vec![decl.name.clone()],
+        TopLevelDecl::Function(decl) => vec![decl.name.clone()],
+        TopLevelDecl::Impl(decl) => decl
+            .methods
+            .iter()
+            .filter_map(|method| method.name.rsplit_once("::"))
+            .map(|(_, method)| method.to_string())
+            .collect(),
+        TopLevelDecl::Trait(decl) => vec![decl.name.clone()],
+        TopLevelDecl::Const(decl) => vec![decl.name.clone()],
+        TopLevelDecl::Static(decl) => vec![decl.name.clone()],
+    }
+}
+
+/// Global names (functions, types, traits, constants, methods) an item refers to.
+fn referenced_names(item: &TopLevelDecl) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let mut functions = Vec::new();
+    match item {
+        TopLevelDecl::Use(..) => {}
+        TopLevelDecl::Struct(decl) => {
+            for (_, ty) in &decl.fields {
+                type_names(ty, &mut names);
+            }
+        }
+        TopLevelDecl::Enum(decl) => {
+            for variant in &decl.variants {
+                match variant {
+                    EnumVariant::Unit(_) => {}
+                    EnumVariant::Tuple(_, ty) => type_names(ty, &mut names),
+                    EnumVariant::Struct(_, fields) => {
+                        for (_, ty) in fields {
+                            type_names(ty, &mut names);
+                        }
+                    }
+                }
+            }
+        }
+        TopLevelDecl::Function(decl) => functions.push(decl),
+        TopLevelDecl::Impl(decl) => {
+            names.extend(decl.trait_name.clone());
+            type_names(&decl.target, &mut names);
+            functions.extend(&decl.methods);
+        }
+        TopLevelDecl::Trait(decl) => {
+            for method in &decl.methods {
+                for param in &method.params {
+                    type_names(&param.ty, &mut names);
+                }
+                type_names(&method.return_type, &mut names);
+            }
+        }
+        TopLevelDecl::Const(ConstDecl { ty, value, .. })
+        | TopLevelDecl::Static(StaticDecl { ty, value, .. }) => {
+            type_names(ty, &mut names);
+            let mut warnings = Vec::new();
+            let mut linter = FunctionLinter::new(&mut warnings);
+            linter.visit_expr(value);
+            names.extend(linter.references);
+        }
+    }
+    for func in functions {
+        let mut warnings = Vec::new();
+        let mut linter = FunctionLinter::new(&mut warnings);
+        linter.visit_function(func);
+        names.extend(linter.references);
+    }
+    names
+}
+
+fn type_names(ty: &Type, names: &mut HashSet<String>) {
+    match ty {
+        Type::Named(name) => {
+            names.insert(name.clone());
+        }
+        Type::Generic(name, args) => {
+            names.insert(name.clone());
+            for arg in args {
+                type_names(arg, names);
+            }
+        }
+        Type::Ref(inner) | Type::List(inner) => type_names(inner, names),
+        Type::Tuple(elements) => {
+            for element in elements {
+                type_names(element, names);
+            }
+        }
+        Type::Function(params, ret) => {
+            for param in params {
+                type_names(param, names);
+            }
+            type_names(ret, names);
+        }
+        Type::Int
+        | Type::Int32
+        | Type::UInt8
+        | Type::Float
+        | Type::Bool
+        | Type::String
+        | Type::Unit
+        | Type::Var(_) => {}
+    }
+}
+
+/// Walks the call graph from `main` and the impl methods. Only names the checker resolved
+/// to a function are followed; other references are constants, statics or types.
+fn warn_unused_functions(
+    program: &Program,
+    typed: &TypedProgram,
+    calls: &HashMap<&str, HashSet<String>>,
+    warnings: &mut Vec<Diagnostic>,
+) {
+    if !typed.function_infos.contains_key("main") {
+        return;
+    }
+    // Method calls are resolved by receiver type, so every method is treated as a root.
+    let mut reachable: Vec<&str> = typed
+        .function_infos
+        .keys()
+        .filter(|name| *name == "main" || name.contains("::"))
+        .map(String::as_str)
+        .collect();
+    let mut next = 0;
+    while let Some(name) = reachable.get(next).copied() {
+        for callee in calls.get(name).into_iter().flatten() {
+            if let Some((known, _)) = typed.function_infos.get_key_value(callee)
+                && !reachable.contains(&known.as_str())
+            {
+                reachable.push(known);
+            }
+        }
+        next += 1;
+    }
+    for item in &program.items {
+        if let TopLevelDecl::Function(func) = item
+            && !reachable.contains(&func.name.as_str())
+            && !func.name.starts_with('_')
+        {
+            warnings.push(
+                Diagnostic::warning("W-LN03", format!("function `{}` is never called", func.name))
+                    .with_span(Some(func.span.clone())),
+            );
+        }
+    }
+}
+
+/// Whether a binding is reported when it is never read.
+#[derive(Clone, Copy, PartialEq)]
+enum BindingKind {
+    Local,
+    Param,
+    /// `self` and struct-pattern bindings, whose names are fixed by the method signature
+    /// or the variant's fields and cannot take an underscore.
+    Unreported,
+}
+
+struct Binding {
+    name: String,
+    span: Span,
+    kind: BindingKind,
+    used: bool,
+}
+
+/// Walks one function body with a scope stack, resolving every name to the innermost binding.
+struct FunctionLinter<'a> {
+    scopes: Vec<Vec<Binding>>,
+    /// Names that did not resolve to a binding: functions, constants, statics and types.
+    references: HashSet<String>,
+    warnings: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> FunctionLinter<'a> {
+    fn new(warnings: &'a mut Vec<Diagnostic>) -> Self {
+        FunctionLinter {
+            scopes: Vec::new(),
+            references: HashSet::new(),
+            warnings,
+        }
+    }
+
+    fn visit_function(&mut self, func: &FunctionDecl) {
+        for param in &func.params {
+            type_names(&param.ty, &mut self.references);
+        }
+        type_names(&func.return_type, &mut self.references);
+        self.references.extend(func.bounds.iter().map(|(_, bound)| bound.clone()));
+
+        self.scopes.push(Vec::new());
+        for param in &func.params {
+            // `self` is part of the method signature, not a choice of the author.
+            let kind = if param.name == "self" {
+                BindingKind::Unreported
+            } else {
+                BindingKind::Param
+            };
+            self.bind(&param.name, &param.span, kind);
+        }
+        self.visit_block(&func.body);
+        self.pop_scope();
+    }
+
+    fn bind(&mut self, name: &str, span: &Span, kind: BindingKind) {
+        self.scopes
+            .last_mut()
+            .expect("binding outside of a scope")
+            .push(Binding {
+                name: name.to_string(),
+                span: span.clone(),
+                kind,
+                used: false,
+            });
+    }
+
+    /// Marks the innermost binding of `name` as read, or records `name` as a global reference.
+    fn use_name(&mut self, name: &str) {
+        let binding = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|binding| binding.name == name);
+        match binding {
+            Some(binding) => binding.used = true,
+            None => {
+                self.references.insert(name.to_string());
+            }
+        }
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().expect("scope stack underflow");
+        for binding in scope {
+            if binding.used || binding.name.starts_with('_') {
+                continue;
+            }
+            let (code, what) = match binding.kind {
+                BindingKind::Local => ("W-LN01", "variable"),
+                BindingKind::Param => ("W-LN02", "parameter"),
+                BindingKind::Unreported => continue,
+            };
+            self.warnings.push(
+                Diagnostic::warning(code, format!("unused {} `{}`", what, binding.name))
+                    .with_span(Some(binding.span))
+                    .with_note(format!(
+                        "if this is intentional, prefix it with an underscore: `_{}`",
+                        binding.name
+                    )),
+            );
+        }
+    }
+
+    }
+
+impl Visitor for FunctionLinter<'_> {
+    /// Visits `stmts` in a new scope and reports the first statement after one that
+    /// [`diverges`].
+    fn visit_block(&mut self, stmts: &[Stmt]) {
+        self.scopes.push(Vec::new());
+        if let Some(pos) = stmts.iter().position(diverges)
+            && let Some(next) = stmts.get(pos + 1)
+        {
+            self.warnings.push(
+                Diagnostic::warning("W-LN05", "unreachable statement")
+                    .with_span(Some(next.span.clone()))
+                    .with_label(
+                        stmts[pos].span.clone(),
+                        "any code following this statement is unreachable",
+                    ),
+            );
+        }
+        walk_block(self, stmts);
+        self.pop_scope();
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let {
+                name,
+                name_span,
+                ty,
+                value,
+            } => {
+                if let Some(ty) = ty {
+                    type_names(ty, &mut self.references);
+                }
+                self.visit_expr(value);
+                self.bind(name, name_span, BindingKind::Local);
+            }
+            // Writing a local does not read it; fields and elements read their base.
+            StmtKind::Assign { target, value, .. } if matches!(target.kind, ExprKind::Local(_)) => {
+                self.visit_expr(value);
+            }
+            StmtKind::If { cond, .. } => {
+                if let ExprKind::Bool(value) = cond.kind {
+                    self.warnings.push(
+                        Diagnostic::warning(
+                            "W-LN06",
+                            format!("`if` condition is always `{}`", value),
+                        )
+                        .with_span(Some(cond.span.clone()))
+                        .with_note("remove the condition or the branch that never runs"),
+                    );
+                }
+                walk_stmt(self, stmt);
+            }
+            StmtKind::For {
+                var,
+                var_span,
+                iter,
+                body,
+            } => {
+                self.visit_expr(iter);
+                self.scopes.push(Vec::new());
+                self.bind(var, var_span, BindingKind::Local);
+                self.visit_block(body);
+                self.pop_scope();
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Local(name) => self.use_name(name),
+            ExprKind::Call { name, .. } => self.use_name(name),
+            ExprKind::StructInit { name, .. } | ExprKind::MethodCall { name, .. } => {
+                self.references.insert(name.clone());
+            }
+            ExprKind::EnumInit { enum_name, .. } => {
+                self.references.insert(enum_name.clone());
+            }
+            ExprKind::Cast { ty, .. } => type_names(ty, &mut self.references),
+            ExprKind::Match { subject, arms } => {
+                self.visit_expr(subject);
+                for arm in arms {
+                    self.scopes.push(Vec::new());
+                    let (bindings, kind) = match &arm.pattern {
+                        Pattern::UnitVariant(_) => (&[][..], BindingKind::Local),
+                        Pattern::TupleVariant(_, bindings) => {
+                            (bindings.as_slice(), BindingKind::Local)
+                        }
+                        Pattern::StructVariant(_, bindings) => {
+                            (bindings.as_slice(), BindingKind::Unreported)
+                        }
+                    };
+                    for binding in bindings {
+                        self.bind(&binding.name, &binding.span, kind);
+                    }
+                    self.visit_expr(&arm.body);
+                    self.pop_scope();
+                }
+                return;
+            }
+            ExprKind::Closure { params, body } => {
+                self.scopes.push(Vec::new());
+                for param in params {
+                    if let Some(ty) = &param.ty {
+                        type_names(ty, &mut self.references);
+                    }
+                    self.bind(&param.name, &param.span, BindingKind::Param);
+                }
+                self.visit_expr(body);
+                self.pop_scope();
+                return;
+            }
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::check;
+
+    fn lint(src: &str) -> Vec<(&'static str, String)> {
+        let checked = check(src).expect("check failed");
+        lint_program(&checked.program, &checked.typed)
+            .into_iter()
+            .map(|warning| {
+                let span = warning.span.expect("warning without span");
+                (warning.code, src[span.start..span.end].to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn warns_about_unused_locals_and_params() {
+        let warnings = lint(
+            "fn main(a: Int, _b: Int) -> Int { let x = 1; let y = 2; let _z = 3; \
+             for i in 0..3 { y = y + 1; } return y; }",
+        );
+        assert_eq!(
+            warnings,
+            [
+                ("W-LN02", "a".to_string()),
+                ("W-LN01", "x".to_string()),
+                ("W-LN01", "i".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_do_not_count_as_uses_but_shadowing_reads_do() {
+        let warnings = lint(
+            "fn main() -> Int { let a = 1; a = 2; let b = 1; let b = b + 1; \
+             let f = |x| x + b; return f(1); }",
+        );
+        assert_eq!(warnings, [("W-LN01", "a".to_string())]);
+    }
+
+    #[test]
+    fn warns_about_unused_closure_params_and_pattern_bindings() {
+        let warnings = lint(
+            "enum E { A(Int), B { x: Int, y: Int } } \
+             fn main() -> Int { let f = |a, _b: Int| 1; let e = E::A(f(1, 2)); \
+             return match e { A(n) => 0, B { x, y } => x }; }",
+        );
+        assert_eq!(
+            warnings,
+            [("W-LN02", "a".to_string()), ("W-LN01", "n".to_string())]
+        );
+    }
+
+    #[test]
+    fn warns_about_functions_unreachable_from_main() {
+        let warnings = lint(
+            "struct P { x: Int } impl P { fn get(self) -> Int { return helper(self.x); } } \
+             fn helper(x: Int) -> Int { return x; } \
+             fn dead() -> Int { return dead(); } \
+             fn main() -> Int { return P { x: 1 }.get(); }",
+        );
+        assert_eq!(warnings, [("W-LN03", "dead".to_string())]);
+        assert!(lint("fn helper() -> Int { return 1; }").is_empty());
+    }
+
+    #[test]
+    fn warns_about_unreachable_statements_and_constant_conditions() {
+        let warnings = lint(
+            "fn main(x: Int) -> Int { if (x > 0) { return 1; } else { return 2; } \
+             let y = 3; return y; }",
+        );
+        assert_eq!(warnings, [("W-LN05", "let y = 3;".to_string())]);
+
+        let warnings = lint(
+            "fn main() -> Int { while true { break; continue; } if false { return 1; } return 0; }",
+        );
+        assert_eq!(
+            warnings,
+            [("W-LN05", "continue;".to_string()), ("W-LN06", "false".to_string())]
+        );
+
+        // Same divergence analysis as the missing-return check: `while true` never ends.
+        let warnings = lint(
+            "fn main() -> Int { let y = 1; while true { if y > 3 { return y; } y += 1; } \
+             y = 5; return y; }",
+        );
+        assert_eq!(warnings, [("W-LN05", "y = 5;".to_string())]);
+    }
+}
@@ -3,8 +3,10 @@ use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 
 use crate::ast::{Program, TopLevelDecl};
+use crate::diagnostics::Diagnostic;
 use crate::lexer::{LexError, lex_file};
 use crate::parser::{ParseError, Parser};
+use crate::token::Span;
 
 #[derive(Debug)]
 pub enum LoadError {
@@ -12,7 +14,8 @@ pub enum LoadError {
     Io { path: PathBuf, message: String },
     Cycle { chain: Vec<PathBuf> },
     Lex { path: PathBuf, src: String, error: LexError },
-    Parse { path: PathBuf, src: String, error: ParseError },
+    /// Every error of the file, parsed with recovery.
+    Parse { path: PathBuf, src: String, errors: Vec<ParseError> },
 }
 
 impl Display for LoadError {
@@ -35,17 +38,89 @@ impl Display for LoadError {
                     .join(" -> ")
             ),
             LoadError::Lex { path, error, .. } => write!(f, "lex error in {}: {}", path.display(), error),
-            LoadError::Parse { path, error, .. } => write!(f, "parse error in {}: {}", path.display(), error),
+            LoadError::Parse { path, errors, .. } => write!(
+                f,
+                "parse error in {}: {}",
+                path.display(),
+                errors.iter().map(|error| error.to_string()).collect::<Vec<_>>().join("; ")
+            ),
         }
     }
 }
 
 impl std::error::Error for LoadError {}
 
+impl LoadError {
+    /// One diagnostic per underlying error; lex and parse errors keep their own codes.
+    pub fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            LoadError::InvalidImportTarget { importer, .. } => {
+                vec![Diagnostic::error("E-LD01", self.to_string()).with_path(importer.clone())]
+            }
+            LoadError::Io { path, .. } => {
+                vec![Diagnostic::error("E-LD02", self.to_string()).with_path(path.clone())]
+            }
+            LoadError::Cycle { .. } => vec![Diagnostic::error("E-LD03", self.to_string())],
+            LoadError::Lex { path, error, .. } => vec![error.to_diagnostic().with_path(path.clone())],
+            LoadError::Parse { path, errors, .. } => errors
+                .iter()
+                .map(|error| error.to_diagnostic().with_path(path.clone()))
+                .collect(),
+        }
+    }
+
+    /// Source text embedded in the error, for rendering its snippets.
+    pub fn source_text(&self) -> Option<&str> {
+        match self {
+            LoadError::Lex { src, .. } | LoadError::Parse { src, .. } => Some(src),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct LoadedProgram {
     pub program: Program,
     pub loaded_files: Vec<PathBuf>,
+    /// Source text of every loaded file, in the order the files were read.
+    pub sources: Vec<SourceFile>,
+    /// Every resolved `use` declaration.
+    pub imports: Vec<Import>,
+}
+
+/// A `use "path";` declaration and the file it resolved to.
+#[derive(Debug)]
+pub struct Import {
+    pub importer: PathBuf,
+    pub target: PathBuf,
+    /// Path as written in the source.
+    pub path: String,
+    /// Span of the declaration in the merged program.
+    pub span: Span,
+}
+
+/// A loaded file; its AST spans are shifted by `offset` so spans of different files never
+/// overlap in the merged program.
+#[derive(Debug)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub src: String,
+    pub offset: usize,
+}
+
+impl LoadedProgram {
+    /// File containing an AST span, plus the span relative to that file's source.
+    pub fn locate(&self, span: &Span) -> Option<(&SourceFile, Span)> {
+        let file = self
+            .sources
+            .iter()
+            .find(|file| (file.offset..=file.offset + file.src.len()).contains(&span.start))?;
+        let local = Span {
+            start: span.start - file.offset,
+            end: (span.end - file.offset).min(file.src.len()),
+        };
+        Some((file, local))
+    }
 }
 
 pub fn load_program_from_entry(path: impl AsRef<Path>) -> Result<LoadedProgram, LoadError> {
@@ -54,6 +129,8 @@ pub fn load_program_from_entry(path: impl AsRef<Path>) -> Result<LoadedProgram,
         order: Vec::new(),
         in_progress: HashSet::new(),
         stack: Vec::new(),
+        sources: Vec::new(),
+        imports: Vec::new(),
     };
     let entry = canonicalize_existing(path.as_ref())?;
     loader.load_file(&entry)?;
@@ -62,7 +139,7 @@ pub fn load_program_from_entry(path: impl AsRef<Path>) -> Result<LoadedProgram,
     for path in &loader.order {
         let program = loader.programs.get(path).expect("loaded program missing");
         for item in &program.items {
-            if !matches!(item, TopLevelDecl::Use(_)) {
+            if !matches!(item, TopLevelDecl::Use(..)) {
                 items.push(item.clone());
             }
         }
@@ -71,6 +148,8 @@ pub fn load_program_from_entry(path: impl AsRef<Path>) -> Result<LoadedProgram,
     Ok(LoadedProgram {
         program: Program { items },
         loaded_files: loader.order,
+        sources: loader.sources,
+        imports: loader.imports,
     })
 }
 
@@ -79,6 +158,8 @@ struct ModuleLoader {
     order: Vec<PathBuf>,
     in_progress: HashSet<PathBuf>,
     stack: Vec<PathBuf>,
+    sources: Vec<SourceFile>,
+    imports: Vec<Import>,
 }
 
 impl ModuleLoader {
@@ -105,17 +186,33 @@ impl ModuleLoader {
             src: src.clone(),
             error,
         })?;
-        let mut parser = Parser::new(&tokens);
-        let program = parser.parse_program().map_err(|error| LoadError::Parse {
+        // One byte of gap keeps an empty file from sharing its offset with the next one.
+        let offset = self
+            .sources
+            .last()
+            .map_or(0, |file| file.offset + file.src.len() + 1);
+        let mut parser = Parser::new(&tokens).with_offset(offset);
+        let program = parser.parse_program_all_errors().map_err(|errors| LoadError::Parse {
             path: canonical.clone(),
             src: src.clone(),
-            error,
+            errors,
         })?;
+        self.sources.push(SourceFile {
+            path: canonical.clone(),
+            src,
+            offset,
+        });
 
         for item in &program.items {
-            if let TopLevelDecl::Use(import_path) = item {
+            if let TopLevelDecl::Use(import_path, span) = item {
                 let next = resolve_import_path(&canonical, import_path)?;
                 self.load_file(&next)?;
+                self.imports.push(Import {
+                    importer: canonical.clone(),
+                    target: next,
+                    path: import_path.clone(),
+                    span: span.clone(),
+                });
             }
         }
 
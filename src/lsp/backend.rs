@@ -8,17 +8,18 @@ use tower_lsp::lsp_types::{
     GotoDefinitionParams, GotoDefinitionResponse, Location,
     Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
     DidOpenTextDocumentParams, Hover, HoverContents, HoverParams, HoverProviderCapability,
-    InitializeParams, InitializeResult, LanguageString, MarkedString, MessageType, Position,
-    Range,
+    InitializeParams, InitializeResult, LanguageString, MarkedString, MessageType,
+    NumberOrString, Position, Range,
     ServerCapabilities, TextDocumentContentChangeEvent, TextDocumentSyncCapability,
     TextDocumentSyncKind, Url,
 };
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
 use crate::analysis::{
-    DocumentSymbolInfo, SymbolKind, analyze_diagnostic, completions_at, definition_at,
+    DocumentSymbolInfo, SymbolKind, analyze_diagnostics, completions_at, definition_at,
     document_symbols, symbol_at,
 };
+use crate::diagnostics::Severity;
 use crate::lsp::source_map::SourceMap;
 use crate::token::Span;
 
@@ -58,26 +59,27 @@ impl Backend {
 
     async fn publish_diagnostics(&self, uri: Url, source: &str) {
         let source_map = SourceMap::new(source);
-        let diagnostics = analyze_diagnostic(source)
+        let diagnostics = analyze_diagnostics(source)
+            .into_iter()
             .map(|diagnostic| {
-                let severity = if diagnostic.span.is_some() {
-                    DiagnosticSeverity::ERROR
-                } else {
-                    DiagnosticSeverity::WARNING
+                let severity = match (diagnostic.severity, &diagnostic.span) {
+                    (Severity::Error, Some(_)) => DiagnosticSeverity::ERROR,
+                    _ => DiagnosticSeverity::WARNING,
                 };
                 let range_span = diagnostic
                     .span
                     .clone()
                     .unwrap_or_else(|| source_map.fallback_span());
-                vec![Diagnostic {
+                Diagnostic {
                     range: span_to_range(range_span, &source_map),
                     severity: Some(severity),
+                    code: Some(NumberOrString::String(diagnostic.code.to_string())),
                     source: Some("eres".to_string()),
                     message: diagnostic.message,
                     ..Diagnostic::default()
-                }]
+                }
             })
-            .unwrap_or_default();
+            .collect();
         self.client.publish_diagnostics(uri, diagnostics, None).await;
     }
 }
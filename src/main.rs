@@ -1,16 +1,25 @@
 use clap::Parser;
 use std::io::{self, Write};
 use std::path::PathBuf;
+use std::process::ExitCode;
 
 use facharbeit::compiler::{
-    CompileArtifacts, CompileError, compile_entry_file, compile_entry_file_check, compile_source,
-    compile_source_check,
+    CompileArtifacts, CompileError, CompileOptions, compile_entry_file_check,
+    compile_entry_file_with_options, compile_source_check, compile_source_with_hosts,
 };
-use facharbeit::lexer::report_lex_error;
-use facharbeit::loader::LoadError;
-use facharbeit::parser::report_parse_error;
+use facharbeit::diagnostics::{Diagnostic, Severity};
+use facharbeit::host::default_host_functions;
 use facharbeit::runner;
 
+/// How compiler, argument and runtime errors are written to stderr.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ErrorFormat {
+    /// Messages with source snippets
+    Human,
+    /// One JSON object per line
+    Json,
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -37,6 +46,10 @@ struct Args {
     #[arg(long, default_value_t = false)]
     check: bool,
 
+    /// Trap on integer overflow in `+`, `-` and `*` instead of wrapping
+    #[arg(long, default_value_t = false)]
+    overflow_checks: bool,
+
     /// Comma-separated i64 arguments for `main`, e.g. "1,2,3"
     #[arg(long)]
     args: Option<String>,
@@ -56,71 +69,118 @@ struct Args {
     /// Write generated wasm bytes to a file
     #[arg(long)]
     emit_wasm: Option<PathBuf>,
+
+    /// Format of error output
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+
+    /// Report warnings as errors and fail instead of running, e.g. in CI
+    #[arg(long, default_value_t = false)]
+    deny_warnings: bool,
 }
 
-fn main() {
+fn main() -> ExitCode {
     let args = Args::parse();
 
     if args.repl {
-        run_repl(&args);
-        return;
+        return if run_repl(&args) {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
     }
 
     let input = match &args.input {
         Some(path) => path,
         None => {
-            eprintln!("Error: missing input file. Provide <input> or use --repl.");
-            return;
+            let diagnostic = Diagnostic::error("E-CLI01", "missing input file")
+                .with_note("provide <input> or use --repl");
+            report_diagnostic(args.error_format, &diagnostic, None);
+            return ExitCode::FAILURE;
         }
     };
     let compile_out = match if args.check {
         compile_entry_file_check(input)
     } else {
-        compile_entry_file(input)
+        compile_entry_file_with_options(input, compile_options(&args))
     } {
         Ok(out) => out,
-        Err(CompileError::Load(e)) => {
-            report_load_error(e);
-            return;
-        }
-        Err(CompileError::Lex(_)) | Err(CompileError::Parse(_)) => {
-            eprintln!("Internal error: file entry compilation should surface lex/parse through loader");
-            return;
-        }
-        Err(CompileError::TypeCheck(e)) => {
-            eprintln!("TypeCheckError [E-TC01]: {}", e);
-            return;
-        }
-        Err(CompileError::Codegen(e)) => {
-            if args.check {
-                eprintln!("TypeCheckError [E-TC01]: {}", e);
-            } else {
-                eprintln!("CodegenError [E-CG01]: {}", e);
-            }
-            return;
+        Err(error) => {
+            report_compile_error(args.error_format, &error, None);
+            return ExitCode::FAILURE;
         }
     };
+    if !report_warnings(&args, &compile_out, None) {
+        return ExitCode::FAILURE;
+    }
 
-    let _ = handle_compiled_output(&args, &compile_out);
+    if handle_compiled_output(&args, &compile_out) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
 }
 
-fn report_load_error(error: LoadError) {
-    match error {
-        LoadError::Lex { path, src, error } => {
-            eprintln!("In file {}:", path.display());
-            report_lex_error(&src, error);
+fn compile_options(args: &Args) -> CompileOptions {
+    CompileOptions {
+        overflow_checks: args.overflow_checks,
+    }
+}
+
+/// Prints every diagnostic of `error`; `src` is the input of single-source compiles.
+fn report_compile_error(format: ErrorFormat, error: &CompileError, src: Option<&str>) {
+    report_diagnostics(format, &error.diagnostics(), |diagnostic| {
+        error.source_of(diagnostic).or(src)
+    });
+}
+
+/// Prints the lint warnings of a successful compile. With `--deny-warnings` they are reported
+/// as errors and `false` is returned.
+fn report_warnings(args: &Args, compile_out: &CompileArtifacts, src: Option<&str>) -> bool {
+    let mut warnings = compile_out.warnings.clone();
+    if args.deny_warnings {
+        for warning in &mut warnings {
+            warning.severity = Severity::Error;
         }
-        LoadError::Parse { path, src, error } => {
+    }
+    report_diagnostics(args.error_format, &warnings, |diagnostic| {
+        compile_out.source_of(diagnostic).or(src)
+    });
+    !args.deny_warnings || warnings.is_empty()
+}
+
+/// Prints diagnostics in order, starting a new `In file` block whenever the file changes.
+fn report_diagnostics<'a>(
+    format: ErrorFormat,
+    diagnostics: &[Diagnostic],
+    source_of: impl Fn(&Diagnostic) -> Option<&'a str>,
+) {
+    let mut current = None;
+    for diagnostic in diagnostics {
+        if format == ErrorFormat::Human
+            && let Some(path) = &diagnostic.path
+            && current != Some(path)
+        {
             eprintln!("In file {}:", path.display());
-            report_parse_error(&src, &error);
+            current = Some(path);
         }
-        other => eprintln!("LoadError [E-LD01]: {}", other),
+        report_diagnostic(format, diagnostic, source_of(diagnostic));
+    }
+}
+
+fn report_diagnostic(format: ErrorFormat, diagnostic: &Diagnostic, src: Option<&str>) {
+    match format {
+        ErrorFormat::Human => eprintln!("{}", diagnostic.render(src)),
+        ErrorFormat::Json => eprintln!("{}", diagnostic.to_json(src)),
     }
 }
 
-fn run_repl(args: &Args) {
+/// Runs the REPL until `:quit` or end of input. Returns `false` if any input failed to
+/// compile or run, so piped sessions report errors through the exit code.
+fn run_repl(args: &Args) -> bool {
     println!("REPL mode. Enter expressions or full programs. Commands: :quit, :help");
     let mut line = String::new();
+    let mut ok = true;
     loop {
         print!("eres> ");
         let _ = io::stdout().flush();
@@ -150,30 +210,19 @@ fn run_repl(args: &Args) {
         let compile_out = match if args.check {
             compile_source_check(&repl_src)
         } else {
-            compile_source(&repl_src)
+            compile_source_with_hosts(&repl_src, &default_host_functions(), compile_options(args))
         } {
             Ok(out) => out,
-            Err(CompileError::Lex(e)) => {
-                report_lex_error(&repl_src, e);
-                continue;
-            }
-            Err(CompileError::Parse(e)) => {
-                report_parse_error(&repl_src, &e);
-                continue;
-            }
-            Err(CompileError::Load(_)) => {
-                eprintln!("Internal error: REPL should not produce loader errors");
-                continue;
-            }
-            Err(CompileError::TypeCheck(e)) => {
-                eprintln!("TypeCheckError [E-TC01]: {}", e);
-                continue;
-            }
-            Err(CompileError::Codegen(e)) => {
-                eprintln!("CodegenError [E-CG01]: {}", e);
+            Err(error) => {
+                report_compile_error(args.error_format, &error, Some(&repl_src));
+                ok = false;
                 continue;
             }
         };
+        if !report_warnings(args, &compile_out, Some(&repl_src)) {
+            ok = false;
+            continue;
+        }
 
         if args.check {
             println!("check ok");
@@ -183,17 +232,27 @@ fn run_repl(args: &Args) {
         let run_args = match parse_cli_i64_args(args.args.as_deref(), compile_out.main_param_count) {
             Ok(v) => v,
             Err(e) => {
-                eprintln!("Argument error [E-CLI01]: {}", e);
+                report_diagnostic(args.error_format, &Diagnostic::error("E-CLI01", e), None);
+                ok = false;
                 continue;
             }
         };
 
-        match runner::run_wasm_bytes(&compile_out.bytes, run_args) {
+        match runner::run_wasm_bytes_rendered(
+            &compile_out.bytes,
+            run_args,
+            &compile_out.main_return_type,
+        ) {
             Ok(Some(result)) => println!("= {}", result),
             Ok(None) => println!("(ok)"),
-            Err(e) => eprintln!("Execution error [E-RT01]: {}", e),
+            Err(e) => {
+                let diagnostic = Diagnostic::error("E-RT01", e.to_string());
+                report_diagnostic(args.error_format, &diagnostic, None);
+                ok = false;
+            }
         }
     }
+    ok
 }
 
 fn handle_compiled_output(args: &Args, compile_out: &CompileArtifacts) -> bool {
@@ -257,15 +316,23 @@ fn handle_compiled_output(args: &Args, compile_out: &CompileArtifacts) -> bool {
     let run_args = match parse_cli_i64_args(args.args.as_deref(), compile_out.main_param_count) {
         Ok(v) => v,
         Err(e) => {
-            eprintln!("Argument error [E-CLI01]: {}", e);
+            report_diagnostic(args.error_format, &Diagnostic::error("E-CLI01", e), None);
             return false;
         }
     };
 
-    match runner::run_wasm_bytes(&compile_out.bytes, run_args) {
+    match runner::run_wasm_bytes_rendered(
+        &compile_out.bytes,
+        run_args,
+        &compile_out.main_return_type,
+    ) {
         Ok(Some(result)) => println!("result of main function: {}", result),
         Ok(None) => println!("main returned no value"),
-        Err(e) => eprintln!("Execution error [E-RT01]: {}", e),
+        Err(e) => {
+            let diagnostic = Diagnostic::error("E-RT01", e.to_string());
+            report_diagnostic(args.error_format, &diagnostic, None);
+            return false;
+        }
     }
     true
 }
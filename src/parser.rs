@@ -1,13 +1,24 @@
 use crate::ast::*;
-use crate::diagnostics::render_snippet;
+use crate::diagnostics::Diagnostic;
 use crate::token::*;
 use std::fmt::{Display, Formatter};
 
+/// Type parameter names plus their `(parameter, trait)` bounds.
+type BoundedTypeParams = (Vec<String>, Vec<(String, String)>);
+
 /// Recursive-descent parser over lexer tokens.
 #[derive(Debug)]
 pub struct Parser<'a> {
     tokens: &'a [Token],
     pos: usize,
+    /// Set while parsing `if`/`while`/`for` heads, where `name {` opens the body.
+    no_struct_literal: bool,
+    /// Type `Self` stands for inside an `impl` or `trait` block.
+    self_type: Option<Type>,
+    /// Added to every AST span, so the items of several loaded files get disjoint spans.
+    offset: usize,
+    /// Errors recovered from so far, in source order.
+    errors: Vec<ParseError>,
 }
 
 #[cfg(test)]
@@ -22,7 +33,7 @@ mod tests {
         let mut p = Parser::new(&tokens);
         let program = p.parse_program().expect("parse");
         match &program.items[0] {
-            TopLevelDecl::Use(path) => assert_eq!(path, "./math.eres"),
+            TopLevelDecl::Use(path, _) => assert_eq!(path, "./math.eres"),
             _ => panic!("expected use declaration"),
         }
     }
@@ -75,14 +86,14 @@ mod tests {
             .expect("function missing");
         assert_eq!(f.body.len(), 1);
 
-        match &f.body[0] {
-            Stmt::If {
+        match &f.body[0].kind {
+            StmtKind::If {
                 cond,
                 then_block,
                 else_block,
             } => {
-                match cond {
-                    Expr::Binary {
+                match &cond.kind {
+                    ExprKind::Binary {
                         op: BinOp::Lt, ..
                     } => {}
                     _ => panic!("expected Lt op"),
@@ -110,30 +121,30 @@ mod tests {
             })
             .expect("function missing");
 
-        match &f.body[0] {
-            Stmt::Let { name, value, .. } => {
+        match &f.body[0].kind {
+            StmtKind::Let { name, value, .. } => {
                 assert_eq!(name, "z");
-                match value {
-                    Expr::Binary { op, left, right } => {
+                match &value.kind {
+                    ExprKind::Binary { op, left, right } => {
                         match op {
                             BinOp::Lt => (),
                             _ => panic!("expected Lt op"),
                         }
-                        match (&**left, &**right) {
+                        match (&left.kind, &right.kind) {
                             (
-                                Expr::Binary {
+                                ExprKind::Binary {
                                     op: lop,
                                     left: lleft,
                                     right: _,
                                 },
-                                Expr::Local(_name),
+                                ExprKind::Local(_name),
                             ) => {
                                 match lop {
                                     BinOp::Add => (),
                                     _ => panic!("expected Add"),
                                 }
-                                match &**lleft {
-                                    Expr::Local(n) => assert_eq!(n, "x"),
+                                match &lleft.kind {
+                                    ExprKind::Local(n) => assert_eq!(n, "x"),
                                     _ => panic!("expected local x"),
                                 }
                             }
@@ -162,8 +173,8 @@ mod tests {
             })
             .expect("function missing");
         assert_eq!(f.body.len(), 1);
-        match &f.body[0] {
-            Stmt::Return(None) => {}
+        match &f.body[0].kind {
+            StmtKind::Return(None) => {}
             _ => panic!("expected bare return"),
         }
     }
@@ -244,20 +255,20 @@ mod tests {
             })
             .expect("function missing");
 
-        match &f.body[0] {
-            Stmt::Let { value: Expr::ListLiteral(items), .. } => {
+        match &f.body[0].kind {
+            StmtKind::Let { value: Expr { kind: ExprKind::ListLiteral(items), .. }, .. } => {
                 assert_eq!(items.len(), 3);
             }
             _ => panic!("expected list literal"),
         }
-        match &f.body[1] {
-            Stmt::Let { value: Expr::TupleLiteral(items), .. } => {
+        match &f.body[1].kind {
+            StmtKind::Let { value: Expr { kind: ExprKind::TupleLiteral(items), .. }, .. } => {
                 assert_eq!(items.len(), 2);
             }
             _ => panic!("expected tuple literal"),
         }
-        match &f.body[2] {
-            Stmt::Let { value: Expr::String(value), .. } => assert_eq!(value, "hi"),
+        match &f.body[2].kind {
+            StmtKind::Let { value: Expr { kind: ExprKind::String(value), .. }, .. } => assert_eq!(value, "hi"),
             _ => panic!("expected string literal"),
         }
     }
@@ -277,8 +288,8 @@ mod tests {
                 _ => None,
             })
             .expect("function missing");
-        match &f.body[0] {
-            Stmt::Let { value: Expr::Match { .. }, .. } => {}
+        match &f.body[0].kind {
+            StmtKind::Let { value: Expr { kind: ExprKind::Match { .. }, .. }, .. } => {}
             _ => panic!("expected match expression"),
         }
     }
@@ -298,19 +309,19 @@ mod tests {
             })
             .expect("function missing");
 
-        match &f.body[0] {
-            Stmt::Return(Some(expr)) => match expr {
-                Expr::Binary {
+        match &f.body[0].kind {
+            StmtKind::Return(Some(expr)) => match &expr.kind {
+                ExprKind::Binary {
                     left,
                     right,
                     ..
                 } => {
-                    match &**left {
-                        Expr::Index { .. } => {}
+                    match &left.kind {
+                        ExprKind::Index { .. } => {}
                         _ => panic!("expected list index on left"),
                     }
-                    match &**right {
-                        Expr::Index { .. } => {}
+                    match &right.kind {
+                        ExprKind::Index { .. } => {}
                         _ => panic!("expected tuple-style dot index on right"),
                     }
                 }
@@ -320,6 +331,330 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_struct_field_access() {
+        let src = "fn f(p: Point) -> Int { return p.x; }";
+        let tokens = lex_file(src).expect("lex");
+        let mut p = Parser::new(&tokens);
+        let program = p.parse_program().expect("parse");
+        let f = program
+            .items
+            .iter()
+            .find_map(|i| match i {
+                TopLevelDecl::Function(func) => Some(func),
+                _ => None,
+            })
+            .expect("function missing");
+
+        match &f.body[0].kind {
+            StmtKind::Return(Some(Expr { kind: ExprKind::Field { base, name }, .. })) => {
+                assert_eq!(name, "x");
+                assert!(matches!(&base.kind, ExprKind::Local(local) if local == "p"));
+            }
+            _ => panic!("expected field access"),
+        }
+    }
+
+    #[test]
+    fn parse_unary_minus_and_remainder() {
+        let src = "fn f(x: Int) -> Int { return -x % 3 - -1; }";
+        let tokens = lex_file(src).expect("lexing failed");
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().expect("parse failed");
+
+        let func = match &program.items[0] {
+            TopLevelDecl::Function(func) => func,
+            _ => panic!("expected function"),
+        };
+
+        match &func.body[0].kind {
+            StmtKind::Return(Some(Expr { kind: ExprKind::Binary { op: BinOp::Sub, left, right }, .. })) => {
+                match &left.kind {
+                    ExprKind::Binary { op: BinOp::Rem, left, .. } => {
+                        assert!(matches!(&left.kind, ExprKind::Neg(_)))
+                    }
+                    other => panic!("unexpected lhs: {:?}", other),
+                }
+                assert!(matches!(&right.kind, ExprKind::Neg(_)));
+            }
+            other => panic!("unexpected statement: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_casts_bind_tighter_than_binary_operators() {
+        let src = "fn f(x: Int, b: Byte) -> Float { return -x as Float * 2.0 + b as Int32 as Float; }";
+        let tokens = lex_file(src).expect("lexing failed");
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().expect("parse failed");
+        let TopLevelDecl::Function(func) = &program.items[0] else {
+            panic!("expected function");
+        };
+        assert_eq!(func.params[1].ty, Type::UInt8);
+        let StmtKind::Return(Some(Expr { kind: ExprKind::Binary { op: BinOp::Add, left, right }, .. })) = &func.body[0].kind else {
+            panic!("unexpected statement: {:?}", func.body[0]);
+        };
+        match &left.kind {
+            ExprKind::Binary { op: BinOp::Mul, left, .. } => assert!(matches!(
+                &left.kind,
+                ExprKind::Cast { expr, ty: Type::Float } if matches!(&expr.kind, ExprKind::Neg(_))
+            )),
+            other => panic!("unexpected lhs: {:?}", other),
+        }
+        assert!(matches!(
+            &right.kind,
+            ExprKind::Cast { expr, ty: Type::Float }
+                if matches!(&expr.kind, ExprKind::Cast { ty: Type::Int32, .. })
+        ));
+    }
+
+    #[test]
+    fn parse_logical_precedence() {
+        let src = "fn f(a: Bool, b: Bool, x: Int) -> Bool { return !a || b && x < 3; }";
+        let tokens = lex_file(src).expect("lexing failed");
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().expect("parse failed");
+
+        let func = match &program.items[0] {
+            TopLevelDecl::Function(func) => func,
+            _ => panic!("expected function"),
+        };
+
+        match &func.body[0].kind {
+            StmtKind::Return(Some(Expr { kind: ExprKind::Binary { op: BinOp::Or, left, right }, .. })) => {
+                assert!(matches!(&left.kind, ExprKind::Not(_)));
+                assert!(matches!(&right.kind, ExprKind::Binary { op: BinOp::And, .. }));
+            }
+            other => panic!("unexpected statement: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_double_reference_types_and_expressions() {
+        let src = "fn f(x: &&Int) -> Int { let y = &&1; return **x; }";
+        let tokens = lex_file(src).expect("lexing failed");
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().expect("parse failed");
+
+        let func = match &program.items[0] {
+            TopLevelDecl::Function(func) => func,
+            _ => panic!("expected function"),
+        };
+        let int_ref = Type::Ref(Box::new(Type::Int));
+        assert_eq!(func.params[0].ty, Type::Ref(Box::new(int_ref)));
+        assert!(matches!(
+            &func.body[0].kind,
+            StmtKind::Let { value: Expr { kind: ExprKind::Ref(inner), .. }, .. }
+                if matches!(inner.kind, ExprKind::Ref(_))
+        ));
+    }
+
+    #[test]
+    fn parse_for_loops_with_break_and_continue() {
+        let src = "fn f(xs: List<Int>, n: Int) -> Int { for i in 0..n + 1 { continue; } for x in xs {} while n > 0 { break; } return 0; }";
+        let tokens = lex_file(src).expect("lexing failed");
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().expect("parse failed");
+
+        let func = match &program.items[0] {
+            TopLevelDecl::Function(func) => func,
+            _ => panic!("expected function"),
+        };
+
+        match &func.body[0].kind {
+            StmtKind::For { var, iter: Expr { kind: ExprKind::Range { end, .. }, .. }, body, .. } => {
+                assert_eq!(var, "i");
+                assert!(matches!(&end.kind, ExprKind::Binary { op: BinOp::Add, .. }));
+                assert!(matches!(body[..], [Stmt { kind: StmtKind::Continue, .. }]));
+            }
+            other => panic!("unexpected statement: {:?}", other),
+        }
+        assert!(matches!(
+            &func.body[1].kind,
+            StmtKind::For { iter: Expr { kind: ExprKind::Local(name), .. }, body, .. } if name == "xs" && body.is_empty()
+        ));
+        assert!(matches!(&func.body[2].kind, StmtKind::While { body, .. } if matches!(body[..], [Stmt { kind: StmtKind::Break, .. }])));
+    }
+
+    #[test]
+    fn parse_assignments() {
+        let src = "fn f(p: Point, xs: List<Int>) -> Int { let x = 1; x = 2; p.x += 3; xs[0] *= 4; return x; }";
+        let tokens = lex_file(src).expect("lexing failed");
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().expect("parse failed");
+
+        let func = match &program.items[0] {
+            TopLevelDecl::Function(func) => func,
+            _ => panic!("expected function"),
+        };
+
+        assert!(matches!(
+            &func.body[1].kind,
+            StmtKind::Assign { target: Expr { kind: ExprKind::Local(name), .. }, op: None, .. } if name == "x"
+        ));
+        assert!(matches!(
+            &func.body[2].kind,
+            StmtKind::Assign { target: Expr { kind: ExprKind::Field { .. }, .. }, op: Some(BinOp::Add), .. }
+        ));
+        assert!(matches!(
+            &func.body[3].kind,
+            StmtKind::Assign { target: Expr { kind: ExprKind::Index { .. }, .. }, op: Some(BinOp::Mul), .. }
+        ));
+    }
+
+    #[test]
+    fn parse_rejects_assignment_to_non_place() {
+        let src = "fn f() -> Int { 1 + 2 = 3; return 0; }";
+        let tokens = lex_file(src).expect("lexing failed");
+        let mut parser = Parser::new(&tokens);
+        assert!(parser.parse_program().is_err());
+    }
+
+    #[test]
+    fn parse_recovers_at_statements_and_items() {
+        let src = "fn a() -> Int { let x = ; let p = P { x: , y: 2 }; return 1; }
+            struct S { x: , y: Int }
+            fn b() -> Int { if true { return 1; return 2; }
+            fn main() -> Int { return 0; }";
+        let tokens = lex_file(src).unwrap();
+        let errors = Parser::new(&tokens)
+            .parse_program_all_errors()
+            .expect_err("expected parse errors");
+        let positions: Vec<_> = errors.iter().map(|err| err.span().start).collect();
+        let fn_main = src.find("fn main").unwrap();
+        assert_eq!(
+            positions,
+            vec![
+                src.find("= ;").unwrap() + 2,
+                src.find(": ,").unwrap() + 2,
+                src.rfind(": ,").unwrap() + 2,
+                fn_main,
+            ]
+        );
+        let mut first = Parser::new(&tokens);
+        assert!(matches!(first.parse_program(), Err(ParseError::ExpectedExpression { .. })));
+    }
+
+    #[test]
+    fn parse_reports_unclosed_blocks_at_eof_once() {
+        let src = "fn main() -> Int { if x { return 1;";
+        let tokens = lex_file(src).unwrap();
+        let errors = Parser::new(&tokens)
+            .parse_program_all_errors()
+            .expect_err("expected parse errors");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ParseError::UnexpectedToken { found, .. } if found.kind == TokenKind::Eof
+        ));
+    }
+
+    #[test]
+    fn parse_generic_items_and_types() {
+        let src = "struct Pair<A, B> { a: A, b: B } enum Maybe<T> { Just(T), Nothing } fn first<T>(xs: List<T>) -> Maybe<T> { let p: Pair<Int, List<T>> = Pair { a: 1, b: xs }; return Maybe::Nothing; }";
+        let tokens = lex_file(src).unwrap();
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().expect("parse failed");
+
+        match &program.items[0] {
+            TopLevelDecl::Struct(def) => assert_eq!(def.type_params, vec!["A", "B"]),
+            _ => panic!("expected struct"),
+        }
+        match &program.items[1] {
+            TopLevelDecl::Enum(def) => assert_eq!(def.type_params, vec!["T"]),
+            _ => panic!("expected enum"),
+        }
+        match &program.items[2] {
+            TopLevelDecl::Function(func) => {
+                assert_eq!(func.type_params, vec!["T"]);
+                assert_eq!(
+                    func.return_type,
+                    Type::Generic("Maybe".to_string(), vec![Type::Named("T".to_string())])
+                );
+                match &func.body[0].kind {
+                    StmtKind::Let { ty: Some(ty), .. } => assert_eq!(ty.to_string(), "Pair<Int, List<T>>"),
+                    _ => panic!("expected annotated let"),
+                }
+            }
+            _ => panic!("expected function"),
+        }
+    }
+
+    #[test]
+    fn parse_question_mark_as_postfix_operator() {
+        let src = "fn f(xs: List<Int>) -> Option<Int> { return Option::Some(xs.get(0)? + 1); }";
+        let tokens = lex_file(src).unwrap();
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().expect("parse failed");
+        let TopLevelDecl::Function(func) = &program.items[0] else {
+            panic!("expected function");
+        };
+        match &func.body[0].kind {
+            StmtKind::Return(Some(Expr { kind: ExprKind::EnumInit { payload, .. }, .. })) => match &payload[0].kind {
+                ExprKind::Binary { left, .. } => assert!(matches!(
+                    &left.kind,
+                    ExprKind::Try(inner) if matches!(&inner.kind, ExprKind::MethodCall { .. })
+                )),
+                other => panic!("expected binary payload, got {:?}", other),
+            },
+            other => panic!("expected enum return, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_closures_with_and_without_annotations() {
+        let src = "fn f() -> Int { let add = |a, b: Int| a + b; let zero = || 0; return add(1, zero()); }";
+        let tokens = lex_file(src).unwrap();
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().expect("parse failed");
+        let TopLevelDecl::Function(func) = &program.items[0] else {
+            panic!("expected function");
+        };
+        match &func.body[0].kind {
+            StmtKind::Let {
+                value: Expr { kind: ExprKind::Closure { params, body }, .. },
+                ..
+            } => {
+                assert_eq!(params.len(), 2);
+                assert!(params[0].ty.is_none());
+                assert_eq!(params[1].ty, Some(Type::Int));
+                assert!(matches!(&body.kind, ExprKind::Binary { op: BinOp::Add, .. }));
+            }
+            other => panic!("expected closure binding, got {:?}", other),
+        }
+        assert!(matches!(
+            &func.body[1].kind,
+            StmtKind::Let { value: Expr { kind: ExprKind::Closure { params, .. }, .. }, .. } if params.is_empty()
+        ));
+    }
+
+    #[test]
+    fn parse_impl_and_trait_blocks() {
+        let src = "trait Area { fn area(&self) -> Int; } impl<T: Area> Pair<T> { fn new(a: T) -> Self { return Self { a: a }; } fn first(&self) -> T { return self.a; } }";
+        let tokens = lex_file(src).unwrap();
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().expect("parse failed");
+        let TopLevelDecl::Trait(def) = &program.items[0] else {
+            panic!("expected trait");
+        };
+        assert_eq!(def.methods[0].params[0].ty, Type::Ref(Box::new(Type::Named("Self".to_string()))));
+        let TopLevelDecl::Impl(imp) = &program.items[1] else {
+            panic!("expected impl");
+        };
+        let pair = Type::Generic("Pair".to_string(), vec![Type::Named("T".to_string())]);
+        assert_eq!(imp.trait_name, None);
+        assert_eq!(imp.target, pair);
+        let names: Vec<_> = program.functions().map(|func| func.name.as_str()).collect();
+        assert_eq!(names, vec!["Pair::new", "Pair::first"]);
+        assert_eq!(imp.methods[0].return_type, pair);
+        assert_eq!(imp.methods[1].params[0].ty, Type::Ref(Box::new(pair)));
+        assert_eq!(imp.methods[1].bounds, vec![("T".to_string(), "Area".to_string())]);
+        assert!(matches!(
+            &imp.methods[0].body[0].kind,
+            StmtKind::Return(Some(Expr { kind: ExprKind::StructInit { name, .. }, .. })) if name == "Pair"
+        ));
+    }
+
     #[test]
     fn parse_list_method_call() {
         let src = "fn f(xs: List<Int>) -> Int { return xs.len(); }";
@@ -335,8 +670,8 @@ mod tests {
             })
             .expect("function missing");
 
-        match &f.body[0] {
-            Stmt::Return(Some(Expr::MethodCall { name, .. })) => {
+        match &f.body[0].kind {
+            StmtKind::Return(Some(Expr { kind: ExprKind::MethodCall { name, .. }, .. })) => {
                 assert_eq!(name, "len");
             }
             _ => panic!("expected method call"),
@@ -347,86 +682,226 @@ mod tests {
 impl<'a> Parser<'a> {
     /// Creates a parser over the token stream.
     pub fn new(tokens: &'a [Token]) -> Parser<'a> {
-        Parser { tokens, pos: 0 }
+        Parser {
+            tokens,
+            pos: 0,
+            no_struct_literal: false,
+            self_type: None,
+            offset: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Shifts all AST spans by `offset`; token spans in parse errors stay file-relative.
+    pub fn with_offset(mut self, offset: usize) -> Parser<'a> {
+        self.offset = offset;
+        self
+    }
+
+    /// Span of the next token, shifted into AST coordinates.
+    fn peek_span(&self) -> Span {
+        let span = &self.peek().span;
+        Span {
+            start: span.start + self.offset,
+            end: span.end + self.offset,
+        }
+    }
+
+    /// Span from the token that started at `start` to the end of the last consumed token.
+    fn span_from(&self, start: usize) -> Span {
+        let end = self.tokens[..self.pos].last().map_or(start, |tok| tok.span.end);
+        Span {
+            start: start + self.offset,
+            end: end.max(start) + self.offset,
+        }
     }
 
     fn peek(&self) -> &Token {
         self.tokens.get(self.pos).unwrap()
     }
 
+    /// Consumes the next token; `Eof` is never consumed, so recovery can always look ahead.
     fn bump(&mut self) -> Token {
         let tok = self.peek().clone();
-        self.pos += 1;
+        if tok.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
         tok
     }
 
+    /// Consumes a token of `kind`; a mismatched token is left in place for error recovery.
     fn expect(&mut self, kind: TokenKind) -> Result<Token, ParseError> {
-        let tok = self.bump();
-        if tok.kind == kind {
-            Ok(tok)
+        if self.peek().kind == kind {
+            Ok(self.bump())
         } else {
             Err(ParseError::UnexpectedToken {
                 expected: kind.name(),
-                found: tok,
+                found: self.peek().clone(),
             })
         }
     }
 
     fn expect_ident(&mut self) -> Result<String, ParseError> {
-        let tok = self.bump();
-        match tok.kind {
-            TokenKind::Ident(s) => Ok(s),
+        match &self.peek().kind {
+            TokenKind::Ident(s) => {
+                let s = s.clone();
+                self.bump();
+                Ok(s)
+            }
             _ => Err(ParseError::UnexpectedToken {
                 expected: "identifier".to_string(),
-                found: tok,
+                found: self.peek().clone(),
             }),
         }
     }
 
-    /// Parses a complete program until `Eof`.
+    /// Parses a complete program until `Eof` and returns its first error.
     pub fn parse_program(&mut self) -> Result<Program, ParseError> {
+        self.parse_program_all_errors().map_err(|mut errors| errors.remove(0))
+    }
+
+    /// Parses a complete program until `Eof`, collecting every error.
+    ///
+    /// After an error the parser resynchronizes: inside a block at the next `;` or the `}`
+    /// closing it, otherwise at the next item keyword, and then continues parsing.
+    pub fn parse_program_all_errors(&mut self) -> Result<Program, Vec<ParseError>> {
         let mut items = Vec::new();
 
         while self.peek().kind != TokenKind::Eof {
-            let item = match self.peek().kind {
-                TokenKind::Use => self.parse_use()?,
-                TokenKind::Struct => self.parse_struct()?,
-                TokenKind::Enum => self.parse_enum()?,
-                TokenKind::Fn => TopLevelDecl::Function(self.parse_function()?),
-                _ => {
-                    return Err(ParseError::UnexpectedToken {
-                        expected: "use|fn|struct|enum".to_string(),
-                        found: self.peek().clone(),
-                    })
+            let start = self.pos;
+            match self.parse_item() {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    self.record_error(error);
+                    self.recover_item(start);
                 }
-            };
-            items.push(item);
+            }
+        }
+
+        if self.errors.is_empty() {
+            Ok(Program { items })
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    fn parse_item(&mut self) -> Result<TopLevelDecl, ParseError> {
+        match self.peek().kind {
+            TokenKind::Use => self.parse_use(),
+            TokenKind::Struct => self.parse_struct(),
+            TokenKind::Enum => self.parse_enum(),
+            TokenKind::Fn => Ok(TopLevelDecl::Function(self.parse_function()?)),
+            TokenKind::Impl => self.parse_impl(),
+            TokenKind::Trait => self.parse_trait(),
+            TokenKind::Const => self.parse_const(),
+            TokenKind::Static => self.parse_static(),
+            _ => Err(ParseError::UnexpectedToken {
+                expected: "use|fn|struct|enum|impl|trait|const|static".to_string(),
+                found: self.peek().clone(),
+            }),
+        }
+    }
+
+    /// Whether the next tokens start a top-level item; `fn` only counts before a name, so
+    /// function types such as `fn(Int) -> Int` do not.
+    fn at_item_start(&self) -> bool {
+        match self.peek().kind {
+            TokenKind::Use
+            | TokenKind::Struct
+            | TokenKind::Enum
+            | TokenKind::Impl
+            | TokenKind::Trait
+            | TokenKind::Const
+            | TokenKind::Static => true,
+            TokenKind::Fn => self
+                .tokens
+                .get(self.pos + 1)
+                .is_some_and(|tok| matches!(tok.kind, TokenKind::Ident(_))),
+            _ => false,
+        }
+    }
+
+    /// Records a recovered error unless one was already reported at the same token; every
+    /// block left open at the end of input would otherwise repeat the same `Eof` error.
+    fn record_error(&mut self, error: ParseError) {
+        if !self.errors.iter().any(|seen| seen.span() == error.span()) {
+            self.errors.push(error);
+        }
+    }
+
+    /// Braces opened and not yet closed by the tokens consumed since `start`.
+    fn open_braces_since(&self, start: usize) -> usize {
+        let depth = self.tokens[start..self.pos].iter().fold(0i64, |depth, tok| match tok.kind {
+            TokenKind::LBrace => depth + 1,
+            TokenKind::RBrace => depth - 1,
+            _ => depth,
+        });
+        depth.max(0) as usize
+    }
+
+    /// Skips the rest of a broken item that started at token `start`, up to the next item
+    /// outside of its braces.
+    fn recover_item(&mut self, start: usize) {
+        // An unclosed block is reported at the next item, which must then be kept.
+        if self.pos > start && self.at_item_start() {
+            return;
+        }
+        let mut depth = self.open_braces_since(start);
+        loop {
+            match self.peek().kind {
+                TokenKind::Eof => return,
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => depth = depth.saturating_sub(1),
+                _ if depth == 0 && self.pos > start && self.at_item_start() => return,
+                _ => {}
+            }
+            self.bump();
         }
+    }
 
-        Ok(Program { items })
+    /// Skips the rest of a broken statement that started at token `start`: through its
+    /// terminating `;`, or up to the `}` closing the enclosing block or the next item.
+    fn recover_stmt(&mut self, start: usize) {
+        let mut depth = self.open_braces_since(start);
+        loop {
+            match self.peek().kind {
+                TokenKind::Eof => return,
+                TokenKind::Semicolon if depth == 0 => {
+                    self.bump();
+                    return;
+                }
+                TokenKind::RBrace if depth == 0 => return,
+                _ if depth == 0 && self.pos > start && self.at_item_start() => return,
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => depth -= 1,
+                _ => {}
+            }
+            self.bump();
+        }
     }
 
     fn parse_use(&mut self) -> Result<TopLevelDecl, ParseError> {
+        let start = self.peek().span.start;
         self.expect(TokenKind::Use)?;
-        let path = match self.bump().kind {
-            TokenKind::StringLit(path) => path,
-            other => {
+        let path = match &self.peek().kind {
+            TokenKind::StringLit(path) => path.clone(),
+            _ => {
                 return Err(ParseError::UnexpectedToken {
                     expected: "string literal".to_string(),
-                    found: Token {
-                        kind: other,
-                        span: self.tokens[self.pos - 1].span.clone(),
-                    },
+                    found: self.peek().clone(),
                 });
             }
         };
+        self.bump();
         self.expect(TokenKind::Semicolon)?;
-        Ok(TopLevelDecl::Use(path))
+        Ok(TopLevelDecl::Use(path, self.span_from(start)))
     }
 
     fn parse_struct(&mut self) -> Result<TopLevelDecl, ParseError> {
         self.expect(TokenKind::Struct)?;
+        let span = self.peek_span();
         let name = self.expect_ident()?;
+        let type_params = self.parse_type_params()?;
         self.expect(TokenKind::LBrace)?;
 
         let mut fields = Vec::new();
@@ -446,12 +921,19 @@ impl<'a> Parser<'a> {
         }
 
         self.expect(TokenKind::RBrace)?;
-        Ok(TopLevelDecl::Struct(StructDecl { name, fields }))
+        Ok(TopLevelDecl::Struct(StructDecl {
+            name,
+            type_params,
+            fields,
+            span,
+        }))
     }
 
     fn parse_enum(&mut self) -> Result<TopLevelDecl, ParseError> {
         self.expect(TokenKind::Enum)?;
+        let span = self.peek_span();
         let name = self.expect_ident()?;
+        let type_params = self.parse_type_params()?;
         self.expect(TokenKind::LBrace)?;
 
         let mut variants = Vec::new();
@@ -497,52 +979,292 @@ impl<'a> Parser<'a> {
         }
 
         self.expect(TokenKind::RBrace)?;
-        Ok(TopLevelDecl::Enum(EnumDecl { name, variants }))
+        Ok(TopLevelDecl::Enum(EnumDecl {
+            name,
+            type_params,
+            variants,
+            span,
+        }))
     }
 
     fn parse_function(&mut self) -> Result<FunctionDecl, ParseError> {
         self.expect(TokenKind::Fn)?;
 
+        let span = self.peek_span();
         let name = self.expect_ident()?;
+        let (type_params, bounds) = self.parse_bounded_type_params()?;
+        let params = self.parse_params()?;
+        self.expect(TokenKind::Arrow)?;
+        let return_type = self.parse_type()?;
+        let body = self.parse_block()?;
 
+        Ok(FunctionDecl {
+            name,
+            type_params,
+            params,
+            body,
+            return_type,
+            bounds,
+            span,
+        })
+    }
+
+    /// Parses a parenthesized parameter list; inside `impl`/`trait` blocks the first
+    /// parameter may be `self` or `&self`.
+    fn parse_params(&mut self) -> Result<Vec<Param>, ParseError> {
         self.expect(TokenKind::LParen)?;
 
         let mut params = Vec::new();
-        if self.peek().kind != TokenKind::RParen {
-            loop {
-                let pname = self.expect_ident()?;
-                self.expect(TokenKind::Colon)?;
-                let pty = self.parse_type()?;
-                params.push(Param { name: pname, ty: pty });
-
+        if let Some(self_type) = self.self_type.clone() {
+            let by_ref = self.peek().kind == TokenKind::Ampersand
+                && matches!(
+                    self.tokens.get(self.pos + 1).map(|tok| &tok.kind),
+                    Some(TokenKind::Ident(name)) if name == "self"
+                );
+            if by_ref {
+                self.bump();
+            }
+            if matches!(&self.peek().kind, TokenKind::Ident(name) if name == "self") {
+                let span = self.peek_span();
+                self.bump();
+                let ty = if by_ref {
+                    Type::Ref(Box::new(self_type))
+                } else {
+                    self_type
+                };
+                params.push(Param {
+                    name: "self".to_string(),
+                    ty,
+                    span,
+                });
                 if self.peek().kind == TokenKind::Comma {
                     self.bump();
-                } else {
-                    break;
                 }
             }
         }
 
+        while self.peek().kind != TokenKind::RParen {
+            let span = self.peek_span();
+            let pname = self.expect_ident()?;
+            self.expect(TokenKind::Colon)?;
+            let pty = self.parse_type()?;
+            params.push(Param {
+                name: pname,
+                ty: pty,
+                span,
+            });
+
+            if self.peek().kind == TokenKind::Comma {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+
         self.expect(TokenKind::RParen)?;
-        self.expect(TokenKind::Arrow)?;
-        let return_type = self.parse_type()?;
-        let body = self.parse_block()?;
+        Ok(params)
+    }
 
-        Ok(FunctionDecl {
+    /// Parses `impl<T> Target { ... }` or `impl Trait for Target { ... }`, lowering the
+    /// methods to functions named `Target::method`.
+    fn parse_impl(&mut self) -> Result<TopLevelDecl, ParseError> {
+        self.expect(TokenKind::Impl)?;
+        let (type_params, bounds) = self.parse_bounded_type_params()?;
+
+        let span = self.peek_span();
+        let first_tok = self.peek().clone();
+        let first = self.parse_type()?;
+        let (trait_name, target) = if self.peek().kind == TokenKind::For {
+            self.bump();
+            let Type::Named(trait_name) = first else {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "trait name".to_string(),
+                    found: first_tok,
+                });
+            };
+            (Some(trait_name), self.parse_type()?)
+        } else {
+            (None, first)
+        };
+        let Some(base) = target.impl_name().map(str::to_string) else {
+            return Err(ParseError::UnexpectedToken {
+                expected: "named type".to_string(),
+                found: first_tok,
+            });
+        };
+
+        self.expect(TokenKind::LBrace)?;
+        let outer_self = self.self_type.replace(target.clone());
+        let mut methods = Vec::new();
+        while self.peek().kind != TokenKind::RBrace {
+            let mut method = match self.parse_function() {
+                Ok(method) => method,
+                Err(err) => {
+                    self.self_type = outer_self;
+                    return Err(err);
+                }
+            };
+            method.name = method_function_name(&base, &method.name);
+            method.type_params = type_params.iter().cloned().chain(method.type_params).collect();
+            method.bounds = bounds.iter().cloned().chain(method.bounds).collect();
+            methods.push(method);
+        }
+        self.self_type = outer_self;
+        self.expect(TokenKind::RBrace)?;
+
+        Ok(TopLevelDecl::Impl(ImplDecl {
+            trait_name,
+            type_params,
+            target,
+            methods,
+            span,
+        }))
+    }
+
+    /// Parses `const NAME: Type = value;`; the type annotation is required.
+    fn parse_const(&mut self) -> Result<TopLevelDecl, ParseError> {
+        self.expect(TokenKind::Const)?;
+        let span = self.peek_span();
+        let name = self.expect_ident()?;
+        self.expect(TokenKind::Colon)?;
+        let ty = self.parse_type()?;
+        self.expect(TokenKind::Equal)?;
+        let value = self.parse_expr()?;
+        self.expect(TokenKind::Semicolon)?;
+        Ok(TopLevelDecl::Const(ConstDecl {
             name,
-            params,
-            body,
-            return_type,
-        })
+            ty,
+            value,
+            span,
+        }))
+    }
+
+    /// Parses `static mut NAME: Type = value;`; only mutable statics exist, constants use `const`.
+    fn parse_static(&mut self) -> Result<TopLevelDecl, ParseError> {
+        self.expect(TokenKind::Static)?;
+        self.expect(TokenKind::Mut)?;
+        let span = self.peek_span();
+        let name = self.expect_ident()?;
+        self.expect(TokenKind::Colon)?;
+        let ty = self.parse_type()?;
+        self.expect(TokenKind::Equal)?;
+        let value = self.parse_expr()?;
+        self.expect(TokenKind::Semicolon)?;
+        Ok(TopLevelDecl::Static(StaticDecl {
+            name,
+            ty,
+            value,
+            span,
+        }))
+    }
+
+    /// Parses `trait Name { fn method(&self, ...) -> T; ... }`.
+    fn parse_trait(&mut self) -> Result<TopLevelDecl, ParseError> {
+        self.expect(TokenKind::Trait)?;
+        let span = self.peek_span();
+        let name = self.expect_ident()?;
+        self.expect(TokenKind::LBrace)?;
+
+        let outer_self = self.self_type.replace(Type::Named("Self".to_string()));
+        let methods = self.parse_trait_methods();
+        self.self_type = outer_self;
+        let methods = methods?;
+
+        self.expect(TokenKind::RBrace)?;
+        Ok(TopLevelDecl::Trait(TraitDecl {
+            name,
+            methods,
+            span,
+        }))
+    }
+
+    fn parse_trait_methods(&mut self) -> Result<Vec<TraitMethod>, ParseError> {
+        let mut methods = Vec::new();
+        while self.peek().kind != TokenKind::RBrace {
+            self.expect(TokenKind::Fn)?;
+            let span = self.peek_span();
+            let name = self.expect_ident()?;
+            let params = self.parse_params()?;
+            self.expect(TokenKind::Arrow)?;
+            let return_type = self.parse_type()?;
+            self.expect(TokenKind::Semicolon)?;
+            methods.push(TraitMethod {
+                name,
+                params,
+                return_type,
+                span,
+            });
+        }
+        Ok(methods)
+    }
+
+    /// Parses an optional `<T: Show + Eq, U>` type parameter list with trait bounds.
+    fn parse_bounded_type_params(&mut self) -> Result<BoundedTypeParams, ParseError> {
+        let mut params = Vec::new();
+        let mut bounds = Vec::new();
+        if self.peek().kind != TokenKind::Less {
+            return Ok((params, bounds));
+        }
+        self.bump();
+        loop {
+            let param = self.expect_ident()?;
+            if self.peek().kind == TokenKind::Colon {
+                self.bump();
+                loop {
+                    bounds.push((param.clone(), self.expect_ident()?));
+                    if self.peek().kind == TokenKind::Plus {
+                        self.bump();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            params.push(param);
+            if self.peek().kind == TokenKind::Comma {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        self.expect(TokenKind::Greater)?;
+        Ok((params, bounds))
+    }
+
+    /// Parses an optional `<A, B>` type parameter list after an item name.
+    fn parse_type_params(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut params = Vec::new();
+        if self.peek().kind != TokenKind::Less {
+            return Ok(params);
+        }
+        self.bump();
+        loop {
+            params.push(self.expect_ident()?);
+            if self.peek().kind == TokenKind::Comma {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        self.expect(TokenKind::Greater)?;
+        Ok(params)
     }
 
     fn parse_block(&mut self) -> Result<Vec<Stmt>, ParseError> {
         self.expect(TokenKind::LBrace)?;
         let mut stmts = Vec::new();
 
-        while self.peek().kind != TokenKind::RBrace {
-            let stmt = self.parse_stmt()?;
-            stmts.push(stmt);
+        // An item keyword means this block was never closed; `expect` below reports it.
+        while !matches!(self.peek().kind, TokenKind::RBrace | TokenKind::Eof)
+            && !self.at_item_start()
+        {
+            let start = self.pos;
+            match self.parse_stmt() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(error) => {
+                    self.record_error(error);
+                    self.recover_stmt(start);
+                }
+            }
         }
 
         self.expect(TokenKind::RBrace)?;
@@ -550,18 +1272,31 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_stmt(&mut self) -> Result<Stmt, ParseError> {
-        match self.peek().kind {
-            TokenKind::Let => self.parse_let(),
-            TokenKind::Return => self.parse_return(),
-            TokenKind::If => self.parse_if(),
-            TokenKind::While => self.parse_while(),
-            _ => self.parse_expr_stmt(),
-        }
+        let start = self.peek().span.start;
+        let kind = match self.peek().kind {
+            TokenKind::Let => self.parse_let()?,
+            TokenKind::Return => self.parse_return()?,
+            TokenKind::If => self.parse_if()?,
+            TokenKind::While => self.parse_while()?,
+            TokenKind::For => self.parse_for()?,
+            TokenKind::Break | TokenKind::Continue => {
+                let stmt = if self.bump().kind == TokenKind::Break {
+                    StmtKind::Break
+                } else {
+                    StmtKind::Continue
+                };
+                self.expect(TokenKind::Semicolon)?;
+                stmt
+            }
+            _ => self.parse_expr_stmt()?,
+        };
+        Ok(Stmt::new(kind, self.span_from(start)))
     }
 
-    fn parse_let(&mut self) -> Result<Stmt, ParseError> {
+    fn parse_let(&mut self) -> Result<StmtKind, ParseError> {
         self.expect(TokenKind::Let)?;
 
+        let name_span = self.peek_span();
         let name = self.expect_ident()?;
         let ty = if self.peek().kind == TokenKind::Colon {
             self.bump();
@@ -574,26 +1309,31 @@ impl<'a> Parser<'a> {
         let value = self.parse_expr()?;
         self.expect(TokenKind::Semicolon)?;
 
-        Ok(Stmt::Let { name, ty, value })
+        Ok(StmtKind::Let {
+            name,
+            name_span,
+            ty,
+            value,
+        })
     }
 
-    fn parse_return(&mut self) -> Result<Stmt, ParseError> {
+    fn parse_return(&mut self) -> Result<StmtKind, ParseError> {
         self.expect(TokenKind::Return)?;
 
         if self.peek().kind == TokenKind::Semicolon {
             self.bump();
-            Ok(Stmt::Return(None))
+            Ok(StmtKind::Return(None))
         } else {
             let expr = self.parse_expr()?;
             self.expect(TokenKind::Semicolon)?;
-            Ok(Stmt::Return(Some(expr)))
+            Ok(StmtKind::Return(Some(expr)))
         }
     }
 
-    fn parse_if(&mut self) -> Result<Stmt, ParseError> {
+    fn parse_if(&mut self) -> Result<StmtKind, ParseError> {
         self.expect(TokenKind::If)?;
 
-        let cond = self.parse_expr()?;
+        let cond = self.parse_head_expr()?;
         let then_block = self.parse_block()?;
         let else_block = if self.peek().kind == TokenKind::Else {
             self.bump();
@@ -602,34 +1342,98 @@ impl<'a> Parser<'a> {
             Vec::new()
         };
 
-        Ok(Stmt::If {
+        Ok(StmtKind::If {
             cond,
             then_block,
             else_block,
         })
     }
 
-    fn parse_while(&mut self) -> Result<Stmt, ParseError> {
+    fn parse_while(&mut self) -> Result<StmtKind, ParseError> {
         self.expect(TokenKind::While)?;
-        let cond = self.parse_expr()?;
+        let cond = self.parse_head_expr()?;
+        let body = self.parse_block()?;
+
+        Ok(StmtKind::While { cond, body })
+    }
+
+    fn parse_for(&mut self) -> Result<StmtKind, ParseError> {
+        self.expect(TokenKind::For)?;
+        let var_span = self.peek_span();
+        let var = self.expect_ident()?;
+        self.expect(TokenKind::In)?;
+        let iter = self.parse_head_expr()?;
         let body = self.parse_block()?;
 
-        Ok(Stmt::While { cond, body })
+        Ok(StmtKind::For {
+            var,
+            var_span,
+            iter,
+            body,
+        })
+    }
+
+    /// Parses the expression in front of a statement block without treating `name {` as a struct literal.
+    fn parse_head_expr(&mut self) -> Result<Expr, ParseError> {
+        let previous = std::mem::replace(&mut self.no_struct_literal, true);
+        let expr = self.parse_expr();
+        self.no_struct_literal = previous;
+        expr
     }
 
-    fn parse_expr_stmt(&mut self) -> Result<Stmt, ParseError> {
+    fn parse_expr_stmt(&mut self) -> Result<StmtKind, ParseError> {
         let expr = self.parse_expr()?;
+        let op = match self.peek().kind {
+            TokenKind::Equal => None,
+            TokenKind::PlusEqual => Some(BinOp::Add),
+            TokenKind::MinusEqual => Some(BinOp::Sub),
+            TokenKind::StarEqual => Some(BinOp::Mul),
+            TokenKind::SlashEqual => Some(BinOp::Div),
+            _ => {
+                self.expect(TokenKind::Semicolon)?;
+                return Ok(StmtKind::Expr(expr));
+            }
+        };
+
+        let assign_token = self.bump();
+        if !matches!(
+            expr.kind,
+            ExprKind::Local(_) | ExprKind::Field { .. } | ExprKind::Index { .. }
+        ) {
+            return Err(ParseError::UnexpectedToken {
+                expected: "`;` (only locals, fields and indexed elements can be assigned)"
+                    .to_string(),
+                found: assign_token,
+            });
+        }
+        let value = self.parse_expr()?;
         self.expect(TokenKind::Semicolon)?;
-        Ok(Stmt::Expr(expr))
+        Ok(StmtKind::Assign {
+            target: expr,
+            op,
+            value,
+        })
     }
 
     fn parse_expr(&mut self) -> Result<Expr, ParseError> {
-        self.parse_binary_expr(0)
+        let first = self.peek().span.start;
+        let start = self.parse_binary_expr(0)?;
+        if self.peek().kind != TokenKind::DotDot {
+            return Ok(start);
+        }
+        self.bump();
+        let end = self.parse_binary_expr(0)?;
+        let kind = ExprKind::Range {
+            start: Box::new(start),
+            end: Box::new(end),
+        };
+        Ok(Expr::new(kind, self.span_from(first)))
     }
 
     // Precedence-climbing parser.
     fn parse_binary_expr(&mut self, min_prec: u8) -> Result<Expr, ParseError> {
-        let mut lhs = self.parse_unary()?;
+        let start = self.peek().span.start;
+        let mut lhs = self.parse_cast()?;
 
         while let Some((op, prec)) = self.token_to_binop_prec(&self.peek().kind) {
             if prec < min_prec {
@@ -641,60 +1445,105 @@ impl<'a> Parser<'a> {
             let next_min = prec + 1;
             let rhs = self.parse_binary_expr(next_min)?;
 
-            lhs = Expr::Binary {
+            let kind = ExprKind::Binary {
                 op,
                 left: Box::new(lhs),
                 right: Box::new(rhs),
             };
+            lhs = Expr::new(kind, self.span_from(start));
         }
 
         Ok(lhs)
     }
 
+    /// `as` binds tighter than every binary operator but looser than prefix operators.
+    fn parse_cast(&mut self) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let mut expr = self.parse_unary()?;
+        while self.peek().kind == TokenKind::As {
+            self.bump();
+            let ty = self.parse_type()?;
+            let kind = ExprKind::Cast {
+                expr: Box::new(expr),
+                ty,
+            };
+            expr = Expr::new(kind, self.span_from(start));
+        }
+        Ok(expr)
+    }
+
     fn parse_unary(&mut self) -> Result<Expr, ParseError> {
-        match self.peek().kind {
+        let start = self.peek().span.start;
+        let kind = match self.peek().kind {
             TokenKind::Ampersand => {
                 self.bump();
-                Ok(Expr::Ref(Box::new(self.parse_unary()?)))
+                ExprKind::Ref(Box::new(self.parse_unary()?))
+            }
+            TokenKind::AndAnd => {
+                // `&&x` lexes as one token but means a reference to a reference.
+                self.bump();
+                let inner = self.parse_unary()?;
+                let span = self.span_from(start + 1);
+                ExprKind::Ref(Box::new(Expr::new(ExprKind::Ref(Box::new(inner)), span)))
             }
             TokenKind::Star => {
                 self.bump();
-                Ok(Expr::Deref(Box::new(self.parse_unary()?)))
+                ExprKind::Deref(Box::new(self.parse_unary()?))
             }
-            _ => self.parse_postfix(),
-        }
+            TokenKind::Bang => {
+                self.bump();
+                ExprKind::Not(Box::new(self.parse_unary()?))
+            }
+            TokenKind::Minus => {
+                self.bump();
+                ExprKind::Neg(Box::new(self.parse_unary()?))
+            }
+            _ => return self.parse_postfix(),
+        };
+        Ok(Expr::new(kind, self.span_from(start)))
     }
 
     fn parse_postfix(&mut self) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
         let mut expr = self.parse_primary()?;
 
-        while let TokenKind::LBracket | TokenKind::Dot = &self.peek().kind {
+        while let TokenKind::LBracket | TokenKind::Dot | TokenKind::Question = &self.peek().kind {
             match self.peek().kind {
+                TokenKind::Question => {
+                    self.bump();
+                    expr = Expr::new(ExprKind::Try(Box::new(expr)), self.span_from(start));
+                }
                 TokenKind::LBracket => {
                     self.bump();
                     let index = self.parse_expr()?;
                     self.expect(TokenKind::RBracket)?;
-                    expr = Expr::Index {
+                    let kind = ExprKind::Index {
                         base: Box::new(expr),
                         index: Box::new(index),
                     };
+                    expr = Expr::new(kind, self.span_from(start));
                 }
                 TokenKind::Dot => {
                     self.bump();
                     let token = self.bump().clone();
                     match token.kind {
                         TokenKind::Int(index) => {
-                            expr = Expr::Index {
+                            let index_span = self.span_from(token.span.start);
+                            let index = Expr::new(ExprKind::Int(index), index_span);
+                            let kind = ExprKind::Index {
                                 base: Box::new(expr),
-                                index: Box::new(Expr::Int(index)),
+                                index: Box::new(index),
                             };
+                            expr = Expr::new(kind, self.span_from(start));
                         }
                         TokenKind::Ident(name) => {
                             if self.peek().kind != TokenKind::LParen {
-                                return Err(ParseError::UnexpectedToken {
-                                    expected: "method call".to_string(),
-                                    found: self.peek().clone(),
-                                });
+                                let kind = ExprKind::Field {
+                                    base: Box::new(expr),
+                                    name,
+                                };
+                                expr = Expr::new(kind, self.span_from(start));
+                                continue;
                             }
                             self.bump();
                             let mut args = Vec::new();
@@ -709,15 +1558,16 @@ impl<'a> Parser<'a> {
                                 }
                             }
                             self.expect(TokenKind::RParen)?;
-                            expr = Expr::MethodCall {
+                            let kind = ExprKind::MethodCall {
                                 receiver: Box::new(expr),
                                 name,
                                 args,
                             };
+                            expr = Expr::new(kind, self.span_from(start));
                         }
                         _ => {
                             return Err(ParseError::UnexpectedToken {
-                                expected: "integer index or method name".to_string(),
+                                expected: "integer index, field or method name".to_string(),
                                 found: token,
                             });
                         }
@@ -732,31 +1582,66 @@ impl<'a> Parser<'a> {
 
     fn token_to_binop_prec(&self, kind: &TokenKind) -> Option<(BinOp, u8)> {
         match kind {
-            TokenKind::Star => Some((BinOp::Mul, 4)),
-            TokenKind::Slash => Some((BinOp::Div, 4)),
-            TokenKind::Plus => Some((BinOp::Add, 3)),
-            TokenKind::Minus => Some((BinOp::Sub, 3)),
-            TokenKind::Less => Some((BinOp::Lt, 2)),
-            TokenKind::LessEqual => Some((BinOp::Le, 2)),
-            TokenKind::Greater => Some((BinOp::Gt, 2)),
-            TokenKind::GreaterEqual => Some((BinOp::Ge, 2)),
-            TokenKind::EqualEqual => Some((BinOp::Eq, 1)),
-            TokenKind::NotEqual => Some((BinOp::NotEq, 1)),
+            TokenKind::Star => Some((BinOp::Mul, 6)),
+            TokenKind::Slash => Some((BinOp::Div, 6)),
+            TokenKind::Percentage => Some((BinOp::Rem, 6)),
+            TokenKind::Plus => Some((BinOp::Add, 5)),
+            TokenKind::Minus => Some((BinOp::Sub, 5)),
+            TokenKind::Less => Some((BinOp::Lt, 4)),
+            TokenKind::LessEqual => Some((BinOp::Le, 4)),
+            TokenKind::Greater => Some((BinOp::Gt, 4)),
+            TokenKind::GreaterEqual => Some((BinOp::Ge, 4)),
+            TokenKind::EqualEqual => Some((BinOp::Eq, 3)),
+            TokenKind::NotEqual => Some((BinOp::NotEq, 3)),
+            TokenKind::AndAnd => Some((BinOp::And, 2)),
+            TokenKind::OrOr => Some((BinOp::Or, 1)),
             _ => None,
         }
     }
 
+    /// Parses the `{...}` holes of an interpolated string, each as a complete expression.
+    fn parse_interpolation(&mut self, parts: Vec<StringPart>) -> Result<ExprKind, ParseError> {
+        let mut parsed = Vec::new();
+        for part in parts {
+            match part {
+                StringPart::Text(text) => parsed.push(InterpolationPart::Text(text)),
+                StringPart::Expr(tokens) => {
+                    let mut inner = Parser::new(&tokens).with_offset(self.offset);
+                    inner.self_type = self.self_type.clone();
+                    let expr = inner.parse_expr()?;
+                    inner.expect(TokenKind::Eof)?;
+                    parsed.push(InterpolationPart::Expr(expr));
+                }
+            }
+        }
+        Ok(ExprKind::Interpolation(parsed))
+    }
+
     fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let start = self.peek().span.start;
+        let kind = self.parse_primary_kind()?;
+        Ok(Expr::new(kind, self.span_from(start)))
+    }
+
+    fn parse_primary_kind(&mut self) -> Result<ExprKind, ParseError> {
         let tok = self.bump().clone();
 
         match tok.kind {
-            TokenKind::Int(value) => Ok(Expr::Int(value)),
-            TokenKind::Float(value) => Ok(Expr::Float(value)),
-            TokenKind::True => Ok(Expr::Bool(true)),
-            TokenKind::False => Ok(Expr::Bool(false)),
-            TokenKind::StringLit(value) => Ok(Expr::String(value)),
+            TokenKind::Int(value) => Ok(ExprKind::Int(value)),
+            TokenKind::Float(value) => Ok(ExprKind::Float(value)),
+            TokenKind::True => Ok(ExprKind::Bool(true)),
+            TokenKind::False => Ok(ExprKind::Bool(false)),
+            TokenKind::StringLit(value) => Ok(ExprKind::String(value)),
+            TokenKind::InterpolatedString(parts) => self.parse_interpolation(parts),
 
             TokenKind::Ident(name) => {
+                // `Self::new(..)` and `Self { .. }` name the impl's type.
+                let name = match &self.self_type {
+                    Some(self_type) if name == "Self" => {
+                        self_type.impl_name().map(str::to_string).unwrap_or(name)
+                    }
+                    _ => name,
+                };
                 if self.peek().kind == TokenKind::DoubleColon {
                     self.bump();
                     let variant = self.expect_ident()?;
@@ -775,13 +1660,13 @@ impl<'a> Parser<'a> {
                             }
                         }
                         self.expect(TokenKind::RParen)?;
-                        Ok(Expr::EnumInit {
+                        Ok(ExprKind::EnumInit {
                             enum_name: name,
                             variant,
                             payload,
                         })
                     } else {
-                        Ok(Expr::EnumInit {
+                        Ok(ExprKind::EnumInit {
                             enum_name: name,
                             variant,
                             payload: Vec::new(),
@@ -803,7 +1688,7 @@ impl<'a> Parser<'a> {
                     }
 
                     self.expect(TokenKind::RParen)?;
-                    Ok(Expr::Call { name, args })
+                    Ok(ExprKind::Call { name, args })
                 } else if self.peek().kind == TokenKind::LBrace && self.looks_like_struct_init() {
                     self.bump();
                     let mut fields = Vec::new();
@@ -822,9 +1707,9 @@ impl<'a> Parser<'a> {
                         }
                     }
                     self.expect(TokenKind::RBrace)?;
-                    Ok(Expr::StructInit { name, fields })
+                    Ok(ExprKind::StructInit { name, fields })
                 } else {
-                    Ok(Expr::Local(name))
+                    Ok(ExprKind::Local(name))
                 }
             }
 
@@ -837,10 +1722,10 @@ impl<'a> Parser<'a> {
                         elements.push(self.parse_expr()?);
                     }
                     self.expect(TokenKind::RParen)?;
-                    Ok(Expr::TupleLiteral(elements))
+                    Ok(ExprKind::TupleLiteral(elements))
                 } else {
                     self.expect(TokenKind::RParen)?;
-                    Ok(first)
+                    Ok(first.kind)
                 }
             }
 
@@ -858,7 +1743,38 @@ impl<'a> Parser<'a> {
                 }
 
                 self.expect(TokenKind::RBracket)?;
-                Ok(Expr::ListLiteral(elements))
+                Ok(ExprKind::ListLiteral(elements))
+            }
+
+            TokenKind::Pipe | TokenKind::OrOr => {
+                // `||` opens a closure without parameters.
+                let mut params = Vec::new();
+                if tok.kind == TokenKind::Pipe && self.peek().kind != TokenKind::Pipe {
+                    loop {
+                        let span = self.peek_span();
+                        let name = self.expect_ident()?;
+                        let ty = if self.peek().kind == TokenKind::Colon {
+                            self.bump();
+                            Some(self.parse_type()?)
+                        } else {
+                            None
+                        };
+                        params.push(ClosureParam { name, ty, span });
+                        if self.peek().kind == TokenKind::Comma {
+                            self.bump();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                if tok.kind == TokenKind::Pipe {
+                    self.expect(TokenKind::Pipe)?;
+                }
+                let body = self.parse_expr()?;
+                Ok(ExprKind::Closure {
+                    params,
+                    body: Box::new(body),
+                })
             }
 
             TokenKind::Match => {
@@ -867,10 +1783,16 @@ impl<'a> Parser<'a> {
 
                 let mut arms = Vec::new();
                 while self.peek().kind != TokenKind::RBrace {
+                    let pattern_start = self.peek().span.start;
                     let pattern = self.parse_pattern()?;
+                    let span = self.span_from(pattern_start);
                     self.expect(TokenKind::FatArrow)?;
                     let body = self.parse_expr()?;
-                    arms.push(MatchArm { pattern, body });
+                    arms.push(MatchArm {
+                        pattern,
+                        body,
+                        span,
+                    });
 
                     if self.peek().kind == TokenKind::Comma {
                         self.bump();
@@ -879,13 +1801,19 @@ impl<'a> Parser<'a> {
                     }
                 }
                 self.expect(TokenKind::RBrace)?;
-                Ok(Expr::Match {
+                Ok(ExprKind::Match {
                     subject: Box::new(subject),
                     arms,
                 })
             }
 
-            _ => Err(ParseError::ExpectedExpression { span: tok.span }),
+            _ => {
+                // Leave the token for error recovery, e.g. the `;` of `let x = ;`.
+                if tok.kind != TokenKind::Eof {
+                    self.pos -= 1;
+                }
+                Err(ParseError::ExpectedExpression { span: tok.span })
+            }
         }
     }
 
@@ -897,8 +1825,9 @@ impl<'a> Parser<'a> {
             let mut vars = Vec::new();
             if self.peek().kind != TokenKind::RParen {
                 loop {
-                    let var = self.expect_ident()?;
-                    vars.push(var);
+                    let span = self.peek_span();
+                    let name = self.expect_ident()?;
+                    vars.push(PatternBinding { name, span });
                     if self.peek().kind == TokenKind::Comma {
                         self.bump();
                     } else {
@@ -913,8 +1842,9 @@ impl<'a> Parser<'a> {
             let mut vars = Vec::new();
             if self.peek().kind != TokenKind::RBrace {
                 loop {
-                    let var = self.expect_ident()?;
-                    vars.push(var);
+                    let span = self.peek_span();
+                    let name = self.expect_ident()?;
+                    vars.push(PatternBinding { name, span });
                     if self.peek().kind == TokenKind::Comma {
                         self.bump();
                     } else {
@@ -932,7 +1862,7 @@ impl<'a> Parser<'a> {
     }
 
     fn looks_like_struct_init(&self) -> bool {
-        if self.peek().kind != TokenKind::LBrace {
+        if self.no_struct_literal || self.peek().kind != TokenKind::LBrace {
             return false;
         }
 
@@ -948,33 +1878,17 @@ impl<'a> Parser<'a> {
             return true;
         }
 
-        // Struct literals require at least one `field: value` entry.
-        let first = self.tokens.get(i);
-        if !matches!(first, Some(tok) if matches!(tok.kind, TokenKind::Ident(_))) {
-            return false;
-        }
-
-        i += 1;
-        while i < len {
-            if self.tokens.get(i).is_none() {
-                return false;
-            }
-            let kind = self.tokens.get(i).unwrap().kind.clone();
-            if kind == TokenKind::Colon {
-                return true;
-            }
-
-            if kind == TokenKind::Comma || kind == TokenKind::RBrace
-            {
-                return false;
-            }
-            i += 1;
-        }
-
-        false
+        // Struct literals require at least one `field: value` entry; checking the colon right
+        // after the name keeps annotated closure parameters in match arms (`A => |x: Int| x`) apart.
+        matches!(self.tokens.get(i), Some(tok) if matches!(tok.kind, TokenKind::Ident(_)))
+            && self
+                .tokens
+                .get(i + 1)
+                .is_some_and(|tok| tok.kind == TokenKind::Colon)
     }
 
-    fn parse_type(&mut self) -> Result<Type, ParseError> {
+    /// Parses a type expression (`Int`, `List<T>`, `(A, B)`, `&T`, `fn(A) -> B`, names).
+    pub fn parse_type(&mut self) -> Result<Type, ParseError> {
         match self.peek().kind.clone() {
             TokenKind::Fn => {
                 self.bump();
@@ -1017,6 +1931,12 @@ impl<'a> Parser<'a> {
                 self.bump();
                 Ok(Type::Ref(Box::new(self.parse_type()?)))
             }
+            TokenKind::AndAnd => {
+                // `&&T` lexes as one token, as in `parse_unary`.
+                self.bump();
+                let inner = self.parse_type()?;
+                Ok(Type::Ref(Box::new(Type::Ref(Box::new(inner)))))
+            }
             TokenKind::LParen => {
                 self.bump();
                 let mut elements = vec![self.parse_type()?];
@@ -1040,17 +1960,29 @@ impl<'a> Parser<'a> {
             }
             TokenKind::Ident(name) => {
                 let _ = self.bump();
-                if name == "List" {
-                    if self.peek().kind == TokenKind::Less {
-                        self.bump();
-                        let inner = self.parse_type()?;
-                        self.expect(TokenKind::Greater)?;
-                        Ok(Type::List(Box::new(inner)))
-                    } else {
-                        Ok(Type::Named(name))
-                    }
+                if name == "Self"
+                    && let Some(self_type) = &self.self_type
+                {
+                    return Ok(self_type.clone());
+                }
+                if self.peek().kind != TokenKind::Less {
+                    return Ok(match name.as_str() {
+                        "Int32" => Type::Int32,
+                        "UInt8" | "Byte" => Type::UInt8,
+                        _ => Type::Named(name),
+                    });
+                }
+                self.bump();
+                let mut args = vec![self.parse_type()?];
+                while self.peek().kind == TokenKind::Comma {
+                    self.bump();
+                    args.push(self.parse_type()?);
+                }
+                self.expect(TokenKind::Greater)?;
+                if name == "List" && args.len() == 1 {
+                    Ok(Type::List(Box::new(args.remove(0))))
                 } else {
-                    Ok(Type::Named(name))
+                    Ok(Type::Generic(name, args))
                 }
             }
             _ => Err(ParseError::UnexpectedToken {
@@ -1086,30 +2018,40 @@ impl Display for ParseError {
     }
 }
 
-/// Prints a human-readable parser error with a source snippet.
-pub fn report_parse_error(src: &str, error: &ParseError) {
-    match error {
-        ParseError::UnexpectedToken { expected, found } => {
-            let snippet = render_snippet(src, &found.span);
-            eprintln!(
-                "ParseError [E-PS01] at line {}, column {}: expected {}, found {}\nhelp: check punctuation/order near this token\n{}\n{}",
-                snippet.line,
-                snippet.column,
-                expected,
-                found.kind.name(),
-                snippet.source_line,
-                snippet.marker_line
-            );
-        }
-        ParseError::ExpectedExpression { span } => {
-            let snippet = render_snippet(src, span);
-            eprintln!(
-                "ParseError [E-PS02] at line {}, column {}: expected expression\nhelp: insert a literal, variable, call, or parenthesized expression\n{}\n{}",
-                snippet.line,
-                snippet.column,
-                snippet.source_line,
-                snippet.marker_line
-            );
+impl ParseError {
+    /// Span of the offending token.
+    pub fn span(&self) -> &Span {
+        match self {
+            ParseError::UnexpectedToken { found, .. } => &found.span,
+            ParseError::ExpectedExpression { span } => span,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedToken { .. } => "E-PS01",
+            ParseError::ExpectedExpression { .. } => "E-PS02",
         }
     }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            ParseError::UnexpectedToken { expected, found } => Diagnostic::error(
+                self.code(),
+                format!("expected {}, found {}", expected, found.kind.name()),
+            )
+            .with_span(Some(found.span.clone()))
+            .with_note("check punctuation/order near this token"),
+            ParseError::ExpectedExpression { span } => {
+                Diagnostic::error(self.code(), "expected expression")
+                    .with_span(Some(span.clone()))
+                    .with_note("insert a literal, variable, call, or parenthesized expression")
+            }
+        }
+    }
+}
+
+/// Prints a human-readable parser error with a source snippet.
+pub fn report_parse_error(src: &str, error: &ParseError) {
+    eprintln!("{}", error.to_diagnostic().render(Some(src)));
 }
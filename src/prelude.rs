@@ -0,0 +1,62 @@
+//! Types that every program can use without declaring them.
+//!
+//! The prelude enums are registered by the type checker for every program. Built-ins such as
+//! `List::get`, host functions and `?` produce them, so a program may not declare a struct or
+//! enum with the same name.
+
+use crate::ast::{EnumDecl, EnumVariant, Type};
+use crate::token::Span;
+
+pub const OPTION: &str = "Option";
+pub const RESULT: &str = "Result";
+
+/// `enum Option<T> { Some(T), None }`
+pub fn option_enum() -> EnumDecl {
+    EnumDecl {
+        name: OPTION.to_string(),
+        type_params: vec!["T".to_string()],
+        variants: vec![
+            EnumVariant::Tuple("Some".to_string(), Type::Named("T".to_string())),
+            EnumVariant::Unit("None".to_string()),
+        ],
+        span: Span::default(),
+    }
+}
+
+/// `enum Result<T, E> { Ok(T), Err(E) }`
+pub fn result_enum() -> EnumDecl {
+    EnumDecl {
+        name: RESULT.to_string(),
+        type_params: vec!["T".to_string(), "E".to_string()],
+        variants: vec![
+            EnumVariant::Tuple("Ok".to_string(), Type::Named("T".to_string())),
+            EnumVariant::Tuple("Err".to_string(), Type::Named("E".to_string())),
+        ],
+        span: Span::default(),
+    }
+}
+
+pub fn prelude_enums() -> Vec<EnumDecl> {
+    vec![option_enum(), result_enum()]
+}
+
+/// Whether `name` is one of the prelude types `Option` and `Result`.
+pub fn is_prelude_type(name: &str) -> bool {
+    name == OPTION || name == RESULT
+}
+
+/// `Option<T>` for `ty`, the result type of `List::get`.
+pub fn option_of(ty: Type) -> Type {
+    Type::Generic(OPTION.to_string(), vec![ty])
+}
+
+/// Success payload of a `?` operand: `T` for `Option<T>` and `Result<T, E>`.
+///
+/// Success variants come first in both enums, so a non-zero variant tag means "propagate".
+pub fn try_payload_type(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Generic(name, args) if name == OPTION && args.len() == 1 => args.first(),
+        Type::Generic(name, args) if name == RESULT && args.len() == 2 => args.first(),
+        _ => None,
+    }
+}
@@ -1,12 +1,25 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::host::default_host_functions;
-use crate::runtime::{decode_string_literal_import_name, string_eq_import_name};
+use crate::ast::Type;
+use crate::host::ast_type_to_abi_type;
+use crate::lexer::lex_file;
+use crate::parser::Parser;
+use crate::runtime::{
+    AGGREGATE_LEN_IMPORT, ENUM_NEW_IMPORT, ENUM_TAG_IMPORT, LIST_NEW_IMPORT, MEMORY_EXPORT,
+    OVERFLOW_IMPORT, RuntimeLane, STRUCT_NEW_IMPORT, TUPLE_NEW_IMPORT, decode_aggregate_get_import_name,
+    decode_aggregate_pop_import_name, decode_aggregate_push_import_name, decode_aggregate_set_import_name, decode_box_import_name,
+    decode_string_literal_import_name, decode_to_string_import_name, decode_unbox_import_name,
+    string_cmp_import_name, string_concat_import_name, string_eq_import_name,
+};
 use eres_abi::{
-    AbiType, HostFunction, RuntimeHeap, RuntimeValue, abi_type_to_val_type, host_value_to_val,
-    val_to_host_value,
+    AbiType, EnumPayload, HostFunction, NamedTypeKind, NamedTypeRef, RuntimeHeap, RuntimeValue,
+    RuntimeValueHandle, abi_type_to_val_type, host_value_to_val, val_to_host_value,
+};
+use wasmtime::{
+    Caller, Engine, Extern, ExternType, Func, FuncType, Instance, Store, Trap, Val, ValType,
 };
-use wasmtime::{Caller, Engine, ExternType, Func, FuncType, Instance, Store, Val};
 
 /// Run wasm bytes calling `main` with the provided i64 arguments.
 /// Returns Ok(Some(i64)) if the function returns a single i64, Ok(None) if
@@ -20,6 +33,45 @@ pub fn run_wasm_bytes_with_hosts(
     args: Vec<i64>,
     hosts: &[HostFunction],
 ) -> Result<Option<i64>, String> {
+    let (_, result) = call_main(bytes, args, hosts)?;
+    match result {
+        Some(Val::I64(v)) => Ok(Some(v)),
+        Some(Val::I32(v)) => Ok(Some(v as i64)),
+        Some(_) => Err("unexpected return value type".to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Runs `main` and renders its result according to the declared return type,
+/// resolving heap handles (strings, lists, tuples, structs, enums) to readable values.
+pub fn run_wasm_bytes_rendered(
+    bytes: &[u8],
+    args: Vec<i64>,
+    return_type: &Type,
+) -> Result<Option<String>, String> {
+    let (store, result) = call_main(bytes, args, &default_host_functions())?;
+    let Some(result) = result else {
+        return Ok(None);
+    };
+    let rendered = match (return_type, result) {
+        (Type::Int, Val::I64(v)) => v.to_string(),
+        (Type::Float, Val::F64(bits)) => format!("{:?}", f64::from_bits(bits)),
+        (Type::Bool, Val::I32(v)) => (v != 0).to_string(),
+        (Type::Int32 | Type::UInt8, Val::I32(v)) => v.to_string(),
+        (_, Val::I32(handle)) => store
+            .data()
+            .resolve(RuntimeValueHandle(handle as u32))
+            .map(|value| render_runtime_value(&value))?,
+        (_, other) => format!("{:?}", other),
+    };
+    Ok(Some(rendered))
+}
+
+fn call_main(
+    bytes: &[u8],
+    args: Vec<i64>,
+    hosts: &[HostFunction],
+) -> Result<(Store<RuntimeHeap>, Option<Val>), String> {
     let engine = Engine::default();
     let module = wasmtime::Module::from_binary(&engine, bytes)
         .map_err(|e| format!("module compile error: {}", e))?;
@@ -43,17 +95,80 @@ pub fn run_wasm_bytes_with_hosts(
 
     let mut results_buf: Vec<Val> = vec![Val::I64(0); results];
 
+    // Report the trap cause (e.g. host/runtime message) instead of the wasm backtrace wrapper.
     func.call(&mut store, &params, &mut results_buf)
-        .map_err(|e| format!("runtime error: {}", e))?;
+        .map_err(|e| format!("runtime error: {}", describe_trap(&e)))?;
+
+    Ok((store, results_buf.into_iter().next()))
+}
+
+/// Raised by the overflow import when checked integer arithmetic overflowed.
+#[derive(Debug)]
+struct IntegerOverflow {
+    function: String,
+}
+
+impl std::fmt::Display for IntegerOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "integer overflow in function `{}`", self.function)
+    }
+}
+
+impl std::error::Error for IntegerOverflow {}
+
+/// Maps wasm arithmetic traps to language-level messages; other errors keep their root cause.
+fn describe_trap(error: &wasmtime::Error) -> String {
+    if let Some(overflow) = error.downcast_ref::<IntegerOverflow>() {
+        return overflow.to_string();
+    }
+    match error.downcast_ref::<Trap>() {
+        Some(Trap::IntegerDivisionByZero) => "division or remainder by zero".to_string(),
+        Some(Trap::IntegerOverflow) => "integer overflow in division".to_string(),
+        _ => error.root_cause().to_string(),
+    }
+}
+
+/// Formats a heap value in source-like syntax for CLI output.
+pub fn render_runtime_value(value: &RuntimeValue) -> String {
+    fn join(values: &[RuntimeValue]) -> String {
+        values
+            .iter()
+            .map(render_runtime_value)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+    fn fields(fields: &[(String, RuntimeValue)]) -> String {
+        fields
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, render_runtime_value(value)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 
-    if results == 1 {
-        match results_buf[0] {
-            Val::I64(v) => Ok(Some(v)),
-            Val::I32(v) => Ok(Some(v as i64)),
-            _ => Err("unexpected return value type".to_string()),
+    match value {
+        RuntimeValue::Int(v) => v.to_string(),
+        RuntimeValue::Float(v) => format!("{:?}", v),
+        RuntimeValue::Bool(v) => v.to_string(),
+        RuntimeValue::String(v) => format!("{:?}", v),
+        RuntimeValue::List { elements, .. } => format!("[{}]", join(elements)),
+        RuntimeValue::Tuple { elements, .. } => format!("({})", join(elements)),
+        RuntimeValue::Struct { named, fields: values } => {
+            format!("{} {{ {} }}", named.name, fields(values))
         }
-    } else {
-        Ok(None)
+        RuntimeValue::Enum {
+            named,
+            variant,
+            payload,
+        } => match payload {
+            EnumPayload::Unit => format!("{}::{}", named.name, variant),
+            EnumPayload::Tuple(inner) => {
+                format!("{}::{}({})", named.name, variant, render_runtime_value(inner))
+            }
+            EnumPayload::Struct(values) => {
+                format!("{}::{} {{ {} }}", named.name, variant, fields(values))
+            }
+        },
+        RuntimeValue::Handle { handle, .. } => format!("<handle {}>", handle.0),
     }
 }
 
@@ -68,6 +183,7 @@ fn instantiate_host_imports(
         .cloned()
         .map(|host| (host.name, host))
         .collect::<HashMap<_, _>>();
+    let named_types = Arc::new(named_types_from_hosts(hosts));
     let mut funcs = Vec::new();
 
     for import in module.imports() {
@@ -85,8 +201,40 @@ fn instantiate_host_imports(
 
         let func = if import.name() == string_eq_import_name() {
             instantiate_string_eq_import(store, engine)
+        } else if import.name() == string_concat_import_name() {
+            instantiate_string_concat_import(store, engine)
+        } else if import.name() == string_cmp_import_name() {
+            instantiate_string_cmp_import(store, engine)
+        } else if let Some(lane) = decode_to_string_import_name(import.name()) {
+            instantiate_to_string_import(store, engine, lane)
         } else if let Some(value) = decode_string_literal_import_name(import.name()) {
             instantiate_const_string_import(store, engine, value)
+        } else if import.name() == STRUCT_NEW_IMPORT {
+            instantiate_struct_new_import(store, engine, named_types.clone())
+        } else if import.name() == ENUM_NEW_IMPORT {
+            instantiate_enum_new_import(store, engine, named_types.clone())
+        } else if import.name() == ENUM_TAG_IMPORT {
+            instantiate_enum_tag_import(store, engine)
+        } else if import.name() == LIST_NEW_IMPORT {
+            instantiate_list_new_import(store, engine, named_types.clone())
+        } else if import.name() == TUPLE_NEW_IMPORT {
+            instantiate_tuple_new_import(store, engine)
+        } else if import.name() == AGGREGATE_LEN_IMPORT {
+            instantiate_aggregate_len_import(store, engine)
+        } else if import.name() == OVERFLOW_IMPORT {
+            instantiate_overflow_import(store, engine)
+        } else if let Some(lane) = decode_aggregate_set_import_name(import.name()) {
+            instantiate_aggregate_set_import(store, engine, lane)
+        } else if let Some(lane) = decode_box_import_name(import.name()) {
+            instantiate_box_import(store, engine, lane)
+        } else if let Some(lane) = decode_unbox_import_name(import.name()) {
+            instantiate_unbox_import(store, engine, lane)
+        } else if let Some(lane) = decode_aggregate_pop_import_name(import.name()) {
+            instantiate_aggregate_pop_import(store, engine, lane)
+        } else if let Some(lane) = decode_aggregate_push_import_name(import.name()) {
+            instantiate_aggregate_push_import(store, engine, lane)
+        } else if let Some(lane) = decode_aggregate_get_import_name(import.name()) {
+            instantiate_aggregate_get_import(store, engine, lane)
         } else {
             let host = host_map
                 .get(import.name())
@@ -210,12 +358,547 @@ fn instantiate_string_eq_import(
     ))
 }
 
+/// Reads the two `String` operands of a string import.
+fn string_operands(
+    heap: &RuntimeHeap,
+    params: &[Val],
+    what: &str,
+) -> Result<(String, String), wasmtime::Error> {
+    let left = heap.get(param_handle(params, 0)?).map_err(wasmtime::Error::msg)?;
+    let right = heap.get(param_handle(params, 1)?).map_err(wasmtime::Error::msg)?;
+    match (left, right) {
+        (RuntimeValue::String(a), RuntimeValue::String(b)) => Ok((a.clone(), b.clone())),
+        other => Err(wasmtime::Error::msg(format!(
+            "{} expects String handles, found {:?}",
+            what, other
+        ))),
+    }
+}
+
+fn instantiate_string_concat_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [ValType::I32, ValType::I32], [ValType::I32]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, results| {
+            let heap = caller.data_mut();
+            let (left, right) = string_operands(heap, params, "string concatenation")?;
+            let handle = heap.allocate(RuntimeValue::String(left + &right));
+            results[0] = Val::I32(handle.0 as i32);
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_string_cmp_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [ValType::I32, ValType::I32], [ValType::I32]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |caller: Caller<'_, RuntimeHeap>, params, results| {
+            let (left, right) = string_operands(caller.data(), params, "string comparison")?;
+            results[0] = Val::I32(left.cmp(&right) as i32);
+            Ok(())
+        },
+    ))
+}
+
+/// Renders a value for interpolation: strings are inserted as they are, everything else
+/// like the CLI prints results.
+fn instantiate_to_string_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+    lane: RuntimeLane,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [lane_val_type(lane)], [ValType::I32]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, results| {
+            let heap = caller.data_mut();
+            let value = match (lane, &params[0]) {
+                (RuntimeLane::Handle, Val::I32(handle)) => heap
+                    .resolve(RuntimeValueHandle(*handle as u32))
+                    .map_err(wasmtime::Error::msg)?,
+                (lane, value) => lane_val_to_runtime(heap, lane, value)?,
+            };
+            let rendered = match value {
+                RuntimeValue::String(value) => value,
+                value => render_runtime_value(&value),
+            };
+            results[0] = Val::I32(heap.allocate(RuntimeValue::String(rendered)).0 as i32);
+            Ok(())
+        },
+    ))
+}
+
+/// Maps language type names to the host's named types so values built in wasm
+/// decode into the matching Rust types.
+fn named_types_from_hosts(hosts: &[HostFunction]) -> HashMap<String, NamedTypeRef> {
+    hosts
+        .iter()
+        .flat_map(|host| host.descriptors.iter())
+        .map(|descriptor| (descriptor.named.name.clone(), descriptor.named.clone()))
+        .collect()
+}
+
+fn resolve_named_type(
+    named_types: &HashMap<String, NamedTypeRef>,
+    name: &str,
+    kind: NamedTypeKind,
+) -> NamedTypeRef {
+    named_types
+        .get(name)
+        .cloned()
+        .unwrap_or_else(|| NamedTypeRef::new(name, name, kind))
+}
+
+/// Parses an element type written in source syntax (as emitted by codegen) into its ABI form.
+fn parse_runtime_type(
+    src: &str,
+    named_types: &HashMap<String, NamedTypeRef>,
+) -> Result<AbiType, wasmtime::Error> {
+    let tokens = lex_file(src).map_err(|e| wasmtime::Error::msg(e.to_string()))?;
+    let ty = Parser::new(&tokens)
+        .parse_type()
+        .map_err(|e| wasmtime::Error::msg(e.to_string()))?;
+    let resolve = |name: &str| resolve_named_type(named_types, name, NamedTypeKind::Struct);
+    ast_type_to_abi_type(&ty, &resolve)
+        .ok_or_else(|| wasmtime::Error::msg(format!("type `{}` cannot be stored in a list", src)))
+}
+
+fn lane_val_type(lane: RuntimeLane) -> ValType {
+    match lane {
+        RuntimeLane::Int => ValType::I64,
+        RuntimeLane::Float => ValType::F64,
+        RuntimeLane::Bool | RuntimeLane::Int32 | RuntimeLane::Handle => ValType::I32,
+    }
+}
+
+fn param_i32(params: &[Val], index: usize, what: &str) -> Result<i32, wasmtime::Error> {
+    match params.get(index) {
+        Some(Val::I32(value)) => Ok(*value),
+        other => Err(wasmtime::Error::msg(format!(
+            "invalid {}: {:?}",
+            what, other
+        ))),
+    }
+}
+
+fn param_handle(params: &[Val], index: usize) -> Result<RuntimeValueHandle, wasmtime::Error> {
+    param_i32(params, index, "runtime handle").map(|value| RuntimeValueHandle(value as u32))
+}
+
+/// Reads a static string (type or field name) from the module's data segment.
+fn read_static_str(
+    caller: &mut Caller<'_, RuntimeHeap>,
+    ptr: i32,
+    len: i32,
+) -> Result<String, wasmtime::Error> {
+    let Some(Extern::Memory(memory)) = caller.get_export(MEMORY_EXPORT) else {
+        return Err(wasmtime::Error::msg("module does not export its memory"));
+    };
+    let start = ptr as u32 as usize;
+    let end = start + len as u32 as usize;
+    let bytes = memory
+        .data(&*caller)
+        .get(start..end)
+        .ok_or_else(|| wasmtime::Error::msg("static string out of bounds"))?;
+    String::from_utf8(bytes.to_vec()).map_err(wasmtime::Error::msg)
+}
+
+/// Converts a lane value into a heap value; handles are shared, not copied.
+fn lane_val_to_runtime(
+    heap: &RuntimeHeap,
+    lane: RuntimeLane,
+    value: &Val,
+) -> Result<RuntimeValue, wasmtime::Error> {
+    match (lane, value) {
+        (RuntimeLane::Int, Val::I64(value)) => Ok(RuntimeValue::Int(*value)),
+        (RuntimeLane::Float, Val::F64(bits)) => Ok(RuntimeValue::Float(f64::from_bits(*bits))),
+        (RuntimeLane::Bool, Val::I32(value)) => Ok(RuntimeValue::Bool(*value != 0)),
+        (RuntimeLane::Int32, Val::I32(value)) => Ok(RuntimeValue::Int(*value as i64)),
+        (RuntimeLane::Handle, Val::I32(handle)) => heap
+            .share(RuntimeValueHandle(*handle as u32))
+            .map_err(wasmtime::Error::msg),
+        (lane, other) => Err(wasmtime::Error::msg(format!(
+            "invalid {:?} lane value: {:?}",
+            lane, other
+        ))),
+    }
+}
+
+fn runtime_to_lane_val(
+    heap: &mut RuntimeHeap,
+    lane: RuntimeLane,
+    value: RuntimeValue,
+) -> Result<Val, wasmtime::Error> {
+    match (lane, value) {
+        (RuntimeLane::Int, RuntimeValue::Int(value)) => Ok(Val::I64(value)),
+        (RuntimeLane::Float, RuntimeValue::Float(value)) => Ok(Val::F64(value.to_bits())),
+        (RuntimeLane::Bool, RuntimeValue::Bool(value)) => Ok(Val::I32(value as i32)),
+        (RuntimeLane::Int32, RuntimeValue::Int(value)) => Ok(Val::I32(value as i32)),
+        (RuntimeLane::Handle, value) => Ok(Val::I32(heap.allocate(value).0 as i32)),
+        (lane, other) => Err(wasmtime::Error::msg(format!(
+            "runtime value {:?} does not fit the {:?} lane",
+            other, lane
+        ))),
+    }
+}
+
+fn instantiate_overflow_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [ValType::I32, ValType::I32], []);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, _results| {
+            let function = read_static_str(
+                &mut caller,
+                param_i32(params, 0, "function name pointer")?,
+                param_i32(params, 1, "function name length")?,
+            )?;
+            Err(wasmtime::Error::new(IntegerOverflow { function }))
+        },
+    ))
+}
+
+fn instantiate_struct_new_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+    named_types: Arc<HashMap<String, NamedTypeRef>>,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [ValType::I32, ValType::I32], [ValType::I32]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, results| {
+            let name = read_static_str(
+                &mut caller,
+                param_i32(params, 0, "struct name pointer")?,
+                param_i32(params, 1, "struct name length")?,
+            )?;
+            let named = resolve_named_type(&named_types, &name, NamedTypeKind::Struct);
+            let handle = caller.data_mut().allocate(RuntimeValue::Struct {
+                named,
+                fields: Vec::new(),
+            });
+            results[0] = Val::I32(handle.0 as i32);
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_enum_new_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+    named_types: Arc<HashMap<String, NamedTypeRef>>,
+) -> Result<Func, String> {
+    let ty = FuncType::new(
+        engine,
+        [ValType::I32, ValType::I32, ValType::I32, ValType::I32],
+        [ValType::I32],
+    );
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, results| {
+            let name = read_static_str(
+                &mut caller,
+                param_i32(params, 0, "enum name pointer")?,
+                param_i32(params, 1, "enum name length")?,
+            )?;
+            let variant = read_static_str(
+                &mut caller,
+                param_i32(params, 2, "variant name pointer")?,
+                param_i32(params, 3, "variant name length")?,
+            )?;
+            let named = resolve_named_type(&named_types, &name, NamedTypeKind::Enum);
+            let handle = caller.data_mut().allocate(RuntimeValue::Enum {
+                named,
+                variant,
+                payload: EnumPayload::Unit,
+            });
+            results[0] = Val::I32(handle.0 as i32);
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_enum_tag_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [ValType::I32, ValType::I32, ValType::I32], [ValType::I32]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, results| {
+            let target = param_handle(params, 0)?;
+            let variants = read_static_str(
+                &mut caller,
+                param_i32(params, 1, "variant list pointer")?,
+                param_i32(params, 2, "variant list length")?,
+            )?;
+            let variant = match caller.data().get(target).map_err(wasmtime::Error::msg)? {
+                RuntimeValue::Enum { variant, .. } => variant,
+                other => {
+                    return Err(wasmtime::Error::msg(format!(
+                        "match expects an enum value, found {:?}",
+                        other
+                    )));
+                }
+            };
+            let tag = variants
+                .split(',')
+                .position(|candidate| candidate == variant)
+                .ok_or_else(|| {
+                    wasmtime::Error::msg(format!("unknown enum variant `{}` in match", variant))
+                })?;
+            results[0] = Val::I32(tag as i32);
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_list_new_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+    named_types: Arc<HashMap<String, NamedTypeRef>>,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [ValType::I32, ValType::I32], [ValType::I32]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, results| {
+            let element_type = read_static_str(
+                &mut caller,
+                param_i32(params, 0, "element type pointer")?,
+                param_i32(params, 1, "element type length")?,
+            )?;
+            let element_type = parse_runtime_type(&element_type, &named_types)?;
+            let handle = caller.data_mut().allocate(RuntimeValue::List {
+                element_type,
+                elements: Vec::new(),
+            });
+            results[0] = Val::I32(handle.0 as i32);
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_box_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+    lane: RuntimeLane,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [lane_val_type(lane)], [ValType::I32]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, results| {
+            let heap = caller.data_mut();
+            let value = lane_val_to_runtime(heap, lane, &params[0])?;
+            results[0] = Val::I32(heap.allocate(value).0 as i32);
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_unbox_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+    lane: RuntimeLane,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [ValType::I32], [lane_val_type(lane)]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, results| {
+            let target = param_handle(params, 0)?;
+            let heap = caller.data_mut();
+            let value = heap.get(target).cloned().map_err(wasmtime::Error::msg)?;
+            results[0] = runtime_to_lane_val(heap, lane, value)?;
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_tuple_new_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, Vec::<ValType>::new(), [ValType::I32]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, _params, results| {
+            let handle = caller.data_mut().allocate(RuntimeValue::Tuple {
+                element_types: Vec::new(),
+                elements: Vec::new(),
+            });
+            results[0] = Val::I32(handle.0 as i32);
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_aggregate_len_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [ValType::I32], [ValType::I64]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |caller: Caller<'_, RuntimeHeap>, params, results| {
+            let target = param_handle(params, 0)?;
+            let len = caller
+                .data()
+                .get(target)
+                .and_then(RuntimeValue::element_count)
+                .map_err(wasmtime::Error::msg)?;
+            results[0] = Val::I64(len as i64);
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_aggregate_pop_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+    lane: RuntimeLane,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [ValType::I32], [lane_val_type(lane)]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, results| {
+            let target = param_handle(params, 0)?;
+            let heap = caller.data_mut();
+            let value = heap
+                .get_mut(target)
+                .and_then(RuntimeValue::pop_element)
+                .map_err(wasmtime::Error::msg)?;
+            results[0] = runtime_to_lane_val(heap, lane, value)?;
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_aggregate_push_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+    lane: RuntimeLane,
+) -> Result<Func, String> {
+    let ty = FuncType::new(
+        engine,
+        [ValType::I32, ValType::I32, ValType::I32, lane_val_type(lane)],
+        [ValType::I32],
+    );
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, results| {
+            let target = param_handle(params, 0)?;
+            let key_len = param_i32(params, 2, "key length")?;
+            let key = if key_len == 0 {
+                None
+            } else {
+                Some(read_static_str(
+                    &mut caller,
+                    param_i32(params, 1, "key pointer")?,
+                    key_len,
+                )?)
+            };
+            let heap = caller.data_mut();
+            let value = lane_val_to_runtime(heap, lane, &params[3])?;
+            heap.get_mut(target)
+                .and_then(|aggregate| aggregate.push_element(key, value))
+                .map_err(wasmtime::Error::msg)?;
+            results[0] = params[0];
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_aggregate_get_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+    lane: RuntimeLane,
+) -> Result<Func, String> {
+    let ty = FuncType::new(engine, [ValType::I32, ValType::I64], [lane_val_type(lane)]);
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, results| {
+            let target = param_handle(params, 0)?;
+            let index = param_index(params, 1)?;
+            let heap = caller.data_mut();
+            let value = usize::try_from(index)
+                .map_err(|_| format!("index {} out of bounds", index))
+                .and_then(|index| heap.get(target)?.element(index).cloned())
+                .map_err(wasmtime::Error::msg)?;
+            results[0] = runtime_to_lane_val(heap, lane, value)?;
+            Ok(())
+        },
+    ))
+}
+
+fn instantiate_aggregate_set_import(
+    store: &mut Store<RuntimeHeap>,
+    engine: &Engine,
+    lane: RuntimeLane,
+) -> Result<Func, String> {
+    let ty = FuncType::new(
+        engine,
+        [ValType::I32, ValType::I64, lane_val_type(lane)],
+        [],
+    );
+    Ok(Func::new(
+        &mut *store,
+        ty,
+        move |mut caller: Caller<'_, RuntimeHeap>, params, _results| {
+            let target = param_handle(params, 0)?;
+            let index = param_index(params, 1)?;
+            let heap = caller.data_mut();
+            let value = lane_val_to_runtime(heap, lane, &params[2])?;
+            usize::try_from(index)
+                .map_err(|_| format!("index {} out of bounds", index))
+                .and_then(|index| {
+                    *heap.get_mut(target)?.element_mut(index)? = value;
+                    Ok(())
+                })
+                .map_err(wasmtime::Error::msg)?;
+            Ok(())
+        },
+    ))
+}
+
+fn param_index(params: &[Val], index: usize) -> Result<i64, wasmtime::Error> {
+    match params.get(index) {
+        Some(Val::I64(value)) => Ok(*value),
+        other => Err(wasmtime::Error::msg(format!(
+            "invalid element index: {:?}",
+            other
+        ))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::compiler::{compile_source, compile_source_with_hosts};
+    use crate::compiler::{CompileOptions, compile_source, compile_source_with_hosts};
     use crate::host::default_host_functions;
-    use eres_abi::{AbiType, EresAbi, HostFunction, HostValue, eres_host_function};
+    use eres_abi::{AbiType, EresAbi, HostFunction, HostTrap, HostValue, eres_host_function};
     use std::fs;
     use std::sync::{Arc, Mutex, OnceLock};
 
@@ -337,6 +1020,24 @@ mod tests {
             ("examples/run_arith.eres", vec![2, 4, 6], Some(12)),
             ("examples/run_float_cond.eres", vec![], Some(1)),
             ("examples/run_strings.eres", vec![], Some(6)),
+            ("examples/run_structs.eres", vec![], Some(50)),
+            ("examples/check_match.eres", vec![], Some(4)),
+            ("examples/check_strings.eres", vec![], Some(1)),
+            ("examples/check_refs_enums.eres", vec![5], Some(10)),
+            ("examples/check_refs_enums.eres", vec![-3], Some(-9)),
+            ("examples/run_refs.eres", vec![], Some(85)),
+            ("examples/run_assign.eres", vec![], Some(35)),
+            ("examples/run_loops.eres", vec![], Some(36)),
+            ("examples/run_generics.eres", vec![], Some(14)),
+            ("examples/run_enums.eres", vec![], Some(23)),
+            ("examples/run_lists.eres", vec![], Some(24)),
+            ("examples/run_tuples.eres", vec![], Some(11)),
+            ("examples/run_option.eres", vec![], Some(62)),
+            ("examples/run_closures.eres", vec![], Some(27)),
+            ("examples/run_traits.eres", vec![], Some(46)),
+            ("examples/factorial.eres", vec![10], Some(3628800)),
+            ("examples/run_consts.eres", vec![], Some(156)),
+            ("examples/run_statics.eres", vec![], Some(2714)),
         ];
 
         for (path, args, expected) in cases {
@@ -379,12 +1080,7 @@ mod tests {
             .get_func(&mut store, "main")
             .expect("main not found");
         let params: Vec<Val> = Vec::new();
-        let result_count = func
-            .ty(&mut store)
-            .results()
-            .len()
-            .try_into()
-            .expect("result count conversion");
+        let result_count = func.ty(&mut store).results().len();
         let mut results_buf: Vec<Val> = vec![Val::I64(0); result_count];
 
         func.call(&mut store, &params, &mut results_buf)
@@ -421,7 +1117,7 @@ mod tests {
         hosts.push(eres_host_function!(count_words, name = "count_words", params = [Vec<String>], result = i64));
         hosts.push(eres_host_function!(make_pair, name = "make_pair", params = [], result = (i64, bool)));
         hosts.push(eres_host_function!(score_pair, name = "score_pair", params = [(i64, bool)], result = i64));
-        let bytes = compile_source_with_hosts(src, &hosts)
+        let bytes = compile_source_with_hosts(src, &hosts, CompileOptions::default())
             .map(|out| out.bytes)
             .expect("compile failed");
 
@@ -429,6 +1125,306 @@ mod tests {
         assert_eq!(result, Some(13));
     }
 
+    #[test]
+    fn eres_built_structs_decode_into_host_types() {
+        let src = r#"
+            fn main() -> Int {
+                let user = User { name: "Grace", active: true };
+                return count_user_name(user) + count_user_name(make_user());
+            }
+        "#;
+
+        let mut hosts = default_host_functions();
+        hosts.push(eres_host_function!(make_user, name = "make_user", params = [], result = User));
+        hosts.push(eres_host_function!(count_user_name, name = "count_user_name", params = [User], result = i64));
+        let bytes = compile_source_with_hosts(src, &hosts, CompileOptions::default())
+            .map(|out| out.bytes)
+            .expect("compile failed");
+
+        let result = run_wasm_bytes_with_hosts(&bytes, vec![], &hosts).expect("run failed");
+        assert_eq!(result, Some(8));
+    }
+
+    #[test]
+    fn host_enums_can_be_matched() {
+        let src = r#"
+            fn main() -> Int {
+                return match tag_user(make_user()) {
+                    Guest => 0,
+                    Named(name) => len(name),
+                    Active { code, label } => code + len(label),
+                };
+            }
+        "#;
+
+        let mut hosts = default_host_functions();
+        hosts.push(eres_host_function!(make_user, name = "make_user", params = [], result = User));
+        hosts.push(eres_host_function!(tag_user, name = "tag_user", params = [User], result = ResultTag));
+        let bytes = compile_source_with_hosts(src, &hosts, CompileOptions::default())
+            .map(|out| out.bytes)
+            .expect("compile failed");
+
+        let result = run_wasm_bytes_with_hosts(&bytes, vec![], &hosts).expect("run failed");
+        assert_eq!(result, Some(6));
+    }
+
+    #[test]
+    fn list_index_out_of_bounds_traps_with_message() {
+        let src = "fn main() -> Int { let xs = [1, 2, 3]; return xs[3]; }";
+        let err = run_source(src, vec![]).expect_err("expected bounds trap");
+        assert!(
+            err.contains("index 3 out of bounds for List<Int> of length 3"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn list_assignment_out_of_bounds_traps_with_message() {
+        let src = "fn main() -> Int { let xs = [1, 2]; xs[5] += 1; return 0; }";
+        let err = run_source(src, vec![]).expect_err("expected bounds trap");
+        assert!(
+            err.contains("index 5 out of bounds for List<Int> of length 2"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn remainder_and_unary_minus_follow_rust_semantics() {
+        let src = "fn main() -> Int { let x = -7; let f = -7.5 % 2.0; if f != -1.5 { return 0; } return x % 3 * 10 + -(-x / 2); }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(-13));
+    }
+
+    #[test]
+    fn division_by_zero_traps_with_message() {
+        for src in [
+            "fn main(d: Int) -> Int { return 10 / d; }",
+            "fn main(d: Int) -> Int { return 10 % d; }",
+        ] {
+            let err = run_source(src, vec![0]).expect_err("expected division trap");
+            assert_eq!(err, "runtime error: division or remainder by zero");
+        }
+    }
+
+    #[test]
+    fn overflow_checks_trap_with_the_function_name() {
+        let src = "fn fact(n: Int) -> Int { if n <= 1 { return 1; } return n * fact(n - 1); } fn small(b: UInt8) -> UInt8 { return b - 4; } fn main(n: Int) -> Int { return fact(n) + (small(3) as Int); }";
+        let checked = CompileOptions {
+            overflow_checks: true,
+        };
+        let hosts = default_host_functions();
+        let bytes = compile_source_with_hosts(src, &hosts, checked)
+            .map(|out| out.bytes)
+            .expect("compile failed");
+        let err = run_wasm_bytes(&bytes, vec![30]).expect_err("expected overflow trap");
+        assert_eq!(err, "runtime error: integer overflow in function `fact`");
+        let err = run_wasm_bytes(&bytes, vec![20]).expect_err("expected overflow trap");
+        assert_eq!(err, "runtime error: integer overflow in function `small`");
+
+        // Without the option the same arithmetic wraps silently.
+        let wrapped = run_source(src, vec![30]).expect("run failed");
+        assert_eq!(wrapped, Some(-8764578968847253504 + 255));
+    }
+
+    #[test]
+    fn small_integer_consts_use_their_declared_type() {
+        let src = "const B: UInt8 = 250; const I: Int32 = -5; static mut S: UInt8 = B; \
+                   fn main() -> Int { let b = B + 10; let n: Int32 = I * 2; S += 1; \
+                   return b as Int * 1000 + n as Int * 10 + S as Int; }";
+        // `B + 10` wraps like any other UInt8 addition.
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(4000 - 100 + 251));
+    }
+
+    #[test]
+    fn static_globals_keep_state_across_calls() {
+        let src = "static mut CALLS: Int = 0; static mut TOTAL: Float = 1.5; fn count(step: Int) -> Int { CALLS += step; TOTAL = TOTAL * 2.0; return CALLS; } fn main() -> Int { let CALLS = 100; count(1); count(2); return count(3) * 10 + (TOTAL as Int) + CALLS; }";
+        // CALLS = 6 after three calls, TOTAL = 12.0; the local `CALLS` shadows the global in `main`.
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(60 + 12 + 100));
+    }
+
+    #[test]
+    fn strings_concatenate_compare_and_interpolate() {
+        let src = "struct P { x: Int, y: Float } fn main() -> String { let s = \"a\" + \"b\"; s += \"c\"; let p = P { x: 1, y: 0.5 }; let ordered = \"apple\" < \"banana\" && \"b\" >= \"b\" && !(\"ab\" <= \"a\"); return \"{s}: {ordered} {p} {[1, 2]} \\{x\\}\"; }";
+        let out = compile_source(src).expect("compile failed");
+        let result = run_wasm_bytes_rendered(&out.bytes, vec![], &out.main_return_type)
+            .expect("run failed");
+        assert_eq!(result.as_deref(), Some("\"abc: true P { x: 1, y: 0.5 } [1, 2] {x}\""));
+    }
+
+    #[test]
+    fn nested_loops_break_and_continue_target_innermost_loop() {
+        let src = "fn main() -> Int { let pairs = 0; for i in 0..4 { for j in 0..4 { if j > i { break; } if j == 1 { continue; } pairs += 1; } } return pairs; }";
+        // i=0: j=0 | i=1: j=0 | i=2: j=0,2 | i=3: j=0,2,3
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(7));
+    }
+
+    #[test]
+    fn for_loop_variable_is_fresh_per_loop() {
+        let src = "fn main() -> Int { let words = [\"ab\", \"cde\"]; let total = 0; for x in words { total += len(x); } for x in 1..3 { total *= x; } return total; }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(10));
+    }
+
+    #[test]
+    fn generic_functions_are_instantiated_per_type() {
+        let src = "struct Pair<A, B> { first: A, second: B } fn pair<A, B>(a: A, b: B) -> Pair<A, B> { return Pair { first: a, second: b }; } fn dup<T>(x: T) -> Pair<T, T> { return pair(x, x); } fn main() -> Pair<Int, Pair<Bool, Bool>> { return pair(7, dup(true)); }";
+        let out = compile_source(src).expect("compile failed");
+        let result = run_wasm_bytes_rendered(&out.bytes, vec![], &out.main_return_type)
+            .expect("run failed");
+        assert_eq!(
+            result.as_deref(),
+            Some("Pair { first: 7, second: Pair { first: true, second: true } }")
+        );
+    }
+
+    #[test]
+    fn question_mark_propagates_none_and_err() {
+        let src = "fn second(xs: List<Int>) -> Option<Int> { let x = xs.get(1)?; return Option::Some(x * 2); } fn main() -> (Option<Int>, Option<Int>) { return (second([1, 5]), second([1])); }";
+        let out = compile_source(src).expect("compile failed");
+        let result = run_wasm_bytes_rendered(&out.bytes, vec![], &out.main_return_type)
+            .expect("run failed");
+        assert_eq!(result.as_deref(), Some("(Option::Some(10), Option::None)"));
+
+        let src = "fn load(path: String) -> Result<Int, String> { let text = read_file(path)?; return Result::Ok(len(text)); } fn main() -> Result<Int, String> { return load(\"/nonexistent/eres.txt\"); }";
+        let out = compile_source(src).expect("compile failed");
+        let result = run_wasm_bytes_rendered(&out.bytes, vec![], &out.main_return_type)
+            .expect("run failed")
+            .expect("main returns a value");
+        assert!(
+            result.starts_with("Result::Err(\"/nonexistent/eres.txt: "),
+            "unexpected result {}",
+            result
+        );
+    }
+
+    #[test]
+    fn empty_lists_and_open_type_arguments_take_types_from_later_uses() {
+        let src = "fn main() -> Int { let xs = []; xs.push(20); xs.push(22); let o = Option::None; if xs.len() == 2 { o = Option::Some(xs[0] + xs[1]); } let picked = match o { None => [], Some(n) => [n, 0] }; let total = 0; for p in picked { total += p; } return total + picked.len(); }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(44));
+    }
+
+    #[test]
+    fn closures_capture_by_value_and_run_through_the_table() {
+        let src = "fn call(f: fn(Int) -> Int, x: Int) -> Int { return f(x); } fn main() -> Int { let k = 2; let scale = |x: Int| x * k; k = 100; let fs: List<fn(Int) -> Int> = [scale, |x| call(scale, x) + k]; let pick = match Option::Some(1) { Some(i) => fs[i], None => scale }; return call(pick, 5); }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(110));
+    }
+
+    #[test]
+    fn closures_share_captured_aggregates() {
+        let src = "struct C { n: Int } fn inc(c: C) -> Int { c.n += 1; return c.n; } \
+                   fn main() -> Int { let xs: List<Int> = [7]; let add = |x: Int| xs.push(x); \
+                   let at = |i: Int| xs[i]; let c = C { n: 1 }; let bump = || inc(c); add(3); xs[0] = 9; bump(); bump(); \
+                   return xs.len() * 100 + at(0) * 10 + c.n; }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(293));
+    }
+
+    #[test]
+    fn unannotated_closures_with_operators_run() {
+        let src = "fn main() -> Int { let sq = |x| x * x; let neg = |x| -x; let add = |a, b| a + b; \
+                   return add(sq(3), neg(2)); }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(7));
+    }
+
+    #[test]
+    fn methods_dispatch_statically_on_the_receiver_type() {
+        let src = "trait Score { fn score(&self) -> Int; } struct Pair<T> { a: T, b: T } impl<T> Pair<T> { fn first(&self) -> T { return self.a; } fn swap(self) -> Pair<T> { return Pair { a: self.b, b: self.a }; } } impl Score for Bool { fn score(&self) -> Int { if *self { return 1; } return 0; } } impl Score for Pair<Int> { fn score(&self) -> Int { return self.a * 10 + self.b; } } fn best<T: Score>(x: T, y: T) -> Int { if x.score() > y.score() { return x.score(); } return y.score(); } fn main() -> Int { let p = Pair { a: 1, b: 2 }.swap(); let r = &p; return r.first() * 100 + best(p, Pair { a: 0, b: 9 }) + best(true, false); }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(222));
+    }
+
+    #[test]
+    fn literal_arithmetic_runs_in_the_expected_integer_type() {
+        let src = "fn f(b: UInt8) -> Int { return b as Int; } fn g() -> Int32 { return 2 * 3; } \
+                   fn main() -> Int { let a: Int32 = 5 - 1; let xs: List<UInt8> = [1, 2 * 3]; \
+                   let n: Int32 = -(4 - 1) + g(); let b: UInt8 = 3; let c = b + (1 + 2); \
+                   return (a as Int) + f(2 + 3) + (xs[1] as Int) + (n as Int) + (c as Int); }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(24));
+    }
+
+    #[test]
+    fn casts_wrap_or_saturate_and_small_integers_wrap() {
+        let src = "fn main() -> Bool { let b: UInt8 = 250; b += 10; let i: Int32 = 2147483647; i = i + 1; let huge = 100000000000000000000.0; let xs: List<Byte> = [7, b]; return b == 4 && i as Int == -2147483648 && 3.99 as Int == 3 && -3.99 as Int == -3 && huge as Int32 == 2147483647 && -1.5 as UInt8 == 0 && 300 as UInt8 == 44 && xs[1] as Int == 4 && (7 as Float) / 2.0 == 3.5 && b / 3 == 1; }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(1));
+    }
+
+    #[test]
+    fn shadowed_bindings_get_distinct_locals() {
+        let src = "fn main() -> Int { let x = 1; if true { let x = \"abc\"; let n = len(x); x = \"d\"; if n != 3 { return 0; } } let x = x + 1; let x = 2.5; let y = x * 2.0; if y > 4.0 { return 5; } return 0; }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(5));
+        let src = "fn main() -> Int { let x = 1; if true { let x = 10; x += 1; } return x; }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(1));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let src = "fn main() -> Int { let xs: List<Int> = []; let hits = 0; if xs.len() > 0 && xs[0] == 1 { hits += 1; } if xs.len() == 0 || xs[0] == 1 { hits += 10; } if !(hits == 10) { return 0; } return hits; }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(10));
+    }
+
+    #[test]
+    fn float_compound_assignment_runs() {
+        let src = "fn main() -> Bool { let scale = 8.0; scale /= 2.0; scale -= 1.5; return scale == 2.5; }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(1));
+    }
+
+    #[test]
+    fn list_pop_on_empty_list_traps() {
+        let src = "fn main() -> Int { let xs: List<Int> = []; return xs.pop(); }";
+        let err = run_source(src, vec![]).expect_err("expected empty pop trap");
+        assert!(err.contains("pop from empty list"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn host_trap_errors_abort_the_program() {
+        fn checked_div(a: i64, b: i64) -> Result<i64, HostTrap> {
+            a.checked_div(b).ok_or_else(|| HostTrap::from("host division by zero"))
+        }
+        let mut hosts = default_host_functions();
+        hosts.push(eres_host_function!(checked_div, name = "checked_div", params = [i64, i64], result = Result<i64, HostTrap>));
+        let src = "fn main(d: Int) -> Int { return checked_div(12, d) + 1; }";
+        let bytes = compile_source_with_hosts(src, &hosts, CompileOptions::default())
+            .map(|out| out.bytes)
+            .expect("compile failed");
+
+        assert_eq!(run_wasm_bytes_with_hosts(&bytes, vec![4], &hosts), Ok(Some(4)));
+        let err = run_wasm_bytes_with_hosts(&bytes, vec![0], &hosts).expect_err("expected trap");
+        assert!(err.contains("host division by zero"), "{}", err);
+    }
+
+    #[test]
+    fn eres_lists_decode_into_host_vectors() {
+        let src = "fn main() -> Int { return count_words([\"a\", \"b\"]); }";
+        let mut hosts = default_host_functions();
+        hosts.push(eres_host_function!(count_words, name = "count_words", params = [Vec<String>], result = i64));
+        let bytes = compile_source_with_hosts(src, &hosts, CompileOptions::default())
+            .map(|out| out.bytes)
+            .expect("compile failed");
+
+        let result = run_wasm_bytes_with_hosts(&bytes, vec![], &hosts).expect("run failed");
+        assert_eq!(result, Some(2));
+    }
+
+    #[test]
+    fn tuple_result_is_rendered() {
+        let src = "fn pair() -> (Int, Float) { return (1, 2.5); } fn main() -> (Int, Float) { return pair(); }";
+        let bytes = compile_bytes_from_src(src).expect("compile failed");
+        let ty = Type::Tuple(vec![Type::Int, Type::Float]);
+        let out = run_wasm_bytes_rendered(&bytes, vec![], &ty).expect("run failed");
+        assert_eq!(out.as_deref(), Some("(1, 2.5)"));
+    }
+
+    #[test]
+    fn eres_tuples_decode_into_host_tuples() {
+        let src = "fn main() -> Int { return score_pair((4, true)); }";
+        let mut hosts = default_host_functions();
+        hosts.push(eres_host_function!(score_pair, name = "score_pair", params = [(i64, bool)], result = i64));
+        let bytes = compile_source_with_hosts(src, &hosts, CompileOptions::default())
+            .map(|out| out.bytes)
+            .expect("compile failed");
+
+        let result = run_wasm_bytes_with_hosts(&bytes, vec![], &hosts).expect("run failed");
+        assert_eq!(result, Some(4));
+    }
+
     #[test]
     fn module_string_example_runs() {
         let out = crate::compiler::compile_entry_file("examples/module_strings_main.eres")
@@ -471,4 +1467,47 @@ mod tests {
         let res = run_source(src, vec![]).expect("run failed");
         assert_eq!(res, Some(9));
     }
+
+    #[test]
+    fn run_functions_that_only_return_from_branches_and_loops() {
+        let src = "fn sign(x: Int) -> Bool { if x < 0 { return false; } else { return true; } } \
+                   fn first_over(limit: Int) -> Int { let i = 0; \
+                   while true { i += 1; if i > limit { return i; } } } \
+                   fn main() -> Int { if sign(-3) { return 0; } return first_over(4); }";
+        let res = run_source(src, vec![]).expect("run failed");
+        assert_eq!(res, Some(5));
+    }
+
+    #[test]
+    fn nested_reads_share_the_stored_handle() {
+        let src = "struct P { x: Int } fn main(n: Int) -> Int { let grid = [[1, 2], [3, 4]]; \
+                   let ps = [P { x: 5 }]; let total = 0; \
+                   for i in 0..n { total += grid[1][0] + grid[0].len() + ps[0].x; } return total; }";
+        let bytes = compile_bytes_from_src(src).expect("compile failed");
+        let heap_slots = |reads: i64| {
+            let (store, result) =
+                call_main(&bytes, vec![reads], &default_host_functions()).expect("run failed");
+            assert!(matches!(result, Some(Val::I64(total)) if total == 10 * reads));
+            store.data().allocated()
+        };
+        assert_eq!(heap_slots(1), heap_slots(50));
+
+        let src = "fn main() -> Int { let grid = [[1, 2]]; let row = grid[0]; row.push(3); \
+                   let copy = grid; copy[0][0] = 7; return grid[0].len() * 10 + row[0]; }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(37));
+    }
+
+    #[test]
+    fn nested_field_and_index_assignments_write_back() {
+        let src = "struct V { x: Int, y: Int } struct S { a: V } \
+                   fn main() -> Int { let s = S { a: V { x: 1, y: 2 } }; s.a.x = 9; s.a.y += 5; \
+                   return s.a.x * 10 + s.a.y; }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(97));
+        let src = "fn main() -> Int { let m = [[1, 2], [3, 4]]; let i = 0; let j = 1; \
+                   m[i][j] = 50; m[1][0] *= 10; return m[0][1] + m[1][0]; }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(80));
+        let src = "struct P { x: Int } fn main() -> Int { let ps = [P { x: 1 }, P { x: 2 }]; \
+                   ps[0].x = 9; ps[1].x += 1; return ps[0].x * 10 + ps[1].x; }";
+        assert_eq!(run_source(src, vec![]).expect("run failed"), Some(93));
+    }
 }
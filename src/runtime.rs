@@ -1,20 +1,202 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashSet};
 
-use crate::ast::{Expr, MatchArm, Program, Stmt, TopLevelDecl};
+use crate::ast::{BinOp, Expr, ExprKind, InterpolationPart, Program, Stmt, StmtKind, Type};
+use crate::consteval::{ConstValue, eval_program_consts};
+use crate::visit::{Visitor, walk_expr, walk_stmt};
+use eres_abi::HostFunction;
 
 pub const STRING_EQ_IMPORT: &str = "__eres_string_eq";
+/// `(left, right) -> handle`: new string holding `left` followed by `right`.
+pub const STRING_CONCAT_IMPORT: &str = "__eres_string_concat";
+/// `(left, right) -> i32`: lexicographic byte order of two strings as `-1`, `0` or `1`.
+pub const STRING_CMP_IMPORT: &str = "__eres_string_cmp";
 const STRING_LITERAL_PREFIX: &str = "__eres_const_string_";
+pub const STRUCT_NEW_IMPORT: &str = "__eres_struct_new";
+/// `(name_ptr, name_len, variant_ptr, variant_len) -> handle`: creates an enum value without payload.
+pub const ENUM_NEW_IMPORT: &str = "__eres_enum_new";
+/// `(handle, variants_ptr, variants_len) -> i32`: position of the value's variant in a comma list.
+pub const ENUM_TAG_IMPORT: &str = "__eres_enum_tag";
+/// `(elem_type_ptr, elem_type_len) -> handle`: creates an empty list; the element type is source syntax.
+pub const LIST_NEW_IMPORT: &str = "__eres_list_new";
+/// `() -> handle`: creates an empty tuple that is filled positionally.
+pub const TUPLE_NEW_IMPORT: &str = "__eres_tuple_new";
+/// `(handle) -> i64`: number of elements of a list or tuple.
+pub const AGGREGATE_LEN_IMPORT: &str = "__eres_len";
+/// `(function_ptr, function_len)`: aborts execution after a checked integer operation overflowed.
+pub const OVERFLOW_IMPORT: &str = "__eres_overflow";
+const AGGREGATE_PUSH_PREFIX: &str = "__eres_push_";
+const AGGREGATE_GET_PREFIX: &str = "__eres_get_";
+const AGGREGATE_POP_PREFIX: &str = "__eres_pop_";
+const AGGREGATE_SET_PREFIX: &str = "__eres_set_";
+const BOX_PREFIX: &str = "__eres_box_";
+const UNBOX_PREFIX: &str = "__eres_unbox_";
+const TO_STRING_PREFIX: &str = "__eres_to_string_";
+
+/// Name of the exported linear memory that holds static runtime metadata (type/field names).
+pub const MEMORY_EXPORT: &str = "memory";
 
 #[derive(Debug, Default, Clone)]
 pub struct RuntimeImports {
     pub string_literals: Vec<String>,
     pub needs_string_eq: bool,
+    /// Whether a `+` may concatenate strings or a string is interpolated.
+    pub needs_string_concat: bool,
+    /// Whether `<`, `<=`, `>` or `>=` may compare strings.
+    pub needs_string_cmp: bool,
+    /// Whether interpolation renders values of other types into strings.
+    pub needs_to_string: bool,
+    /// Whether heap aggregates (structs, enums, ...) are built or inspected from wasm.
+    pub needs_aggregates: bool,
+}
+
+/// Wasm value lane used to move a language value in or out of a heap aggregate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeLane {
+    /// `Int` as `i64`.
+    Int,
+    /// `Float` as `f64`.
+    Float,
+    /// `Bool` as `i32` (`0/1`).
+    Bool,
+    /// `Int32` and `UInt8` as `i32`, stored as `Int` values in the heap.
+    Int32,
+    /// Any heap value (`String`, lists, tuples, structs, enums, references) as `i32` handle.
+    Handle,
+}
+
+impl RuntimeLane {
+    pub const ALL: [RuntimeLane; 5] = [
+        RuntimeLane::Int,
+        RuntimeLane::Float,
+        RuntimeLane::Bool,
+        RuntimeLane::Int32,
+        RuntimeLane::Handle,
+    ];
+
+    /// Picks the lane for a language type, `None` for types that cannot live in the heap.
+    pub fn for_type(ty: &Type) -> Option<Self> {
+        match ty {
+            Type::Int => Some(RuntimeLane::Int),
+            Type::Float => Some(RuntimeLane::Float),
+            Type::Bool => Some(RuntimeLane::Bool),
+            Type::Int32 | Type::UInt8 => Some(RuntimeLane::Int32),
+            Type::String
+            | Type::List(_)
+            | Type::Tuple(_)
+            | Type::Named(_)
+            | Type::Generic(_, _)
+            | Type::Ref(_)
+            | Type::Function(_, _) => Some(RuntimeLane::Handle),
+            Type::Unit | Type::Var(_) => None,
+        }
+    }
+
+    /// Whether values of this lane live directly on the wasm stack (and need boxing for `&`).
+    pub fn is_scalar(self) -> bool {
+        self != RuntimeLane::Handle
+    }
+
+    /// Representative language type used for the lane in import signatures.
+    pub fn value_type(self) -> Type {
+        match self {
+            RuntimeLane::Int => Type::Int,
+            RuntimeLane::Float => Type::Float,
+            RuntimeLane::Bool => Type::Bool,
+            RuntimeLane::Int32 => Type::Int32,
+            RuntimeLane::Handle => Type::String,
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            RuntimeLane::Int => "int",
+            RuntimeLane::Float => "float",
+            RuntimeLane::Bool => "bool",
+            RuntimeLane::Int32 => "int32",
+            RuntimeLane::Handle => "handle",
+        }
+    }
+
+    fn from_suffix(suffix: &str) -> Option<Self> {
+        RuntimeLane::ALL.into_iter().find(|lane| lane.suffix() == suffix)
+    }
 }
 
 pub fn string_eq_import_name() -> &'static str {
     STRING_EQ_IMPORT
 }
 
+pub fn string_concat_import_name() -> &'static str {
+    STRING_CONCAT_IMPORT
+}
+
+pub fn string_cmp_import_name() -> &'static str {
+    STRING_CMP_IMPORT
+}
+
+/// `(value) -> handle`: renders a value as a string for interpolation.
+pub fn to_string_import_name(lane: RuntimeLane) -> String {
+    format!("{}{}", TO_STRING_PREFIX, lane.suffix())
+}
+
+pub fn decode_to_string_import_name(name: &str) -> Option<RuntimeLane> {
+    RuntimeLane::from_suffix(name.strip_prefix(TO_STRING_PREFIX)?)
+}
+
+/// `(target, key_ptr, key_len, value) -> target`: appends a value to an aggregate.
+pub fn aggregate_push_import_name(lane: RuntimeLane) -> String {
+    format!("{}{}", AGGREGATE_PUSH_PREFIX, lane.suffix())
+}
+
+/// `(target, index: i64) -> value`: reads one aggregate element by position.
+pub fn aggregate_get_import_name(lane: RuntimeLane) -> String {
+    format!("{}{}", AGGREGATE_GET_PREFIX, lane.suffix())
+}
+
+/// `(target, index: i64, value)`: overwrites one aggregate element by position.
+pub fn aggregate_set_import_name(lane: RuntimeLane) -> String {
+    format!("{}{}", AGGREGATE_SET_PREFIX, lane.suffix())
+}
+
+/// `(list) -> value`: removes and returns the last list element.
+pub fn aggregate_pop_import_name(lane: RuntimeLane) -> String {
+    format!("{}{}", AGGREGATE_POP_PREFIX, lane.suffix())
+}
+
+/// `(value) -> handle`: boxes a scalar so `&x` can be passed around as a handle.
+pub fn box_import_name(lane: RuntimeLane) -> String {
+    format!("{}{}", BOX_PREFIX, lane.suffix())
+}
+
+/// `(handle) -> value`: reads a boxed scalar back (`*r`).
+pub fn unbox_import_name(lane: RuntimeLane) -> String {
+    format!("{}{}", UNBOX_PREFIX, lane.suffix())
+}
+
+pub fn decode_box_import_name(name: &str) -> Option<RuntimeLane> {
+    RuntimeLane::from_suffix(name.strip_prefix(BOX_PREFIX)?)
+}
+
+pub fn decode_unbox_import_name(name: &str) -> Option<RuntimeLane> {
+    RuntimeLane::from_suffix(name.strip_prefix(UNBOX_PREFIX)?)
+}
+
+pub fn decode_aggregate_push_import_name(name: &str) -> Option<RuntimeLane> {
+    RuntimeLane::from_suffix(name.strip_prefix(AGGREGATE_PUSH_PREFIX)?)
+}
+
+pub fn decode_aggregate_get_import_name(name: &str) -> Option<RuntimeLane> {
+    RuntimeLane::from_suffix(name.strip_prefix(AGGREGATE_GET_PREFIX)?)
+}
+
+pub fn decode_aggregate_set_import_name(name: &str) -> Option<RuntimeLane> {
+    RuntimeLane::from_suffix(name.strip_prefix(AGGREGATE_SET_PREFIX)?)
+}
+
+pub fn decode_aggregate_pop_import_name(name: &str) -> Option<RuntimeLane> {
+    RuntimeLane::from_suffix(name.strip_prefix(AGGREGATE_POP_PREFIX)?)
+}
+
 pub fn string_literal_import_name(value: &str) -> String {
     format!("{}{}", STRING_LITERAL_PREFIX, hex_encode(value.as_bytes()))
 }
@@ -26,87 +208,119 @@ pub fn decode_string_literal_import_name(name: &str) -> Option<String> {
 }
 
 pub fn collect_runtime_imports(program: &Program) -> RuntimeImports {
-    let mut imports = RuntimeImports::default();
-    let mut strings = BTreeSet::new();
+    collect_runtime_imports_with_hosts(program, &[])
+}
 
-    for item in &program.items {
-        if let TopLevelDecl::Function(func) = item {
-            for stmt in &func.body {
-                visit_stmt(stmt, &mut strings, &mut imports.needs_string_eq);
-            }
-        }
+/// Like `collect_runtime_imports`; `hosts` are needed to spot host functions used as values.
+pub fn collect_runtime_imports_with_hosts(
+    program: &Program,
+    hosts: &[HostFunction],
+) -> RuntimeImports {
+    let functions: HashSet<&str> = program
+        .functions()
+        .map(|func| func.name.as_str())
+        .chain(hosts.iter().map(|host| host.name))
+        .collect();
+    let mut collector = ImportCollector {
+        functions,
+        strings: BTreeSet::new(),
+        imports: RuntimeImports::default(),
+    };
+    for func in program.functions() {
+        collector.visit_block(&func.body);
+    }
+
+    let ImportCollector {
+        mut strings,
+        mut imports,
+        ..
+    } = collector;
+    // String consts are inlined as literals at their uses.
+    if let Ok(consts) = eval_program_consts(program) {
+        strings.extend(consts.into_values().filter_map(|value| match value {
+            ConstValue::String(value) => Some(value),
+            _ => None,
+        }));
     }
 
     imports.string_literals = strings.into_iter().collect();
     imports
 }
 
-fn visit_stmt(stmt: &Stmt, strings: &mut BTreeSet<String>, needs_string_eq: &mut bool) {
-    match stmt {
-        Stmt::Let { value, .. } => visit_expr(value, strings, needs_string_eq),
-        Stmt::Return(Some(expr)) | Stmt::Expr(expr) => visit_expr(expr, strings, needs_string_eq),
-        Stmt::Return(None) => {}
-        Stmt::If {
-            cond,
-            then_block,
-            else_block,
-        } => {
-            visit_expr(cond, strings, needs_string_eq);
-            for stmt in then_block {
-                visit_stmt(stmt, strings, needs_string_eq);
-            }
-            for stmt in else_block {
-                visit_stmt(stmt, strings, needs_string_eq);
+/// Finds the string literals and runtime imports function bodies need.
+struct ImportCollector<'a> {
+    /// The program's and host function names: using one as a value builds a closure,
+    /// calling any other name goes through a function value.
+    functions: HashSet<&'a str>,
+    strings: BTreeSet<String>,
+    imports: RuntimeImports,
+}
+
+impl Visitor for ImportCollector<'_> {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Assign { target, op, .. } => {
+                if !matches!(target.kind, ExprKind::Local(_)) {
+                    self.imports.needs_aggregates = true;
+                }
+                if matches!(op, Some(BinOp::Add)) {
+                    self.imports.needs_string_concat = true;
+                }
             }
-        }
-        Stmt::While { cond, body } => {
-            visit_expr(cond, strings, needs_string_eq);
-            for stmt in body {
-                visit_stmt(stmt, strings, needs_string_eq);
+            StmtKind::For { iter, .. } if !matches!(iter.kind, ExprKind::Range { .. }) => {
+                self.imports.needs_aggregates = true;
             }
+            _ => {}
         }
+        walk_stmt(self, stmt);
     }
-}
 
-fn visit_expr(expr: &Expr, strings: &mut BTreeSet<String>, needs_string_eq: &mut bool) {
-    match expr {
-        Expr::String(value) => {
-            strings.insert(value.clone());
-        }
-        Expr::Binary { op, left, right } => {
-            if matches!(op, crate::ast::BinOp::Eq | crate::ast::BinOp::NotEq) {
-                *needs_string_eq = true;
-            }
-            visit_expr(left, strings, needs_string_eq);
-            visit_expr(right, strings, needs_string_eq);
-        }
-        Expr::Call { args, .. } | Expr::MethodCall { args, .. } => {
-            for arg in args {
-                visit_expr(arg, strings, needs_string_eq);
+    fn visit_expr(&mut self, expr: &Expr) {
+        let imports = &mut self.imports;
+        match &expr.kind {
+            ExprKind::String(value) => {
+                self.strings.insert(value.clone());
             }
-        }
-        Expr::StructInit { fields, .. } => {
-            for (_, expr) in fields {
-                visit_expr(expr, strings, needs_string_eq);
+            ExprKind::Binary { op, .. } => match op {
+                BinOp::Eq | BinOp::NotEq => imports.needs_string_eq = true,
+                BinOp::Add => imports.needs_string_concat = true,
+                BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => imports.needs_string_cmp = true,
+                _ => {}
+            },
+            // Calling a local reads the table slot out of the function value.
+            ExprKind::Call { name, .. } if !self.functions.contains(name.as_str()) => {
+                imports.needs_aggregates = true;
             }
-        }
-        Expr::EnumInit { payload, .. } | Expr::TupleLiteral(payload) | Expr::ListLiteral(payload) => {
-            for expr in payload {
-                visit_expr(expr, strings, needs_string_eq);
+            ExprKind::Local(name) if self.functions.contains(name.as_str()) => {
+                imports.needs_aggregates = true;
             }
-        }
-        Expr::Match { subject, arms } => {
-            visit_expr(subject, strings, needs_string_eq);
-            for MatchArm { body, .. } in arms {
-                visit_expr(body, strings, needs_string_eq);
+            // Aggregates, references and closures (with their captured values) live in the heap.
+            ExprKind::MethodCall { .. }
+            | ExprKind::StructInit { .. }
+            | ExprKind::EnumInit { .. }
+            | ExprKind::TupleLiteral(_)
+            | ExprKind::ListLiteral(_)
+            | ExprKind::Match { .. }
+            | ExprKind::Field { .. }
+            | ExprKind::Index { .. }
+            | ExprKind::Ref(_)
+            | ExprKind::Deref(_)
+            | ExprKind::Try(_)
+            | ExprKind::Closure { .. } => imports.needs_aggregates = true,
+            ExprKind::Interpolation(parts) => {
+                imports.needs_string_concat = true;
+                for part in parts {
+                    match part {
+                        InterpolationPart::Text(text) => {
+                            self.strings.insert(text.clone());
+                        }
+                        InterpolationPart::Expr(_) => imports.needs_to_string = true,
+                    }
+                }
             }
+            _ => {}
         }
-        Expr::Index { base, index } => {
-            visit_expr(base, strings, needs_string_eq);
-            visit_expr(index, strings, needs_string_eq);
-        }
-        Expr::Ref(inner) | Expr::Deref(inner) => visit_expr(inner, strings, needs_string_eq),
-        Expr::Local(_) | Expr::Int(_) | Expr::Float(_) | Expr::Bool(_) => {}
+        walk_expr(self, expr);
     }
 }
 
@@ -120,7 +334,7 @@ fn hex_encode(bytes: &[u8]) -> String {
 }
 
 fn hex_decode(input: &str) -> Option<Vec<u8>> {
-    if input.len() % 2 != 0 {
+    if !input.len().is_multiple_of(2) {
         return None;
     }
 
@@ -140,19 +354,35 @@ fn hex_decode(input: &str) -> Option<Vec<u8>> {
 mod tests {
     use super::*;
     use crate::ast::{BinOp, Expr, FunctionDecl, Program, Stmt, TopLevelDecl, Type};
+    use crate::token::Span;
 
     #[test]
     fn runtime_imports_collect_strings_and_eq() {
         let program = Program {
             items: vec![TopLevelDecl::Function(FunctionDecl {
                 name: "main".to_string(),
+                type_params: vec![],
                 params: vec![],
                 return_type: Type::Bool,
-                body: vec![Stmt::Return(Some(Expr::Binary {
-                    op: BinOp::Eq,
-                    left: Box::new(Expr::String("a".to_string())),
-                    right: Box::new(Expr::String("b".to_string())),
-                }))],
+                body: vec![Stmt::new(
+                    StmtKind::Return(Some(Expr::new(
+                        ExprKind::Binary {
+                            op: BinOp::Eq,
+                            left: Box::new(Expr::new(
+                                ExprKind::String("a".to_string()),
+                                Span::default(),
+                            )),
+                            right: Box::new(Expr::new(
+                                ExprKind::String("b".to_string()),
+                                Span::default(),
+                            )),
+                        },
+                        Span::default(),
+                    ))),
+                    Span::default(),
+                )],
+                bounds: vec![],
+                span: Span::default(),
             })],
         };
         let imports = collect_runtime_imports(&program);
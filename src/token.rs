@@ -8,14 +8,26 @@ pub enum TokenKind {
     If,
     Else,
     While,
+    For,
+    In,
+    Break,
+    Continue,
     Return,
     Struct,
     Enum,
     Match,
+    Impl,
+    Trait,
+    As,
+    Const,
+    Static,
+    Mut,
 
     // Identifiers + literals
     Ident(String),
     StringLit(String),
+    /// String literal containing `{expr}` holes (`"x = {x}"`).
+    InterpolatedString(Vec<StringPart>),
     Int(i64),
     Float(f64),
     True,
@@ -28,7 +40,16 @@ pub enum TokenKind {
     Slash,
     Percentage,
     Equal,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
     Ampersand,
+    AndAnd,
+    OrOr,
+    Pipe,
+    Bang,
+    Question,
 
     // Comparison operators
     EqualEqual,
@@ -48,6 +69,7 @@ pub enum TokenKind {
     Semicolon,
     Comma,
     Dot,
+    DotDot,
     Colon,
     DoubleColon,
     Arrow,
@@ -67,7 +89,9 @@ impl TokenKind {
     pub fn name(&self) -> String {
         match self {
             TokenKind::Ident(_) => "identifier".to_string(),
-            TokenKind::StringLit(_) => "string literal".to_string(),
+            TokenKind::StringLit(_) | TokenKind::InterpolatedString(_) => {
+                "string literal".to_string()
+            }
             TokenKind::Int(_) => "integer literal".to_string(),
             TokenKind::Float(_) => "float literal".to_string(),
             _ => format!("{:?}", self),
@@ -75,15 +99,26 @@ impl TokenKind {
     }
 }
 
+/// Piece of an interpolated string literal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    /// Tokens of the expression between `{` and `}`, ending with `Eof`; spans point into
+    /// the enclosing source.
+    Expr(Vec<Token>),
+}
+
 /// One token with source span information.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
 }
 
 /// Half-open byte span `[start, end)` in the original source.
-#[derive(Debug, Clone)]
+///
+/// Items without source text (prelude and host types) use the empty default span.
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Span {
     pub start: usize,
     pub end: usize,
@@ -1,7 +1,12 @@
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 
 use crate::ast::*;
+use crate::consteval::{ConstError, ConstValue, eval_const, reads_any};
+use crate::diagnostics::{Diagnostic, Label};
 use crate::host::{abi_type_to_ast_type, host_descriptor_to_ast};
+use crate::prelude::{self, option_of, try_payload_type};
+use crate::token::Span;
 use eres_abi::{HostFunction, TypeDescriptor};
 
 #[derive(Debug)]
@@ -9,19 +14,177 @@ pub struct TypedProgram {
     pub function_infos: HashMap<String, TypedFunction>,
     pub structs: HashMap<String, StructDecl>,
     pub enums: HashMap<String, EnumDecl>,
+    pub traits: HashMap<String, TraitDecl>,
+    /// `(trait, type)` pairs with an `impl Trait for Type` block.
+    pub trait_impls: HashSet<(String, String)>,
+    /// Evaluated `const` items, inlined by codegen wherever they are used.
+    pub consts: HashMap<String, TypedConst>,
+    /// `static mut` items in declaration order, which is also their wasm global index.
+    pub statics: Vec<TypedStatic>,
 }
 
-#[derive(Debug)]
+/// A checked `const` with its declared type; `Int32` and `UInt8` values are stored as `Int`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedConst {
+    pub ty: Type,
+    pub value: ConstValue,
+}
+
+/// A checked `static mut` with its evaluated initial value.
+#[derive(Debug, Clone)]
+pub struct TypedStatic {
+    pub name: String,
+    pub ty: Type,
+    pub init: ConstValue,
+}
+
+#[derive(Debug, Clone)]
 pub struct TypedFunction {
     pub name: String,
+    /// Type parameter names of a generic function; its types below mention them.
+    pub type_params: Vec<String>,
     pub params: Vec<Param>,
     pub return_type: Type,
+    /// Every `let`/`for` binding in source order; binding `i` lives in slot `params.len() + i`.
     pub locals: Vec<(String, Type)>,
+    /// Bindings visible at the end of the function body (parameters and top-level `let`s).
     pub local_map: HashMap<String, (u32, Type)>,
 }
 
+impl TypedFunction {
+    /// Copy of a generic function with `type_args` substituted for its type parameters.
+    pub fn instantiate(&self, name: &str, type_args: &[Type]) -> TypedFunction {
+        let bindings = type_bindings(&self.type_params, type_args);
+        TypedFunction {
+            name: name.to_string(),
+            type_params: Vec::new(),
+            params: self
+                .params
+                .iter()
+                .map(|param| Param {
+                    name: param.name.clone(),
+                    ty: param.ty.substitute(&bindings),
+                    span: param.span.clone(),
+                })
+                .collect(),
+            return_type: self.return_type.substitute(&bindings),
+            locals: self
+                .locals
+                .iter()
+                .map(|(name, ty)| (name.clone(), ty.substitute(&bindings)))
+                .collect(),
+            local_map: self
+                .local_map
+                .iter()
+                .map(|(name, (slot, ty))| (name.clone(), (*slot, ty.substitute(&bindings))))
+                .collect(),
+        }
+    }
+}
+
+/// A type error plus the source span it was reported at.
 #[derive(Debug)]
-pub enum TypeError {
+pub struct TypeError {
+    /// Boxed to keep `Result<_, TypeError>` small; the kinds carry whole types.
+    pub kind: Box<TypeErrorKind>,
+    /// Offending node; `None` only for items without source text, such as host descriptors.
+    pub span: Option<Span>,
+    /// Related locations, e.g. the first declaration of a duplicated name.
+    pub labels: Vec<Label>,
+}
+
+impl TypeError {
+    /// Attaches `span` unless a more precise, inner span was already recorded.
+    fn at(mut self, span: &Span) -> TypeError {
+        if self.span.is_none() {
+            self.span = Some(span.clone());
+        }
+        self
+    }
+
+    fn with_label(mut self, span: &Span, message: impl Into<String>) -> TypeError {
+        self.labels.push(Label {
+            span: span.clone(),
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let mut diagnostic =
+            Diagnostic::error(self.kind.code(), self.kind.to_string()).with_span(self.span.clone());
+        diagnostic.labels = self.labels.clone();
+        diagnostic
+    }
+}
+
+impl TypeErrorKind {
+    fn at(self, span: &Span) -> TypeError {
+        TypeError {
+            kind: Box::new(self),
+            span: Some(span.clone()),
+            labels: Vec::new(),
+        }
+    }
+
+    /// Stable diagnostic code, one per variant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TypeErrorKind::UnknownType { .. } => "E-TC01",
+            TypeErrorKind::DuplicateType { .. } => "E-TC02",
+            TypeErrorKind::DuplicateFunction { .. } => "E-TC03",
+            TypeErrorKind::UnknownFunction { .. } => "E-TC04",
+            TypeErrorKind::UnknownVariable { .. } => "E-TC05",
+            TypeErrorKind::DuplicateVariable { .. } => "E-TC06",
+            TypeErrorKind::InvalidFunctionReturn { .. } => "E-TC07",
+            TypeErrorKind::TypeMismatch { .. } => "E-TC08",
+            TypeErrorKind::NotBooleanCondition { .. } => "E-TC09",
+            TypeErrorKind::UnknownStruct { .. } => "E-TC10",
+            TypeErrorKind::UnknownField { .. } => "E-TC11",
+            TypeErrorKind::UnknownEnum { .. } => "E-TC12",
+            TypeErrorKind::UnknownEnumVariant { .. } => "E-TC13",
+            TypeErrorKind::NonExhaustiveMatch { .. } => "E-TC14",
+            TypeErrorKind::MatchPatternError { .. } => "E-TC15",
+            TypeErrorKind::DuplicateVariantPattern { .. } => "E-TC16",
+            TypeErrorKind::InvalidRefOperand { .. } => "E-TC17",
+            TypeErrorKind::CannotInferEmptyList { .. } => "E-TC18",
+            TypeErrorKind::InvalidMainFunction { .. } => "E-TC19",
+            TypeErrorKind::LoopControlOutsideLoop { .. } => "E-TC20",
+            TypeErrorKind::MisplacedRange => "E-TC21",
+            TypeErrorKind::WrongTypeArgumentCount { .. } => "E-TC22",
+            TypeErrorKind::CannotInferTypeArgument { .. } => "E-TC23",
+            TypeErrorKind::InvalidTry { .. } => "E-TC24",
+            TypeErrorKind::CannotInferClosure { .. } => "E-TC25",
+            TypeErrorKind::GenericFunctionValue { .. } => "E-TC26",
+            TypeErrorKind::UnknownTrait { .. } => "E-TC27",
+            TypeErrorKind::UnknownMethod { .. } => "E-TC28",
+            TypeErrorKind::MissingTraitMethod { .. } => "E-TC29",
+            TypeErrorKind::NotATraitMethod { .. } => "E-TC30",
+            TypeErrorKind::UnsatisfiedBound { .. } => "E-TC31",
+            TypeErrorKind::InvalidCast { .. } => "E-TC32",
+            TypeErrorKind::IntegerLiteralOutOfRange { .. } => "E-TC33",
+            TypeErrorKind::MixedNumericOperands { .. } => "E-TC34",
+            TypeErrorKind::NonConstantInitializer { .. } => "E-TC35",
+            TypeErrorKind::InvalidConst { .. } => "E-TC36",
+            TypeErrorKind::AssignToConst { .. } => "E-TC37",
+            TypeErrorKind::MissingReturn { .. } => "E-TC38",
+            TypeErrorKind::ReservedTypeName { .. } => "E-TC39",
+        }
+    }
+}
+
+impl From<TypeErrorKind> for TypeError {
+    fn from(kind: TypeErrorKind) -> TypeError {
+        TypeError {
+            kind: Box::new(kind),
+            span: None,
+            labels: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TypeErrorKind {
     UnknownType {
         name: String,
     },
@@ -58,6 +221,10 @@ pub enum TypeError {
     UnknownStruct {
         name: String,
     },
+    UnknownField {
+        struct_name: String,
+        field: String,
+    },
     UnknownEnum {
         name: String,
     },
@@ -87,30 +254,113 @@ pub enum TypeError {
     InvalidMainFunction {
         what: String,
     },
+    LoopControlOutsideLoop {
+        keyword: String,
+    },
+    MisplacedRange,
+    WrongTypeArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    CannotInferTypeArgument {
+        item: String,
+        param: String,
+    },
+    InvalidTry {
+        operand: Type,
+        return_type: Type,
+    },
+    /// A closure parameter (`Some(name)`) or return type (`None`) stayed unknown.
+    CannotInferClosure {
+        param: Option<String>,
+    },
+    GenericFunctionValue {
+        name: String,
+    },
+    UnknownTrait {
+        name: String,
+    },
+    UnknownMethod {
+        ty: Type,
+        method: String,
+    },
+    MissingTraitMethod {
+        trait_name: String,
+        ty: Type,
+        method: String,
+    },
+    NotATraitMethod {
+        trait_name: String,
+        method: String,
+    },
+    UnsatisfiedBound {
+        ty: Type,
+        trait_name: String,
+        item: String,
+    },
+    InvalidCast {
+        from: Type,
+        to: Type,
+    },
+    IntegerLiteralOutOfRange {
+        value: i64,
+        ty: Type,
+    },
+    MixedNumericOperands {
+        op: BinOp,
+        left: Type,
+        right: Type,
+    },
+    /// `kind` is `const` or `static`.
+    NonConstantInitializer {
+        kind: String,
+        name: String,
+        reason: String,
+    },
+    InvalidConst {
+        kind: String,
+        name: String,
+        reason: String,
+    },
+    AssignToConst {
+        name: String,
+    },
+    /// A non-`Unit` function whose body can end without a `return`.
+    MissingReturn {
+        function: String,
+        expected: Type,
+    },
+    /// A struct or enum named like a prelude type (`Option`, `Result`); `kind` is `struct` or
+    /// `enum`.
+    ReservedTypeName {
+        kind: String,
+        name: String,
+    },
 }
 
-impl std::fmt::Display for TypeError {
+impl std::fmt::Display for TypeErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            TypeError::UnknownType { name } => {
+            TypeErrorKind::UnknownType { name } => {
                 write!(f, "unknown type `{}`", name)
             }
-            TypeError::DuplicateType { kind, name } => {
+            TypeErrorKind::DuplicateType { kind, name } => {
                 write!(f, "duplicate {} declaration `{}`", kind, name)
             }
-            TypeError::DuplicateFunction { name } => {
+            TypeErrorKind::DuplicateFunction { name } => {
                 write!(f, "duplicate function declaration `{}`", name)
             }
-            TypeError::UnknownFunction { name } => {
+            TypeErrorKind::UnknownFunction { name } => {
                 write!(f, "unknown function `{}`", name)
             }
-            TypeError::UnknownVariable { name } => {
+            TypeErrorKind::UnknownVariable { name } => {
                 write!(f, "unknown variable `{}`", name)
             }
-            TypeError::DuplicateVariable { name } => {
+            TypeErrorKind::DuplicateVariable { name } => {
                 write!(f, "duplicate local declaration `{}`", name)
             }
-            TypeError::InvalidFunctionReturn {
+            TypeErrorKind::InvalidFunctionReturn {
                 function,
                 expected,
                 found,
@@ -123,19 +373,22 @@ impl std::fmt::Display for TypeError {
                     found
                 )
             }
-            TypeError::TypeMismatch {
+            TypeErrorKind::TypeMismatch {
                 expected,
                 found,
                 what,
             } => {
                 write!(f, "{}: expected {}, found {}", what, expected, found)
             }
-            TypeError::NotBooleanCondition { what, found } => {
+            TypeErrorKind::NotBooleanCondition { what, found } => {
                 write!(f, "{} must be Bool, found {}", what, found)
             }
-            TypeError::UnknownStruct { name } => write!(f, "unknown struct `{}`", name),
-            TypeError::UnknownEnum { name } => write!(f, "unknown enum `{}`", name),
-            TypeError::UnknownEnumVariant {
+            TypeErrorKind::UnknownStruct { name } => write!(f, "unknown struct `{}`", name),
+            TypeErrorKind::UnknownField { struct_name, field } => {
+                write!(f, "struct `{}` has no field `{}`", struct_name, field)
+            }
+            TypeErrorKind::UnknownEnum { name } => write!(f, "unknown enum `{}`", name),
+            TypeErrorKind::UnknownEnumVariant {
                 enum_name,
                 variant,
             } => {
@@ -145,10 +398,10 @@ impl std::fmt::Display for TypeError {
                     variant, enum_name
                 )
             }
-            TypeError::NonExhaustiveMatch { enum_name } => {
+            TypeErrorKind::NonExhaustiveMatch { enum_name } => {
                 write!(f, "non-exhaustive match on enum `{}`", enum_name)
             }
-            TypeError::MatchPatternError {
+            TypeErrorKind::MatchPatternError {
                 enum_name,
                 variant,
                 message,
@@ -161,35 +414,236 @@ impl std::fmt::Display for TypeError {
                     message
                 )
             }
-            TypeError::DuplicateVariantPattern {
+            TypeErrorKind::DuplicateVariantPattern {
                 enum_name,
                 variant,
             } => {
                 write!(f, "match on {} has duplicated pattern {}", enum_name, variant)
             }
-            TypeError::InvalidRefOperand { found, what } => {
+            TypeErrorKind::InvalidRefOperand { found, what } => {
                 write!(f, "{}: expected reference, found {}", what, found)
             }
-            TypeError::CannotInferEmptyList { what } => {
+            TypeErrorKind::CannotInferEmptyList { what } => {
                 write!(f, "{}", what)
             }
-            TypeError::InvalidMainFunction { what } => {
+            TypeErrorKind::InvalidMainFunction { what } => {
                 write!(f, "invalid `main` signature: {}", what)
             }
+            TypeErrorKind::LoopControlOutsideLoop { keyword } => {
+                write!(f, "`{}` outside of a loop", keyword)
+            }
+            TypeErrorKind::MisplacedRange => {
+                write!(f, "range expressions are only allowed as `for` loop iterables")
+            }
+            TypeErrorKind::WrongTypeArgumentCount {
+                name,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "`{}` expects {} type argument(s), found {}",
+                    name, expected, found
+                )
+            }
+            TypeErrorKind::CannotInferTypeArgument { item, param } => {
+                write!(
+                    f,
+                    "cannot infer type parameter `{}` of `{}`; add a type annotation",
+                    param, item
+                )
+            }
+            TypeErrorKind::InvalidTry {
+                operand,
+                return_type,
+            } => {
+                write!(
+                    f,
+                    "`?` on {} needs a function returning a matching Option/Result, found {}",
+                    operand, return_type
+                )
+            }
+            TypeErrorKind::CannotInferClosure { param: Some(name) } => {
+                write!(
+                    f,
+                    "cannot infer type of closure parameter `{}`; add a type annotation",
+                    name
+                )
+            }
+            TypeErrorKind::CannotInferClosure { param: None } => {
+                write!(f, "cannot infer return type of closure; add a type annotation")
+            }
+            TypeErrorKind::GenericFunctionValue { name } => {
+                write!(
+                    f,
+                    "generic function `{}` cannot be used as a value; wrap the call in a closure",
+                    name
+                )
+            }
+            TypeErrorKind::UnknownTrait { name } => write!(f, "unknown trait `{}`", name),
+            TypeErrorKind::UnknownMethod { ty, method } => {
+                write!(f, "no method `{}` on type {}", method, ty)
+            }
+            TypeErrorKind::MissingTraitMethod {
+                trait_name,
+                ty,
+                method,
+            } => {
+                write!(
+                    f,
+                    "impl of trait `{}` for {} is missing method `{}`",
+                    trait_name, ty, method
+                )
+            }
+            TypeErrorKind::NotATraitMethod { trait_name, method } => {
+                write!(f, "method `{}` is not a member of trait `{}`", method, trait_name)
+            }
+            TypeErrorKind::UnsatisfiedBound {
+                ty,
+                trait_name,
+                item,
+            } => {
+                write!(
+                    f,
+                    "type {} does not implement trait `{}` required by `{}`",
+                    ty, trait_name, item
+                )
+            }
+            TypeErrorKind::InvalidCast { from, to } => {
+                write!(
+                    f,
+                    "cannot cast {} to {}: `as` converts between Int, Int32, UInt8 and Float \
+                     (and from Bool to integers); integer casts wrap, Float to integer saturates",
+                    from, to
+                )
+            }
+            TypeErrorKind::IntegerLiteralOutOfRange { value, ty } => {
+                let (min, max) = integer_range(ty);
+                write!(
+                    f,
+                    "integer literal {} out of range for {} ({}..={}); use `as` to wrap it",
+                    value, ty, min, max
+                )
+            }
+            TypeErrorKind::MixedNumericOperands { op, left, right } => {
+                write!(
+                    f,
+                    "binary operator {:?} on {} and {}: numeric types do not mix, convert one \
+                     operand with `as` (Int32/UInt8 arithmetic wraps on overflow)",
+                    op, left, right
+                )
+            }
+            TypeErrorKind::NonConstantInitializer { kind, name, reason } => {
+                write!(
+                    f,
+                    "initializer of {} `{}` is not a constant expression: {}",
+                    kind, name, reason
+                )
+            }
+            TypeErrorKind::InvalidConst { kind, name, reason } => {
+                write!(f, "invalid {} `{}`: {}", kind, name, reason)
+            }
+            TypeErrorKind::AssignToConst { name } => {
+                write!(f, "cannot assign to const `{}`", name)
+            }
+            TypeErrorKind::MissingReturn { function, expected } => write!(
+                f,
+                "missing return in function `{}`: not every path returns a value of type {}",
+                function, expected
+            ),
+            TypeErrorKind::ReservedTypeName { kind, name } => write!(
+                f,
+                "{} `{}` cannot be declared: `{}` is a built-in prelude type",
+                kind, name, name
+            ),
         }
     }
 }
 
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
 impl std::error::Error for TypeError {}
 
+/// Prints a human-readable type error with a source snippet when it has a span.
+pub fn report_type_error(src: &str, error: &TypeError) {
+    eprintln!("{}", error.to_diagnostic().render(Some(src)));
+}
+
 pub fn check_program(program: &Program) -> Result<TypedProgram, TypeError> {
     check_program_with_hosts(program, &[])
 }
 
+/// Type-checks `program` against the given host functions and returns the first error.
 pub fn check_program_with_hosts(
     program: &Program,
     host_functions: &[HostFunction],
 ) -> Result<TypedProgram, TypeError> {
+    check_program_all_errors(program, host_functions).map_err(|mut errors| errors.remove(0))
+}
+
+/// Like [`check_program_with_hosts`], but keeps checking after an error and returns every
+/// error: those of consts and statics in source order, then those of function bodies in
+/// function order.
+///
+/// Other errors in item declarations (duplicate names, unknown types) still stop the check,
+/// since the function bodies are checked against those declarations.
+pub fn check_program_all_errors(
+    program: &Program,
+    host_functions: &[HostFunction],
+) -> Result<TypedProgram, Vec<TypeError>> {
+    let (mut typed, function_signatures) =
+        check_items(program, host_functions).map_err(|err| vec![err])?;
+    let mut errors = Vec::new();
+    let clashing = check_value_names(program, &function_signatures, &mut errors);
+    let (consts, failed_consts) = check_consts(program, &clashing, &mut errors);
+    typed.statics = check_statics(program, &clashing, &consts, &failed_consts, &mut errors);
+    typed.consts = consts;
+    errors.sort_by_key(|err| err.span.as_ref().map_or(0, |span| span.start));
+    let static_types: HashMap<String, Type> = typed
+        .statics
+        .iter()
+        .map(|item| (item.name.clone(), item.ty.clone()))
+        .collect();
+    let traits_env = Traits {
+        decls: &typed.traits,
+        impls: &typed.trait_impls,
+    };
+
+    let mut function_infos = HashMap::new();
+    for func in program.functions() {
+        match infer_function(
+            &function_signatures,
+            &typed.structs,
+            &typed.enums,
+            &traits_env,
+            &typed.consts,
+            &static_types,
+            func,
+        ) {
+            Ok(info) => {
+                function_infos.insert(func.name.clone(), info);
+            }
+            Err(err) => errors.push(err.at(&func.span)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    typed.function_infos = function_infos;
+    Ok(typed)
+}
+
+/// Checks every item declaration except consts and statics, returning the program tables
+/// (without function bodies) and the signatures the bodies are checked against.
+fn check_items(
+    program: &Program,
+    host_functions: &[HostFunction],
+) -> Result<(TypedProgram, HashMap<String, FnSignature>), TypeError> {
     let mut structs = HashMap::new();
     let mut enums = HashMap::new();
     let mut function_signatures = HashMap::new();
@@ -199,6 +653,7 @@ pub fn check_program_with_hosts(
         function_signatures.insert(
             host.name.to_string(),
             FnSignature {
+                type_params: Vec::new(),
                 params: host
                     .params
                     .iter()
@@ -206,125 +661,808 @@ pub fn check_program_with_hosts(
                     .map(|(idx, ty)| Param {
                         name: format!("arg{}", idx + 1),
                         ty: abi_type_to_ast_type(ty),
+                        span: Span::default(),
                     })
                     .collect(),
                 return_type: abi_type_to_ast_type(&host.result),
+                bounds: Vec::new(),
             },
         );
     }
 
+    let mut traits: HashMap<String, TraitDecl> = HashMap::new();
     for item in &program.items {
+        // `List::get`, host functions and `?` produce the prelude types, so they cannot be
+        // shadowed.
+        let declared = match item {
+            TopLevelDecl::Struct(def) => Some(("struct", &def.name, &def.span)),
+            TopLevelDecl::Enum(def) => Some(("enum", &def.name, &def.span)),
+            _ => None,
+        };
+        if let Some((kind, name, span)) = declared
+            && prelude::is_prelude_type(name)
+        {
+            return Err(TypeErrorKind::ReservedTypeName {
+                kind: kind.to_string(),
+                name: name.clone(),
+            }
+            .at(span));
+        }
         match item {
-            TopLevelDecl::Use(_) => {}
+            TopLevelDecl::Use(..)
+            | TopLevelDecl::Function(_)
+            | TopLevelDecl::Impl(_)
+            | TopLevelDecl::Const(_)
+            | TopLevelDecl::Static(_) => {}
             TopLevelDecl::Struct(def) => {
-                if structs.contains_key(&def.name) {
-                    return Err(TypeError::DuplicateType {
+                if let Some(first) = structs.get(&def.name) {
+                    return Err(TypeErrorKind::DuplicateType {
                         kind: "struct".to_string(),
                         name: def.name.clone(),
-                    });
+                    }
+                    .at(&def.span)
+                    .with_label(&first.span, "first declared here"));
                 }
                 structs.insert(def.name.clone(), def.clone());
             }
             TopLevelDecl::Enum(def) => {
-                if enums.contains_key(&def.name) {
-                    return Err(TypeError::DuplicateType {
+                if let Some(first) = enums.get(&def.name) {
+                    return Err(TypeErrorKind::DuplicateType {
                         kind: "enum".to_string(),
                         name: def.name.clone(),
-                    });
+                    }
+                    .at(&def.span)
+                    .with_label(&first.span, "first declared here"));
                 }
                 enums.insert(def.name.clone(), def.clone());
             }
-            TopLevelDecl::Function(func) => {
-                if function_signatures.contains_key(&func.name) {
-                    return Err(TypeError::DuplicateFunction {
-                        name: func.name.clone(),
-                    });
+            TopLevelDecl::Trait(def) => {
+                if let Some(first) = traits.get(&def.name) {
+                    return Err(TypeErrorKind::DuplicateType {
+                        kind: "trait".to_string(),
+                        name: def.name.clone(),
+                    }
+                    .at(&def.span)
+                    .with_label(&first.span, "first declared here"));
+                }
+                traits.insert(def.name.clone(), def.clone());
+            }
+        }
+    }
+
+    // Impl methods are registered under their lowered `Type::method` names.
+    for func in program.functions() {
+        if function_signatures.contains_key(&func.name) {
+            let error = TypeErrorKind::DuplicateFunction {
+                name: func.name.clone(),
+            }
+            .at(&func.span);
+            // Host functions have no source; only a user declaration gets a label.
+            let first = program.functions().find(|other| other.name == func.name);
+            return Err(match first {
+                Some(first) => error.with_label(&first.span, "first declared here"),
+                None => error,
+            });
+        }
+        function_signatures.insert(
+            func.name.clone(),
+            FnSignature {
+                type_params: func.type_params.clone(),
+                params: func.params.clone(),
+                return_type: func.return_type.clone(),
+                bounds: func.bounds.clone(),
+            },
+        );
+    }
+
+    for def in prelude::prelude_enums() {
+        enums.insert(def.name.clone(), def);
+    }
+
+    for item in &program.items {
+        let checked = match item {
+            TopLevelDecl::Struct(def) => check_type_params(&def.type_params).and_then(|()| {
+                def.fields.iter().try_for_each(|(_, ty)| {
+                    validate_named_type(&structs, &enums, &def.type_params, ty)
+                })
+            }),
+            TopLevelDecl::Enum(def) => check_type_params(&def.type_params).and_then(|()| {
+                def.variants.iter().try_for_each(|variant| match variant {
+                    EnumVariant::Unit(_) => Ok(()),
+                    EnumVariant::Tuple(_, ty) => {
+                        validate_named_type(&structs, &enums, &def.type_params, ty)
+                    }
+                    EnumVariant::Struct(_, fields) => fields.iter().try_for_each(|(_, ty)| {
+                        validate_named_type(&structs, &enums, &def.type_params, ty)
+                    }),
+                })
+            }),
+            TopLevelDecl::Trait(def) => {
+                let self_param = ["Self".to_string()];
+                def.methods.iter().try_for_each(|method| {
+                    for param in &method.params {
+                        validate_named_type(&structs, &enums, &self_param, &param.ty)
+                            .map_err(|err| err.at(&param.span))?;
+                    }
+                    validate_named_type(&structs, &enums, &self_param, &method.return_type)
+                        .map_err(|err| err.at(&method.span))
+                })
+            }
+            TopLevelDecl::Impl(imp) => check_impl(imp, &structs, &enums, &traits),
+            TopLevelDecl::Function(_)
+            | TopLevelDecl::Use(..)
+            | TopLevelDecl::Const(_)
+            | TopLevelDecl::Static(_) => Ok(()),
+        };
+        checked.map_err(|err| err.at(item.span()))?;
+    }
+
+    let mut trait_impls = HashSet::new();
+    for item in &program.items {
+        if let TopLevelDecl::Impl(ImplDecl {
+            trait_name: Some(trait_name),
+            target,
+            ..
+        }) = item
+            && let Some(type_name) = target.impl_name()
+            && !trait_impls.insert((trait_name.clone(), type_name.to_string()))
+        {
+            return Err(TypeErrorKind::DuplicateType {
+                kind: "impl".to_string(),
+                name: format!("{} for {}", trait_name, type_name),
+            }
+            .at(item.span()));
+        }
+    }
+
+    for func in program.functions() {
+        check_type_params(&func.type_params).map_err(|err| err.at(&func.span))?;
+        for (param, trait_name) in &func.bounds {
+            if !traits.contains_key(trait_name) {
+                return Err(TypeErrorKind::UnknownTrait {
+                    name: trait_name.clone(),
+                }
+                .at(&func.span));
+            }
+            if !func.type_params.contains(param) {
+                return Err(TypeErrorKind::UnknownType {
+                    name: param.clone(),
                 }
-                function_signatures.insert(
-                    func.name.clone(),
-                    FnSignature {
-                        params: func.params.clone(),
-                        return_type: func.return_type.clone(),
-                    },
-                );
+                .at(&func.span));
             }
         }
     }
 
-    if let Some(main_sig) = function_signatures.get("main") {
-        for (idx, p) in main_sig.params.iter().enumerate() {
+    if let Some(main_fn) = program.functions().find(|func| func.name == "main") {
+        if !main_fn.type_params.is_empty() {
+            return Err(TypeErrorKind::InvalidMainFunction {
+                what: "main must not have type parameters".to_string(),
+            }
+            .at(&main_fn.span));
+        }
+        for (idx, p) in main_fn.params.iter().enumerate() {
             if p.ty != Type::Int {
-                return Err(TypeError::InvalidMainFunction {
+                return Err(TypeErrorKind::InvalidMainFunction {
                     what: format!(
                         "main parameter {} `{}` must be Int, found {}",
                         idx + 1,
                         p.name,
                         p.ty
                     ),
-                });
+                }
+                .at(&p.span));
+            }
+        }
+    }
+
+    let typed = TypedProgram {
+        function_infos: HashMap::new(),
+        structs,
+        enums,
+        traits,
+        trait_impls,
+        consts: HashMap::new(),
+        statics: Vec::new(),
+    };
+    Ok((typed, function_signatures))
+}
+
+/// Checks that functions, consts and statics have distinct names, in source order: each
+/// later declaration reusing a name gets an error labelled with the first one. Returns the
+/// indices into `program.items` of the consts and statics to skip.
+///
+/// Clashes between two functions are already rejected by [`check_items`]; `functions` adds
+/// the host functions, which have no declaration to point at.
+fn check_value_names(
+    program: &Program,
+    functions: &HashMap<String, FnSignature>,
+    errors: &mut Vec<TypeError>,
+) -> HashSet<usize> {
+    let mut first: HashMap<&str, (&str, &Span)> = HashMap::new();
+    let mut clashing = HashSet::new();
+    for (index, item) in program.items.iter().enumerate() {
+        let (kind, name, span) = match item {
+            TopLevelDecl::Function(func) => ("function", &func.name, &func.span),
+            TopLevelDecl::Const(decl) => ("const", &decl.name, &decl.span),
+            TopLevelDecl::Static(decl) => ("static", &decl.name, &decl.span),
+            _ => continue,
+        };
+        let error = TypeErrorKind::DuplicateType {
+            kind: kind.to_string(),
+            name: name.clone(),
+        };
+        match first.get(name.as_str()) {
+            Some(("function", _)) if kind == "function" => {}
+            Some((_, first_span)) => {
+                errors.push(error.at(span).with_label(first_span, "first declared here"));
+                clashing.insert(index);
+            }
+            None if kind != "function"
+                && functions.contains_key(name)
+                && !program.functions().any(|func| &func.name == name) =>
+            {
+                errors.push(error.at(span));
+                clashing.insert(index);
+            }
+            None => {
+                first.insert(name, (kind, span));
+            }
+        }
+    }
+    clashing
+}
+
+/// Evaluates the `const` items in source order and checks their values against the declared
+/// types, pushing one error per failing const onto `errors`.
+///
+/// A failed const still gets a placeholder value of its declared type, so function bodies
+/// using it are checked as usual; its name is returned in the second set. Consts reading a
+/// failed const are skipped silently, as are the `clashing` items of [`check_value_names`].
+fn check_consts(
+    program: &Program,
+    clashing: &HashSet<usize>,
+    errors: &mut Vec<TypeError>,
+) -> (HashMap<String, TypedConst>, HashSet<String>) {
+    let mut consts = HashMap::new();
+    // Values visible to later initializers.
+    let mut values = HashMap::new();
+    let mut failed = HashSet::new();
+    for (index, item) in program.items.iter().enumerate() {
+        let TopLevelDecl::Const(decl) = item else {
+            continue;
+        };
+        if clashing.contains(&index) {
+            continue;
+        }
+        let checked = if reads_any(&decl.value, &failed) {
+            None
+        } else {
+            let supported =
+                [Type::Int, Type::Int32, Type::UInt8, Type::Float, Type::Bool, Type::String];
+            match eval_initializer("const", &decl.name, &decl.ty, &decl.value, &supported, &values)
+            {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    errors.push(err.at(&decl.span));
+                    None
+                }
+            }
+        };
+        let value = match checked {
+            Some(value) => value,
+            None => {
+                failed.insert(decl.name.clone());
+                match placeholder(&decl.ty) {
+                    Some(value) => value,
+                    None => continue,
+                }
+            }
+        };
+        values.insert(decl.name.clone(), value.clone());
+        consts.insert(
+            decl.name.clone(),
+            TypedConst {
+                ty: decl.ty.clone(),
+                value,
+            },
+        );
+    }
+    (consts, failed)
+}
+
+/// Checks `static mut` items in source order: a scalar type and an initializer that consts
+/// can evaluate. Like [`check_consts`], every failing static adds one error to `errors`, and
+/// statics reading a `failed_consts` entry are not reported again.
+fn check_statics(
+    program: &Program,
+    clashing: &HashSet<usize>,
+    consts: &HashMap<String, TypedConst>,
+    failed_consts: &HashSet<String>,
+    errors: &mut Vec<TypeError>,
+) -> Vec<TypedStatic> {
+    let values: HashMap<String, ConstValue> = consts
+        .iter()
+        .map(|(name, item)| (name.clone(), item.value.clone()))
+        .collect();
+    let mut statics: Vec<TypedStatic> = Vec::new();
+    for (index, item) in program.items.iter().enumerate() {
+        let TopLevelDecl::Static(decl) = item else {
+            continue;
+        };
+        if clashing.contains(&index) {
+            continue;
+        }
+        let init = if reads_any(&decl.value, failed_consts) {
+            placeholder(&decl.ty)
+        } else {
+            let supported = [Type::Int, Type::Int32, Type::UInt8, Type::Float, Type::Bool];
+            match eval_initializer("static", &decl.name, &decl.ty, &decl.value, &supported, &values)
+            {
+                Ok(init) => Some(init),
+                Err(err) => {
+                    errors.push(err.at(&decl.span));
+                    placeholder(&decl.ty)
+                }
+            }
+        };
+        if let Some(init) = init {
+            statics.push(TypedStatic {
+                name: decl.name.clone(),
+                ty: decl.ty.clone(),
+                init,
+            });
+        }
+    }
+    statics
+}
+
+/// Evaluates the initializer of a const or static (`kind`) and checks the value against the
+/// declared type `ty`, which must be one of `supported`. `Int32` and `UInt8` take any `Int`
+/// value in their range.
+fn eval_initializer(
+    kind: &str,
+    name: &str,
+    ty: &Type,
+    value: &Expr,
+    supported: &[Type],
+    consts: &HashMap<String, ConstValue>,
+) -> Result<ConstValue, TypeError> {
+    let invalid = |reason: String| -> TypeError {
+        TypeErrorKind::InvalidConst {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            reason,
+        }
+        .into()
+    };
+    if !supported.contains(ty) {
+        let names: Vec<String> = supported.iter().map(Type::to_string).collect();
+        let (last, rest) = names.split_last().expect("supported types are not empty");
+        return Err(invalid(format!(
+            "type {} is not supported, use {} or {}",
+            ty,
+            rest.join(", "),
+            last
+        )));
+    }
+    let init = eval_const(value, consts).map_err(|err| match err {
+        ConstError::NotConstant(reason) => TypeErrorKind::NonConstantInitializer {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            reason,
+        }
+        .into(),
+        ConstError::Invalid(reason) => invalid(reason),
+    })?;
+    match (ty, &init) {
+        (Type::Int32 | Type::UInt8, ConstValue::Int(value)) => {
+            let (min, max) = integer_range(ty);
+            if !(min..=max).contains(value) {
+                return Err(invalid(format!("{} is out of range for {}", value, ty)));
+            }
+        }
+        _ if init.ty() == *ty => {}
+        _ => {
+            return Err(TypeErrorKind::TypeMismatch {
+                expected: ty.clone(),
+                found: init.ty(),
+                what: format!("{} `{}`", kind, name),
+            }
+            .into());
+        }
+    }
+    Ok(init)
+}
+
+/// Stand-in value of type `ty` for a const or static whose initializer failed to check.
+fn placeholder(ty: &Type) -> Option<ConstValue> {
+    match ty {
+        Type::Int | Type::Int32 | Type::UInt8 => Some(ConstValue::Int(0)),
+        Type::Float => Some(ConstValue::Float(0.0)),
+        Type::Bool => Some(ConstValue::Bool(false)),
+        Type::String => Some(ConstValue::String(String::new())),
+        _ => None,
+    }
+}
+
+/// Checks an impl block: its target type and, for trait impls, that the methods match the
+/// trait's signatures with `Self` replaced by the target.
+fn check_impl(
+    imp: &ImplDecl,
+    structs: &HashMap<String, StructDecl>,
+    enums: &HashMap<String, EnumDecl>,
+    traits: &HashMap<String, TraitDecl>,
+) -> Result<(), TypeError> {
+    check_type_params(&imp.type_params)?;
+    validate_named_type(structs, enums, &imp.type_params, &imp.target)?;
+
+    let Some(trait_name) = &imp.trait_name else {
+        return Ok(());
+    };
+    let def = traits.get(trait_name).ok_or_else(|| TypeErrorKind::UnknownTrait {
+        name: trait_name.clone(),
+    })?;
+    let type_name = imp.target.impl_name().unwrap_or_default();
+    let self_binding = HashMap::from([("Self".to_string(), imp.target.clone())]);
+
+    for method in &imp.methods {
+        let short_name = method.name.strip_prefix(&format!("{}::", type_name)).unwrap_or(&method.name);
+        if !def.methods.iter().any(|required| required.name == short_name) {
+            return Err(TypeErrorKind::NotATraitMethod {
+                trait_name: trait_name.clone(),
+                method: short_name.to_string(),
+            }
+            .at(&method.span));
+        }
+    }
+
+    for required in &def.methods {
+        let name = method_function_name(type_name, &required.name);
+        let Some(method) = imp.methods.iter().find(|method| method.name == name) else {
+            return Err(TypeErrorKind::MissingTraitMethod {
+                trait_name: trait_name.clone(),
+                ty: imp.target.clone(),
+                method: required.name.clone(),
+            }
+            .into());
+        };
+        let expected = Type::Function(
+            required.params.iter().map(|param| param.ty.substitute(&self_binding)).collect(),
+            Box::new(required.return_type.substitute(&self_binding)),
+        );
+        let found = Type::Function(
+            method.params.iter().map(|param| param.ty.clone()).collect(),
+            Box::new(method.return_type.clone()),
+        );
+        if expected != found || method.type_params.len() != imp.type_params.len() {
+            return Err(TypeErrorKind::TypeMismatch {
+                expected,
+                found,
+                what: format!("method `{}` of trait `{}`", required.name, trait_name),
+            }
+            .at(&method.span));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
+struct FnSignature {
+    type_params: Vec<String>,
+    params: Vec<Param>,
+    return_type: Type,
+    bounds: Vec<(String, String)>,
+}
+
+/// Trait declarations and the `(trait, type)` pairs implementing them.
+struct Traits<'a> {
+    decls: &'a HashMap<String, TraitDecl>,
+    impls: &'a HashSet<(String, String)>,
+}
+
+/// Item tables and inference state shared by every check inside one function body.
+struct FnEnv<'a> {
+    structs: &'a HashMap<String, StructDecl>,
+    enums: &'a HashMap<String, EnumDecl>,
+    functions: &'a HashMap<String, FnSignature>,
+    traits: &'a Traits<'a>,
+    consts: &'a HashMap<String, TypedConst>,
+    /// Types of the `static mut` globals, readable and assignable everywhere.
+    statics: &'a HashMap<String, Type>,
+    func: &'a FunctionDecl,
+    vars: TypeVars,
+    /// Bounds on type arguments that were still unsolved at their call: `(type, trait, item)`.
+    pending_bounds: RefCell<Vec<(Type, String, String)>>,
+    /// Operators applied to a still unsolved operand (`|x| x * x`): `(type, operator, span)`.
+    pending_operators: RefCell<Vec<(Type, Operator, Span)>>,
+    /// Return types of the closures being checked, innermost last; `?` returns from them.
+    closure_returns: RefCell<Vec<Type>>,
+}
+
+impl FnEnv<'_> {
+    /// Return type that `?` propagates into.
+    fn current_return(&self) -> Type {
+        self.closure_returns
+            .borrow()
+            .last()
+            .cloned()
+            .unwrap_or_else(|| self.func.return_type.clone())
+    }
+}
+
+/// What introduced an inference variable, reported if it is never solved.
+///
+/// Each origin keeps the span of the literal, call or closure that created the variable.
+#[derive(Debug, Clone)]
+enum VarOrigin {
+    EmptyList { span: Span },
+    TypeArgument { item: String, param: String, span: Span },
+    ClosureParam { name: String, span: Span },
+    ClosureReturn { span: Span },
+}
+
+/// Inference variables of one function body; `Type::Var(i)` refers to entry `i`.
+///
+/// Variables are solved by unification as statements are checked, so a binding such as
+/// `let xs = [];` gets its element type from a later `xs.push(1)`.
+#[derive(Debug, Default)]
+struct TypeVars {
+    entries: RefCell<Vec<(Option<Type>, VarOrigin)>>,
+}
+
+impl TypeVars {
+    fn fresh(&self, origin: VarOrigin) -> Type {
+        let mut entries = self.entries.borrow_mut();
+        entries.push((None, origin));
+        Type::Var(entries.len() as u32 - 1)
+    }
+
+    /// `ty` with every solved variable replaced by its solution.
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(id) => {
+                let solution = self.entries.borrow()[*id as usize].0.clone();
+                match solution {
+                    Some(solution) => self.resolve(&solution),
+                    None => ty.clone(),
+                }
+            }
+            Type::Generic(name, args) => {
+                Type::Generic(name.clone(), args.iter().map(|arg| self.resolve(arg)).collect())
+            }
+            Type::Ref(inner) => Type::Ref(Box::new(self.resolve(inner))),
+            Type::List(inner) => Type::List(Box::new(self.resolve(inner))),
+            Type::Tuple(elements) => {
+                Type::Tuple(elements.iter().map(|elem| self.resolve(elem)).collect())
+            }
+            Type::Function(params, ret) => Type::Function(
+                params.iter().map(|param| self.resolve(param)).collect(),
+                Box::new(self.resolve(ret)),
+            ),
+            Type::Int
+            | Type::Int32
+            | Type::UInt8
+            | Type::Float
+            | Type::Bool
+            | Type::String
+            | Type::Named(_)
+            | Type::Unit => ty.clone(),
+        }
+    }
+
+    /// Solves variables so that `a` and `b` become the same type.
+    ///
+    /// On failure no variable is changed, so callers can try a fallback rule.
+    fn unify(&self, a: &Type, b: &Type) -> bool {
+        let snapshot = self.entries.borrow().clone();
+        let unified = self.unify_inner(a, b);
+        if !unified {
+            *self.entries.borrow_mut() = snapshot;
+        }
+        unified
+    }
+
+    fn unify_inner(&self, a: &Type, b: &Type) -> bool {
+        let (a, b) = (self.resolve(a), self.resolve(b));
+        match (&a, &b) {
+            (Type::Var(x), Type::Var(y)) if x == y => true,
+            (Type::Var(id), other) | (other, Type::Var(id)) => {
+                if occurs(*id, other) {
+                    return false;
+                }
+                self.entries.borrow_mut()[*id as usize].0 = Some(other.clone());
+                true
+            }
+            (Type::List(a), Type::List(b)) | (Type::Ref(a), Type::Ref(b)) => self.unify_inner(a, b),
+            (Type::Tuple(a), Type::Tuple(b)) => self.unify_all(a, b),
+            (Type::Generic(a_name, a), Type::Generic(b_name, b)) => {
+                a_name == b_name && self.unify_all(a, b)
+            }
+            (Type::Function(a, a_ret), Type::Function(b, b_ret)) => {
+                self.unify_all(a, b) && self.unify_inner(a_ret, b_ret)
+            }
+            _ => a == b,
+        }
+    }
+
+    fn unify_all(&self, a: &[Type], b: &[Type]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.unify_inner(a, b))
+    }
+
+    /// Whether a `provided` value may be stored where `expected` is declared, solving
+    /// variables on the way. Lists and tuples compare element-wise and references auto-deref.
+    fn assignable(&self, expected: &Type, provided: &Type) -> bool {
+        let (expected, provided) = (self.resolve(expected), self.resolve(provided));
+        match (&expected, &provided) {
+            _ if self.unify(&expected, &provided) => true,
+            (Type::List(exp_elem), Type::List(prov_elem)) => self.assignable(exp_elem, prov_elem),
+            (Type::Tuple(exp_fields), Type::Tuple(prov_fields)) => {
+                exp_fields.len() == prov_fields.len()
+                    && exp_fields
+                        .iter()
+                        .zip(prov_fields)
+                        .all(|(exp_ty, prov_ty)| self.assignable(exp_ty, prov_ty))
             }
+            (_, Type::Ref(inner)) => self.assignable(&expected, inner),
+            _ => false,
         }
     }
 
-    let mut function_infos = HashMap::new();
+    /// Error for the first variable that no statement of the body pinned down.
+    fn unsolved(&self) -> Option<TypeError> {
+        let entries = self.entries.borrow();
+        let (_, origin) = entries.iter().find(|(solution, _)| solution.is_none())?;
+        Some(origin.cannot_infer())
+    }
 
-    for item in &program.items {
-        if let TopLevelDecl::Function(func) = item {
-            let info = infer_function(&function_signatures, &structs, &enums, func)?;
-            function_infos.insert(func.name.clone(), info);
+    /// Turns an error about a value whose type is still unknown (`x.len()` on an unannotated
+    /// closure parameter) into the "cannot infer" error of its variable, so no `Type::Var`
+    /// reaches the message.
+    fn explain(&self, err: TypeError) -> TypeError {
+        let subject = match err.kind.as_ref() {
+            TypeErrorKind::TypeMismatch { found, .. }
+            | TypeErrorKind::NotBooleanCondition { found, .. }
+            | TypeErrorKind::InvalidRefOperand { found, .. }
+            | TypeErrorKind::InvalidFunctionReturn { found, .. } => found,
+            TypeErrorKind::UnknownMethod { ty, .. } | TypeErrorKind::UnsatisfiedBound { ty, .. } => {
+                ty
+            }
+            TypeErrorKind::InvalidCast { from, .. } => from,
+            TypeErrorKind::InvalidTry { operand, .. } => operand,
+            _ => return err,
+        };
+        let Type::Var(id) = self.resolve(auto_deref(subject)) else {
+            return err;
+        };
+        let origin = self.entries.borrow()[id as usize].1.clone();
+        TypeError {
+            span: err.span,
+            labels: err.labels,
+            ..origin.cannot_infer()
         }
     }
+}
 
-    Ok(TypedProgram {
-        function_infos,
-        structs,
-        enums,
-    })
+impl VarOrigin {
+    fn cannot_infer(&self) -> TypeError {
+        match self.clone() {
+            VarOrigin::EmptyList { span } => TypeErrorKind::CannotInferEmptyList {
+                what: "cannot infer type of empty list literal; add a type annotation"
+                    .to_string(),
+            }
+            .at(&span),
+            VarOrigin::TypeArgument { item, param, span } => {
+                TypeErrorKind::CannotInferTypeArgument { item, param }.at(&span)
+            }
+            VarOrigin::ClosureParam { name, span } => {
+                TypeErrorKind::CannotInferClosure { param: Some(name) }.at(&span)
+            }
+            VarOrigin::ClosureReturn { span } => {
+                TypeErrorKind::CannotInferClosure { param: None }.at(&span)
+            }
+        }
+    }
 }
 
-#[derive(Clone)]
-struct FnSignature {
-    params: Vec<Param>,
-    return_type: Type,
+/// Whether variable `id` occurs in `ty`, which would make a solution infinite.
+fn occurs(id: u32, ty: &Type) -> bool {
+    match ty {
+        Type::Var(found) => *found == id,
+        Type::Generic(_, args) | Type::Tuple(args) => args.iter().any(|arg| occurs(id, arg)),
+        Type::Ref(inner) | Type::List(inner) => occurs(id, inner),
+        Type::Function(params, ret) => params.iter().any(|param| occurs(id, param)) || occurs(id, ret),
+        Type::Int
+        | Type::Int32
+        | Type::UInt8
+        | Type::Float
+        | Type::Bool
+        | Type::String
+        | Type::Named(_)
+        | Type::Unit => false,
+    }
 }
 
 fn infer_function(
     function_signatures: &HashMap<String, FnSignature>,
     structs: &HashMap<String, StructDecl>,
     enums: &HashMap<String, EnumDecl>,
+    traits: &Traits,
+    consts: &HashMap<String, TypedConst>,
+    statics: &HashMap<String, Type>,
     func: &FunctionDecl,
 ) -> Result<TypedFunction, TypeError> {
     let mut local_map = HashMap::new();
+    // Every binding gets its own slot; parameters occupy the first ones.
     let mut locals = Vec::new();
 
-    validate_named_type(structs, enums, &func.return_type)?;
+    validate_named_type(structs, enums, &func.type_params, &func.return_type)?;
 
     for (idx, param) in func.params.iter().enumerate() {
-        validate_named_type(structs, enums, &param.ty)?;
+        validate_named_type(structs, enums, &func.type_params, &param.ty)
+            .map_err(|err| err.at(&param.span))?;
         if local_map
             .insert(param.name.clone(), (idx as u32, param.ty.clone()))
             .is_some()
         {
-            return Err(TypeError::DuplicateVariable {
+            return Err(TypeErrorKind::DuplicateVariable {
                 name: param.name.clone(),
-            });
+            }
+            .at(&param.span));
         }
+        locals.push((param.name.clone(), param.ty.clone()));
     }
 
+    check_loop_control(&func.body, false)?;
+    let env = FnEnv {
+        structs,
+        enums,
+        functions: function_signatures,
+        traits,
+        consts,
+        statics,
+        func,
+        vars: TypeVars::default(),
+        pending_bounds: RefCell::new(Vec::new()),
+        pending_operators: RefCell::new(Vec::new()),
+        closure_returns: RefCell::new(Vec::new()),
+    };
     for stmt in &func.body {
-        infer_stmt(
-            stmt,
-            &mut local_map,
-            &mut locals,
-            structs,
-            enums,
-            function_signatures,
-            &func.return_type,
-        )?;
+        infer_stmt(stmt, &mut local_map, &mut locals, &env)
+            .map_err(|err| env.vars.explain(err.at(&stmt.span)))?;
+    }
+    if func.return_type != Type::Unit && !always_returns(&func.body) {
+        let error = TypeErrorKind::MissingReturn {
+            function: func.name.clone(),
+            expected: func.return_type.clone(),
+        }
+        .at(&func.span);
+        return Err(match func.body.last() {
+            Some(last) => error.with_label(&last.span, "the function can end after this statement"),
+            None => error,
+        });
+    }
+
+    if let Some(err) = env.vars.unsolved() {
+        return Err(err);
+    }
+    for (ty, operator, span) in env.pending_operators.take() {
+        operator_result(&operator, &env.vars.resolve(&ty)).map_err(|err| err.at(&span))?;
     }
+    for (ty, trait_name, item) in env.pending_bounds.take() {
+        check_bound(&ty, &trait_name, &item, &env)?;
+    }
+    for (_, ty) in &mut locals {
+        *ty = env.vars.resolve(ty);
+    }
+    for (_, ty) in local_map.values_mut() {
+        *ty = env.vars.resolve(ty);
+    }
+
+    let locals = locals.split_off(func.params.len());
 
     Ok(TypedFunction {
         name: func.name.clone(),
+        type_params: func.type_params.clone(),
         params: func.params.clone(),
         return_type: func.return_type.clone(),
         locals,
@@ -332,33 +1470,144 @@ fn infer_function(
     })
 }
 
+/// Whether every path through `body` ends in a `return` or an endless `while true` loop.
+///
+/// Codegen relies on this for non-`Unit` functions: control never reaches the end of their
+/// body or the end of an `if` whose branches both return.
+pub fn always_returns(body: &[Stmt]) -> bool {
+    body.iter().any(|stmt| never_completes(stmt, false))
+}
+
+/// Whether control never reaches the statement after `stmt`: it returns, is an endless
+/// `while true` loop, or leaves the enclosing loop with `break`/`continue`.
+///
+/// This is the divergence analysis behind [`always_returns`] as well; the lint uses it to
+/// find unreachable statements.
+pub fn diverges(stmt: &Stmt) -> bool {
+    never_completes(stmt, true)
+}
+
+/// `loop_exits` decides whether `break` and `continue` count as leaving.
+fn never_completes(stmt: &Stmt, loop_exits: bool) -> bool {
+    let block = |body: &[Stmt]| body.iter().any(|stmt| never_completes(stmt, loop_exits));
+    match &stmt.kind {
+        StmtKind::Return(_) => true,
+        StmtKind::Break | StmtKind::Continue => loop_exits,
+        StmtKind::If {
+            then_block,
+            else_block,
+            ..
+        } => block(then_block) && block(else_block),
+        StmtKind::While { cond, body } => {
+            matches!(cond.kind, ExprKind::Bool(true)) && !breaks_out(body)
+        }
+        _ => false,
+    }
+}
+
+/// Whether a `break` in `body` leaves the loop owning `body`; nested loops catch their own.
+fn breaks_out(body: &[Stmt]) -> bool {
+    body.iter().any(|stmt| match &stmt.kind {
+        StmtKind::Break => true,
+        StmtKind::If {
+            then_block,
+            else_block,
+            ..
+        } => breaks_out(then_block) || breaks_out(else_block),
+        _ => false,
+    })
+}
+
+/// Rejects `break`/`continue` that are not nested inside a `while` or `for` body.
+fn check_loop_control(body: &[Stmt], in_loop: bool) -> Result<(), TypeError> {
+    for stmt in body {
+        match &stmt.kind {
+            StmtKind::Break | StmtKind::Continue if !in_loop => {
+                let keyword = if matches!(stmt.kind, StmtKind::Break) { "break" } else { "continue" };
+                return Err(TypeErrorKind::LoopControlOutsideLoop {
+                    keyword: keyword.to_string(),
+                }
+                .at(&stmt.span));
+            }
+            StmtKind::If {
+                then_block,
+                else_block,
+                ..
+            } => {
+                check_loop_control(then_block, in_loop)?;
+                check_loop_control(else_block, in_loop)?;
+            }
+            StmtKind::While { body, .. } | StmtKind::For { body, .. } => check_loop_control(body, true)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Rejects repeated names in a type parameter list.
+fn check_type_params(type_params: &[String]) -> Result<(), TypeError> {
+    let mut seen = HashSet::new();
+    for param in type_params {
+        if !seen.insert(param) {
+            return Err(TypeErrorKind::DuplicateType {
+                kind: "type parameter".to_string(),
+                name: param.clone(),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every name in `ty` is a declared struct/enum or one of `type_params`,
+/// and that generic types get the right number of type arguments.
 fn validate_named_type(
     structs: &HashMap<String, StructDecl>,
     enums: &HashMap<String, EnumDecl>,
+    type_params: &[String],
     ty: &Type,
 ) -> Result<(), TypeError> {
     match ty {
-        Type::Named(name) => {
-            if structs.contains_key(name) || enums.contains_key(name) {
-                Ok(())
-            } else {
-                Err(TypeError::UnknownType { name: name.clone() })
+        Type::Named(name) if type_params.contains(name) => Ok(()),
+        Type::Named(name) | Type::Generic(name, _) => {
+            let args: &[Type] = match ty {
+                Type::Generic(_, args) => args,
+                _ => &[],
+            };
+            let expected = match (structs.get(name), enums.get(name)) {
+                (Some(def), _) => def.type_params.len(),
+                (None, Some(def)) => def.type_params.len(),
+                (None, None) => {
+                    return Err(TypeErrorKind::UnknownType { name: name.clone() }.into());
+                }
+            };
+            if expected != args.len() {
+                return Err(TypeErrorKind::WrongTypeArgumentCount {
+                    name: name.clone(),
+                    expected,
+                    found: args.len(),
+                }
+                .into());
+            }
+            for arg in args {
+                validate_named_type(structs, enums, type_params, arg)?;
             }
+            Ok(())
         }
         Type::String => Ok(()),
-        Type::Ref(inner) => validate_named_type(structs, enums, inner),
-        Type::List(inner) => validate_named_type(structs, enums, inner),
+        Type::Ref(inner) => validate_named_type(structs, enums, type_params, inner),
+        Type::List(inner) => validate_named_type(structs, enums, type_params, inner),
         Type::Tuple(elements) => {
             for elem in elements {
-                validate_named_type(structs, enums, elem)?;
+                validate_named_type(structs, enums, type_params, elem)?;
             }
             Ok(())
         }
         Type::Function(args, ret) => {
             for arg in args {
-                validate_named_type(structs, enums, arg)?;
+                validate_named_type(structs, enums, type_params, arg)?;
             }
-            validate_named_type(structs, enums, ret)
+            validate_named_type(structs, enums, type_params, ret)
         }
         _ => Ok(()),
     }
@@ -368,106 +1617,116 @@ fn infer_stmt(
     stmt: &Stmt,
     locals: &mut HashMap<String, (u32, Type)>,
     locals_decl: &mut Vec<(String, Type)>,
-    structs: &HashMap<String, StructDecl>,
-    enums: &HashMap<String, EnumDecl>,
-    functions: &HashMap<String, FnSignature>,
-    expected_return: &Type,
+    env: &FnEnv,
 ) -> Result<(), TypeError> {
-    match stmt {
-        Stmt::Let { name, ty, value } => {
-            if locals.contains_key(name) {
-                return Err(TypeError::DuplicateVariable {
-                    name: name.clone(),
-                });
-            }
-
+    let expected_return = &env.func.return_type;
+    match &stmt.kind {
+        StmtKind::Let { name, ty, value, .. } => {
             let value_type = match ty {
                 Some(annotated) => infer_expr_with_expected(
                     value,
                     Some(annotated),
                     locals,
-                    structs,
-                    enums,
-                    functions,
+                    env,
                 )?,
-                None => infer_expr(value, locals, structs, enums, functions)?,
+                None => infer_expr(value, locals, env)?,
             };
             let bound_type = match ty {
                 Some(annotated) => {
-                    validate_named_type(structs, enums, annotated)?;
-                    if !is_assignable(annotated, &value_type) {
-                        return Err(TypeError::TypeMismatch {
+                    validate_named_type(env.structs, env.enums, &env.func.type_params, annotated)?;
+                    if !env.vars.assignable(annotated, &value_type) {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: annotated.clone(),
                             found: value_type,
                             what: format!("`{}` variable declaration", name),
-                        });
+                        }
+                        .at(&value.span));
                     }
                     annotated.clone()
                 }
                 None => value_type,
             };
 
-            let index = locals.len() as u32;
-            locals.insert(name.clone(), (index, bound_type));
-            let bound = locals
-                .get(name)
-                .map(|(_, ty)| ty.clone())
-                .ok_or_else(|| TypeError::UnknownVariable {
-                    name: name.clone(),
-                })?;
-            locals_decl.push((name.clone(), bound));
+            // A repeated `let` shadows the previous binding from here on.
+            bind_local(name, bound_type, locals, locals_decl);
             Ok(())
         }
-        Stmt::Return(expr_opt) => match expr_opt {
+        StmtKind::Return(expr_opt) => match expr_opt {
             Some(expr) => {
                 if expected_return == &Type::Unit {
-                    return Err(TypeError::InvalidFunctionReturn {
+                    return Err(TypeErrorKind::InvalidFunctionReturn {
                         function: "<return>".to_string(),
                         expected: Type::Unit,
                         found: infer_expr_with_expected(
                             expr,
                             Some(expected_return),
                             locals,
-                            structs,
-                            enums,
-                            functions,
+                            env,
                         )?,
-                    });
+                    }
+                    .at(&expr.span));
                 }
 
                 let found = infer_expr_with_expected(
                     expr,
                     Some(expected_return),
                     locals,
-                    structs,
-                    enums,
-                    functions,
+                    env,
                 )?;
-                if !is_assignable(expected_return, &found) {
-                    return Err(TypeError::InvalidFunctionReturn {
+                if !env.vars.assignable(expected_return, &found) {
+                    return Err(TypeErrorKind::InvalidFunctionReturn {
                         function: "<return>".to_string(),
                         expected: expected_return.clone(),
                         found,
-                    });
+                    }
+                    .at(&expr.span));
                 }
                 Ok(())
             }
             None => {
                 if expected_return != &Type::Unit {
-                    return Err(TypeError::InvalidFunctionReturn {
+                    return Err(TypeErrorKind::InvalidFunctionReturn {
                         function: "<return>".to_string(),
                         expected: expected_return.clone(),
                         found: Type::Unit,
-                    });
+                    }
+                    .into());
                 }
                 Ok(())
             }
         },
-        Stmt::Expr(expr) => {
-            let _ = infer_expr(expr, locals, structs, enums, functions)?;
+        StmtKind::Expr(expr) => {
+            let _ = infer_expr(expr, locals, env)?;
+            Ok(())
+        }
+        StmtKind::Assign { target, op, value } => {
+            if let ExprKind::Local(name) = &target.kind
+                && !locals.contains_key(name)
+                && env.consts.contains_key(name)
+            {
+                return Err(TypeErrorKind::AssignToConst { name: name.clone() }.into());
+            }
+            let target_ty = infer_expr(target, locals, env)?;
+            let value_ty = match op {
+                Some(op) => infer_binary(op, target, value, None, &stmt.span, locals, env)?,
+                None => infer_expr_with_expected(
+                    value,
+                    Some(&target_ty),
+                    locals,
+                    env,
+                )?,
+            };
+            if !env.vars.assignable(&target_ty, &value_ty) {
+                return Err(TypeErrorKind::TypeMismatch {
+                    expected: target_ty,
+                    found: value_ty,
+                    what: format!("assignment to {}", describe_place(target)),
+                }
+                .at(&value.span));
+            }
             Ok(())
         }
-        Stmt::If {
+        StmtKind::If {
             cond,
             then_block,
             else_block,
@@ -476,139 +1735,433 @@ fn infer_stmt(
                 cond,
                 Some(&Type::Bool),
                 locals,
-                structs,
-                enums,
-                functions,
+                env,
             )?;
-            if cond_ty != Type::Bool {
-                return Err(TypeError::NotBooleanCondition {
+            if !env.vars.unify(&Type::Bool, &cond_ty) {
+                return Err(TypeErrorKind::NotBooleanCondition {
                     what: "if condition".to_string(),
                     found: cond_ty,
-                });
-            }
-            for stmt in then_block {
-                infer_stmt(
-                    stmt,
-                    locals,
-                    locals_decl,
-                    structs,
-                    enums,
-                    functions,
-                    expected_return,
-                )?;
+                }
+                .at(&cond.span));
             }
-            for stmt in else_block {
-                infer_stmt(
-                    stmt,
-                    locals,
+            for block in [then_block, else_block] {
+                infer_block(
+                    block,
+                    locals.clone(),
                     locals_decl,
-                    structs,
-                    enums,
-                    functions,
-                    expected_return,
+                    env,
                 )?;
             }
             Ok(())
         }
-        Stmt::While { cond, body } => {
+        StmtKind::While { cond, body } => {
             let cond_ty = infer_expr_with_expected(
                 cond,
                 Some(&Type::Bool),
                 locals,
-                structs,
-                enums,
-                functions,
+                env,
             )?;
-            if cond_ty != Type::Bool {
-                return Err(TypeError::NotBooleanCondition {
+            if !env.vars.unify(&Type::Bool, &cond_ty) {
+                return Err(TypeErrorKind::NotBooleanCondition {
                     what: "while condition".to_string(),
                     found: cond_ty,
-                });
-            }
-            for stmt in body {
-                infer_stmt(
-                    stmt,
-                    locals,
-                    locals_decl,
-                    structs,
-                    enums,
-                    functions,
-                    expected_return,
-                )?;
+                }
+                .at(&cond.span));
             }
-            Ok(())
+            infer_block(
+                body,
+                locals.clone(),
+                locals_decl,
+                env,
+            )
+        }
+        StmtKind::For { var, iter, body, .. } => {
+            let item_ty = match &iter.kind {
+                ExprKind::Range { start, end } => {
+                    for bound in [start, end] {
+                        let bound_ty = infer_expr_with_expected(
+                            bound,
+                            Some(&Type::Int),
+                            locals,
+                            env,
+                        )?;
+                        if auto_deref(&bound_ty) != &Type::Int {
+                            return Err(TypeErrorKind::TypeMismatch {
+                                expected: Type::Int,
+                                found: bound_ty,
+                                what: "range bound".to_string(),
+                            }
+                            .at(&bound.span));
+                        }
+                    }
+                    Type::Int
+                }
+                _ => {
+                    let iter_ty = infer_expr(iter, locals, env)?;
+                    match auto_deref(&iter_ty) {
+                        Type::List(item) => (**item).clone(),
+                        _ => {
+                            return Err(TypeErrorKind::TypeMismatch {
+                                expected: Type::Named("List or range".to_string()),
+                                found: iter_ty,
+                                what: "for loop iterable".to_string(),
+                            }
+                            .at(&iter.span));
+                        }
+                    }
+                }
+            };
+
+            // The loop variable is only visible inside the body.
+            let mut scope = locals.clone();
+            bind_local(var, item_ty, &mut scope, locals_decl);
+            infer_block(
+                body,
+                scope,
+                locals_decl,
+                env,
+            )
         }
+        StmtKind::Break | StmtKind::Continue => Ok(()),
+    }
+}
+
+/// Checks a nested block in its own scope: bindings made inside do not leak out.
+fn infer_block(
+    body: &[Stmt],
+    mut scope: HashMap<String, (u32, Type)>,
+    locals_decl: &mut Vec<(String, Type)>,
+    env: &FnEnv,
+) -> Result<(), TypeError> {
+    for stmt in body {
+        infer_stmt(
+            stmt,
+            &mut scope,
+            locals_decl,
+            env,
+        )
+        .map_err(|err| err.at(&stmt.span))?;
     }
+    Ok(())
+}
+
+/// Declares a new binding with its own slot, shadowing any visible one of the same name.
+fn bind_local(
+    name: &str,
+    ty: Type,
+    scope: &mut HashMap<String, (u32, Type)>,
+    locals_decl: &mut Vec<(String, Type)>,
+) {
+    let slot = locals_decl.len() as u32;
+    locals_decl.push((name.to_string(), ty.clone()));
+    scope.insert(name.to_string(), (slot, ty));
 }
 
 fn infer_expr(
     expr: &Expr,
     locals: &HashMap<String, (u32, Type)>,
-    structs: &HashMap<String, StructDecl>,
-    enums: &HashMap<String, EnumDecl>,
-    functions: &HashMap<String, FnSignature>,
+    env: &FnEnv,
 ) -> Result<Type, TypeError> {
-    infer_expr_with_expected(expr, None, locals, structs, enums, functions)
+    infer_expr_with_expected(expr, None, locals, env)
 }
 
+/// Infers the type of `expr`, using `expected` as a hint where the expression alone is
+/// ambiguous. The result has every inference variable solved so far substituted.
 fn infer_expr_with_expected(
     expr: &Expr,
     expected: Option<&Type>,
     locals: &HashMap<String, (u32, Type)>,
-    structs: &HashMap<String, StructDecl>,
-    enums: &HashMap<String, EnumDecl>,
-    functions: &HashMap<String, FnSignature>,
+    env: &FnEnv,
+) -> Result<Type, TypeError> {
+    let expected = expected.map(|ty| env.vars.resolve(ty));
+    let found =
+        infer_expr_node(expr, expected.as_ref(), locals, env).map_err(|err| err.at(&expr.span))?;
+    Ok(env.vars.resolve(&found))
+}
+
+fn infer_expr_node(
+    expr: &Expr,
+    expected: Option<&Type>,
+    locals: &HashMap<String, (u32, Type)>,
+    env: &FnEnv,
 ) -> Result<Type, TypeError> {
-    match expr {
-        Expr::Int(_) => Ok(Type::Int),
-        Expr::Float(_) => Ok(Type::Float),
-        Expr::Bool(_) => Ok(Type::Bool),
-        Expr::String(_) => Ok(Type::String),
-        Expr::Local(name) => locals
-            .get(name)
-            .map(|(_, ty)| ty.clone())
-            .ok_or_else(|| TypeError::UnknownVariable { name: name.clone() }),
-        Expr::Ref(inner) => {
-            let inner_type = infer_expr_with_expected(inner, None, locals, structs, enums, functions)?;
+    match &expr.kind {
+        // Integer literals take a smaller integer type from context (`let b: UInt8 = 200;`).
+        ExprKind::Int(value) => match expected {
+            Some(ty @ (Type::Int32 | Type::UInt8)) => {
+                let (min, max) = integer_range(ty);
+                if !(min..=max).contains(value) {
+                    return Err(TypeErrorKind::IntegerLiteralOutOfRange {
+                        value: *value,
+                        ty: ty.clone(),
+                    }
+                    .into());
+                }
+                Ok(ty.clone())
+            }
+            _ => Ok(Type::Int),
+        },
+        ExprKind::Float(_) => Ok(Type::Float),
+        ExprKind::Bool(_) => Ok(Type::Bool),
+        ExprKind::String(_) => Ok(Type::String),
+        ExprKind::Interpolation(parts) => {
+            for part in parts {
+                let InterpolationPart::Expr(expr) = part else {
+                    continue;
+                };
+                let ty = infer_expr_with_expected(expr, None, locals, env)?;
+                if matches!(ty, Type::Unit | Type::Function(_, _)) {
+                    return Err(TypeErrorKind::TypeMismatch {
+                        expected: Type::Named("a printable value".to_string()),
+                        found: ty,
+                        what: "interpolated expression".to_string(),
+                    }
+                    .into());
+                }
+            }
+            Ok(Type::String)
+        }
+        ExprKind::Local(name) => {
+            if let Some((_, ty)) = locals.get(name) {
+                return Ok(ty.clone());
+            }
+            if let Some(item) = env.consts.get(name) {
+                return Ok(item.ty.clone());
+            }
+            if let Some(ty) = env.statics.get(name) {
+                return Ok(ty.clone());
+            }
+            // A function name used as a value.
+            let sig = env
+                .functions
+                .get(name)
+                .ok_or_else(|| TypeErrorKind::UnknownVariable { name: name.clone() })?;
+            if !sig.type_params.is_empty() {
+                return Err(TypeErrorKind::GenericFunctionValue { name: name.clone() }.into());
+            }
+            Ok(Type::Function(
+                sig.params.iter().map(|param| param.ty.clone()).collect(),
+                Box::new(sig.return_type.clone()),
+            ))
+        }
+        ExprKind::Ref(inner) => {
+            let inner_type = infer_expr_with_expected(inner, None, locals, env)?;
             Ok(Type::Ref(Box::new(inner_type)))
         }
-        Expr::Deref(inner) => {
-            let inner_type = infer_expr_with_expected(inner, None, locals, structs, enums, functions)?;
+        ExprKind::Deref(inner) => {
+            let inner_type = infer_expr_with_expected(inner, None, locals, env)?;
             match inner_type {
                 Type::Ref(inner) => Ok(*inner),
-                _ => Err(TypeError::InvalidRefOperand {
+                _ => Err(TypeErrorKind::InvalidRefOperand {
                     found: inner_type,
                     what: "deref".to_string(),
-                }),
+                }
+                .into()),
+            }
+        }
+        ExprKind::Not(inner) => {
+            let inner_type = infer_expr_with_expected(inner, Some(&Type::Bool), locals, env)?;
+            if !env.vars.unify(&Type::Bool, &inner_type) {
+                return Err(TypeErrorKind::TypeMismatch {
+                    expected: Type::Bool,
+                    found: inner_type,
+                    what: "operand of `!`".to_string(),
+                }
+                .into());
+            }
+            Ok(Type::Bool)
+        }
+        ExprKind::Neg(inner) => {
+            let inner_type = infer_expr_with_expected(inner, expected, locals, env)?;
+            apply_operator(Operator::Neg, inner_type, &expr.span, env)
+        }
+        ExprKind::Try(inner) => {
+            let operand = infer_expr_with_expected(inner, None, locals, env)?;
+            // A closure whose return type is still open returns the operand's enum.
+            let current_return = env.current_return();
+            if let (Type::Var(_), Type::Generic(name, args)) =
+                (env.vars.resolve(&current_return), &operand)
+            {
+                let mut open_args = args.clone();
+                if let Some(payload) = open_args.first_mut() {
+                    *payload = env.vars.fresh(VarOrigin::ClosureReturn {
+                        span: expr.span.clone(),
+                    });
+                }
+                env.vars
+                    .unify(&current_return, &Type::Generic(name.clone(), open_args));
+            }
+            let expected_return = &env.vars.resolve(&current_return);
+            let payload = try_payload_type(&operand).ok_or_else(|| TypeErrorKind::TypeMismatch {
+                expected: Type::Named("Option or Result".to_string()),
+                found: operand.clone(),
+                what: "operand of `?`".to_string(),
+            })?;
+            // `None` propagates into any `Option`, `Err(e)` into a `Result` with the same error type.
+            let compatible = match (&operand, expected_return) {
+                (Type::Generic(name, _), Type::Generic(ret, _))
+                    if name == prelude::OPTION && ret == prelude::OPTION =>
+                {
+                    true
+                }
+                (Type::Generic(name, args), Type::Generic(ret, ret_args))
+                    if name == prelude::RESULT && ret == prelude::RESULT =>
+                {
+                    matches!(
+                        (ret_args.get(1), args.get(1)),
+                        (Some(ret_err), Some(err)) if env.vars.unify(ret_err, err)
+                    )
+                }
+                _ => false,
+            };
+            if !compatible {
+                return Err(TypeErrorKind::InvalidTry {
+                    operand,
+                    return_type: expected_return.clone(),
+                }
+                .into());
+            }
+            Ok(payload.clone())
+        }
+        ExprKind::Binary { op, left, right } => {
+            infer_binary(op, left, right, expected, &expr.span, locals, env)
+        }
+        ExprKind::Call { name, args } if locals.contains_key(name) => {
+            let callee = env.vars.resolve(&locals[name].1);
+            let Type::Function(params, ret) = callee else {
+                return Err(TypeErrorKind::TypeMismatch {
+                    expected: Type::Named("function".to_string()),
+                    found: callee,
+                    what: format!("call of `{}`", name),
+                }
+                .into());
+            };
+            if params.len() != args.len() {
+                return Err(TypeErrorKind::TypeMismatch {
+                    expected: Type::Named(format!("{} arguments", params.len())),
+                    found: Type::Named(format!("{} arguments", args.len())),
+                    what: format!("function value `{}` argument count", name),
+                }
+                .into());
+            }
+            for (arg, param) in args.iter().zip(&params) {
+                let found = infer_expr_with_expected(arg, Some(param), locals, env)?;
+                if !env.vars.assignable(param, &found) {
+                    return Err(TypeErrorKind::TypeMismatch {
+                        expected: param.clone(),
+                        found,
+                        what: format!("argument for `{}`", name),
+                    }
+                    .at(&arg.span));
+                }
             }
+            Ok(*ret)
         }
-        Expr::Binary { op, left, right } => infer_binary(op, left, right, locals, structs, enums, functions),
-        Expr::Call { name, args } => {
-            let sig = functions
+        ExprKind::Call { name, args } => {
+            let sig = env.functions
                 .get(name)
-                .ok_or_else(|| TypeError::UnknownFunction { name: name.clone() })?;
+                .ok_or_else(|| TypeErrorKind::UnknownFunction { name: name.clone() })?;
 
             if sig.params.len() != args.len() {
-                return Err(TypeError::TypeMismatch {
+                return Err(TypeErrorKind::TypeMismatch {
                     expected: Type::Named(format!("{} arguments", sig.params.len())),
                     found: Type::Named(format!("{} arguments", args.len())),
                     what: format!("function `{}` argument count", name),
-                });
+                }
+                .into());
             }
 
-            for (arg, param) in args.iter().zip(sig.params.iter()) {
-                let arg_type = infer_expr_with_expected(arg, Some(&param.ty), locals, structs, enums, functions)?;
-                if !is_assignable(&param.ty, &arg_type) {
-                    return Err(TypeError::TypeMismatch {
-                        expected: param.ty.clone(),
-                        found: arg_type,
-                        what: format!("argument for `{}`", name),
-                    });
+            let slots: Vec<_> = args
+                .iter()
+                .zip(&sig.params)
+                .map(|(arg, param)| (arg, &param.ty, format!("argument for `{}`", name)))
+                .collect();
+            let type_args = infer_type_args(
+                (name, &expr.span),
+                &sig.type_params,
+                &slots,
+                &sig.return_type,
+                expected,
+                locals,
+                env,
+            )?;
+            let bindings = type_bindings(&sig.type_params, &type_args);
+            for (param, trait_name) in &sig.bounds {
+                let ty = env.vars.resolve(&bindings[param]);
+                if matches!(ty, Type::Var(_)) {
+                    // Decided once the rest of the body has solved the argument.
+                    env.pending_bounds
+                        .borrow_mut()
+                        .push((ty, trait_name.clone(), name.clone()));
+                } else {
+                    check_bound(&ty, trait_name, name, env)?;
+                }
+            }
+            Ok(sig.return_type.substitute(&bindings))
+        }
+        ExprKind::Cast { expr: inner, ty } => {
+            let from = infer_expr_with_expected(inner, None, locals, env)?;
+            let from = auto_deref(&from).clone();
+            let valid = (from.is_numeric() || from == Type::Bool) && ty.is_numeric()
+                && !(from == Type::Bool && *ty == Type::Float);
+            if !valid {
+                return Err(TypeErrorKind::InvalidCast {
+                    from,
+                    to: ty.clone(),
+                }
+                .into());
+            }
+            Ok(ty.clone())
+        }
+        ExprKind::Closure { params, body } => {
+            let (expected_params, expected_ret) = match expected {
+                Some(Type::Function(params, ret)) => (Some(params), Some(ret.as_ref())),
+                _ => (None, None),
+            };
+            let mut scope = locals.clone();
+            let mut param_tys = Vec::with_capacity(params.len());
+            for (idx, param) in params.iter().enumerate() {
+                let ty = match &param.ty {
+                    Some(annotated) => {
+                        validate_named_type(env.structs, env.enums, &env.func.type_params, annotated)?;
+                        annotated.clone()
+                    }
+                    None => expected_params
+                        .filter(|expected| expected.len() == params.len())
+                        .map(|expected| expected[idx].clone())
+                        .unwrap_or_else(|| {
+                            env.vars.fresh(VarOrigin::ClosureParam {
+                                name: param.name.clone(),
+                                span: expr.span.clone(),
+                            })
+                        }),
+                };
+                // Closure parameters shadow outer locals inside the body.
+                scope.insert(param.name.clone(), (u32::MAX, ty.clone()));
+                param_tys.push(ty);
+            }
+
+            let ret = match expected_ret {
+                Some(ret) => ret.clone(),
+                None => env.vars.fresh(VarOrigin::ClosureReturn {
+                    span: expr.span.clone(),
+                }),
+            };
+            env.closure_returns.borrow_mut().push(ret.clone());
+            let body_ty = infer_expr_with_expected(body, Some(&ret), &scope, env);
+            env.closure_returns.borrow_mut().pop();
+            let body_ty = body_ty?;
+            if !env.vars.assignable(&ret, &body_ty) {
+                return Err(TypeErrorKind::TypeMismatch {
+                    expected: ret,
+                    found: body_ty,
+                    what: "closure body".to_string(),
                 }
+                .into());
             }
-            Ok(sig.return_type.clone())
+            Ok(Type::Function(param_tys, Box::new(ret)))
         }
-        Expr::MethodCall {
+        ExprKind::MethodCall {
             receiver,
             name,
             args,
@@ -617,106 +2170,108 @@ fn infer_expr_with_expected(
                 receiver,
                 None,
                 locals,
-                structs,
-                enums,
-                functions,
+                env,
             )?;
 
             match (name.as_str(), receiver_ty.clone()) {
                 ("len", Type::List(_)) => {
                     if !args.is_empty() {
-                        return Err(TypeError::TypeMismatch {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: Type::Named("0 arguments".to_string()),
                             found: Type::Named(format!("{} arguments", args.len())),
                             what: "method `len` call".to_string(),
-                        });
+                        }
+                        .into());
                     }
                     Ok(Type::Int)
                 }
                 ("get", Type::List(inner)) => {
                     if args.len() != 1 {
-                        return Err(TypeError::TypeMismatch {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: Type::Named("1 argument".to_string()),
                             found: Type::Named(format!("{} arguments", args.len())),
                             what: "method `get` call".to_string(),
-                        });
+                        }
+                        .into());
                     }
                     let index = infer_expr_with_expected(
                         &args[0],
                         Some(&Type::Int),
                         locals,
-                        structs,
-                        enums,
-                        functions,
+                        env,
                     )?;
-                    if index != Type::Int {
-                        return Err(TypeError::TypeMismatch {
+                    if !env.vars.unify(&Type::Int, &index) {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: Type::Int,
                             found: index,
                             what: "method `get` index".to_string(),
-                        });
+                        }
+                        .into());
                     }
-                    Ok(*inner)
+                    Ok(option_of(*inner))
                 }
                 ("push", Type::List(inner)) => {
                     if args.len() != 1 {
-                        return Err(TypeError::TypeMismatch {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: Type::Named("1 argument".to_string()),
                             found: Type::Named(format!("{} arguments", args.len())),
                             what: "method `push` call".to_string(),
-                        });
+                        }
+                        .into());
                     }
                     let arg_ty = infer_expr_with_expected(
                         &args[0],
                         Some(&inner),
                         locals,
-                        structs,
-                        enums,
-                        functions,
+                        env,
                     )?;
-                    if !is_assignable(&inner, &arg_ty) {
-                        return Err(TypeError::TypeMismatch {
+                    if !env.vars.assignable(&inner, &arg_ty) {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: *inner.clone(),
                             found: arg_ty,
                             what: format!("method `push` argument for `List<{}>`", inner),
-                        });
+                        }
+                        .at(&args[0].span));
                     }
                     Ok(Type::Unit)
                 }
                 ("pop", Type::List(inner)) => {
                     if !args.is_empty() {
-                        return Err(TypeError::TypeMismatch {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: Type::Named("0 arguments".to_string()),
                             found: Type::Named(format!("{} arguments", args.len())),
                             what: "method `pop` call".to_string(),
-                        });
+                        }
+                        .into());
                     }
                     Ok(*inner)
                 }
-                (name, Type::List(_)) => Err(TypeError::MatchPatternError {
+                (name, Type::List(_)) => Err(TypeErrorKind::MatchPatternError {
                     enum_name: "list".to_string(),
                     variant: name.to_string(),
                     message: "unknown list method; supported: len, get, push, pop".to_string(),
-                }),
-                (_, other) => Err(TypeError::TypeMismatch {
-                    expected: Type::Named("list receiver".to_string()),
-                    found: other,
-                    what: "method call receiver".to_string(),
-                }),
+                }
+                .into()),
+                _ => infer_method_call(receiver, &receiver_ty, name, args, expected, locals, env),
             }
         }
-        Expr::ListLiteral(elems) => {
+        ExprKind::ListLiteral(elems) => {
             if elems.is_empty() {
                 match expected {
                     Some(Type::List(inner)) => Ok(Type::List(inner.clone())),
-                    Some(other) => Err(TypeError::TypeMismatch {
+                    // The element type is left to later uses such as `push`.
+                    Some(Type::Var(_)) | None => {
+                        let origin = VarOrigin::EmptyList {
+                            span: expr.span.clone(),
+                        };
+                        Ok(Type::List(Box::new(env.vars.fresh(origin))))
+                    }
+                    Some(other) => Err(TypeErrorKind::TypeMismatch {
                         expected: other.clone(),
                         found: Type::List(Box::new(Type::Unit)),
                         what: "empty list literal".to_string(),
-                    }),
-                    None => Err(TypeError::CannotInferEmptyList {
-                        what: "cannot infer type of empty list literal without expected type annotation".to_string(),
-                    }),
+                    }
+                    .into()),
                 }
             } else {
                 if let Some(Type::List(inner)) = expected {
@@ -725,60 +2280,60 @@ fn infer_expr_with_expected(
                             elem,
                             Some(inner),
                             locals,
-                            structs,
-                            enums,
-                            functions,
+                            env,
                         )?;
-                        if !is_assignable(inner, &elem_ty) {
-                            return Err(TypeError::TypeMismatch {
+                        if !env.vars.assignable(inner, &elem_ty) {
+                            return Err(TypeErrorKind::TypeMismatch {
                                 expected: *inner.clone(),
                                 found: elem_ty,
                                 what: "list element".to_string(),
-                            });
+                            }
+                            .into());
                         }
                     }
                     return Ok(Type::List(inner.clone()));
                 }
 
                 let mut it = elems.iter();
-                let first = infer_expr_with_expected(it.next().unwrap(), None, locals, structs, enums, functions)?;
+                let first = infer_expr_with_expected(it.next().unwrap(), None, locals, env)?;
                 for (idx, elem) in it.enumerate() {
-                    let elem_ty = infer_expr_with_expected(elem, None, locals, structs, enums, functions)?;
-                    if !is_assignable(&first, &elem_ty) {
-                        return Err(TypeError::TypeMismatch {
+                    let elem_ty = infer_expr_with_expected(elem, None, locals, env)?;
+                    if !env.vars.assignable(&first, &elem_ty) {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: first.clone(),
                             found: elem_ty,
                             what: format!("list element at index {}", idx + 1),
-                        });
+                        }
+                        .into());
                     }
                 }
                 Ok(Type::List(Box::new(first)))
             }
         }
-        Expr::TupleLiteral(fields) => {
+        ExprKind::TupleLiteral(fields) => {
             if let Some(Type::Tuple(expected_fields)) = expected {
                 if fields.len() != expected_fields.len() {
-                    return Err(TypeError::TypeMismatch {
+                    return Err(TypeErrorKind::TypeMismatch {
                         expected: Type::Tuple(expected_fields.clone()),
                         found: Type::Named(format!("{} fields", fields.len())),
                         what: "tuple arity".to_string(),
-                    });
+                    }
+                    .into());
                 }
                 for (idx, expr) in fields.iter().enumerate() {
                     let elem_ty = infer_expr_with_expected(
                         expr,
                         Some(&expected_fields[idx]),
                         locals,
-                        structs,
-                        enums,
-                        functions,
+                        env,
                     )?;
-                    if !is_assignable(&expected_fields[idx], &elem_ty) {
-                        return Err(TypeError::TypeMismatch {
+                    if !env.vars.assignable(&expected_fields[idx], &elem_ty) {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: expected_fields[idx].clone(),
                             found: elem_ty,
                             what: format!("tuple element {}", idx),
-                        });
+                        }
+                        .into());
                     }
                 }
                 Ok(Type::Tuple(expected_fields.clone()))
@@ -789,62 +2344,90 @@ fn infer_expr_with_expected(
                         expr,
                         None,
                         locals,
-                        structs,
-                        enums,
-                        functions,
+                        env,
                     )?);
                 }
                 Ok(Type::Tuple(inferred))
             }
         }
-        Expr::Index { base, index } => {
-            let base_ty = infer_expr_with_expected(base, None, locals, structs, enums, functions)?;
-            let index_ty = infer_expr_with_expected(index, None, locals, structs, enums, functions)?;
-            if index_ty != Type::Int {
-                return Err(TypeError::TypeMismatch {
+        ExprKind::Range { .. } => Err(TypeErrorKind::MisplacedRange.into()),
+        ExprKind::Index { base, index } => {
+            let base_ty = infer_expr_with_expected(base, None, locals, env)?;
+            let index_ty = infer_expr_with_expected(index, None, locals, env)?;
+            if !env.vars.unify(&Type::Int, &index_ty) {
+                return Err(TypeErrorKind::TypeMismatch {
                     expected: Type::Int,
                     found: index_ty,
                     what: "index expression".to_string(),
-                });
+                }
+                .into());
             }
             match base_ty {
                 Type::List(inner) => Ok(*inner),
-                Type::Tuple(fields) => match index.as_ref() {
-                    Expr::Int(i) => {
+                Type::Tuple(fields) => match &index.kind {
+                    ExprKind::Int(i) => {
                         if *i < 0 {
-                            return Err(TypeError::TypeMismatch {
+                            return Err(TypeErrorKind::TypeMismatch {
                                 expected: Type::Named("non-negative index".to_string()),
                                 found: Type::Int,
                                 what: "tuple index".to_string(),
-                            });
+                            }
+                            .into());
                         }
                         let idx = *i as usize;
                         fields
                             .get(idx)
                             .cloned()
-                            .ok_or_else(|| TypeError::TypeMismatch {
+                            .ok_or_else(|| TypeErrorKind::TypeMismatch {
                                 expected: Type::Named(format!("tuple index < {}", fields.len())),
                                 found: Type::Int,
                                 what: format!("tuple index {}", idx),
-                            })
+                            }
+                            .into())
                     }
-                    _ => Err(TypeError::TypeMismatch {
+                    _ => Err(TypeErrorKind::TypeMismatch {
                         expected: Type::Int,
                         found: Type::Named("non-constant tuple index".to_string()),
                         what: "tuple index".to_string(),
-                    }),
+                    }
+                    .into()),
                 },
-                _ => Err(TypeError::TypeMismatch {
+                _ => Err(TypeErrorKind::TypeMismatch {
                     expected: Type::Named("List or Tuple".to_string()),
                     found: base_ty,
                     what: "indexing".to_string(),
-                }),
+                }
+                .into()),
             }
         }
-        Expr::StructInit { name, fields } => {
-            let def = structs
+        ExprKind::Field { base, name } => {
+            let base_ty = infer_expr_with_expected(base, None, locals, env)?;
+            let def = match auto_deref(&base_ty) {
+                ty @ (Type::Named(_) | Type::Generic(_, _)) => instantiate_struct(env.structs, ty)?,
+                other => {
+                    return Err(TypeErrorKind::TypeMismatch {
+                        expected: Type::Named("struct".to_string()),
+                        found: other.clone(),
+                        what: format!("field access `.{}`", name),
+                    }
+                    .into());
+                }
+            };
+            let struct_name = def.name.clone();
+            def.fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, ty)| ty.clone())
+                .ok_or_else(|| TypeErrorKind::UnknownField {
+                    struct_name,
+                    field: name.clone(),
+                }
+                .into())
+        }
+        ExprKind::StructInit { name, fields } => {
+            let def = env.structs
                 .get(name)
-                .ok_or_else(|| TypeError::UnknownStruct { name: name.clone() })?;
+                .ok_or_else(|| TypeErrorKind::UnknownStruct { name: name.clone() })?;
 
             let mut defined: HashMap<String, &Type> = HashMap::new();
             for (fname, fty) in &def.fields {
@@ -852,146 +2435,157 @@ fn infer_expr_with_expected(
             }
 
             let mut seen = HashSet::new();
+            let mut slots = Vec::new();
             for (fname, fexpr) in fields {
-                let expected = match defined.get(fname) {
-                    Some(ty) => ty,
+                let field_ty = match defined.get(fname) {
+                    Some(ty) => *ty,
                     None => {
-                        return Err(TypeError::MatchPatternError {
+                        return Err(TypeErrorKind::MatchPatternError {
                             enum_name: "struct".to_string(),
                             variant: fname.clone(),
                             message: "unknown field".to_string(),
-                        });
+                        }
+                        .into());
                     }
                 };
 
                 if !seen.insert(fname.clone()) {
-                    return Err(TypeError::MatchPatternError {
+                    return Err(TypeErrorKind::MatchPatternError {
                         enum_name: name.clone(),
                         variant: fname.clone(),
                         message: "duplicate field".to_string(),
-                    });
+                    }
+                    .into());
                 }
 
-                let found = infer_expr_with_expected(fexpr, Some(expected), locals, structs, enums, functions)?;
-                if !is_assignable(expected, &found) {
-                    return Err(TypeError::TypeMismatch {
-                        expected: (*expected).clone(),
-                        found,
-                        what: format!("field `{}` in struct `{}`", fname, name),
-                    });
-                }
+                slots.push((fexpr, field_ty, format!("field `{}` in struct `{}`", fname, name)));
             }
+            let self_ty = generic_self_type(name, &def.type_params);
+            let type_args = infer_type_args(
+                (name, &expr.span),
+                &def.type_params,
+                &slots,
+                &self_ty,
+                expected,
+                locals,
+                env,
+            )?;
 
             if fields.len() != def.fields.len() {
-                return Err(TypeError::TypeMismatch {
+                return Err(TypeErrorKind::TypeMismatch {
                     expected: Type::Named(format!("{} fields", def.fields.len())),
                     found: Type::Named(format!("{} fields", fields.len())),
                     what: format!("struct `{}` construction", name),
-                });
+                }
+                .into());
             }
 
-            Ok(Type::Named(name.clone()))
+            Ok(applied_type(name, type_args))
         }
-        Expr::EnumInit {
+        ExprKind::EnumInit {
             enum_name,
             variant,
             payload,
         } => {
-            let def = enums
+            if let Some(function) = associated_function(enum_name, variant, env) {
+                let call = ExprKind::Call {
+                    name: function,
+                    args: payload.clone(),
+                };
+                let call = Expr::new(call, expr.span.clone());
+                return infer_expr_node(&call, expected, locals, env);
+            }
+            let def = env.enums
                 .get(enum_name)
-                .ok_or_else(|| TypeError::UnknownEnum { name: enum_name.clone() })?;
+                .ok_or_else(|| TypeErrorKind::UnknownEnum { name: enum_name.clone() })?;
 
             let variant_info = def
                 .variants
                 .iter()
                 .find(|v| v.name() == *variant)
-                .ok_or_else(|| TypeError::UnknownEnumVariant {
+                .ok_or_else(|| TypeErrorKind::UnknownEnumVariant {
                     enum_name: enum_name.clone(),
                     variant: variant.clone(),
                 })?;
 
-            match variant_info {
+            let slots: Vec<_> = match variant_info {
                 EnumVariant::Unit(_) => {
                     if !payload.is_empty() {
-                        return Err(TypeError::TypeMismatch {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: Type::Named("0 payload elements".to_string()),
                             found: Type::Named(format!("{} payload elements", payload.len())),
                             what: format!("payload for {}::{}", enum_name, variant),
-                        });
+                        }
+                        .into());
                     }
+                    Vec::new()
                 }
                 EnumVariant::Tuple(_, payload_type) => {
                     if payload.len() != 1 {
-                        return Err(TypeError::TypeMismatch {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: Type::Named("1 payload element".to_string()),
                             found: Type::Named(format!("{} payload elements", payload.len())),
                             what: format!("payload for {}::{}", enum_name, variant),
-                        });
-                    }
-                    let found =
-                        infer_expr_with_expected(&payload[0], Some(payload_type), locals, structs, enums, functions)?;
-                    if !is_assignable(payload_type, &found) {
-                        return Err(TypeError::TypeMismatch {
-                            expected: payload_type.clone(),
-                            found,
-                            what: format!("payload for {}::{}", enum_name, variant),
-                        });
+                        }
+                        .into());
                     }
+                    vec![(
+                        &payload[0],
+                        payload_type,
+                        format!("payload for {}::{}", enum_name, variant),
+                    )]
                 }
                 EnumVariant::Struct(_, fields) => {
                     if payload.len() != fields.len() {
-                        return Err(TypeError::TypeMismatch {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: Type::Named(format!("{} payload fields", fields.len())),
                             found: Type::Named(format!("{} payload fields", payload.len())),
                             what: format!("payload for {}::{}", enum_name, variant),
-                        });
-                    }
-                    for (idx, expected_field) in fields.iter().enumerate() {
-                        let found = infer_expr_with_expected(
-                            &payload[idx],
-                            Some(&expected_field.1),
-                            locals,
-                            structs,
-                            enums,
-                            functions,
-                        )?;
-                        if !is_assignable(&expected_field.1, &found) {
-                            return Err(TypeError::TypeMismatch {
-                                expected: expected_field.1.clone(),
-                                found,
-                                what: format!(
-                                    "payload index {} for {}::{}",
-                                    idx, enum_name, variant
-                                ),
-                            });
                         }
+                        .into());
                     }
+                    payload
+                        .iter()
+                        .zip(fields)
+                        .enumerate()
+                        .map(|(idx, (value, (_, field_ty)))| {
+                            (
+                                value,
+                                field_ty,
+                                format!("payload index {} for {}::{}", idx, enum_name, variant),
+                            )
+                        })
+                        .collect()
                 }
-            }
+            };
+            let self_ty = generic_self_type(enum_name, &def.type_params);
+            let type_args = infer_type_args(
+                (enum_name, &expr.span),
+                &def.type_params,
+                &slots,
+                &self_ty,
+                expected,
+                locals,
+                env,
+            )?;
 
-            Ok(Type::Named(enum_name.clone()))
+            Ok(applied_type(enum_name, type_args))
         }
-        Expr::Match { subject, arms } => {
-            let subject_type = infer_expr_with_expected(subject, None, locals, structs, enums, functions)?;
-            let enum_name = match subject_type {
-                Type::Named(name) => {
-                    if !enums.contains_key(&name) {
-                        return Err(TypeError::UnknownEnum { name });
-                    }
-                    name
-                }
+        ExprKind::Match { subject, arms } => {
+            let subject_type = infer_expr_with_expected(subject, None, locals, env)?;
+            let def = match &subject_type {
+                Type::Named(_) | Type::Generic(_, _) => instantiate_enum(env.enums, &subject_type)?,
                 _ => {
-                    return Err(TypeError::TypeMismatch {
+                    return Err(TypeErrorKind::TypeMismatch {
                         expected: Type::Named("enum type".to_string()),
                         found: subject_type,
                         what: "match subject".to_string(),
-                    })
+                    }
+                    .into())
                 }
             };
-
-            let def = enums
-                .get(&enum_name)
-                .ok_or_else(|| TypeError::UnknownEnum { name: enum_name.clone() })?;
+            let def = &def;
+            let enum_name = def.name.clone();
 
             let mut used = HashSet::new();
             let mut arm_types: Option<Type> = None;
@@ -1002,26 +2596,26 @@ fn infer_expr_with_expected(
                     &enum_name,
                     def,
                     locals,
-                    structs,
-                    enums,
-                    functions,
+                    env,
                 )?;
 
                 let variant_name = arm.pattern.variant_name();
                 if !used.insert(variant_name.clone()) {
-                    return Err(TypeError::DuplicateVariantPattern {
+                    return Err(TypeErrorKind::DuplicateVariantPattern {
                         enum_name: enum_name.clone(),
                         variant: variant_name,
-                    });
+                    }
+                    .into());
                 }
 
                 match &arm_types {
-                    Some(prev) if prev != &variant_ty => {
-                        return Err(TypeError::TypeMismatch {
+                    Some(prev) if !env.vars.unify(prev, &variant_ty) => {
+                        return Err(TypeErrorKind::TypeMismatch {
                             expected: prev.clone(),
                             found: variant_ty,
                             what: "match arm body type".to_string(),
-                        });
+                        }
+                        .into());
                     }
                     None => arm_types = Some(variant_ty),
                     _ => {}
@@ -1029,9 +2623,10 @@ fn infer_expr_with_expected(
             }
 
             if used.len() != def.variants.len() {
-                return Err(TypeError::NonExhaustiveMatch {
+                return Err(TypeErrorKind::NonExhaustiveMatch {
                     enum_name: enum_name.clone(),
-                });
+                }
+                .into());
             }
 
             Ok(arm_types.unwrap_or(Type::Unit))
@@ -1043,140 +2638,231 @@ fn infer_binary(
     op: &BinOp,
     left: &Expr,
     right: &Expr,
+    expected: Option<&Type>,
+    span: &Span,
     locals: &HashMap<String, (u32, Type)>,
-    structs: &HashMap<String, StructDecl>,
-    enums: &HashMap<String, EnumDecl>,
-    functions: &HashMap<String, FnSignature>,
+    env: &FnEnv,
 ) -> Result<Type, TypeError> {
-    let left_ty = infer_expr_with_expected(left, None, locals, structs, enums, functions)?;
-    let right_ty = infer_expr_with_expected(right, None, locals, structs, enums, functions)?;
-    if !types_match(&left_ty, &right_ty) {
-        return Err(TypeError::TypeMismatch {
+    // Literal-only integer arithmetic takes the type of the other side (`b + 1` on a `UInt8`);
+    // with literals on both sides, an arithmetic result takes it from the context
+    // (`let n: Int32 = 2 * 3;`).
+    let (left_ty, right_ty) = match (left.is_untyped_int(), right.is_untyped_int()) {
+        (true, true) => {
+            let arithmetic = matches!(
+                op,
+                BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem
+            );
+            let hint = expected.filter(|_| arithmetic);
+            let left_ty = infer_expr_with_expected(left, hint, locals, env)?;
+            let right_ty = infer_expr_with_expected(right, hint, locals, env)?;
+            (left_ty, right_ty)
+        }
+        (true, false) => {
+            let right_ty = infer_expr_with_expected(right, None, locals, env)?;
+            let left_ty = infer_expr_with_expected(left, Some(&right_ty), locals, env)?;
+            (left_ty, right_ty)
+        }
+        (false, right_untyped) => {
+            let left_ty = infer_expr_with_expected(left, None, locals, env)?;
+            let hint = right_untyped.then_some(&left_ty);
+            let right_ty = infer_expr_with_expected(right, hint, locals, env)?;
+            (left_ty, right_ty)
+        }
+    };
+    if matches!(op, BinOp::And | BinOp::Or) {
+        let operator = if matches!(op, BinOp::And) { "&&" } else { "||" };
+        for found in [left_ty, right_ty] {
+            if !env.vars.unify(&Type::Bool, &found) {
+                return Err(TypeErrorKind::TypeMismatch {
+                    expected: Type::Bool,
+                    found,
+                    what: format!("operand of `{}`", operator),
+                }
+                .into());
+            }
+        }
+        return Ok(Type::Bool);
+    }
+    if !env.vars.unify(&left_ty, &right_ty) {
+        if left_ty.is_numeric() && right_ty.is_numeric() {
+            return Err(TypeErrorKind::MixedNumericOperands {
+                op: op.clone(),
+                left: left_ty,
+                right: right_ty,
+            }
+            .into());
+        }
+        return Err(TypeErrorKind::TypeMismatch {
             expected: left_ty,
             found: right_ty,
             what: format!("binary operator {:?}", op),
-        });
+        }
+        .into());
+    }
+    apply_operator(Operator::Binary(op.clone()), env.vars.resolve(&left_ty), span, env)
+}
+
+/// An operator whose operand type decides whether it applies.
+#[derive(Debug, Clone)]
+enum Operator {
+    Binary(BinOp),
+    Neg,
+}
+
+/// Result type of `operator` on `operand`. An operand that is still an inference variable
+/// (a closure parameter such as `x` in `|x| x * x`) is checked once the body has solved it.
+fn apply_operator(
+    operator: Operator,
+    operand: Type,
+    span: &Span,
+    env: &FnEnv,
+) -> Result<Type, TypeError> {
+    if !matches!(operand, Type::Var(_)) {
+        return operator_result(&operator, &operand);
     }
+    let result = match operator {
+        Operator::Binary(BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem)
+        | Operator::Neg => operand.clone(),
+        Operator::Binary(_) => Type::Bool,
+    };
+    env.pending_operators
+        .borrow_mut()
+        .push((operand, operator, span.clone()));
+    Ok(result)
+}
 
+fn operator_result(operator: &Operator, operand: &Type) -> Result<Type, TypeError> {
+    let op = match operator {
+        Operator::Binary(op) => op,
+        Operator::Neg => {
+            return match auto_deref(operand) {
+                ty @ (Type::Int | Type::Int32 | Type::Float) => Ok(ty.clone()),
+                other => Err(TypeErrorKind::TypeMismatch {
+                    expected: Type::Named("Int, Int32 or Float".to_string()),
+                    found: other.clone(),
+                    what: "operand of unary `-`".to_string(),
+                }
+                .into()),
+            };
+        }
+    };
     match op {
-        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div => match left_ty {
-            Type::Int => Ok(Type::Int),
-            Type::Float => Ok(Type::Float),
-            _ => Err(TypeError::TypeMismatch {
-                expected: Type::Named("Int or Float".to_string()),
-                found: left_ty,
+        BinOp::Add if *operand == Type::String => Ok(Type::String),
+        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem => match operand {
+            ty if ty.is_numeric() => Ok(ty.clone()),
+            _ => Err(TypeErrorKind::TypeMismatch {
+                expected: Type::Named("a numeric type".to_string()),
+                found: operand.clone(),
                 what: "arithmetic expression".to_string(),
-            }),
+            }
+            .into()),
         },
-        BinOp::Eq | BinOp::NotEq => match left_ty {
-            Type::Int | Type::Float | Type::Bool | Type::String => Ok(Type::Bool),
-            _ => Err(TypeError::TypeMismatch {
-                expected: Type::Named("Int, Float, Bool, or String".to_string()),
-                found: left_ty,
+        BinOp::Eq | BinOp::NotEq => match operand {
+            ty if ty.is_numeric() || matches!(ty, Type::Bool | Type::String) => Ok(Type::Bool),
+            _ => Err(TypeErrorKind::TypeMismatch {
+                expected: Type::Named("a numeric type, Bool, or String".to_string()),
+                found: operand.clone(),
                 what: "equality expression".to_string(),
-            }),
+            }
+            .into()),
         },
-        BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => match left_ty {
-            Type::Int | Type::Float => Ok(Type::Bool),
-            _ => Err(TypeError::TypeMismatch {
-                expected: Type::Named("Int or Float".to_string()),
-                found: left_ty,
+        BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => match operand {
+            ty if ty.is_numeric() || *ty == Type::String => Ok(Type::Bool),
+            _ => Err(TypeErrorKind::TypeMismatch {
+                expected: Type::Named("a numeric type or String".to_string()),
+                found: operand.clone(),
                 what: "ordering expression".to_string(),
-            }),
+            }
+            .into()),
         },
+        BinOp::And | BinOp::Or => Ok(Type::Bool),
     }
 }
 
 fn infer_match_variant(
     arm: &MatchArm,
-    enum_name: &String,
+    enum_name: &str,
     def: &EnumDecl,
     locals: &HashMap<String, (u32, Type)>,
-    structs: &HashMap<String, StructDecl>,
-    enums: &HashMap<String, EnumDecl>,
-    functions: &HashMap<String, FnSignature>,
+    env: &FnEnv,
 ) -> Result<Type, TypeError> {
     let variant_name = arm.pattern.variant_name();
     let variant = def
         .variants
         .iter()
         .find(|v| v.name() == variant_name)
-        .ok_or_else(|| TypeError::UnknownEnumVariant {
-            enum_name: enum_name.clone(),
+        .ok_or_else(|| TypeErrorKind::UnknownEnumVariant {
+            enum_name: enum_name.to_string(),
             variant: variant_name,
         })?;
 
     let mut scope = locals.clone();
     match arm.pattern.clone() {
         Pattern::UnitVariant(_) => {
-            arm_body_type(&arm.body, &scope, structs, enums, functions)
+            arm_body_type(&arm.body, &scope, env)
         }
         Pattern::TupleVariant(_, vars) => match variant {
             EnumVariant::Tuple(_, payload_ty) => {
                 if vars.len() != 1 {
-                    return Err(TypeError::MatchPatternError {
-                        enum_name: enum_name.clone(),
+                    return Err(TypeErrorKind::MatchPatternError {
+                        enum_name: enum_name.to_string(),
                         variant: arm.pattern.variant_name(),
                         message: "tuple variant expects exactly one binding".to_string(),
-                    });
-                }
-                let var_name = vars[0].clone();
-                if !scope.insert(var_name.clone(), (scope.len() as u32, payload_ty.clone())).is_none() {
-                    return Err(TypeError::DuplicateVariable {
-                        name: var_name,
-                    });
+                    }
+                    .into());
                 }
-                arm_body_type(&arm.body, &scope, structs, enums, functions)
+                // Pattern bindings shadow outer locals inside the arm.
+                scope.insert(vars[0].name.clone(), (u32::MAX, payload_ty.clone()));
+                arm_body_type(&arm.body, &scope, env)
             }
-            _ => Err(TypeError::MatchPatternError {
-                enum_name: enum_name.clone(),
+            _ => Err(TypeErrorKind::MatchPatternError {
+                enum_name: enum_name.to_string(),
                 variant: arm.pattern.variant_name(),
                 message: "not a tuple variant".to_string(),
-            }),
+            }
+            .into()),
         },
         Pattern::StructVariant(_, vars) => match variant {
             EnumVariant::Struct(_, fields) => {
                 if vars.len() != fields.len() {
-                    return Err(TypeError::MatchPatternError {
-                        enum_name: enum_name.clone(),
+                    return Err(TypeErrorKind::MatchPatternError {
+                        enum_name: enum_name.to_string(),
                         variant: arm.pattern.variant_name(),
                         message: "struct variant binding count mismatch".to_string(),
-                    });
+                    }
+                    .into());
                 }
 
                 let mut seen = HashSet::new();
-                for field_name in vars {
+                for binding in vars {
+                    let field_name = &binding.name;
                     if !seen.insert(field_name.clone()) {
-                        return Err(TypeError::MatchPatternError {
-                            enum_name: enum_name.clone(),
+                        return Err(TypeErrorKind::MatchPatternError {
+                            enum_name: enum_name.to_string(),
                             variant: arm.pattern.variant_name(),
                             message: "duplicate binding".to_string(),
-                        });
-                    }
-                    if let Some((_, field_ty)) = fields.iter().find(|(name, _)| name == &field_name) {
-                        if !scope
-                            .insert(field_name.clone(), (scope.len() as u32, field_ty.clone()))
-                            .is_none()
-                        {
-                            return Err(TypeError::DuplicateVariable {
-                                name: field_name,
-                            });
                         }
+                        .at(&binding.span));
+                    }
+                    if let Some((_, field_ty)) = fields.iter().find(|(name, _)| name == field_name) {
+                        scope.insert(field_name.clone(), (u32::MAX, field_ty.clone()));
                     } else {
-                        return Err(TypeError::MatchPatternError {
-                            enum_name: enum_name.clone(),
+                        return Err(TypeErrorKind::MatchPatternError {
+                            enum_name: enum_name.to_string(),
                             variant: arm.pattern.variant_name(),
                             message: format!("unknown field `{}`", field_name),
-                        });
+                        }
+                        .at(&binding.span));
                     }
                 }
-                arm_body_type(&arm.body, &scope, structs, enums, functions)
+                arm_body_type(&arm.body, &scope, env)
             }
-            _ => Err(TypeError::MatchPatternError {
-                enum_name: enum_name.clone(),
+            _ => Err(TypeErrorKind::MatchPatternError {
+                enum_name: enum_name.to_string(),
                 variant: arm.pattern.variant_name(),
                 message: "not a struct variant".to_string(),
-            }),
+            }
+            .into()),
         },
     }
 }
@@ -1184,32 +2870,304 @@ fn infer_match_variant(
 fn arm_body_type(
     body: &Expr,
     locals: &HashMap<String, (u32, Type)>,
-    structs: &HashMap<String, StructDecl>,
-    enums: &HashMap<String, EnumDecl>,
-    functions: &HashMap<String, FnSignature>,
+    env: &FnEnv,
 ) -> Result<Type, TypeError> {
-    infer_expr(body, locals, structs, enums, functions)
+    infer_expr(body, locals, env)
 }
 
-fn is_assignable(expected: &Type, provided: &Type) -> bool {
-    match (expected, provided) {
-        (expected, provided) if types_match(expected, provided) => true,
-        (Type::List(exp_elem), Type::List(prov_elem)) => {
-            is_assignable(exp_elem, prov_elem)
-        }
-        (Type::Tuple(exp_fields), Type::Tuple(prov_fields)) => {
-            if exp_fields.len() != prov_fields.len() {
-                return false;
+/// Checks a call of a user-defined method (`p.norm()`).
+///
+/// Methods on concrete types become calls of their `Type::method` function with the
+/// receiver as first argument, borrowed automatically for `&self` methods. On a receiver
+/// whose type is a bounded type parameter the method is looked up in the bounding traits.
+fn infer_method_call(
+    receiver: &Expr,
+    receiver_ty: &Type,
+    name: &str,
+    args: &[Expr],
+    expected: Option<&Type>,
+    locals: &HashMap<String, (u32, Type)>,
+    env: &FnEnv,
+) -> Result<Type, TypeError> {
+    let base_ty = auto_deref(receiver_ty).clone();
+    let unknown = || TypeErrorKind::UnknownMethod {
+        ty: base_ty.clone(),
+        method: name.to_string(),
+    };
+
+    if let Type::Named(param) = &base_ty
+        && env.func.type_params.contains(param)
+    {
+        let method = env
+            .func
+            .bounds
+            .iter()
+            .filter(|(bounded, _)| bounded == param)
+            .filter_map(|(_, trait_name)| env.traits.decls.get(trait_name))
+            .find_map(|def| def.methods.iter().find(|method| method.name == name))
+            .filter(|method| method.params.first().is_some_and(|first| first.name == "self"))
+            .ok_or_else(unknown)?;
+        let self_binding = HashMap::from([("Self".to_string(), base_ty.clone())]);
+        if method.params.len() != args.len() + 1 {
+            return Err(TypeErrorKind::TypeMismatch {
+                expected: Type::Named(format!("{} arguments", method.params.len() - 1)),
+                found: Type::Named(format!("{} arguments", args.len())),
+                what: format!("method `{}` argument count", name),
             }
-            for (exp_ty, prov_ty) in exp_fields.iter().zip(prov_fields.iter()) {
-                if !is_assignable(exp_ty, prov_ty) {
-                    return false;
+            .into());
+        }
+        for (arg, param) in args.iter().zip(&method.params[1..]) {
+            let param_ty = param.ty.substitute(&self_binding);
+            let found = infer_expr_with_expected(arg, Some(&param_ty), locals, env)?;
+            if !env.vars.assignable(&param_ty, &found) {
+                return Err(TypeErrorKind::TypeMismatch {
+                    expected: param_ty,
+                    found,
+                    what: format!("argument for method `{}`", name),
                 }
+                .at(&arg.span));
             }
-            true
         }
-        (_, Type::Ref(inner)) => is_assignable(expected, inner),
-        _ => false,
+        return Ok(method.return_type.substitute(&self_binding));
+    }
+
+    let function = base_ty
+        .impl_name()
+        .map(|type_name| method_function_name(type_name, name))
+        .ok_or_else(unknown)?;
+    let self_param = env
+        .functions
+        .get(&function)
+        .and_then(|sig| sig.params.first())
+        .filter(|param| param.name == "self")
+        .ok_or_else(unknown)?;
+    let call = method_call(receiver, receiver_ty, function, &self_param.ty, args);
+    infer_expr_node(&call, expected, locals, env)
+}
+
+/// `receiver.method(args)` as a call of the method function `function`, borrowing the
+/// receiver when the method takes `&self` and the receiver is not a reference yet.
+pub(crate) fn method_call(
+    receiver: &Expr,
+    receiver_ty: &Type,
+    function: String,
+    self_ty: &Type,
+    args: &[Expr],
+) -> Expr {
+    let span = Span {
+        start: receiver.span.start,
+        end: args.last().map_or(receiver.span.end, |arg| arg.span.end),
+    };
+    let receiver = if matches!(self_ty, Type::Ref(_)) && !matches!(receiver_ty, Type::Ref(_)) {
+        Expr::new(ExprKind::Ref(Box::new(receiver.clone())), receiver.span.clone())
+    } else {
+        receiver.clone()
+    };
+    let call = ExprKind::Call {
+        name: function,
+        args: std::iter::once(receiver).chain(args.iter().cloned()).collect(),
+    };
+    Expr::new(call, span)
+}
+
+/// The function behind `Type::name(..)` when it names an associated function rather than
+/// an enum variant.
+fn associated_function(type_name: &str, name: &str, env: &FnEnv) -> Option<String> {
+    let is_variant = env
+        .enums
+        .get(type_name)
+        .is_some_and(|def| def.variants.iter().any(|variant| variant.name() == name));
+    let function = method_function_name(type_name, name);
+    (!is_variant && env.functions.contains_key(&function)).then_some(function)
+}
+
+/// Checks that `ty` implements `trait_name`, as required by a bound of `item`.
+fn check_bound(ty: &Type, trait_name: &str, item: &str, env: &FnEnv) -> Result<(), TypeError> {
+    let ty = env.vars.resolve(ty);
+    let ty = auto_deref(&ty).clone();
+    let satisfied = match &ty {
+        Type::Named(param) if env.func.type_params.contains(param) => env
+            .func
+            .bounds
+            .iter()
+            .any(|(bounded, bound)| bounded == param && bound == trait_name),
+        _ => ty.impl_name().is_some_and(|type_name| {
+            env.traits
+                .impls
+                .contains(&(trait_name.to_string(), type_name.to_string()))
+        }),
+    };
+    if satisfied {
+        Ok(())
+    } else {
+        Err(TypeErrorKind::UnsatisfiedBound {
+            ty,
+            trait_name: trait_name.to_string(),
+            item: item.to_string(),
+        }
+        .into())
+    }
+}
+
+/// Infers the type arguments of a generic function, struct or enum from its use at `span`.
+///
+/// `slots` pairs each value with its declared type and a diagnostic label. Every type
+/// parameter starts as an inference variable; the expected type is unified with `result`
+/// first (which is all `Option::None` has to go on), then every value is checked against
+/// its slot type. Variables left open can still be solved by later statements of the body.
+fn infer_type_args(
+    (item, span): (&str, &Span),
+    type_params: &[String],
+    slots: &[(&Expr, &Type, String)],
+    result: &Type,
+    expected: Option<&Type>,
+    locals: &HashMap<String, (u32, Type)>,
+    env: &FnEnv,
+) -> Result<Vec<Type>, TypeError> {
+    let type_args: Vec<Type> = type_params
+        .iter()
+        .map(|param| {
+            env.vars.fresh(VarOrigin::TypeArgument {
+                item: item.to_string(),
+                param: param.clone(),
+                span: span.clone(),
+            })
+        })
+        .collect();
+    let bindings = type_bindings(type_params, &type_args);
+    if let Some(expected) = expected {
+        // Only a hint: callers report a conflicting expected type themselves.
+        env.vars.unify(&result.substitute(&bindings), expected);
+    }
+
+    for (value, slot_ty, what) in slots {
+        let slot_ty = slot_ty.substitute(&bindings);
+        let found = infer_expr_with_expected(value, Some(&slot_ty), locals, env)?;
+        if !env.vars.assignable(&slot_ty, &found) {
+            return Err(TypeErrorKind::TypeMismatch {
+                expected: env.vars.resolve(&slot_ty),
+                found,
+                what: what.clone(),
+            }
+            .at(&value.span));
+        }
+    }
+    Ok(type_args.iter().map(|arg| env.vars.resolve(arg)).collect())
+}
+
+/// Binds the type parameters occurring in `pattern` by matching it against `actual`.
+///
+/// The first binding of a parameter wins; conflicts surface later as assignability errors.
+pub(crate) fn unify(
+    pattern: &Type,
+    actual: &Type,
+    type_params: &[String],
+    bindings: &mut HashMap<String, Type>,
+) {
+    match (pattern, actual) {
+        (Type::Named(name), _) if type_params.contains(name) => {
+            bindings
+                .entry(name.clone())
+                .or_insert_with(|| actual.clone());
+        }
+        (Type::List(pattern), Type::List(actual)) | (Type::Ref(pattern), Type::Ref(actual)) => {
+            unify(pattern, actual, type_params, bindings)
+        }
+        (Type::Tuple(patterns), Type::Tuple(actuals)) if patterns.len() == actuals.len() => {
+            for (pattern, actual) in patterns.iter().zip(actuals) {
+                unify(pattern, actual, type_params, bindings);
+            }
+        }
+        (Type::Generic(name, patterns), Type::Generic(actual_name, actuals))
+            if name == actual_name && patterns.len() == actuals.len() =>
+        {
+            for (pattern, actual) in patterns.iter().zip(actuals) {
+                unify(pattern, actual, type_params, bindings);
+            }
+        }
+        (Type::Function(patterns, pattern_ret), Type::Function(actuals, actual_ret))
+            if patterns.len() == actuals.len() =>
+        {
+            for (pattern, actual) in patterns.iter().zip(actuals) {
+                unify(pattern, actual, type_params, bindings);
+            }
+            unify(pattern_ret, actual_ret, type_params, bindings);
+        }
+        // Auto-deref: a `&T` argument can fill a by-value slot.
+        (_, Type::Ref(inner)) => unify(pattern, inner, type_params, bindings),
+        _ => {}
+    }
+}
+
+/// `Name<A, B>` with the declaration's own parameters as arguments (`Named` for plain items).
+pub(crate) fn generic_self_type(name: &str, type_params: &[String]) -> Type {
+    applied_type(
+        name,
+        type_params.iter().map(|param| Type::Named(param.clone())).collect(),
+    )
+}
+
+/// The type of a struct/enum value: plain `Named` unless it has type arguments.
+pub(crate) fn applied_type(name: &str, type_args: Vec<Type>) -> Type {
+    if type_args.is_empty() {
+        Type::Named(name.to_string())
+    } else {
+        Type::Generic(name.to_string(), type_args)
+    }
+}
+
+/// Looks up the struct behind `ty` with its type arguments substituted into the fields.
+fn instantiate_struct(
+    structs: &HashMap<String, StructDecl>,
+    ty: &Type,
+) -> Result<StructDecl, TypeError> {
+    let (name, args) = match ty {
+        Type::Generic(name, args) => (name, args.as_slice()),
+        Type::Named(name) => (name, &[][..]),
+        other => return Err(TypeErrorKind::UnknownStruct { name: other.to_string() }.into()),
+    };
+    structs
+        .get(name)
+        .map(|def| def.instantiate(args))
+        .ok_or_else(|| TypeErrorKind::UnknownStruct { name: name.clone() }.into())
+}
+
+/// Looks up the enum behind `ty` with its type arguments substituted into the variants.
+fn instantiate_enum(enums: &HashMap<String, EnumDecl>, ty: &Type) -> Result<EnumDecl, TypeError> {
+    let (name, args) = match ty {
+        Type::Generic(name, args) => (name, args.as_slice()),
+        Type::Named(name) => (name, &[][..]),
+        other => return Err(TypeErrorKind::UnknownEnum { name: other.to_string() }.into()),
+    };
+    enums
+        .get(name)
+        .map(|def| def.instantiate(args))
+        .ok_or_else(|| TypeErrorKind::UnknownEnum { name: name.clone() }.into())
+}
+
+/// Short description of an assignment target for diagnostics.
+fn describe_place(target: &Expr) -> String {
+    match &target.kind {
+        ExprKind::Local(name) => format!("`{}`", name),
+        ExprKind::Field { name, .. } => format!("field `{}`", name),
+        _ => "indexed element".to_string(),
+    }
+}
+
+/// Strips any number of reference layers, mirroring the checker's auto-deref rule.
+/// Smallest and largest value of a fixed-width integer type.
+pub(crate) fn integer_range(ty: &Type) -> (i64, i64) {
+    match ty {
+        Type::Int32 => (i32::MIN as i64, i32::MAX as i64),
+        Type::UInt8 => (0, u8::MAX as i64),
+        _ => (i64::MIN, i64::MAX),
+    }
+}
+
+fn auto_deref(ty: &Type) -> &Type {
+    match ty {
+        Type::Ref(inner) => auto_deref(inner),
+        other => other,
     }
 }
 
@@ -1223,10 +3181,11 @@ fn register_host_descriptors(
             Some(TopLevelDecl::Struct(def)) => {
                 if let Some(existing) = structs.get(&def.name) {
                     if existing.fields != def.fields {
-                        return Err(TypeError::DuplicateType {
+                        return Err(TypeErrorKind::DuplicateType {
                             kind: "struct".to_string(),
                             name: def.name.clone(),
-                        });
+                        }
+                        .into());
                     }
                 } else {
                     structs.insert(def.name.clone(), def);
@@ -1235,10 +3194,11 @@ fn register_host_descriptors(
             Some(TopLevelDecl::Enum(def)) => {
                 if let Some(existing) = enums.get(&def.name) {
                     if existing.variants.len() != def.variants.len() {
-                        return Err(TypeError::DuplicateType {
+                        return Err(TypeErrorKind::DuplicateType {
                             kind: "enum".to_string(),
                             name: def.name.clone(),
-                        });
+                        }
+                        .into());
                     }
                 } else {
                     enums.insert(def.name.clone(), def);
@@ -1250,41 +3210,17 @@ fn register_host_descriptors(
     Ok(())
 }
 
-fn types_match(a: &Type, b: &Type) -> bool {
-    a == b
-}
-
-impl Pattern {
-    fn variant_name(&self) -> String {
-        match self {
-            Pattern::UnitVariant(v) => v.clone(),
-            Pattern::TupleVariant(v, _) => v.clone(),
-            Pattern::StructVariant(v, _) => v.clone(),
-        }
-    }
-}
-
-impl EnumVariant {
-    fn name(&self) -> String {
-        match self {
-            EnumVariant::Unit(name) => name.clone(),
-            EnumVariant::Tuple(name, _) => name.clone(),
-            EnumVariant::Struct(name, _) => name.clone(),
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::lexer::lex_file;
     use crate::parser::Parser;
 
-    fn type_check(src: &str) -> Result<TypedProgram, TypeError> {
+    fn type_check(src: &str) -> Result<TypedProgram, TypeErrorKind> {
         let tokens = lex_file(src).unwrap();
         let mut parser = Parser::new(&tokens);
         let program = parser.parse_program().unwrap();
-        check_program_with_hosts(&program, &[])
+        check_program_with_hosts(&program, &[]).map_err(|err| *err.kind)
     }
 
     #[test]
@@ -1323,6 +3259,169 @@ mod tests {
         assert!(type_check(src).is_ok());
     }
 
+    #[test]
+    fn struct_field_access_typechecks() {
+        let src = "struct P { x: Int, y: Float } fn f(p: P) -> Float { let _: Int = p.x; return p.y; }";
+        assert!(type_check(src).is_ok());
+    }
+
+    #[test]
+    fn unknown_struct_field_is_rejected() {
+        let src = "struct P { x: Int } fn f(p: P) -> Int { return p.z; }";
+        assert!(matches!(
+            type_check(src),
+            Err(TypeErrorKind::UnknownField { .. })
+        ));
+    }
+
+    #[test]
+    fn remainder_and_negation_typecheck() {
+        assert!(type_check("fn f(x: Int, y: Float) -> Float { let r = -x % 4; return -y % 2.0; }").is_ok());
+        assert!(matches!(
+            type_check("fn f(b: Bool) -> Bool { return -b; }"),
+            Err(TypeErrorKind::TypeMismatch { what, .. }) if what == "operand of unary `-`"
+        ));
+        assert!(type_check("fn f(s: String) -> String { return s % s; }").is_err());
+    }
+
+    #[test]
+    fn logical_operators_require_bool_operands() {
+        assert!(type_check("fn f(a: Bool, x: Int) -> Bool { return !a && x > 0 || false; }").is_ok());
+        assert!(matches!(
+            type_check("fn f(x: Int) -> Bool { return x && true; }"),
+            Err(TypeErrorKind::TypeMismatch { what, .. }) if what == "operand of `&&`"
+        ));
+        assert!(matches!(
+            type_check("fn f(x: Int) -> Bool { return !x; }"),
+            Err(TypeErrorKind::TypeMismatch { what, .. }) if what == "operand of `!`"
+        ));
+    }
+
+    #[test]
+    fn for_loops_bind_scoped_loop_variables() {
+        let ok = "fn f(xs: List<Float>, n: Int) -> Float { let total = 0.0; for x in xs { total += x; } for x in 0..n { if x > 2 { break; } continue; } return total; }";
+        assert!(type_check(ok).is_ok());
+
+        let leaked = "fn f(n: Int) -> Int { for i in 0..n {} return i; }";
+        assert!(matches!(type_check(leaked), Err(TypeErrorKind::UnknownVariable { .. })));
+
+        let bad_bound = "fn f() -> Int { for i in 0..1.5 {} return 0; }";
+        assert!(matches!(
+            type_check(bad_bound),
+            Err(TypeErrorKind::TypeMismatch { what, .. }) if what == "range bound"
+        ));
+    }
+
+    #[test]
+    fn loop_control_and_ranges_are_rejected_outside_loops() {
+        assert!(matches!(
+            type_check("fn f() -> Int { if true { break; } return 0; }"),
+            Err(TypeErrorKind::LoopControlOutsideLoop { keyword }) if keyword == "break"
+        ));
+        assert!(matches!(
+            type_check("fn f() -> Int { let r = 0..3; return 0; }"),
+            Err(TypeErrorKind::MisplacedRange)
+        ));
+    }
+
+    #[test]
+    fn assignments_are_checked_against_declared_types() {
+        let ok = "struct P { x: Int } fn f(p: P, xs: List<Float>) -> Int { let n = 1; n = 2; n += 3; p.x *= n; xs[0] /= 2.0; return n; }";
+        assert!(type_check(ok).is_ok());
+
+        let bad = "fn f() -> Int { let n = 1; n = true; return n; }";
+        assert!(matches!(
+            type_check(bad),
+            Err(TypeErrorKind::TypeMismatch { what, .. }) if what == "assignment to `n`"
+        ));
+    }
+
+    #[test]
+    fn locals_are_block_scoped_and_may_shadow() {
+        let ok = "fn f(x: Int) -> Int { let x = x > 0; if x { let y = 1; } else { let y = 2.5; } let x = 3; return x; }";
+        assert!(type_check(ok).is_ok());
+
+        let escaped = "fn f() -> Int { if true { let y = 1; } return y; }";
+        assert!(matches!(
+            type_check(escaped),
+            Err(TypeErrorKind::UnknownVariable { name }) if name == "y"
+        ));
+
+        let loop_var = "fn f() -> Int { for i in 0..3 { } return i; }";
+        assert!(matches!(
+            type_check(loop_var),
+            Err(TypeErrorKind::UnknownVariable { name }) if name == "i"
+        ));
+    }
+
+    #[test]
+    fn generic_calls_infer_type_arguments() {
+        let src = "enum Maybe<T> { Just(T), Nothing } fn first<T>(xs: List<T>) -> Maybe<T> { return Maybe::Just(xs[0]); } fn main() -> Int { let m = first([1.5]); let n: Maybe<Int> = Maybe::Nothing; return 0; }";
+        let typed = type_check(src).expect("type check failed");
+        let main = &typed.function_infos["main"];
+        assert_eq!(
+            main.locals[0].1,
+            Type::Generic("Maybe".to_string(), vec![Type::Float])
+        );
+
+        let mixed = "fn same<T>(a: T, b: T) -> T { return a; } fn main() -> Int { return same(1, true); }";
+        assert!(matches!(
+            type_check(mixed),
+            Err(TypeErrorKind::TypeMismatch { what, .. }) if what == "argument for `same`"
+        ));
+    }
+
+    #[test]
+    fn generic_items_report_uninferable_and_misapplied_types() {
+        let open = "enum Maybe<T> { Just(T), Nothing } fn main() -> Int { let m = Maybe::Nothing; return 0; }";
+        assert!(matches!(
+            type_check(open),
+            Err(TypeErrorKind::CannotInferTypeArgument { item, param }) if item == "Maybe" && param == "T"
+        ));
+
+        let arity = "struct Pair<A, B> { a: A, b: B } fn f(p: Pair<Int>) -> Int { return 0; }";
+        assert!(matches!(
+            type_check(arity),
+            Err(TypeErrorKind::WrongTypeArgumentCount { expected: 2, found: 1, .. })
+        ));
+
+        let opaque = "fn add<T>(a: T, b: T) -> T { return a + b; }";
+        assert!(type_check(opaque).is_err());
+    }
+
+    #[test]
+    fn prelude_option_result_and_question_mark() {
+        let ok = "fn half(x: Int) -> Result<Int, String> { if x % 2 != 0 { return Result::Err(\"odd\"); } return Result::Ok(x / 2); } fn quarter(x: Int) -> Result<Int, String> { let h = half(x)?; return half(h); } fn first(xs: List<Float>) -> Option<Float> { let x = xs.get(0)?; return Option::Some(x); }";
+        assert!(type_check(ok).is_ok());
+
+        let not_carrier = "fn f(x: Int) -> Option<Int> { let y = x?; return Option::None; }";
+        assert!(matches!(
+            type_check(not_carrier),
+            Err(TypeErrorKind::TypeMismatch { what, .. }) if what == "operand of `?`"
+        ));
+
+        let wrong_return = "fn f(xs: List<Int>) -> Int { return xs.get(0)?; }";
+        assert!(matches!(type_check(wrong_return), Err(TypeErrorKind::InvalidTry { .. })));
+
+        let other_error = "fn f(r: Result<Int, Bool>) -> Result<Int, String> { let x = r?; return Result::Ok(x); }";
+        assert!(matches!(type_check(other_error), Err(TypeErrorKind::InvalidTry { .. })));
+
+        // The prelude types cannot be redeclared, since built-ins keep producing them.
+        let shadowed = "enum Result<T, E> { Good(T), Bad(E) } fn main() -> Int { return 0; }";
+        assert!(matches!(type_check(shadowed), Err(TypeErrorKind::ReservedTypeName { .. })));
+        let as_struct = "struct Option { a: Int } fn main() -> Int { let r = parse_int(\"3\"); return r.a; }";
+        assert!(matches!(type_check(as_struct), Err(TypeErrorKind::ReservedTypeName { .. })));
+    }
+
+    #[test]
+    fn assignment_to_unknown_local_is_rejected() {
+        let src = "fn f() -> Int { y = 1; return 0; }";
+        assert!(matches!(
+            type_check(src),
+            Err(TypeErrorKind::UnknownVariable { .. })
+        ));
+    }
+
     #[test]
     fn list_method_call_typechecking() {
         let src = "fn f(x: List<Int>) -> Int { let _: List<Int> = x; x.push(1); return x.len(); }";
@@ -1338,7 +3437,209 @@ mod tests {
     #[test]
     fn empty_list_requires_context() {
         let src = "fn main() -> Int { let xs = []; return 0; }";
-        assert!(type_check(src).is_err());
+        assert!(matches!(
+            type_check(src),
+            Err(TypeErrorKind::CannotInferEmptyList { .. })
+        ));
+    }
+
+    #[test]
+    fn later_uses_infer_empty_lists_and_open_type_arguments() {
+        let src = "enum Maybe<T> { Just(T), Nothing } fn main() -> Int { let xs = []; let m = Maybe::Nothing; for x in xs { m = Maybe::Just(x); } xs.push(1.5); let ys = match m { Just(y) => [y], Nothing => [] }; return 0; }";
+        let typed = type_check(src).expect("type check failed");
+        let main = &typed.function_infos["main"];
+        let list_of_float = Type::List(Box::new(Type::Float));
+        assert_eq!(main.locals[0].1, list_of_float);
+        assert_eq!(
+            main.locals[1].1,
+            Type::Generic("Maybe".to_string(), vec![Type::Float])
+        );
+        assert_eq!(main.locals[2], ("x".to_string(), Type::Float));
+        assert_eq!(main.local_map["ys"].1, list_of_float);
+
+        let conflicting = "fn main() -> Int { let xs = []; xs.push(1); xs.push(true); return 0; }";
+        assert!(matches!(
+            type_check(conflicting),
+            Err(TypeErrorKind::TypeMismatch { .. })
+        ));
+        let infinite = "fn main() -> Int { let xs = []; xs.push(xs); return 0; }";
+        assert!(type_check(infinite).is_err());
+    }
+
+    #[test]
+    fn closures_and_function_values_typecheck() {
+        let src = "fn twice(x: Int) -> Int { return x * 2; } fn apply(f: fn(Int) -> Int, x: Int) -> Int { return f(x); } fn main() -> Int { let k = 1; let add = |x| x + k; let g = twice; return apply(add, 1) + apply(|y| g(y), 2); }";
+        let typed = type_check(src).expect("type check failed");
+        let int_fn = Type::Function(vec![Type::Int], Box::new(Type::Int));
+        assert_eq!(typed.function_infos["main"].local_map["add"].1, int_fn);
+        assert_eq!(typed.function_infos["main"].local_map["g"].1, int_fn);
+
+        let unannotated = "fn main() -> Int { let f = |x| x; return 0; }";
+        assert!(matches!(
+            type_check(unannotated),
+            Err(TypeErrorKind::CannotInferClosure { .. })
+        ));
+        let generic = "fn id<T>(x: T) -> T { return x; } fn main() -> Int { let f = id; return 0; }";
+        assert!(matches!(
+            type_check(generic),
+            Err(TypeErrorKind::GenericFunctionValue { .. })
+        ));
+        let not_callable = "fn main() -> Int { let n = 1; return n(2); }";
+        assert!(matches!(type_check(not_callable), Err(TypeErrorKind::TypeMismatch { .. })));
+        let arity = "fn main() -> Int { let f = |x: Int| x; return f(1, 2); }";
+        assert!(type_check(arity).is_err());
+    }
+
+    #[test]
+    fn operators_on_unannotated_closure_params_wait_for_the_call() {
+        let src = "fn main() -> Int { let sq = |x| x * x; let neg = |x| -x; let add = |a, b| a + b; \
+                   let less = |a, b| a < b; let join = |a, b| a + b; let s = join(\"a\", \"b\"); \
+                   if less(1.5, 2.0) && s == \"ab\" { return add(sq(3), neg(2)); } return 0; }";
+        let typed = type_check(src).expect("type check failed");
+        let int_fn = Type::Function(vec![Type::Int], Box::new(Type::Int));
+        let locals = &typed.function_infos["main"].local_map;
+        assert_eq!(locals["sq"].1, int_fn);
+        assert_eq!(locals["neg"].1, int_fn);
+        assert_eq!(
+            locals["less"].1,
+            Type::Function(vec![Type::Float, Type::Float], Box::new(Type::Bool))
+        );
+        assert_eq!(
+            locals["join"].1,
+            Type::Function(vec![Type::String, Type::String], Box::new(Type::String))
+        );
+
+        let not_numeric = "fn main() -> Int { let f = |x| x * x; f(true); return 0; }";
+        let tokens = lex_file(not_numeric).unwrap();
+        let program = Parser::new(&tokens).parse_program().unwrap();
+        let err = check_program(&program).expect_err("expected type error");
+        assert!(matches!(*err.kind, TypeErrorKind::TypeMismatch { .. }));
+        let span = err.span.expect("type error without span");
+        assert_eq!(&not_numeric[span.start..span.end], "x * x");
+    }
+
+    #[test]
+    fn unknown_types_are_reported_as_not_inferred() {
+        let method = "fn main() -> Int { let f = |x| x.len(); return 0; }";
+        assert!(matches!(
+            type_check(method),
+            Err(TypeErrorKind::CannotInferClosure { param: Some(name) }) if name == "x"
+        ));
+        let index = "fn main() -> Int { let g = |y| y[0]; return 0; }";
+        assert!(matches!(type_check(index), Err(TypeErrorKind::CannotInferClosure { .. })));
+
+        let nested = "fn main() -> Int { let xs = []; let n: Int = xs; return n; }";
+        let err = type_check(nested).expect_err("expected type error");
+        assert_eq!(
+            err.to_string(),
+            "`n` variable declaration: expected Int, found List<{unknown}>"
+        );
+    }
+
+    #[test]
+    fn methods_and_trait_bounds_typecheck() {
+        let src = "trait Area { fn area(&self) -> Int; } struct Sq { s: Int } impl Sq { fn new(s: Int) -> Self { return Self { s: s }; } } impl Area for Sq { fn area(&self) -> Int { return self.s * self.s; } } fn total<T: Area>(xs: List<T>) -> Int { let n = 0; for x in xs { n += x.area(); } return n; } fn main() -> Int { let sq = Sq::new(2); let xs = []; xs.push(sq); return sq.area() + total(xs); }";
+        let typed = type_check(src).expect("type check failed");
+        assert!(typed.function_infos.contains_key("Sq::area"));
+        assert!(typed.trait_impls.contains(&("Area".to_string(), "Sq".to_string())));
+
+        let unbounded = "trait Area { fn area(&self) -> Int; } fn f<T: Area>(x: T) -> Int { return x.area(); } fn main() -> Int { return f(1); }";
+        assert!(matches!(type_check(unbounded), Err(TypeErrorKind::UnsatisfiedBound { .. })));
+        let no_bound = "trait Area { fn area(&self) -> Int; } fn f<T>(x: T) -> Int { return x.area(); } fn main() -> Int { return 0; }";
+        assert!(matches!(type_check(no_bound), Err(TypeErrorKind::UnknownMethod { .. })));
+        let missing = "trait Area { fn area(&self) -> Int; } struct Sq { s: Int } impl Area for Sq { } fn main() -> Int { return 0; }";
+        assert!(matches!(type_check(missing), Err(TypeErrorKind::MissingTraitMethod { .. })));
+        let extra = "trait Area { fn area(&self) -> Int; } struct Sq { s: Int } impl Area for Sq { fn area(&self) -> Int { return 1; } fn side(&self) -> Int { return 1; } } fn main() -> Int { return 0; }";
+        assert!(matches!(type_check(extra), Err(TypeErrorKind::NotATraitMethod { .. })));
+        let signature = "trait Area { fn area(&self) -> Int; } struct Sq { s: Int } impl Area for Sq { fn area(&self) -> Float { return 1.0; } } fn main() -> Int { return 0; }";
+        assert!(matches!(type_check(signature), Err(TypeErrorKind::TypeMismatch { .. })));
+        let unknown_trait = "struct Sq { s: Int } impl Area for Sq { } fn main() -> Int { return 0; }";
+        assert!(matches!(type_check(unknown_trait), Err(TypeErrorKind::UnknownTrait { .. })));
+    }
+
+    #[test]
+    fn casts_and_small_integer_literals_typecheck() {
+        let src = "fn main() -> Int { let b: UInt8 = 255; let i: Int32 = -5; let f = i as Float * 2.0; b += 1; return f as Int + (b - 1) as Int; }";
+        let typed = type_check(src).expect("type check failed");
+        assert_eq!(typed.function_infos["main"].local_map["f"].1, Type::Float);
+
+        let mixed = "fn main() -> Int { let f = 1.5; return 1 + f; }";
+        assert!(matches!(type_check(mixed), Err(TypeErrorKind::MixedNumericOperands { .. })));
+        let mixed_ints = "fn main() -> Int { let i: Int32 = 1; return i + 1 as Int; }";
+        assert!(matches!(type_check(mixed_ints), Err(TypeErrorKind::MixedNumericOperands { .. })));
+        let too_big = "fn main() -> Int { let b: Byte = 256; return 0; }";
+        assert!(matches!(
+            type_check(too_big),
+            Err(TypeErrorKind::IntegerLiteralOutOfRange { value: 256, .. })
+        ));
+        let bad_cast = "fn main() -> Int { return 1.0 as Bool as Int; }";
+        assert!(matches!(type_check(bad_cast), Err(TypeErrorKind::InvalidCast { .. })));
+    }
+
+    #[test]
+    fn literal_arithmetic_takes_the_expected_integer_type() {
+        let src = "fn f(b: UInt8) -> UInt8 { return b; } fn g() -> Int32 { return 2 * 3; } \
+                   fn main() -> Int { let a: Int32 = 5 - 1; let xs: List<UInt8> = [1, 2 * 3]; \
+                   let n: Int32 = -(4 - 1); let m = 1 + 2; let less = 1 < 2; \
+                   return (f(2 + 3) as Int) + m; }";
+        let typed = type_check(src).expect("type check failed");
+        let locals = &typed.function_infos["main"].local_map;
+        assert_eq!(locals["a"].1, Type::Int32);
+        assert_eq!(locals["n"].1, Type::Int32);
+        assert_eq!(locals["m"].1, Type::Int);
+        assert_eq!(locals["less"].1, Type::Bool);
+
+        let too_big = "fn main() -> Int { let b: UInt8 = 2 * 300; return 0; }";
+        assert!(matches!(
+            type_check(too_big),
+            Err(TypeErrorKind::IntegerLiteralOutOfRange { value: 300, .. })
+        ));
+    }
+
+    #[test]
+    fn const_items_are_typed_and_read_only() {
+        let src = "const LIMIT: Int = 10 * 10; fn main() -> Int { let f = LIMIT as Float; return LIMIT; }";
+        let typed = type_check(src).expect("type check failed");
+        assert_eq!(typed.consts["LIMIT"].value, ConstValue::Int(100));
+        assert_eq!(typed.function_infos["main"].local_map["f"].1, Type::Float);
+
+        let assign = "const LIMIT: Int = 1; fn main() -> Int { LIMIT += 1; return LIMIT; }";
+        assert!(matches!(type_check(assign), Err(TypeErrorKind::AssignToConst { .. })));
+        let call = "fn one() -> Int { return 1; } const A: Int = one(); fn main() -> Int { return A; }";
+        assert!(matches!(type_check(call), Err(TypeErrorKind::NonConstantInitializer { .. })));
+        let mismatch = "const A: Bool = 1; fn main() -> Int { return 0; }";
+        assert!(matches!(type_check(mismatch), Err(TypeErrorKind::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn small_integer_consts_are_range_checked() {
+        let src = "const B: UInt8 = 200; const I: Int32 = -7; fn main() -> Int { let b = B + 1; return b as Int; }";
+        let typed = type_check(src).expect("type check failed");
+        assert_eq!(typed.consts["B"].ty, Type::UInt8);
+        assert_eq!(typed.function_infos["main"].local_map["b"].1, Type::UInt8);
+
+        let too_big = "const B: UInt8 = 256; fn main() -> Int { return 0; }";
+        assert!(matches!(type_check(too_big), Err(TypeErrorKind::InvalidConst { .. })));
+        let float = "const I: Int32 = 1.5; fn main() -> Int { return 0; }";
+        assert!(matches!(type_check(float), Err(TypeErrorKind::TypeMismatch { .. })));
+        let wrong_use = "const I: Int32 = 1; fn main() -> Int { let n: Int = I; return n; }";
+        assert!(matches!(type_check(wrong_use), Err(TypeErrorKind::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn static_items_need_scalar_types_and_constant_initializers() {
+        let src = "const BASE: Int = 2; static mut N: Int = BASE * 3; static mut B: Byte = 200; fn main() -> Int { N += 1; return N; }";
+        let typed = type_check(src).expect("type check failed");
+        assert_eq!(typed.statics.len(), 2);
+        assert_eq!(typed.statics[0].init, ConstValue::Int(6));
+        assert_eq!(typed.statics[1].ty, Type::UInt8);
+
+        let string = "static mut S: String = \"x\"; fn main() -> Int { return 0; }";
+        assert!(matches!(type_check(string), Err(TypeErrorKind::InvalidConst { .. })));
+        let call = "fn one() -> Int { return 1; } static mut N: Int = one(); fn main() -> Int { return N; }";
+        assert!(matches!(type_check(call), Err(TypeErrorKind::NonConstantInitializer { .. })));
+        let wrong = "static mut N: Int = 1; fn main() -> Int { N = 1.5; return N; }";
+        assert!(matches!(type_check(wrong), Err(TypeErrorKind::TypeMismatch { .. })));
     }
 
     #[test]
@@ -1348,8 +3649,150 @@ mod tests {
     }
 
     #[test]
-    fn string_arithmetic_is_rejected() {
-        let src = "fn main() -> String { return \"a\" + \"b\"; }";
-        assert!(type_check(src).is_err());
+    fn strings_concatenate_and_compare_but_reject_other_arithmetic() {
+        let ok = "fn main() -> Bool { let s = \"a\" + \"b\"; s += \"{1 + 2}\"; return s < \"b\"; }";
+        assert!(type_check(ok).is_ok());
+        assert!(type_check("fn main() -> String { return \"a\" - \"b\"; }").is_err());
+        assert!(type_check("fn f() -> Int { return 1; } fn main() -> String { return \"{f}\"; }").is_err());
+    }
+
+    #[test]
+    fn checking_continues_after_a_failing_function() {
+        let src = "fn a() -> Int { return nope; } fn b() -> Int { return 1; } fn c() -> Bool { return 1; }";
+        let tokens = lex_file(src).unwrap();
+        let program = Parser::new(&tokens).parse_program().unwrap();
+        let errors = check_program_all_errors(&program, &[]).expect_err("expected type errors");
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(*errors[0].kind, TypeErrorKind::UnknownVariable { .. }));
+        assert!(matches!(*errors[1].kind, TypeErrorKind::InvalidFunctionReturn { .. }));
+    }
+
+    #[test]
+    fn every_const_and_static_error_is_reported_in_source_order() {
+        let src = "fn f() -> Int { return 1; } static mut S: Int = 1.5; const C: Float = 1; \
+                   const D: Int = f(); const E: Float = C * 2.0; static mut T: Float = E; \
+                   fn main() -> Int { let x: Float = C; return x; }";
+        let tokens = lex_file(src).unwrap();
+        let program = Parser::new(&tokens).parse_program().unwrap();
+        let errors = check_program_all_errors(&program, &[]).expect_err("expected type errors");
+        let kinds: Vec<_> = errors.iter().map(|err| err.kind.code()).collect();
+        // `E` and `T` only read the failed `C`, so they are not reported again.
+        assert_eq!(kinds, ["E-TC08", "E-TC08", "E-TC35", "E-TC07"]);
+        let names: Vec<_> = errors[..3]
+            .iter()
+            .map(|err| {
+                let span = err.span.as_ref().expect("item error without span");
+                &src[span.start..span.end]
+            })
+            .collect();
+        assert_eq!(names, ["S", "C", "D"]);
+    }
+
+    #[test]
+    fn global_name_clashes_are_reported_at_the_later_declaration() {
+        let src = "static mut X: Int = 1; const X: Int = 2; \
+                   const f: Int = 3; fn f() -> Int { return 1; } \
+                   fn main() -> Int { X = 3; return X; }";
+        let tokens = lex_file(src).unwrap();
+        let program = Parser::new(&tokens).parse_program().unwrap();
+        let errors = check_program_all_errors(&program, &[]).expect_err("expected type errors");
+        let text = |span: &Span| &src[span.start..span.end];
+        let clashes: Vec<_> = errors
+            .iter()
+            .map(|err| {
+                let span = err.span.as_ref().expect("item error without span");
+                (err.to_string(), text(span), text(&err.labels[0].span), span.start)
+            })
+            .collect();
+        // The static stays declared, so `X = 3` is not reported as an assignment to a const.
+        let later_x = src.find("const X").unwrap() + 6;
+        let later_f = src.find("fn f").unwrap() + 3;
+        assert_eq!(
+            clashes,
+            [
+                ("duplicate const declaration `X`".to_string(), "X", "X", later_x),
+                ("duplicate function declaration `f`".to_string(), "f", "f", later_f),
+            ]
+        );
+    }
+
+    #[test]
+    fn non_unit_functions_must_return_on_every_path() {
+        let check = |src: &str| {
+            let tokens = lex_file(src).unwrap();
+            check_program(&Parser::new(&tokens).parse_program().unwrap())
+        };
+        let src = "fn f(x: Int) -> Int { if (x > 0) { return 1; } }";
+        let err = check(src).expect_err("expected missing return");
+        assert!(matches!(*err.kind, TypeErrorKind::MissingReturn { .. }));
+        let span = err.span.expect("missing return without span");
+        assert_eq!(&src[span.start..span.end], "f");
+        let label = &err.labels[0].span;
+        assert_eq!(&src[label.start..label.end], "if (x > 0) { return 1; }");
+
+        for src in [
+            "fn f() -> Int { }",
+            "fn f() -> Int { while true { break; } }",
+            "fn f(x: Int) -> Int { for i in 0..x { return i; } }",
+        ] {
+            let err = check(src).expect_err("expected missing return");
+            assert!(matches!(*err.kind, TypeErrorKind::MissingReturn { .. }), "{}", src);
+        }
+        for src in [
+            "fn f(x: Int) -> Int { if x > 0 { return 1; } else { return 2; } }",
+            "fn f() -> Int { while true { while true { break; } return 1; } }",
+        ] {
+            assert!(check(src).is_ok(), "{}", src);
+        }
+    }
+
+    #[test]
+    fn type_errors_point_at_the_offending_node() {
+        let span_of = |src: &str| {
+            let tokens = lex_file(src).unwrap();
+            let program = Parser::new(&tokens).parse_program().unwrap();
+            let err = check_program(&program).expect_err("expected type error");
+            let span = err.span.expect("type error without span");
+            src[span.start..span.end].to_string()
+        };
+        assert_eq!(span_of("fn main(x: Int) -> Int { return x + y; }"), "y");
+        assert_eq!(span_of("fn main() -> Int { if 1 { return 1; } return 0; }"), "1");
+        assert_eq!(span_of("fn main() -> Int { let b: Bool = 2 + 3; return 0; }"), "2 + 3");
+        assert_eq!(span_of("fn f(a: Int, a: Int) -> Int { return a; }"), "a");
+        assert_eq!(span_of("struct P { x: Nope } fn main() -> Int { return 0; }"), "P");
+        assert_eq!(span_of("fn main() -> Int { let xs = []; return 0; }"), "[]");
+        let generic = "fn empty<T>() -> List<T> { return []; } \
+                       fn main() -> Int { empty(); return 0; }";
+        assert_eq!(span_of(generic), "empty()");
+        assert_eq!(span_of("fn main() -> Int { let f = |x| 1; return 0; }"), "|x| 1");
+        let call = "fn g(a: Int, b: Bool) -> Int { return a; } \
+                    fn main() -> Int { return g(1, 2 + 3); }";
+        assert_eq!(span_of(call), "2 + 3");
+        let value = "fn main() -> Int { let f = |a: Int| a; return f(true); }";
+        assert_eq!(span_of(value), "true");
+        let method = "struct P { x: Int } impl P { fn add(&self, y: Int) -> Int { return y; } } \
+                      fn main() -> Int { let p = P { x: 1 }; return p.add(false); }";
+        assert_eq!(span_of(method), "false");
+        assert_eq!(span_of("fn main() -> Int { let xs = [1]; xs.push(\"a\"); return 0; }"), "\"a\"");
+    }
+
+    #[test]
+    fn type_error_diagnostics_have_codes_and_first_declaration_labels() {
+        let src = "fn f() -> Int { return 1; }\nfn f() -> Int { return 2; }";
+        let tokens = lex_file(src).unwrap();
+        let program = Parser::new(&tokens).parse_program().unwrap();
+        let diagnostic = check_program(&program).expect_err("expected type error").to_diagnostic();
+        assert_eq!(diagnostic.code, "E-TC03");
+        let label = &diagnostic.labels[0];
+        assert_eq!(label.message, "first declared here");
+        assert_eq!(&src[label.span.start..label.span.end], "f");
+        assert!(label.span.start < diagnostic.span.expect("missing span").start);
+
+        let codes = [
+            TypeErrorKind::UnknownType { name: "T".to_string() }.code(),
+            TypeErrorKind::MisplacedRange.code(),
+            TypeErrorKind::AssignToConst { name: "C".to_string() }.code(),
+        ];
+        assert_eq!(codes, ["E-TC01", "E-TC21", "E-TC37"]);
     }
 }
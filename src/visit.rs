@@ -0,0 +1,122 @@
+//! Shared traversal of function bodies.
+//!
+//! Passes over statements and expressions implement [`Visitor`] and override the nodes they
+//! care about. The default methods call the `walk_*` functions, which visit every child in
+//! source order; an override calls them itself to keep descending.
+
+use crate::ast::{Expr, ExprKind, InterpolationPart, Stmt, StmtKind};
+
+pub trait Visitor {
+    fn visit_block(&mut self, stmts: &[Stmt]) {
+        walk_block(self, stmts);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, stmts: &[Stmt]) {
+    for stmt in stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match &stmt.kind {
+        StmtKind::Let { value, .. } => visitor.visit_expr(value),
+        StmtKind::Return(Some(expr)) | StmtKind::Expr(expr) => visitor.visit_expr(expr),
+        StmtKind::Assign { target, value, .. } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        StmtKind::If {
+            cond,
+            then_block,
+            else_block,
+        } => {
+            visitor.visit_expr(cond);
+            visitor.visit_block(then_block);
+            visitor.visit_block(else_block);
+        }
+        StmtKind::While { cond, body } => {
+            visitor.visit_expr(cond);
+            visitor.visit_block(body);
+        }
+        StmtKind::For { iter, body, .. } => {
+            visitor.visit_expr(iter);
+            visitor.visit_block(body);
+        }
+        StmtKind::Return(None) | StmtKind::Break | StmtKind::Continue => {}
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match &expr.kind {
+        ExprKind::Int(_)
+        | ExprKind::Float(_)
+        | ExprKind::Bool(_)
+        | ExprKind::String(_)
+        | ExprKind::Local(_) => {}
+        ExprKind::Ref(inner)
+        | ExprKind::Deref(inner)
+        | ExprKind::Not(inner)
+        | ExprKind::Neg(inner)
+        | ExprKind::Try(inner)
+        | ExprKind::Cast { expr: inner, .. } => visitor.visit_expr(inner),
+        ExprKind::Binary { left, right, .. } => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        ExprKind::Range { start, end } => {
+            visitor.visit_expr(start);
+            visitor.visit_expr(end);
+        }
+        ExprKind::Index { base, index } => {
+            visitor.visit_expr(base);
+            visitor.visit_expr(index);
+        }
+        ExprKind::Field { base, .. } => visitor.visit_expr(base),
+        ExprKind::Call { args, .. } => {
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::MethodCall { receiver, args, .. } => {
+            visitor.visit_expr(receiver);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::StructInit { fields, .. } => {
+            for (_, value) in fields {
+                visitor.visit_expr(value);
+            }
+        }
+        ExprKind::EnumInit { payload, .. }
+        | ExprKind::TupleLiteral(payload)
+        | ExprKind::ListLiteral(payload) => {
+            for value in payload {
+                visitor.visit_expr(value);
+            }
+        }
+        ExprKind::Match { subject, arms } => {
+            visitor.visit_expr(subject);
+            for arm in arms {
+                visitor.visit_expr(&arm.body);
+            }
+        }
+        ExprKind::Closure { body, .. } => visitor.visit_expr(body),
+        ExprKind::Interpolation(parts) => {
+            for part in parts {
+                if let InterpolationPart::Expr(expr) = part {
+                    visitor.visit_expr(expr);
+                }
+            }
+        }
+    }
+}